@@ -1,5 +1,5 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
 use syn::spanned::Spanned;
 
 pub fn derive_params(input: TokenStream) -> TokenStream {
@@ -29,8 +29,9 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
     // JSON. The `nested` fields should also implement the `Params` trait and their fields will be
     // inherited and added to this field's param mapping list. The order follows the declaration
     // order We'll also enforce that there are no duplicate keys for `id` fields at compile time.
-    // TODO: This duplication check doesn't work for nested fields since we don't know anything
-    //       about the fields on the nested structs
+    // Since we don't know anything about the fields on the nested structs here, duplicate IDs and ID
+    // hash collisions involving nested fields are checked using constant evaluation instead. See
+    // `static_id_check_tokens()`.
     let mut params: Vec<Param> = Vec::new();
     let mut persistent_fields: Vec<PersistentField> = Vec::new();
     for field in fields.named {
@@ -40,10 +41,32 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         };
 
         // All attributes are mutually exclusive. If we encounter multiple or duplicate attributes,
-        // then we'll error out.
+        // then we'll error out. The exception are the migration attributes, which modify an `id`
+        // or `persist` attribute on the same field.
         let mut processed_attribute = false;
+        let num_params = params.len();
+        let num_persistent_fields = persistent_fields.len();
+        let mut field_old_ids: Vec<syn::LitStr> = Vec::new();
+        let mut field_old_keys: Vec<syn::LitStr> = Vec::new();
+        let mut field_migrations: Vec<Migration> = Vec::new();
         for attr in &field.attrs {
-            if attr.path.is_ident("id") {
+            if attr.path.is_ident("old_ids") || attr.path.is_ident("old_keys") {
+                let old_names = match parse_string_list(attr) {
+                    Ok(old_names) => old_names,
+                    Err(err) => return err.to_compile_error().into(),
+                };
+
+                if attr.path.is_ident("old_ids") {
+                    field_old_ids.extend(old_names);
+                } else {
+                    field_old_keys.extend(old_names);
+                }
+            } else if attr.path.is_ident("migrate") {
+                match Migration::parse(attr) {
+                    Ok(migration) => field_migrations.push(migration),
+                    Err(err) => return err.to_compile_error().into(),
+                }
+            } else if attr.path.is_ident("id") {
                 match attr.parse_meta() {
                     Ok(syn::Meta::NameValue(syn::MetaNameValue {
                         lit: syn::Lit::Str(s),
@@ -73,9 +96,17 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                             .into();
                         }
 
+                        if s.value().is_empty() {
+                            return syn::Error::new(s.span(), "Parameter IDs cannot be empty")
+                                .to_compile_error()
+                                .into();
+                        }
+
                         params.push(Param::Single {
                             id: s,
                             field: field_name.clone(),
+                            old_ids: Vec::new(),
+                            migrations: Vec::new(),
                         });
 
                         processed_attribute = true;
@@ -90,7 +121,8 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                         .into()
                     }
                 };
-            } else if attr.path.is_ident("persist") {
+            } else if attr.path.is_ident("persist") || attr.path.is_ident("persist_bytes") {
+                let bytes = attr.path.is_ident("persist_bytes");
                 match attr.parse_meta() {
                     Ok(syn::Meta::NameValue(syn::MetaNameValue {
                         lit: syn::Lit::Str(s),
@@ -117,10 +149,22 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                         persistent_fields.push(PersistentField {
                             key: s,
                             field: field_name.clone(),
+                            bytes,
+                            old_keys: Vec::new(),
+                            migrations: Vec::new(),
                         });
 
                         processed_attribute = true;
                     }
+                    _ if bytes => {
+                        return syn::Error::new(
+                            attr.span(),
+                            "The persist_bytes attribute should be a key-value pair with a string \
+                             argument: #[persist_bytes = \"foo_bar\"]",
+                        )
+                        .to_compile_error()
+                        .into()
+                    }
                     _ => {
                         return syn::Error::new(
                             attr.span(),
@@ -222,15 +266,18 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 params.push(Param::Nested(match (nested_array, nested_id_prefix) {
                     (true, None) => NestedParams::Array {
                         field: field_name.clone(),
+                        ty: field.ty.clone(),
                         group: nested_group,
                     },
                     (false, Some(id_prefix)) => NestedParams::Prefixed {
                         field: field_name.clone(),
+                        ty: field.ty.clone(),
                         id_prefix,
                         group: nested_group,
                     },
                     (false, None) => NestedParams::Inline {
                         field: field_name.clone(),
+                        ty: field.ty.clone(),
                         group: nested_group,
                     },
                     (true, Some(_)) => {
@@ -246,6 +293,69 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 processed_attribute = true;
             }
         }
+
+        // Now that we know what kind of field this is, the migration attributes can be attached to
+        // the field's parameter or persistent field
+        let new_param = params[num_params..].last_mut();
+        let new_persistent_field = persistent_fields[num_persistent_fields..].last_mut();
+        match (new_param, new_persistent_field) {
+            (
+                Some(Param::Single {
+                    old_ids,
+                    migrations,
+                    ..
+                }),
+                None,
+            ) if field_old_keys.is_empty() => {
+                *old_ids = field_old_ids;
+                *migrations = field_migrations;
+            }
+            (None, Some(persistent_field)) if field_old_ids.is_empty() => {
+                persistent_field.old_keys = field_old_keys;
+                persistent_field.migrations = field_migrations;
+            }
+            _ if field_old_ids.is_empty()
+                && field_old_keys.is_empty()
+                && field_migrations.is_empty() => {}
+            _ => {
+                return syn::Error::new(
+                    field.span(),
+                    "The old_ids and migrate attributes can only be used together with the id \
+                     attribute, and the old_keys and migrate attributes can only be used together \
+                     with the persist and persist_bytes attributes",
+                )
+                .to_compile_error()
+                .into()
+            }
+        }
+    }
+
+    // Old IDs and keys may not clash with the current ones, as that would make the migrations
+    // ambiguous
+    {
+        let mut current_ids: Vec<String> = Vec::new();
+        let mut old_ids: Vec<&syn::LitStr> = Vec::new();
+        for param in &params {
+            if let Param::Single {
+                id,
+                old_ids: param_old_ids,
+                ..
+            } = param
+            {
+                current_ids.push(id.value());
+                old_ids.extend(param_old_ids);
+            }
+        }
+        if let Err(err) = check_old_names(&current_ids, &old_ids, "parameter ID") {
+            return err.to_compile_error().into();
+        }
+
+        let current_keys: Vec<String> = persistent_fields.iter().map(|p| p.key.value()).collect();
+        let old_keys: Vec<&syn::LitStr> =
+            persistent_fields.iter().flat_map(|p| &p.old_keys).collect();
+        if let Err(err) = check_old_names(&current_keys, &old_keys, "persistent field key") {
+            return err.to_compile_error().into();
+        }
     }
 
     // The next step is build the gathered information into tokens that can be spliced into a
@@ -265,169 +375,446 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         }
     };
 
-    let (serialize_fields_tokens, deserialize_fields_tokens) = {
-        // Like with `param_map()`, we'll try to do the serialization for this struct and then
-        // recursively call the child parameter structs. We don't know anything about the actual
-        // field types, but because we can generate this function we can get type erasure for free
-        // since we only need to worry about byte vectors.
-        let (serialize_fields_self_tokens, deserialize_fields_match_self_tokens): (Vec<_>, Vec<_>) =
-            persistent_fields
-                .into_iter()
-                .map(|PersistentField { field, key }| {
-                    (
+    let migrate_state_tokens = {
+        // Parameters and persistent fields on this struct are migrated first, after which the
+        // nested structs can migrate their own parameters with any prefixes and suffixes stripped
+        let migrate_params_tokens = params.iter().filter_map(|p| match p {
+            Param::Single {
+                id,
+                old_ids,
+                migrations,
+                ..
+            } => Some(migration_tokens(
+                id,
+                old_ids,
+                migrations,
+                quote! { ::nih_plug::params::migrate::rename_param },
+                quote! { state.params },
+            )),
+            Param::Nested(_) => None,
+        });
+        let migrate_fields_tokens = persistent_fields.iter().map(
+            |PersistentField {
+                 key,
+                 bytes,
+                 old_keys,
+                 migrations,
+                 ..
+             }| {
+                if *bytes {
+                    migration_tokens(
+                        key,
+                        old_keys,
+                        migrations,
+                        quote! { ::nih_plug::params::migrate::rename_binary_field },
+                        quote! { state.binary_fields },
+                    )
+                } else {
+                    migration_tokens(
+                        key,
+                        old_keys,
+                        migrations,
+                        quote! { ::nih_plug::params::migrate::rename_field },
+                        quote! { state.fields },
+                    )
+                }
+            },
+        );
+        let migrate_nested_tokens = params.iter().filter_map(|p| match p {
+            Param::Single { .. } => None,
+            Param::Nested(NestedParams::Inline { field, .. }) => Some(quote! {
+                self.#field.migrate_state(state);
+            }),
+            Param::Nested(NestedParams::Prefixed {
+                field, id_prefix, ..
+            }) => Some(quote! {
+                ::nih_plug::params::migrate::migrate_prefixed(
+                    state,
+                    &format!("{}_", #id_prefix),
+                    |state| self.#field.migrate_state(state),
+                );
+            }),
+            Param::Nested(NestedParams::Array { field, .. }) => Some(quote! {
+                for (field_idx, field) in self.#field.iter().enumerate() {
+                    let idx = field_idx + 1;
+                    ::nih_plug::params::migrate::migrate_suffixed(
+                        state,
+                        &format!("_{}", idx),
+                        |state| field.migrate_state(state),
+                    );
+                }
+            }),
+        });
+
+        quote! {
+            #(#migrate_params_tokens)*
+            #(#migrate_fields_tokens)*
+            #(#migrate_nested_tokens)*
+        }
+    };
+
+    let (serialize_fields_tokens, deserialize_fields_tokens) =
+        persistent_fields_tokens(&persistent_fields, &params, false);
+    let (serialize_binary_fields_tokens, deserialize_binary_fields_tokens) =
+        persistent_fields_tokens(&persistent_fields, &params, true);
+
+    // The const-evaluated ID checks need to know the concrete types of the nested fields, so these
+    // are skipped for generic structs
+    let static_id_check_tokens = if ast.generics.params.is_empty() {
+        static_id_check_tokens(struct_name, &params)
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #static_id_check_tokens
+
+        unsafe impl #impl_generics Params for #struct_name #ty_generics #where_clause {
+            fn param_map(&self) -> Vec<(String, nih_plug::prelude::ParamPtr, String)> {
+                #param_map_tokens
+            }
+
+            fn serialize_fields(&self) -> ::std::collections::BTreeMap<String, String> {
+                #serialize_fields_tokens
+            }
+
+            fn deserialize_fields(&self, serialized: &::std::collections::BTreeMap<String, String>) {
+                #deserialize_fields_tokens
+            }
+
+            fn serialize_binary_fields(&self) -> ::std::collections::BTreeMap<String, Vec<u8>> {
+                #serialize_binary_fields_tokens
+            }
+
+            fn deserialize_binary_fields(
+                &self,
+                serialized: &::std::collections::BTreeMap<String, Vec<u8>>,
+            ) {
+                #deserialize_binary_fields_tokens
+            }
+
+            #[allow(unused_variables)]
+            fn migrate_state(&self, state: &mut ::nih_plug::prelude::PluginState) {
+                #migrate_state_tokens
+            }
+        }
+    }
+    .into()
+}
+
+/// Generate the bodies of the `serialize_fields()` and `deserialize_fields()` methods, or those of
+/// the `serialize_binary_fields()` and `deserialize_binary_fields()` methods if `bytes` is set. The
+/// latter handle the `#[persist_bytes = "..."]` fields.
+fn persistent_fields_tokens(
+    persistent_fields: &[PersistentField],
+    params: &[Param],
+    bytes: bool,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let (serialize_fn, deserialize_fn) = if bytes {
+        (
+            format_ident!("serialize_binary_fields"),
+            format_ident!("deserialize_binary_fields"),
+        )
+    } else {
+        (
+            format_ident!("serialize_fields"),
+            format_ident!("deserialize_fields"),
+        )
+    };
+
+    // Like with `param_map()`, we'll try to do the serialization for this struct and then
+    // recursively call the child parameter structs. We don't know anything about the actual
+    // field types, but because we can generate this function we can get type erasure for free
+    // since we only need to worry about byte vectors.
+    let (serialize_fields_self_tokens, deserialize_fields_match_self_tokens): (Vec<_>, Vec<_>) =
+        persistent_fields
+            .iter()
+            .filter(|persistent_field| persistent_field.bytes == bytes)
+            .map(|PersistentField { field, key, .. }| {
+                if bytes {
+                    return (
                         quote! {
-                            match ::nih_plug::params::persist::PersistentField::map(
-                                &self.#field,
-                                ::nih_plug::params::persist::serialize_field,
-                            ) {
-                                Ok(data) => {
-                                    serialized.insert(String::from(#key), data);
+                            serialized.insert(
+                                String::from(#key),
+                                ::nih_plug::params::persist::PersistentBytesField::map_bytes(
+                                    &self.#field,
+                                    <[u8]>::to_vec,
+                                ),
+                            );
+                        },
+                        quote! {
+                            #key => {
+                                ::nih_plug::params::persist::PersistentBytesField::set_bytes(
+                                    &self.#field,
+                                    data.clone(),
+                                );
+                            }
+                        },
+                    );
+                }
+
+                (
+                    quote! {
+                        match ::nih_plug::params::persist::PersistentField::map(
+                            &self.#field,
+                            ::nih_plug::params::persist::serialize_field,
+                        ) {
+                            Ok(data) => {
+                                serialized.insert(String::from(#key), data);
+                            }
+                            Err(err) => {
+                                ::nih_plug::nih_debug_assert_failure!(
+                                    "Could not serialize '{}': {}",
+                                    #key,
+                                    err
+                                )
+                            }
+                        };
+                    },
+                    quote! {
+                        #key => {
+                            match ::nih_plug::params::persist::deserialize_field(&data) {
+                                Ok(deserialized) => {
+                                    ::nih_plug::params::persist::PersistentField::set(
+                                        &self.#field,
+                                        deserialized,
+                                    );
                                 }
                                 Err(err) => {
                                     ::nih_plug::nih_debug_assert_failure!(
-                                        "Could not serialize '{}': {}",
+                                        "Could not deserialize '{}': {}",
                                         #key,
                                         err
                                     )
                                 }
                             };
-                        },
-                        quote! {
-                            #key => {
-                                match ::nih_plug::params::persist::deserialize_field(&data) {
-                                    Ok(deserialized) => {
-                                        ::nih_plug::params::persist::PersistentField::set(
-                                            &self.#field,
-                                            deserialized,
-                                        );
-                                    }
-                                    Err(err) => {
-                                        ::nih_plug::nih_debug_assert_failure!(
-                                            "Could not deserialize '{}': {}",
-                                            #key,
-                                            err
-                                        )
-                                    }
-                                };
-                            }
-                        },
-                    )
-                })
-                .unzip();
-
-        // ID prefixes are also added for nested objects
-        let (serialize_fields_nested_tokens, deserialize_fields_nested_tokens): (Vec<_>, Vec<_>) =
-            params
-                .iter()
-                .filter_map(|p| match p {
-                    Param::Single { .. } => None,
-                    Param::Nested(nested) => Some(nested),
-                })
-                .map(|nested| match nested {
-                    NestedParams::Inline { field, .. } => (
-                        quote! { serialized.extend(self.#field.serialize_fields()); },
-                        quote! { self.#field.deserialize_fields(serialized); },
-                    ),
-                    NestedParams::Prefixed {
-                        field, id_prefix, ..
-                    } => (
-                        quote! {
-                            let prefixed = self
-                                .#field
-                                .serialize_fields()
+                        }
+                    },
+                )
+            })
+            .unzip();
+
+    // ID prefixes are also added for nested objects
+    let (serialize_fields_nested_tokens, deserialize_fields_nested_tokens): (Vec<_>, Vec<_>) =
+        params
+            .iter()
+            .filter_map(|p| match p {
+                Param::Single { .. } => None,
+                Param::Nested(nested) => Some(nested),
+            })
+            .map(|nested| match nested {
+                NestedParams::Inline { field, .. } => (
+                    quote! { serialized.extend(self.#field.#serialize_fn()); },
+                    quote! { self.#field.#deserialize_fn(serialized); },
+                ),
+                NestedParams::Prefixed {
+                    field, id_prefix, ..
+                } => (
+                    quote! {
+                        let prefixed = self
+                            .#field
+                            .#serialize_fn()
+                            .into_iter()
+                            .map(|(key, value)| (format!("{}_{}", #id_prefix, key), value));
+
+                        serialized.extend(prefixed);
+                    },
+                    quote! {
+                        let prefix = format!("{}_", #id_prefix);
+                        let matching_fields = serialized
+                            .iter()
+                            .filter_map(|(key, value)| {
+                                let original_key = key.strip_prefix(&prefix)?;
+                                Some((original_key.to_owned(), value.to_owned()))
+                            })
+                            .collect();
+
+                        self.#field.#deserialize_fn(&matching_fields);
+                    },
+                ),
+                NestedParams::Array { field, .. } => (
+                    quote! {
+                        for (field_idx, field) in self.#field.iter().enumerate() {
+                            let idx = field_idx + 1;
+                            let suffixed = field
+                                .#serialize_fn()
                                 .into_iter()
-                                .map(|(key, value)| (format!("{}_{}", #id_prefix, key), value));
+                                .map(|(key, value)| (format!("{}_{}", key, idx), value));
 
-                            serialized.extend(prefixed);
-                        },
-                        quote! {
-                            let prefix = format!("{}_", #id_prefix);
+                            serialized.extend(suffixed);
+                        }
+                    },
+                    quote! {
+                        for (field_idx, field) in self.#field.iter().enumerate() {
+                            let idx = field_idx + 1;
+                            let suffix = format!("_{}", idx);
                             let matching_fields = serialized
                                 .iter()
                                 .filter_map(|(key, value)| {
-                                    let original_key = key.strip_prefix(&prefix)?;
+                                    let original_key = key.strip_suffix(&suffix)?;
                                     Some((original_key.to_owned(), value.to_owned()))
                                 })
                                 .collect();
 
-                            self.#field.deserialize_fields(&matching_fields);
-                        },
-                    ),
-                    NestedParams::Array { field, .. } => (
-                        quote! {
-                            for (field_idx, field) in self.#field.iter().enumerate() {
-                                let idx = field_idx + 1;
-                                let suffixed = field
-                                    .serialize_fields()
-                                    .into_iter()
-                                    .map(|(key, value)| (format!("{}_{}", key, idx), value));
-
-                                serialized.extend(suffixed);
-                            }
-                        },
-                        quote! {
-                            for (field_idx, field) in self.#field.iter().enumerate() {
-                                let idx = field_idx + 1;
-                                let suffix = format!("_{}", idx);
-                                let matching_fields = serialized
-                                    .iter()
-                                    .filter_map(|(key, value)| {
-                                        let original_key = key.strip_suffix(&suffix)?;
-                                        Some((original_key.to_owned(), value.to_owned()))
-                                    })
-                                    .collect();
-
-                                field.deserialize_fields(&matching_fields);
-                            }
-                        },
-                    ),
-                })
-                .unzip();
+                            field.#deserialize_fn(&matching_fields);
+                        }
+                    },
+                ),
+            })
+            .unzip();
 
-        let serialize_fields_tokens = quote! {
-            #[allow(unused_mut)]
-            let mut serialized = ::std::collections::BTreeMap::new();
-            #(#serialize_fields_self_tokens);*
+    let serialize_fields_tokens = quote! {
+        #[allow(unused_mut)]
+        let mut serialized = ::std::collections::BTreeMap::new();
+        #(#serialize_fields_self_tokens);*
 
-            #(#serialize_fields_nested_tokens);*
+        #(#serialize_fields_nested_tokens);*
 
-            serialized
-        };
+        serialized
+    };
 
-        let deserialize_fields_tokens = quote! {
-            for (field_name, data) in serialized {
-                match field_name.as_str() {
-                    #(#deserialize_fields_match_self_tokens)*
-                    _ => ::nih_plug::nih_trace!("Unknown serialized field name: {} (this may not be accurate when using nested param structs)", field_name),
-                }
+    let deserialize_fields_tokens = quote! {
+        for (field_name, data) in serialized {
+            match field_name.as_str() {
+                #(#deserialize_fields_match_self_tokens)*
+                _ => ::nih_plug::nih_trace!("Unknown serialized field name: {} (this may not be accurate when using nested param structs)", field_name),
             }
+        }
 
-            // FIXME: The above warning will course give false postiives when using nested
-            //        parameter structs. An easy fix would be to use
-            //        https://doc.rust-lang.org/std/collections/struct.HashMap.html#method.drain_filter
-            //        once that gets stabilized.
-            #(#deserialize_fields_nested_tokens);*
+        // FIXME: The above warning will course give false postiives when using nested
+        //        parameter structs. An easy fix would be to use
+        //        https://doc.rust-lang.org/std/collections/struct.HashMap.html#method.drain_filter
+        //        once that gets stabilized.
+        #(#deserialize_fields_nested_tokens);*
+    };
+
+    (serialize_fields_tokens, deserialize_fields_tokens)
+}
+
+/// Generate an implementation of `nih_plug::params::internals::static_ids::StaticParamIds` for the
+/// struct listing all of its parameter IDs, including those from nested structs, along with
+/// constant evaluated checks that fail to compile if any of the struct's fields introduces a
+/// duplicate parameter ID or an ID with the same hash as an existing ID. Each check uses the span
+/// of the field it checks so the error points at the offending field. Nested fields with types that
+/// don't use the derive macro and nested arrays using types other than `[T; N]` are skipped, and
+/// the wrappers will instead check those in debug builds.
+fn static_id_check_tokens(struct_name: &syn::Ident, params: &[Param]) -> proc_macro2::TokenStream {
+    let ids_of = |ty: &syn::Type| quote! { static_ids::ParamIdsOf::<#ty>::PARAM_IDS };
+
+    let mut field_consts = Vec::new();
+    let mut field_const_tokens = Vec::new();
+    let mut check_tokens = Vec::new();
+    for (idx, param) in params.iter().enumerate() {
+        let field_const = format_ident!("__FIELD_{}", idx);
+        let (field, ids_tokens, duplicate_message, hash_collision_message) = match param {
+            Param::Single { field, id, .. } => (
+                field,
+                quote! { &[static_ids::StaticParamId::new(#id)] },
+                format!(
+                    "The parameter ID {:?} on the `{}` field is already in use",
+                    id.value(),
+                    field
+                ),
+                format!(
+                    "The parameter ID {:?} on the `{}` field has the same hash as another \
+                     parameter's ID. Parameters are identified by these hashes in CLAP and VST3, \
+                     so one of the IDs needs to be changed.",
+                    id.value(),
+                    field
+                ),
+            ),
+            Param::Nested(nested) => {
+                let (field, ids_tokens) = match nested {
+                    NestedParams::Inline { field, ty, .. } => (field, ids_of(ty)),
+                    NestedParams::Prefixed {
+                        field,
+                        ty,
+                        id_prefix,
+                        ..
+                    } => {
+                        let ids = ids_of(ty);
+                        (
+                            field,
+                            quote! {
+                                &static_ids::with_prefix::<{ #ids.len() }>(#ids, #id_prefix)
+                            },
+                        )
+                    }
+                    NestedParams::Array {
+                        field,
+                        ty: syn::Type::Array(syn::TypeArray { elem, len, .. }),
+                        ..
+                    } => {
+                        let ids = ids_of(elem);
+                        (
+                            field,
+                            quote! {
+                                &static_ids::with_array_indices::<{ #ids.len() * (#len) }>(
+                                    #ids,
+                                    #len,
+                                )
+                            },
+                        )
+                    }
+                    NestedParams::Array { field, .. } => (field, quote! { &[] }),
+                };
+
+                (
+                    field,
+                    ids_tokens,
+                    format!(
+                        "One of the parameter IDs from the nested `{}` field is already in use",
+                        field
+                    ),
+                    format!(
+                        "One of the parameter IDs from the nested `{}` field has the same hash as \
+                         another parameter's ID. Parameters are identified by these hashes in \
+                         CLAP and VST3, so one of the IDs needs to be changed.",
+                        field
+                    ),
+                )
+            }
         };
 
-        (serialize_fields_tokens, deserialize_fields_tokens)
-    };
+        field_const_tokens.push(quote! {
+            const #field_const: &[static_ids::StaticParamId] = #ids_tokens;
+        });
+
+        let start = quote! { 0 #(+ #field_consts.len())* };
+        check_tokens.push(quote_spanned! {field.span()=>
+            static_ids::check_ids(
+                &__PARAM_IDS,
+                &__PARAM_ID_HASHES,
+                #start,
+                #start + #field_const.len(),
+                #duplicate_message,
+                #hash_collision_message,
+            );
+        });
+
+        field_consts.push(field_const);
+    }
 
     quote! {
-        unsafe impl #impl_generics Params for #struct_name #ty_generics #where_clause {
-            fn param_map(&self) -> Vec<(String, nih_plug::prelude::ParamPtr, String)> {
-                #param_map_tokens
-            }
+        const _: () = {
+            // This trait is needed for the `ParamIdsOf` fallback used for nested fields
+            #[allow(unused_imports)]
+            use ::nih_plug::params::internals::static_ids::{self, UnknownParamIds as _};
 
-            fn serialize_fields(&self) -> ::std::collections::BTreeMap<String, String> {
-                #serialize_fields_tokens
-            }
+            #(#field_const_tokens)*
 
-            fn deserialize_fields(&self, serialized: &::std::collections::BTreeMap<String, String>) {
-                #deserialize_fields_tokens
+            const __PARAM_IDS: [static_ids::StaticParamId; 0 #(+ #field_consts.len())*] =
+                static_ids::concat(&[#(#field_consts),*]);
+            // The checks compare these hashes instead of hashing every ID again for every check
+            const __PARAM_ID_HASHES: [u32; __PARAM_IDS.len()] = static_ids::hashes(&__PARAM_IDS);
+
+            impl static_ids::StaticParamIds for #struct_name {
+                const PARAM_IDS: &'static [static_ids::StaticParamId] = &__PARAM_IDS;
             }
-        }
+
+            #(#check_tokens)*
+        };
     }
-    .into()
 }
 
 /// A parameter defined on this struct using the `#[id = "..."]` attribute, or another object that
@@ -440,6 +827,10 @@ enum Param {
         field: syn::Ident,
         /// The parameter's unique ID.
         id: syn::LitStr,
+        /// The parameter's previous IDs, set using the `#[old_ids(...)]` attribute.
+        old_ids: Vec<syn::LitStr>,
+        /// Value transformations for old states, set using `#[migrate(...)]` attributes.
+        migrations: Vec<Migration>,
     },
     /// Another struct also implementing `Params`. This object's parameters are inlined in the
     /// parameter list.
@@ -451,7 +842,7 @@ impl Param {
     /// parameter map.
     fn param_map_tokens(&self) -> proc_macro2::TokenStream {
         match self {
-            Param::Single { field, id } => {
+            Param::Single { field, id, .. } => {
                 quote! { [(String::from(#id), self.#field.as_ptr(), String::new())] }
             }
             Param::Nested(params) => params.param_map_tokens(),
@@ -466,6 +857,138 @@ struct PersistentField {
     field: syn::Ident,
     /// The field's unique key.
     key: syn::LitStr,
+    /// Whether this field uses the `#[persist_bytes = "..."]` attribute, in which case it's stored
+    /// as raw bytes instead of as JSON.
+    bytes: bool,
+    /// The field's previous keys, set using the `#[old_keys(...)]` attribute.
+    old_keys: Vec<syn::LitStr>,
+    /// Value transformations for old states, set using `#[migrate(...)]` attributes.
+    migrations: Vec<Migration>,
+}
+
+/// A `#[migrate(before = "0.2.0", with = "function")]` attribute. The function is applied to the
+/// stored value when loading a state saved with a plugin version older than `before`.
+#[derive(Debug)]
+struct Migration {
+    before: syn::LitStr,
+    with: syn::Path,
+}
+
+impl Migration {
+    fn parse(attr: &syn::Attribute) -> syn::Result<Self> {
+        let usage_error = || {
+            syn::Error::new(
+                attr.span(),
+                "The migrate attribute should be a list in the following format: \
+                 #[migrate(before = \"0.2.0\", with = \"function\")]",
+            )
+        };
+
+        let nested_attrs = match attr.parse_meta() {
+            Ok(syn::Meta::List(syn::MetaList { nested, .. })) => nested,
+            _ => return Err(usage_error()),
+        };
+
+        let mut before = None;
+        let mut with = None;
+        for nested_attr in nested_attrs {
+            match nested_attr {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(s),
+                    ..
+                })) if path.is_ident("before") => {
+                    before = Some(s);
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(s),
+                    ..
+                })) if path.is_ident("with") => {
+                    with = Some(s.parse()?);
+                }
+                _ => return Err(usage_error()),
+            }
+        }
+
+        match (before, with) {
+            (Some(before), Some(with)) => Ok(Migration { before, with }),
+            _ => Err(usage_error()),
+        }
+    }
+}
+
+/// Parse an attribute in the format `#[attr("foo", "bar")]`, as used for the `old_ids` and
+/// `old_keys` attributes.
+fn parse_string_list(attr: &syn::Attribute) -> syn::Result<Vec<syn::LitStr>> {
+    let usage_error = || {
+        syn::Error::new(
+            attr.span(),
+            "This attribute should be a list of strings: #[old_ids(\"foo\", \"bar\")]",
+        )
+    };
+
+    match attr.parse_meta() {
+        Ok(syn::Meta::List(syn::MetaList { nested, .. })) => nested
+            .into_iter()
+            .map(|nested_attr| match nested_attr {
+                syn::NestedMeta::Lit(syn::Lit::Str(s)) => Ok(s),
+                _ => Err(usage_error()),
+            })
+            .collect(),
+        _ => Err(usage_error()),
+    }
+}
+
+/// Make sure none of the old IDs or keys are still in use, and that the same old ID or key isn't
+/// listed twice. `kind` is used in the error message.
+fn check_old_names(current: &[String], old: &[&syn::LitStr], kind: &str) -> syn::Result<()> {
+    for (idx, old_name) in old.iter().enumerate() {
+        let value = old_name.value();
+        if current.contains(&value) {
+            return Err(syn::Error::new(
+                old_name.span(),
+                format!("The old {kind} '{value}' is still in use"),
+            ));
+        }
+        if old[..idx].iter().any(|other| other.value() == value) {
+            return Err(syn::Error::new(
+                old_name.span(),
+                format!("The old {kind} '{value}' is used more than once"),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Generate the code for migrating a parameter's or persistent field's stored value in
+/// `Params::migrate_state()`. `rename_fn` is the function from `nih_plug::params::migrate` used to
+/// rename old IDs or keys, and `map` is the map in the `PluginState` containing the values.
+fn migration_tokens(
+    name: &syn::LitStr,
+    old_names: &[syn::LitStr],
+    migrations: &[Migration],
+    rename_fn: proc_macro2::TokenStream,
+    map: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let rename_tokens = if old_names.is_empty() {
+        quote! {}
+    } else {
+        quote! { #rename_fn(state, #name, &[#(#old_names),*]); }
+    };
+    let migration_tokens = migrations.iter().map(|Migration { before, with }| {
+        quote! {
+            if ::nih_plug::params::migrate::version_older_than(&state.version, #before) {
+                ::nih_plug::params::migrate::map_value(&mut #map, #name, #with);
+            }
+        }
+    });
+
+    quote! {
+        #rename_tokens
+        #(#migration_tokens)*
+    }
 }
 
 /// A field containing another object whose parameters and persistent fields should be added to this
@@ -475,12 +998,14 @@ enum NestedParams {
     /// The nested struct's parameters are taken as is.
     Inline {
         field: syn::Ident,
+        ty: syn::Type,
         group: Option<syn::LitStr>,
     },
     /// The nested struct's parameters will get an ID prefix. The original parameter with ID `foo`
     /// will become `{id_prefix}_foo`.
     Prefixed {
         field: syn::Ident,
+        ty: syn::Type,
         id_prefix: syn::LitStr,
         group: Option<syn::LitStr>,
     },
@@ -489,6 +1014,7 @@ enum NestedParams {
     /// is set then the group will be `{group_name} {array_idx + 1}`.
     Array {
         field: syn::Ident,
+        ty: syn::Type,
         group: Option<syn::LitStr>,
     },
 }
@@ -509,6 +1035,7 @@ impl NestedParams {
             NestedParams::Inline {
                 field,
                 group: Some(group),
+                ..
             } => quote! {
                 self.#field.param_map().into_iter().map(|(param_id, param_ptr, nested_group_name)| {
                     if nested_group_name.is_empty() {
@@ -518,13 +1045,16 @@ impl NestedParams {
                     }
                 })
             },
-            NestedParams::Inline { field, group: None } => quote! {
+            NestedParams::Inline {
+                field, group: None, ..
+            } => quote! {
                 self.#field.param_map()
             },
             NestedParams::Prefixed {
                 field,
                 id_prefix,
                 group: Some(group),
+                ..
             } => quote! {
                 self.#field.param_map().into_iter().map(|(param_id, param_ptr, nested_group_name)| {
                     let param_id = format!("{}_{}", #id_prefix, param_id);
@@ -540,6 +1070,7 @@ impl NestedParams {
                 field,
                 id_prefix,
                 group: None,
+                ..
             } => quote! {
                 self.#field.param_map().into_iter().map(|(param_id, param_ptr, nested_group_name)| {
                     let param_id = format!("{}_{}", #id_prefix, param_id);
@@ -552,6 +1083,7 @@ impl NestedParams {
             NestedParams::Array {
                 field,
                 group: Some(group),
+                ..
             } => quote! {
                 self.#field.iter().enumerate().flat_map(|(idx, params)| {
                     let idx = idx + 1;
@@ -569,7 +1101,9 @@ impl NestedParams {
                     })
                 })
             },
-            NestedParams::Array { field, group: None } => quote! {
+            NestedParams::Array {
+                field, group: None, ..
+            } => quote! {
                 self.#field.iter().enumerate().flat_map(|(idx, params)| {
                     let idx = idx + 1;
 
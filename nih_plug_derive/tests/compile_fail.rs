@@ -0,0 +1,8 @@
+//! The `Params` derive macro's compile time checks. The expected compiler errors are stored next to
+//! the test cases in `tests/compile_fail/`.
+
+#[test]
+fn compile_fail() {
+    let tests = trybuild::TestCases::new();
+    tests.compile_fail("tests/compile_fail/*.rs");
+}
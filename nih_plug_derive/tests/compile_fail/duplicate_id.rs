@@ -0,0 +1,23 @@
+use nih_plug::prelude::*;
+
+#[derive(Params)]
+struct InnerParams {
+    #[id = "gain"]
+    pub gain: FloatParam,
+}
+
+// The nested struct's `foo_gain` parameter clashes with the `gain` field's ID, so the error should
+// point at the `inner` field
+#[derive(Params)]
+struct DuplicateParams {
+    #[id = "foo_gain"]
+    pub gain: FloatParam,
+
+    #[id = "mix"]
+    pub mix: FloatParam,
+
+    #[nested(id_prefix = "foo")]
+    pub inner: InnerParams,
+}
+
+fn main() {}
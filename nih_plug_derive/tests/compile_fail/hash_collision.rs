@@ -0,0 +1,13 @@
+use nih_plug::prelude::*;
+
+// `Aa` and `BB` have the same hash, so the error should point at the `bb` field
+#[derive(Params)]
+struct CollidingParams {
+    #[id = "Aa"]
+    pub aa: FloatParam,
+
+    #[id = "BB"]
+    pub bb: FloatParam,
+}
+
+fn main() {}
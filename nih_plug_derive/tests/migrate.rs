@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use nih_plug::prelude::*;
+
+#[derive(Params)]
+struct RenamedParams {
+    #[id = "gain"]
+    #[old_ids("gn", "old_gain")]
+    #[migrate(before = "0.2.0", with = "double")]
+    pub gain: FloatParam,
+
+    #[persist = "data"]
+    #[old_keys("old_data")]
+    pub data: Mutex<String>,
+}
+
+impl Default for RenamedParams {
+    fn default() -> Self {
+        RenamedParams {
+            gain: FloatParam::new("gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            data: Mutex::new(String::new()),
+        }
+    }
+}
+
+#[derive(Params, Default)]
+struct WrapperParams {
+    #[nested(id_prefix = "foo")]
+    pub inner: RenamedParams,
+
+    #[nested(array)]
+    pub inners: [RenamedParams; 2],
+}
+
+fn double(value: ParamValue) -> ParamValue {
+    match value {
+        ParamValue::F32(value) => ParamValue::F32(value * 2.0),
+        value => value,
+    }
+}
+
+fn state(version: &str, params: &[(&str, f32)], fields: &[(&str, &str)]) -> PluginState {
+    PluginState {
+        version: String::from(version),
+        params: params
+            .iter()
+            .map(|(id, value)| (String::from(*id), ParamValue::F32(*value)))
+            .collect(),
+        fields: fields
+            .iter()
+            .map(|(key, value)| (String::from(*key), String::from(*value)))
+            .collect(),
+        binary_fields: BTreeMap::new(),
+        midi_mappings: BTreeMap::new(),
+        state_slots: None,
+    }
+}
+
+fn param_value(state: &PluginState, id: &str) -> f32 {
+    match state.params[id] {
+        ParamValue::F32(value) => value,
+        _ => panic!("Unexpected value for '{id}'"),
+    }
+}
+
+mod migrate {
+    use super::*;
+
+    #[test]
+    fn rename() {
+        let params = RenamedParams::default();
+        let mut state = state("0.2.0", &[("old_gain", 0.25)], &[("old_data", "\"foo\"")]);
+        params.migrate_state(&mut state);
+
+        assert_eq!(state.params.len(), 1);
+        assert_eq!(param_value(&state, "gain"), 0.25);
+        assert_eq!(state.fields.len(), 1);
+        assert_eq!(state.fields["data"], "\"foo\"");
+    }
+
+    #[test]
+    fn transform_old_version() {
+        let params = RenamedParams::default();
+        let mut state = state("0.1.5", &[("gn", 0.25)], &[]);
+        params.migrate_state(&mut state);
+
+        assert_eq!(param_value(&state, "gain"), 0.5);
+    }
+
+    #[test]
+    fn transform_pre_release() {
+        let params = RenamedParams::default();
+        let mut state = state("0.2.0-beta", &[("gn", 0.25)], &[]);
+        params.migrate_state(&mut state);
+
+        assert_eq!(param_value(&state, "gain"), 0.5);
+    }
+
+    #[test]
+    fn nested() {
+        let params = WrapperParams::default();
+        let mut state = state(
+            "0.1.0",
+            &[("foo_gn", 0.1), ("gn_1", 0.2), ("gain_2", 0.3)],
+            &[("old_data_2", "\"bar\"")],
+        );
+        params.migrate_state(&mut state);
+
+        assert_eq!(state.params.len(), 3);
+        assert_eq!(param_value(&state, "foo_gain"), 0.2);
+        assert_eq!(param_value(&state, "gain_1"), 0.4);
+        assert_eq!(param_value(&state, "gain_2"), 0.6);
+        assert_eq!(state.fields["data_2"], "\"bar\"");
+    }
+}
@@ -316,3 +316,21 @@ mod param_groups {
         );
     }
 }
+
+mod static_param_ids {
+    use super::*;
+    use nih_plug::params::internals::static_ids::StaticParamIds;
+
+    #[test]
+    fn nested() {
+        // The compile time ID checks should see the same parameters as `.param_map()`
+        assert_eq!(
+            <GroupedGroupedParams as StaticParamIds>::PARAM_IDS.len(),
+            GroupedGroupedParams::default().param_map().len()
+        );
+        assert_eq!(
+            <NestedArrayParams as StaticParamIds>::PARAM_IDS.len(),
+            NestedArrayParams::default().param_map().len()
+        );
+    }
+}
@@ -40,6 +40,21 @@ unsafe impl Params for InnerParams {
     }
 }
 
+#[derive(Params, Default)]
+struct BinaryParams {
+    #[persist = "name"]
+    pub name: Mutex<String>,
+
+    #[persist_bytes = "samples"]
+    pub samples: Mutex<Vec<u8>>,
+}
+
+#[derive(Params, Default)]
+struct BinaryWrapperParams {
+    #[nested(id_prefix = "foo")]
+    pub inner: BinaryParams,
+}
+
 mod persist {
     mod nested_prefix {
 
@@ -135,4 +150,33 @@ mod persist {
             }
         }
     }
+    mod binary {
+        use super::super::*;
+
+        #[test]
+        fn serialize() {
+            let params = BinaryWrapperParams::default();
+            *params.inner.name.lock().unwrap() = String::from("foo");
+            *params.inner.samples.lock().unwrap() = vec![1, 2, 3];
+
+            // The raw bytes are kept separate from the JSON fields
+            let serialized = params.serialize_fields();
+            assert_eq!(serialized.len(), 1);
+            assert_eq!(serialized["foo_name"], "\"foo\"");
+
+            let serialized = params.serialize_binary_fields();
+            assert_eq!(serialized.len(), 1);
+            assert_eq!(serialized["foo_samples"], [1, 2, 3]);
+        }
+
+        #[test]
+        fn deserialize() {
+            let mut serialized = BTreeMap::new();
+            serialized.insert(String::from("foo_samples"), vec![4, 5]);
+
+            let params = BinaryWrapperParams::default();
+            params.deserialize_binary_fields(&serialized);
+            assert_eq!(*params.inner.samples.lock().unwrap(), [4, 5]);
+        }
+    }
 }
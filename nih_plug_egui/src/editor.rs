@@ -3,7 +3,7 @@
 use baseview::gl::GlConfig;
 use baseview::{Size, WindowHandle, WindowOpenOptions, WindowScalePolicy};
 use crossbeam::atomic::AtomicCell;
-use egui::Context;
+use egui::{Context, Key, Modifiers};
 use egui_baseview::EguiWindow;
 use nih_plug::prelude::{Editor, GuiContext, ParamSetter, ParentWindowHandle};
 use parking_lot::RwLock;
@@ -108,6 +108,7 @@ where
                 // this we would also have a blank GUI when it gets first opened because most DAWs open
                 // their GUI while the window is still unmapped.
                 egui_ctx.request_repaint();
+                handle_undo_shortcuts(egui_ctx, context.as_ref());
                 (update)(egui_ctx, &setter, &mut state.write());
             },
         );
@@ -164,3 +165,30 @@ impl Drop for EguiEditorHandle {
         self.window.close();
     }
 }
+
+/// Undo or redo the last change made from the editor when Ctrl+Z, Ctrl+Shift+Z, or Ctrl+Y (or Cmd
+/// on macOS) is pressed. This is skipped while a widget has keyboard focus so text fields can still
+/// use these shortcuts for their own undo history.
+fn handle_undo_shortcuts(egui_ctx: &Context, context: &dyn GuiContext) {
+    if egui_ctx.memory(|mem| mem.focus().is_some()) {
+        return;
+    }
+
+    let (undo, redo) = egui_ctx.input_mut(|input| {
+        let redo = input.consume_key(
+            Modifiers {
+                shift: true,
+                ..Modifiers::COMMAND
+            },
+            Key::Z,
+        ) || input.consume_key(Modifiers::COMMAND, Key::Y);
+        let undo = input.consume_key(Modifiers::COMMAND, Key::Z);
+
+        (undo, redo)
+    });
+    if undo {
+        context.undo();
+    } else if redo {
+        context.redo();
+    }
+}
@@ -7,6 +7,10 @@
 
 pub mod generic_ui;
 mod param_slider;
+mod state_slot_switcher;
+mod text_param_edit;
 pub mod util;
 
 pub use param_slider::ParamSlider;
+pub use state_slot_switcher::StateSlotSwitcher;
+pub use text_param_edit::TextParamEdit;
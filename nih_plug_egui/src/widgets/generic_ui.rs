@@ -56,7 +56,7 @@ pub fn create(
                     ui.allocate_space(padding);
                 }
 
-                ui.label(unsafe { param_ptr.name() });
+                ui.label(unsafe { param_ptr.name() }.as_str());
                 unsafe { widget.add_widget_raw(ui, &param_ptr, setter) };
 
                 first_widget = false;
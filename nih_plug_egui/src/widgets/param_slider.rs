@@ -19,7 +19,8 @@ lazy_static! {
 
 /// A slider widget similar to [`egui::widgets::Slider`] that knows about NIH-plug parameters ranges
 /// and can get values for it. The slider supports double click and control click to reset,
-/// shift+drag for granular dragging, text value entry by clicking on the value text.
+/// shift+drag for granular dragging, text value entry by clicking on the value text. Middle
+/// clicking arms MIDI learn for the parameter, and shift+middle click removes its MIDI mapping.
 ///
 /// TODO: Vertical orientation
 /// TODO: Check below for more input methods that should be added
@@ -182,7 +183,18 @@ impl<'a, P: Param> ParamSlider<'a, P> {
             self.begin_drag();
             Self::set_drag_amount_memory(ui, 0.0);
         }
-        if let Some(click_pos) = response.interact_pointer_pos() {
+        let middle_clicked = response.clicked_by(egui::PointerButton::Middle)
+            || ui.input(|i| i.pointer.middle_down());
+        if response.clicked_by(egui::PointerButton::Middle) {
+            if ui.input(|i| i.modifiers.shift) {
+                self.setter.clear_midi_mapping(self.param);
+            } else if self.setter.is_midi_learn_armed(self.param) {
+                self.setter.cancel_midi_learn();
+            } else {
+                self.setter.arm_midi_learn(self.param);
+            }
+        }
+        if let Some(click_pos) = response.interact_pointer_pos().filter(|_| !middle_clicked) {
             if ui.input(|i| i.modifiers.command) {
                 // Like double clicking, Ctrl+Click should reset the parameter
                 self.reset_param();
@@ -232,11 +244,13 @@ impl<'a, P: Param> ParamSlider<'a, P> {
                 ui.painter().rect_filled(filled_rect, 0.0, filled_bg);
             }
 
-            ui.painter().rect_stroke(
-                response.rect,
-                0.0,
-                Stroke::new(1.0, ui.visuals().widgets.active.bg_fill),
-            );
+            // The border is highlighted while MIDI learn is waiting for a CC for this parameter
+            let stroke = if self.setter.is_midi_learn_armed(self.param) {
+                ui.visuals().selection.stroke
+            } else {
+                Stroke::new(1.0, ui.visuals().widgets.active.bg_fill)
+            };
+            ui.painter().rect_stroke(response.rect, 0.0, stroke);
         }
     }
 
@@ -0,0 +1,53 @@
+use egui::{Response, Ui, Widget};
+use nih_plug::prelude::ParamSetter;
+
+/// A row of buttons for switching between the plugin's state slots, along with a menu for copying
+/// the active slot to another slot. With the default two slots this is a classic A/B switch. See
+/// [`StateSlots`][nih_plug::prelude::StateSlots] for more information.
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct StateSlotSwitcher<'a> {
+    setter: &'a ParamSetter<'a>,
+}
+
+impl<'a> StateSlotSwitcher<'a> {
+    /// Create a new state slot switcher. The [`ParamSetter`] is used to access the plugin's state
+    /// slots.
+    pub fn new(setter: &'a ParamSetter<'a>) -> Self {
+        Self { setter }
+    }
+}
+
+impl Widget for StateSlotSwitcher<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let context = self.setter.raw_context;
+        let state_slots = context.state_slots();
+        let num_slots = state_slots.num_slots();
+        let active = state_slots.active();
+
+        ui.horizontal(|ui| {
+            for slot in 0..num_slots {
+                if ui
+                    .selectable_label(slot == active, state_slots.slot_name(slot))
+                    .clicked()
+                {
+                    context.switch_state_slot(slot);
+                }
+            }
+
+            ui.menu_button("Copy", |ui| {
+                for slot in (0..num_slots).filter(|slot| *slot != active) {
+                    let label = format!(
+                        "{} \u{2192} {}",
+                        state_slots.slot_name(active),
+                        state_slots.slot_name(slot)
+                    );
+                    if ui.button(label).clicked() {
+                        context.copy_state_slot(active, slot);
+                        ui.close_menu();
+                    }
+                }
+            });
+        })
+        .response
+    }
+}
@@ -0,0 +1,94 @@
+use egui::{Response, TextEdit, Ui, Widget};
+use nih_plug::prelude::TextParam;
+
+/// A text input bound to a [`StringParam`][nih_plug::prelude::StringParam] or a
+/// [`PathParam`][nih_plug::prelude::PathParam]. While the text field has keyboard focus the edits
+/// are kept in a separate buffer, and the parameter is only updated once the field loses focus or
+/// when Enter is pressed in a single line field. This avoids running the parameter's callback on
+/// every keystroke.
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct TextParamEdit<'a, P: TextParam> {
+    param: &'a P,
+
+    multiline: bool,
+    code_editor: bool,
+    desired_width: Option<f32>,
+    desired_rows: Option<usize>,
+}
+
+impl<'a, P: TextParam> TextParamEdit<'a, P> {
+    /// Create a new single line text input for a parameter. Use the other methods to modify the
+    /// widget before passing it to [`Ui::add()`].
+    pub fn for_param(param: &'a P) -> Self {
+        Self {
+            param,
+
+            multiline: false,
+            code_editor: false,
+            desired_width: None,
+            desired_rows: None,
+        }
+    }
+
+    /// Use a multiline text input instead. The value is then only committed when the text field
+    /// loses focus.
+    pub fn multiline(mut self) -> Self {
+        self.multiline = true;
+        self
+    }
+
+    /// Use a monospace font and allow entering tabs, for editing code.
+    pub fn code_editor(mut self) -> Self {
+        self.code_editor = true;
+        self
+    }
+
+    /// Set a custom width for the text input.
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.desired_width = Some(width);
+        self
+    }
+
+    /// Set the number of rows a multiline text input should occupy.
+    pub fn with_rows(mut self, rows: usize) -> Self {
+        self.desired_rows = Some(rows);
+        self
+    }
+}
+
+impl<P: TextParam> Widget for TextParamEdit<'_, P> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        // The edit buffer is stored under the text field's ID. This is stable between frames as
+        // long as the layout doesn't change.
+        let id = ui.auto_id_with(self.param.name());
+        let mut text = ui
+            .memory_mut(|mem| mem.data.get_temp::<String>(id))
+            .unwrap_or_else(|| self.param.text());
+
+        let mut text_edit = if self.multiline {
+            TextEdit::multiline(&mut text)
+        } else {
+            TextEdit::singleline(&mut text)
+        }
+        .id(id);
+        if self.code_editor {
+            text_edit = text_edit.code_editor();
+        }
+        if let Some(width) = self.desired_width {
+            text_edit = text_edit.desired_width(width);
+        }
+        if let Some(rows) = self.desired_rows {
+            text_edit = text_edit.desired_rows(rows);
+        }
+
+        let response = ui.add(text_edit);
+        if response.has_focus() {
+            ui.memory_mut(|mem| mem.data.insert_temp(id, text));
+        } else if response.lost_focus() {
+            ui.memory_mut(|mem| mem.data.remove::<String>(id));
+            self.param.set_text(&text);
+        }
+
+        response.on_hover_text(self.param.name())
+    }
+}
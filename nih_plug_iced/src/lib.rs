@@ -230,6 +230,10 @@ pub trait IcedEditor: 'static + Send + Sync + Sized {
                 context.raw_set_parameter_normalized(p, v)
             },
             ParamMessage::EndSetParameter(p) => unsafe { context.raw_end_set_parameter(p) },
+            ParamMessage::ArmMidiLearn(p) => context.midi_learn().arm(p),
+            ParamMessage::ClearMidiMapping(p) => context.midi_learn().set_mapping(p, None),
+            ParamMessage::SwitchStateSlot(slot) => context.switch_state_slot(slot),
+            ParamMessage::CopyStateSlot(from, to) => context.copy_state_slot(from, to),
         }
     }
 }
@@ -10,10 +10,14 @@ use nih_plug::prelude::ParamPtr;
 pub mod generic_ui;
 pub mod param_slider;
 pub mod peak_meter;
+pub mod state_slot_switcher;
+pub mod text_param_input;
 pub mod util;
 
 pub use param_slider::ParamSlider;
 pub use peak_meter::PeakMeter;
+pub use state_slot_switcher::StateSlotSwitcher;
+pub use text_param_input::TextParamInput;
 
 /// A message to update a parameter value. Since NIH-plug manages the parameters, interacting with
 /// parameter values with iced works a little different from updating any other state. This main
@@ -29,4 +33,14 @@ pub enum ParamMessage {
     SetParameterNormalized(ParamPtr, f32),
     /// End an automation gesture for a parameter.
     EndSetParameter(ParamPtr),
+    /// Map the next MIDI CC the plugin receives to this parameter.
+    ArmMidiLearn(ParamPtr),
+    /// Remove the parameter's MIDI CC mapping, if it has one.
+    ClearMidiMapping(ParamPtr),
+    /// Switch to another state slot. See
+    /// [`GuiContext::switch_state_slot()`][nih_plug::prelude::GuiContext::switch_state_slot()].
+    SwitchStateSlot(usize),
+    /// Copy the first state slot to the second state slot. See
+    /// [`GuiContext::copy_state_slot()`][nih_plug::prelude::GuiContext::copy_state_slot()].
+    CopyStateSlot(usize, usize),
 }
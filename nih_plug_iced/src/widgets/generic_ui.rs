@@ -188,7 +188,7 @@ where
                 .align_items(Alignment::Center)
                 .spacing(spacing * 2)
                 .push(
-                    Text::new(unsafe { param_ptr.name() })
+                    Text::new(unsafe { param_ptr.name() }.as_str())
                         .height(20.into())
                         .width(Length::Fill)
                         .horizontal_alignment(alignment::Horizontal::Right)
@@ -346,6 +346,19 @@ impl<'a, P: Param> Widget<ParamMessage, Renderer> for ParamSlider<'a, P> {
                     return event::Status::Captured;
                 }
             }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Middle)) => {
+                if bounds.contains(cursor_position) {
+                    // Middle clicking arms MIDI learn, and Shift+Middle click removes the
+                    // parameter's existing MIDI mapping
+                    if self.state.keyboard_modifiers.shift() {
+                        shell.publish(ParamMessage::ClearMidiMapping(self.param.as_ptr()));
+                    } else {
+                        shell.publish(ParamMessage::ArmMidiLearn(self.param.as_ptr()));
+                    }
+
+                    return event::Status::Captured;
+                }
+            }
             Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
             | Event::Touch(touch::Event::FingerLifted { .. } | touch::Event::FingerLost { .. }) => {
                 if self.state.drag_active {
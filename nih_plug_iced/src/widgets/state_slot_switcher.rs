@@ -0,0 +1,136 @@
+//! Buttons for switching between and copying the plugin's state slots.
+
+use nih_plug::prelude::StateSlots;
+
+use crate::{button, Background, Button, Color, Element, Length, Row, Text, Vector};
+
+use super::ParamMessage;
+
+/// A row of buttons for switching between the plugin's state slots, followed by a button that
+/// copies the active slot to the next slot. With the default two slots this is a classic A/B
+/// switch. See [`StateSlots`] for more information. The plugin's state slots can be accessed
+/// through the [`GuiContext`][nih_plug::prelude::GuiContext] returned by
+/// [`IcedEditor::context()`][crate::IcedEditor::context()].
+///
+/// The buttons emit [`ParamMessage::SwitchStateSlot`] and [`ParamMessage::CopyStateSlot`] messages,
+/// so those need to be passed to
+/// [`IcedEditor::handle_param_message()`][crate::IcedEditor::handle_param_message()].
+pub struct StateSlotSwitcher<'a> {
+    state: &'a mut State,
+
+    state_slots: &'a StateSlots,
+
+    spacing: u16,
+    padding: u16,
+    text_size: Option<u16>,
+}
+
+/// State for a [`StateSlotSwitcher`].
+#[derive(Debug, Default)]
+pub struct State {
+    slot_buttons: Vec<button::State>,
+    copy_button: button::State,
+}
+
+/// The style for the active slot's button.
+struct ActiveSlotStyle;
+
+impl button::StyleSheet for ActiveSlotStyle {
+    fn active(&self) -> button::Style {
+        button::Style {
+            shadow_offset: Vector::new(0.0, 0.0),
+            background: Some(Background::Color(Color::from_rgb(0.35, 0.35, 0.35))),
+            border_radius: 2.0,
+            border_width: 1.0,
+            border_color: Color::BLACK,
+            text_color: Color::WHITE,
+        }
+    }
+}
+
+impl<'a> StateSlotSwitcher<'a> {
+    /// Creates a new [`StateSlotSwitcher`] for the plugin's state slots.
+    pub fn new(state: &'a mut State, state_slots: &'a StateSlots) -> Self {
+        state
+            .slot_buttons
+            .resize_with(state_slots.num_slots(), Default::default);
+
+        Self {
+            state,
+
+            state_slots,
+
+            spacing: 5,
+            padding: 5,
+            text_size: None,
+        }
+    }
+
+    /// Sets the spacing between the buttons.
+    pub fn spacing(mut self, spacing: u16) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Sets the padding of the buttons.
+    pub fn padding(mut self, padding: u16) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the text size of the buttons.
+    pub fn text_size(mut self, size: u16) -> Self {
+        self.text_size = Some(size);
+        self
+    }
+
+    /// Convert this [`StateSlotSwitcher`] into an [`Element`] with the correct message. See
+    /// [`ParamSlider::map()`][super::ParamSlider::map()].
+    pub fn map<Message, F>(self, f: F) -> Element<'a, Message>
+    where
+        Message: 'static,
+        F: Fn(ParamMessage) -> Message + 'static,
+    {
+        Element::from(self).map(f)
+    }
+}
+
+impl<'a> From<StateSlotSwitcher<'a>> for Element<'a, ParamMessage> {
+    fn from(widget: StateSlotSwitcher<'a>) -> Self {
+        let state_slots = widget.state_slots;
+        let active = state_slots.active();
+        let text = |label: String| match widget.text_size {
+            Some(size) => Text::new(label).size(size),
+            None => Text::new(label),
+        };
+
+        let mut row = Row::new().spacing(widget.spacing).width(Length::Shrink);
+        for (slot, button_state) in widget.state.slot_buttons.iter_mut().enumerate() {
+            let button = Button::new(button_state, text(state_slots.slot_name(slot)))
+                .padding(widget.padding)
+                .on_press(ParamMessage::SwitchStateSlot(slot));
+            row = if slot == active {
+                row.push(button.style(ActiveSlotStyle))
+            } else {
+                row.push(button)
+            };
+        }
+
+        let num_slots = state_slots.num_slots();
+        if num_slots > 1 {
+            let target = (active + 1) % num_slots;
+            let label = format!(
+                "{} \u{2192} {}",
+                state_slots.slot_name(active),
+                state_slots.slot_name(target)
+            );
+            row = row.push(
+                Button::new(&mut widget.state.copy_button, text(label))
+                    .padding(widget.padding)
+                    .on_press(ParamMessage::CopyStateSlot(active, target)),
+            );
+        }
+
+        row.into()
+    }
+}
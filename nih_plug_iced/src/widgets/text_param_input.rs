@@ -0,0 +1,235 @@
+//! A text input that integrates with NIH-plug's [`TextParam`] types.
+
+use atomic_refcell::AtomicRefCell;
+use nih_plug::prelude::TextParam;
+
+use crate::backend::widget;
+use crate::backend::Renderer;
+use crate::text::Renderer as TextRenderer;
+use crate::{
+    event, layout, mouse, renderer, Clipboard, Element, Event, Font, Layout, Length, Point,
+    Rectangle, Shell, TextInput, Widget,
+};
+
+use super::ParamMessage;
+
+/// A text input bound to a [`StringParam`][nih_plug::prelude::StringParam] or a
+/// [`PathParam`][nih_plug::prelude::PathParam]. Edits are kept in the widget's [`State`] and are
+/// only written to the parameter when Enter is pressed or when the text input loses focus. Since
+/// these parameters are not automatable, this widget never publishes any [`ParamMessage`]s.
+pub struct TextParamInput<'a, P: TextParam> {
+    state: &'a mut State,
+
+    param: &'a P,
+
+    width: Length,
+    padding: u16,
+    text_size: Option<u16>,
+    font: Font,
+}
+
+/// State for a [`TextParamInput`].
+#[derive(Debug, Default)]
+pub struct State {
+    /// State for the wrapped text input widget.
+    text_input_state: AtomicRefCell<widget::text_input::State>,
+    /// The text that's currently being edited. If this is `None` then the parameter's current value
+    /// is shown.
+    editing_value: Option<String>,
+}
+
+/// An internal message for handling output from the wrapped [`TextInput`] widget.
+#[derive(Debug, Clone)]
+enum TextInputMessage {
+    /// A new value was entered in the text input.
+    Value(String),
+    /// Enter was pressed.
+    Submit,
+}
+
+impl<'a, P: TextParam> TextParamInput<'a, P> {
+    /// Creates a new [`TextParamInput`] for the given parameter.
+    pub fn new(state: &'a mut State, param: &'a P) -> Self {
+        Self {
+            state,
+
+            param,
+
+            width: Length::Fill,
+            padding: 5,
+            text_size: None,
+            font: <Renderer as TextRenderer>::Font::default(),
+        }
+    }
+
+    /// Sets the width of the [`TextParamInput`].
+    pub fn width(mut self, width: Length) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets the padding of the [`TextParamInput`].
+    pub fn padding(mut self, padding: u16) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the text size of the [`TextParamInput`].
+    pub fn text_size(mut self, size: u16) -> Self {
+        self.text_size = Some(size);
+        self
+    }
+
+    /// Sets the font of the [`TextParamInput`].
+    pub fn font(mut self, font: Font) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Create a temporary [`TextInput`] showing either the value that's currently being edited or
+    /// the parameter's current value, and do something with it.
+    fn with_text_input<T, F>(&self, f: F) -> T
+    where
+        F: FnOnce(TextInput<'_, TextInputMessage>) -> T,
+    {
+        let current_value = match &self.state.editing_value {
+            Some(value) => value.clone(),
+            None => self.param.text(),
+        };
+
+        let mut text_input_state = self.state.text_input_state.borrow_mut();
+        let mut text_input = TextInput::new(
+            &mut text_input_state,
+            self.param.name(),
+            &current_value,
+            TextInputMessage::Value,
+        )
+        .font(self.font)
+        .width(self.width)
+        .padding(self.padding)
+        .on_submit(TextInputMessage::Submit);
+        if let Some(text_size) = self.text_size {
+            text_input = text_input.size(text_size);
+        }
+
+        f(text_input)
+    }
+
+    /// Write the edited value to the parameter and show the parameter's value again.
+    fn commit(&mut self) {
+        if let Some(value) = self.state.editing_value.take() {
+            self.param.set_text(&value);
+        }
+    }
+}
+
+impl<'a, P: TextParam> Widget<ParamMessage, Renderer> for TextParamInput<'a, P> {
+    fn width(&self) -> Length {
+        self.width
+    }
+
+    fn height(&self) -> Length {
+        Length::Shrink
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.with_text_input(|text_input| {
+            Widget::<TextInputMessage, Renderer>::layout(&text_input, renderer, limits)
+        })
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        _shell: &mut Shell<'_, ParamMessage>,
+    ) -> event::Status {
+        // Like in `ParamSlider`, we'll intercept the text input's messages using our own `Shell`
+        let mut messages = Vec::new();
+        let mut text_input_shell = Shell::new(&mut messages);
+        let status = self.with_text_input(|mut text_input| {
+            text_input.on_event(
+                event,
+                layout,
+                cursor_position,
+                renderer,
+                clipboard,
+                &mut text_input_shell,
+            )
+        });
+
+        for message in messages {
+            match message {
+                TextInputMessage::Value(s) => self.state.editing_value = Some(s),
+                TextInputMessage::Submit => self.commit(),
+            }
+        }
+
+        // Clicking outside of the text input or pressing escape unfocuses it, which also counts as
+        // a submit
+        if !self.state.text_input_state.borrow().is_focused() {
+            self.commit();
+        }
+
+        status
+    }
+
+    fn mouse_interaction(
+        &self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.with_text_input(|text_input| {
+            Widget::<TextInputMessage, Renderer>::mouse_interaction(
+                &text_input,
+                layout,
+                cursor_position,
+                viewport,
+                renderer,
+            )
+        })
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) {
+        self.with_text_input(|text_input| {
+            Widget::<TextInputMessage, Renderer>::draw(
+                &text_input,
+                renderer,
+                style,
+                layout,
+                cursor_position,
+                viewport,
+            )
+        })
+    }
+}
+
+impl<'a, P: TextParam> TextParamInput<'a, P> {
+    /// Convert this [`TextParamInput`] into an [`Element`] with the correct message. See
+    /// [`ParamSlider::map()`][super::ParamSlider::map()].
+    pub fn map<Message, F>(self, f: F) -> Element<'a, Message>
+    where
+        Message: 'static,
+        F: Fn(ParamMessage) -> Message + 'static,
+    {
+        Element::from(self).map(f)
+    }
+}
+
+impl<'a, P: TextParam> From<TextParamInput<'a, P>> for Element<'a, ParamMessage> {
+    fn from(widget: TextParamInput<'a, P>) -> Self {
+        Element::new(widget)
+    }
+}
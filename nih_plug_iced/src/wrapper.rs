@@ -7,14 +7,16 @@ use std::sync::Arc;
 
 use crate::futures::FutureExt;
 use crate::{
-    futures, subscription, Application, Color, Command, Element, IcedEditor, ParameterUpdate,
-    Subscription, WindowQueue, WindowScalePolicy, WindowSubs,
+    event, futures, keyboard, subscription, Application, Color, Command, Element, Event,
+    IcedEditor, ParameterUpdate, Subscription, WindowQueue, WindowScalePolicy, WindowSubs,
 };
 
 /// Wraps an `iced_baseview` [`Application`] around [`IcedEditor`]. Needed to allow editors to
 /// always receive a copy of the GUI context.
 pub(crate) struct IcedEditorWrapperApplication<E: IcedEditor> {
     editor: E,
+    /// Used to undo and redo changes when the matching keyboard shortcuts are pressed.
+    context: Arc<dyn GuiContext>,
 
     /// We will receive notifications about parameters being changed on here. Whenever a parameter
     /// update gets sent, we will trigger a [`Message::parameterUpdate`] which causes the UI to be
@@ -28,6 +30,10 @@ pub(crate) struct IcedEditorWrapperApplication<E: IcedEditor> {
 pub enum Message<E: IcedEditor> {
     EditorMessage(E::Message),
     ParameterUpdate,
+    /// Ctrl+Z (or Cmd+Z on macOS) was pressed and not handled by any widget.
+    Undo,
+    /// Ctrl+Shift+Z or Ctrl+Y (or Cmd on macOS) was pressed and not handled by any widget.
+    Redo,
 }
 
 impl<E: IcedEditor> std::fmt::Debug for Message<E> {
@@ -35,6 +41,8 @@ impl<E: IcedEditor> std::fmt::Debug for Message<E> {
         match self {
             Self::EditorMessage(arg0) => f.debug_tuple("EditorMessage").field(arg0).finish(),
             Self::ParameterUpdate => write!(f, "ParameterUpdate"),
+            Self::Undo => write!(f, "Undo"),
+            Self::Redo => write!(f, "Redo"),
         }
     }
 }
@@ -44,6 +52,8 @@ impl<E: IcedEditor> Clone for Message<E> {
         match self {
             Self::EditorMessage(arg0) => Self::EditorMessage(arg0.clone()),
             Self::ParameterUpdate => Self::ParameterUpdate,
+            Self::Undo => Self::Undo,
+            Self::Redo => Self::Redo,
         }
     }
 }
@@ -60,11 +70,12 @@ impl<E: IcedEditor> Application for IcedEditorWrapperApplication<E> {
     fn new(
         (context, parameter_updates_receiver, flags): Self::Flags,
     ) -> (Self, Command<Self::Message>) {
-        let (editor, command) = E::new(flags, context);
+        let (editor, command) = E::new(flags, context.clone());
 
         (
             Self {
                 editor,
+                context,
                 parameter_updates_receiver,
             },
             command.map(Message::EditorMessage),
@@ -84,6 +95,14 @@ impl<E: IcedEditor> Application for IcedEditorWrapperApplication<E> {
                 .map(Message::EditorMessage),
             // This message only exists to force a redraw
             Message::ParameterUpdate => Command::none(),
+            Message::Undo => {
+                self.context.undo();
+                Command::none()
+            }
+            Message::Redo => {
+                self.context.redo();
+                Command::none()
+            }
         }
     }
 
@@ -121,6 +140,29 @@ impl<E: IcedEditor> Application for IcedEditorWrapperApplication<E> {
                     Err(_) => futures::future::pending().boxed(),
                 },
             ),
+            // Keyboard shortcuts for undoing and redoing changes. Focused text inputs capture these
+            // events, so they won't trigger while entering text.
+            subscription::events_with(|event, status| match (event, status) {
+                (
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key_code: keyboard::KeyCode::Z,
+                        modifiers,
+                    }),
+                    event::Status::Ignored,
+                ) if modifiers.command() => Some(if modifiers.shift() {
+                    Message::Redo
+                } else {
+                    Message::Undo
+                }),
+                (
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key_code: keyboard::KeyCode::Y,
+                        modifiers,
+                    }),
+                    event::Status::Ignored,
+                ) if modifiers.command() => Some(Message::Redo),
+                _ => None,
+            }),
             self.editor
                 .subscription(&mut editor_window_subs)
                 .map(Message::EditorMessage),
@@ -63,6 +63,7 @@ impl Editor for ViziaEditor {
             // handle them automatically.
             widgets::ParamModel {
                 context: context.clone(),
+                midi_learn_armed_param: None,
             }
             .build(cx);
 
@@ -76,6 +77,7 @@ impl Editor for ViziaEditor {
                     current_inner_window_size.width,
                     current_inner_window_size.height,
                 )),
+                active_state_slot: context.state_slots().active(),
             }
             .build(cx);
 
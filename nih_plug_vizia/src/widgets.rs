@@ -19,6 +19,8 @@ mod param_button;
 mod param_slider;
 mod peak_meter;
 mod resize_handle;
+mod state_slot_switcher;
+mod text_param_input;
 pub mod util;
 
 pub use generic_ui::GenericUi;
@@ -26,6 +28,8 @@ pub use param_button::{ParamButton, ParamButtonExt};
 pub use param_slider::{ParamSlider, ParamSliderExt, ParamSliderStyle};
 pub use peak_meter::PeakMeter;
 pub use resize_handle::ResizeHandle;
+pub use state_slot_switcher::StateSlotSwitcher;
+pub use text_param_input::TextParamInput;
 
 /// Register the default theme for the widgets exported by this module. This is automatically called
 /// for you when using [`create_vizia_editor()`][super::create_vizia_editor()].
@@ -53,6 +57,10 @@ pub enum ParamEvent<'a, P: Param> {
     SetParameterNormalized(&'a P, f32),
     /// End an automation gesture for a parameter.
     EndSetParameter(&'a P),
+    /// Map the next MIDI CC the plugin receives to this parameter.
+    ArmMidiLearn(&'a P),
+    /// Remove the parameter's MIDI CC mapping, if it has one.
+    ClearMidiMapping(&'a P),
 }
 
 /// The same as [`ParamEvent`], but type erased. Use `ParamEvent` as an easier way to construct
@@ -66,12 +74,23 @@ pub enum RawParamEvent {
     SetParameterNormalized(ParamPtr, f32),
     /// End an automation gesture for a parameter.
     EndSetParameter(ParamPtr),
+    /// Map the next MIDI CC the plugin receives to this parameter.
+    ArmMidiLearn(ParamPtr),
+    /// Cancel MIDI learn if it is armed for any parameter.
+    CancelMidiLearn,
+    /// Remove the parameter's MIDI CC mapping, if it has one.
+    ClearMidiMapping(ParamPtr),
+    /// Sent whenever MIDI learn gets armed or disarmed, including when a CC has been learned. This
+    /// contains the parameter MIDI learn is armed for, if any. Widgets can use this to indicate
+    /// that they are waiting for a MIDI CC.
+    MidiLearnChanged(Option<ParamPtr>),
     /// Sent by the wrapper to indicate that one or more parameter values have changed. Useful when
     /// using properties based on a parameter's value that are computed inside of an event handler.
     ParametersChanged,
 }
 
-/// Events that directly interact with the [`GuiContext`]. Used to trigger resizes.
+/// Events that directly interact with the [`GuiContext`]. Used to trigger resizes, to undo or redo
+/// changes, and to switch between state slots.
 pub enum GuiContextEvent {
     /// Resize the window to match the current size reported by the [`ViziaState`]'s size function.
     /// By changing the plugin's state that is used to determine the window's size before emitting
@@ -106,12 +125,26 @@ pub enum GuiContextEvent {
     /// }
     /// ```
     Resize,
+    /// Undo the last parameter change or state restore using [`GuiContext::undo()`]. This is also
+    /// sent when pressing Ctrl+Z, or Cmd+Z on macOS.
+    Undo,
+    /// Redo the last undone change using [`GuiContext::redo()`]. This is also sent when pressing
+    /// Ctrl+Shift+Z or Ctrl+Y, or Cmd+Shift+Z on macOS.
+    Redo,
+    /// Switch to another state slot using [`GuiContext::switch_state_slot()`].
+    SwitchStateSlot(usize),
+    /// Copy the first state slot to the second state slot using
+    /// [`GuiContext::copy_state_slot()`].
+    CopyStateSlot(usize, usize),
 }
 
 /// Handles parameter updates for VIZIA GUIs. Registered in
 /// [`ViziaEditor::spawn()`][super::ViziaEditor::spawn()].
 pub(crate) struct ParamModel {
     pub context: Arc<dyn GuiContext>,
+    /// The parameter MIDI learn was armed for the last time we checked. Used to send
+    /// [`RawParamEvent::MidiLearnChanged`] events when this changes.
+    pub midi_learn_armed_param: Option<ParamPtr>,
 }
 
 /// Handles interactions through `WindowEvent` for VIZIA GUIs by updating the `ViziaState`.
@@ -124,10 +157,13 @@ pub(crate) struct WindowModel {
     /// The last known unscaled logical window size. Used to prevent sending duplicate resize
     /// requests.
     pub last_inner_window_size: AtomicCell<(u32, u32)>,
+    /// The index of the active state slot. This is updated whenever the state slots or the
+    /// parameter values change.
+    pub active_state_slot: usize,
 }
 
 impl Model for ParamModel {
-    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
         // `ParamEvent` gets downcast into `NormalizedParamEvent` by the `Message`
         // implementation below
         event.map(|param_event, _| match *param_event {
@@ -138,9 +174,24 @@ impl Model for ParamModel {
                 self.context.raw_set_parameter_normalized(p, v)
             },
             RawParamEvent::EndSetParameter(p) => unsafe { self.context.raw_end_set_parameter(p) },
+            RawParamEvent::ArmMidiLearn(p) => self.context.midi_learn().arm(p),
+            RawParamEvent::CancelMidiLearn => self.context.midi_learn().disarm(),
+            RawParamEvent::ClearMidiMapping(p) => self.context.midi_learn().set_mapping(p, None),
+            RawParamEvent::MidiLearnChanged(_) => (),
             // This can be used by widgets to be notified when parameter values have changed
             RawParamEvent::ParametersChanged => (),
         });
+
+        // Learning a MIDI CC also changes the parameter's value, so this will be picked up when
+        // the wrapper sends the resulting `ParametersChanged` event
+        let armed_param = self.context.midi_learn().armed_param();
+        if armed_param != self.midi_learn_armed_param {
+            self.midi_learn_armed_param = armed_param;
+            cx.emit_custom(
+                Event::new(RawParamEvent::MidiLearnChanged(armed_param))
+                    .propagate(Propagation::Subtree),
+            );
+        }
     }
 }
 
@@ -155,6 +206,48 @@ impl Model for WindowModel {
 
                 meta.consume();
             }
+            GuiContextEvent::Undo => {
+                self.context.undo();
+                meta.consume();
+            }
+            GuiContextEvent::Redo => {
+                self.context.redo();
+                meta.consume();
+            }
+            GuiContextEvent::SwitchStateSlot(slot) => {
+                self.context.switch_state_slot(*slot);
+                self.active_state_slot = self.context.state_slots().active();
+                meta.consume();
+            }
+            GuiContextEvent::CopyStateSlot(from, to) => {
+                self.context.copy_state_slot(*from, *to);
+                meta.consume();
+            }
+        });
+
+        // Loading a state can also change the active slot
+        event.map(|param_event, _| {
+            if let RawParamEvent::ParametersChanged = param_event {
+                self.active_state_slot = self.context.state_slots().active();
+            }
+        });
+
+        // Keyboard shortcuts for undo and redo. Key presses handled by a focused widget, like a text
+        // box, won't reach this model.
+        event.map(|window_event, _| {
+            if let WindowEvent::KeyDown(code, _) = window_event {
+                let modifiers = *cx.modifiers();
+                if !modifiers.command() {
+                    return;
+                }
+
+                match code {
+                    Code::KeyZ if modifiers.shift() => cx.emit(GuiContextEvent::Redo),
+                    Code::KeyZ => cx.emit(GuiContextEvent::Undo),
+                    Code::KeyY => cx.emit(GuiContextEvent::Redo),
+                    _ => (),
+                }
+            }
         });
 
         // This gets fired whenever the inner window gets resized
@@ -212,6 +305,8 @@ impl<P: Param> From<ParamEvent<'_, P>> for RawParamEvent {
                 RawParamEvent::SetParameterNormalized(p.as_ptr(), v)
             }
             ParamEvent::EndSetParameter(p) => RawParamEvent::EndSetParameter(p.as_ptr()),
+            ParamEvent::ArmMidiLearn(p) => RawParamEvent::ArmMidiLearn(p.as_ptr()),
+            ParamEvent::ClearMidiMapping(p) => RawParamEvent::ClearMidiMapping(p.as_ptr()),
         }
     }
 }
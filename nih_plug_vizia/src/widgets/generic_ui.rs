@@ -32,8 +32,15 @@ impl GenericUi {
         // Basic styling is done in the `theme.css` style sheet
         Self::new_custom(cx, params, move |cx, param_ptr| {
             HStack::new(cx, |cx| {
-                // Align this on the right
-                Label::new(cx, unsafe { param_ptr.name() }).class("label");
+                // Align this on the right. Parameter names can change at runtime, so this needs to
+                // be a lens.
+                Label::new(
+                    cx,
+                    params
+                        .clone()
+                        .map(move |_| unsafe { param_ptr.name() }.to_string()),
+                )
+                .class("label");
 
                 Self::draw_widget(cx, params, param_ptr);
             })
@@ -1,6 +1,7 @@
 //! A base widget for creating other widgets that integrate with NIH-plug's [`Param`] types.
 
 use nih_plug::prelude::*;
+use std::sync::Arc;
 use vizia::prelude::*;
 
 use super::RawParamEvent;
@@ -227,7 +228,27 @@ impl ParamWidgetBase {
         cx.emit(RawParamEvent::EndSetParameter(self.param_ptr));
     }
 
-    param_ptr_forward!(pub fn name(&self) -> &str);
+    /// Map the next MIDI CC the plugin receives to this parameter.
+    pub fn arm_midi_learn(&self, cx: &mut EventContext) {
+        cx.emit(RawParamEvent::ArmMidiLearn(self.param_ptr));
+    }
+
+    /// Cancel MIDI learn if it is armed for any parameter.
+    pub fn cancel_midi_learn(&self, cx: &mut EventContext) {
+        cx.emit(RawParamEvent::CancelMidiLearn);
+    }
+
+    /// Remove this parameter's MIDI CC mapping, if it has one.
+    pub fn clear_midi_mapping(&self, cx: &mut EventContext) {
+        cx.emit(RawParamEvent::ClearMidiMapping(self.param_ptr));
+    }
+
+    /// Whether the parameter from a [`RawParamEvent::MidiLearnChanged`] event is this parameter.
+    pub fn is_midi_learn_armed(&self, armed_param: Option<ParamPtr>) -> bool {
+        armed_param == Some(self.param_ptr)
+    }
+
+    param_ptr_forward!(pub fn name(&self) -> Arc<String>);
     param_ptr_forward!(pub fn unit(&self) -> &'static str);
     param_ptr_forward!(pub fn poly_modulation_id(&self) -> Option<u32>);
     param_ptr_forward!(pub fn modulated_plain_value(&self) -> f32);
@@ -52,7 +52,9 @@ impl ParamButton {
                 Binding::new(cx, Self::label_override, move |cx, label_override| {
                     match label_override.get(cx) {
                         Some(label_override) => Label::new(cx, &label_override),
-                        None => Label::new(cx, param_data.param().name()),
+                        None => {
+                            Label::new(cx, param_data.make_lens(|param| param.name().to_string()))
+                        }
                     }
                     .hoverable(false);
                 })
@@ -5,6 +5,7 @@ use vizia::prelude::*;
 
 use super::param_base::ParamWidgetBase;
 use super::util::{self, ModifiersExt};
+use super::RawParamEvent;
 
 /// When shift+dragging a parameter, one pixel dragged corresponds to this much change in the
 /// normalized parameter.
@@ -26,6 +27,9 @@ pub struct ParamSlider {
     /// dragging for higher precision dragging. This is a `None` value when granular dragging is not
     /// active.
     granular_drag_status: Option<GranularDragStatus>,
+    /// Whether MIDI learn is armed for this parameter. Middle clicking on the slider toggles this,
+    /// and the slider gets the `midi-learn` class while it is waiting for a MIDI CC.
+    midi_learn_armed: bool,
 
     // These fields are set through modifiers:
     /// Whether or not to listen to scroll events for changing the parameter's value in steps.
@@ -105,6 +109,7 @@ impl ParamSlider {
             text_input_active: false,
             drag_active: false,
             granular_drag_status: None,
+            midi_learn_armed: false,
 
             use_scroll_wheel: true,
             scrolled_lines: 0.0,
@@ -431,6 +436,13 @@ impl View for ParamSlider {
             }
         });
 
+        event.map(|param_event, _| {
+            if let RawParamEvent::MidiLearnChanged(armed_param) = param_event {
+                self.midi_learn_armed = self.param_base.is_midi_learn_armed(*armed_param);
+                cx.toggle_class("midi-learn", self.midi_learn_armed);
+            }
+        });
+
         event.map(|window_event, meta| match window_event {
             // Vizia always captures the third mouse click as a triple click. Treating that triple
             // click as a regular mouse button makes double click followed by another drag work as
@@ -491,6 +503,19 @@ impl View for ParamSlider {
 
                 meta.consume();
             }
+            WindowEvent::MouseDown(MouseButton::Middle) => {
+                // Middle clicking arms MIDI learn, and Shift+Middle click removes the parameter's
+                // existing MIDI mapping
+                if cx.modifiers().shift() {
+                    self.param_base.clear_midi_mapping(cx);
+                } else if self.midi_learn_armed {
+                    self.param_base.cancel_midi_learn(cx);
+                } else {
+                    self.param_base.arm_midi_learn(cx);
+                }
+
+                meta.consume();
+            }
             WindowEvent::MouseUp(MouseButton::Left) => {
                 if self.drag_active {
                     self.drag_active = false;
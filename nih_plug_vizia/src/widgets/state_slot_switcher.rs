@@ -0,0 +1,57 @@
+//! Buttons for switching between and copying the plugin's state slots.
+
+use vizia::prelude::*;
+
+use super::{GuiContextEvent, WindowModel};
+
+/// A row of buttons for switching between the plugin's state slots, followed by a button that
+/// copies the active slot to the next slot. With the default two slots this is a classic A/B
+/// switch. See [`StateSlots`][nih_plug::prelude::StateSlots] for more information. The active
+/// slot's button has the `:checked` pseudoclass, and the copy button has the `copy` class.
+pub struct StateSlotSwitcher;
+
+impl StateSlotSwitcher {
+    /// Creates a new [`StateSlotSwitcher`]. This uses the plugin's
+    /// [`GuiContext`][nih_plug::prelude::GuiContext] to access the state slots.
+    pub fn new(cx: &mut Context) -> Handle<Self> {
+        let context = WindowModel::context.get(cx);
+
+        Self.build(cx, move |cx| {
+            Binding::new(cx, WindowModel::active_state_slot, move |cx, active| {
+                let active = active.get(cx);
+                let state_slots = context.state_slots();
+                let num_slots = state_slots.num_slots();
+
+                for slot in 0..num_slots {
+                    Button::new(
+                        cx,
+                        move |cx| cx.emit(GuiContextEvent::SwitchStateSlot(slot)),
+                        |cx| Label::new(cx, &state_slots.slot_name(slot)),
+                    )
+                    .checked(slot == active);
+                }
+
+                if num_slots > 1 {
+                    let target = (active + 1) % num_slots;
+                    let label = format!(
+                        "{} \u{2192} {}",
+                        state_slots.slot_name(active),
+                        state_slots.slot_name(target)
+                    );
+                    Button::new(
+                        cx,
+                        move |cx| cx.emit(GuiContextEvent::CopyStateSlot(active, target)),
+                        |cx| Label::new(cx, &label),
+                    )
+                    .class("copy");
+                }
+            });
+        })
+    }
+}
+
+impl View for StateSlotSwitcher {
+    fn element(&self) -> Option<&'static str> {
+        Some("state-slot-switcher")
+    }
+}
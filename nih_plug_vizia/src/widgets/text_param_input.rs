@@ -0,0 +1,75 @@
+//! A text input that integrates with NIH-plug's [`TextParam`] types.
+
+use nih_plug::prelude::TextParam;
+use vizia::prelude::*;
+
+/// A text input bound to a [`StringParam`][nih_plug::prelude::StringParam] or a
+/// [`PathParam`][nih_plug::prelude::PathParam]. The parameter is updated when the text box is
+/// submitted or loses focus. Changes made elsewhere, for instance by loading a preset, are picked up
+/// through the `params` lens.
+pub struct TextParamInput {
+    /// The parameter this text box is bound to. Like with
+    /// [`ParamWidgetBase`][super::param_base::ParamWidgetBase] the lifetime is erased here, since
+    /// in the context of NIH-plug the parameter outlives the editor.
+    param: *const dyn TextParam,
+}
+
+enum TextParamInputEvent {
+    /// The text box was submitted with this value.
+    Submit(String),
+}
+
+impl TextParamInput {
+    /// Creates a new [`TextParamInput`] for the given parameter. See
+    /// [`ParamSlider`][super::ParamSlider] for more information on this function's arguments.
+    pub fn new<L, Params, P, FMap>(
+        cx: &mut Context,
+        params: L,
+        params_to_param: FMap,
+    ) -> Handle<Self>
+    where
+        L: Lens<Target = Params> + Clone,
+        Params: 'static,
+        P: TextParam + 'static,
+        FMap: Fn(&Params) -> &P + Copy + 'static,
+    {
+        let param: *const P = params
+            .clone()
+            .map(move |params| params_to_param(params) as *const P)
+            .get(cx);
+
+        Self {
+            param: param as *const dyn TextParam,
+        }
+        .build(cx, move |cx| {
+            Textbox::new(cx, params.map(move |params| params_to_param(params).text()))
+                .on_submit(|cx, text, _success| {
+                    // Losing focus is treated the same as pressing Enter
+                    cx.emit(TextParamInputEvent::Submit(text));
+                })
+                .width(Stretch(1.0));
+        })
+    }
+
+    fn param(&self) -> &dyn TextParam {
+        // SAFETY: The parameter outlives the editor, see the comment on the field
+        unsafe { &*self.param }
+    }
+}
+
+impl View for TextParamInput {
+    fn element(&self) -> Option<&'static str> {
+        Some("text-param-input")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(
+            |text_param_input_event, meta| match text_param_input_event {
+                TextParamInputEvent::Submit(text) => {
+                    self.param().set_text(text);
+                    meta.consume();
+                }
+            },
+        );
+    }
+}
@@ -15,14 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use nih_plug::debug::*;
+use nih_plug::util::filter::{BiquadCoefficients, FirCoefficients, NEUTRAL_Q};
 use realfft::num_complex::Complex32;
 use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
 use std::f32;
 use std::sync::Arc;
 
-use self::filter::{FftFirFilter, FirCoefficients, FFT_INPUT_SIZE, FFT_SIZE};
+use self::filter::{FftFirFilter, FFT_INPUT_SIZE, FFT_SIZE};
 use crate::crossover::fir::filter::FILTER_SIZE;
-use crate::crossover::iir::biquad::{BiquadCoefficients, NEUTRAL_Q};
 use crate::{NUM_BANDS, NUM_CHANNELS};
 
 pub mod filter;
@@ -221,7 +221,7 @@ impl FirCrossover {
                 // center frequencies would have. The algorithm works in two stages. First, the IIR
                 // low-pass filters for the 1-4 crossovers used in the equivalent IIR LR24 version
                 // are computed and converted to equivalent linear-phase FIR filters using the
-                // algorithm described below in `FirCoefficients`. Then these are used to build the
+                // algorithm described in `FirCoefficients`. Then these are used to build the
                 // coefficients for the 2-5 bands:
                 //
                 // - The first band is always simply the first band's
@@ -297,11 +297,7 @@ impl FirCrossover {
 
                 // And finally we can do a spectral inversion of the accumulated IR to the the last
                 // band's high-pass filter
-                let mut fir_hp_coefs = accumulated_ir;
-                for coef in fir_hp_coefs.0.iter_mut() {
-                    *coef = -*coef;
-                }
-                fir_hp_coefs.0[FILTER_SIZE / 2] += 1.0;
+                let fir_hp_coefs = accumulated_ir.spectral_inversion();
 
                 self.band_filters[num_bands - 1].recompute_coefficients(
                     fir_hp_coefs,
@@ -16,13 +16,13 @@
 
 use nih_plug::buffer::ChannelSamples;
 use nih_plug::debug::*;
+use nih_plug::util::filter::{
+    Biquad, BiquadCoefficients, LinkwitzRileyCrossover, LinkwitzRileyOrder, NEUTRAL_Q,
+};
 use std::simd::f32x2;
 
-use self::biquad::{Biquad, BiquadCoefficients, NEUTRAL_Q};
 use crate::NUM_BANDS;
 
-pub mod biquad;
-
 #[derive(Debug)]
 pub struct IirCrossover {
     /// The kind of crossover to use. `.update_filters()` must be called after changing this.
@@ -30,7 +30,7 @@ pub struct IirCrossover {
 
     /// The crossovers. Depending on the number of bands argument passed to `.process()` one to four
     /// of these may be used.
-    crossovers: [Crossover; NUM_BANDS - 1],
+    crossovers: [LinkwitzRileyCrossover<f32x2>; NUM_BANDS - 1],
     /// Used to compensate the earlier bands for the phase shift introduced in the higher bands.
     all_passes: AllPassCascade,
 }
@@ -43,18 +43,6 @@ pub enum IirCrossoverType {
     LinkwitzRiley24,
 }
 
-/// A single crossover using multiple biquads in series to get steeper slopes. This can do both the
-/// low-pass and the high-pass parts of the crossover.
-#[derive(Debug, Clone, Default)]
-struct Crossover {
-    /// Filters for the low-pass section of the crossover. Not all filters may be used dependign on
-    /// the crossover type.
-    lp_filters: [Biquad<f32x2>; 2],
-    /// Filters for the high-pass section of the crossover. Not all filters may be used dependign on
-    /// the crossover type.
-    hp_filters: [Biquad<f32x2>; 2],
-}
-
 /// The crossover is super simple and feeds the low-passed result to the next band output while
 /// using the high-passed version as the input for the next band. Because the higher bands will thus
 /// have had more filters applied to them, the lower bands need to have their phase response
@@ -82,7 +70,11 @@ impl IirCrossover {
     pub fn new(mode: IirCrossoverType) -> Self {
         Self {
             mode,
-            crossovers: Default::default(),
+            crossovers: match mode {
+                IirCrossoverType::LinkwitzRiley24 => {
+                    [LinkwitzRileyCrossover::new(LinkwitzRileyOrder::Lr24); NUM_BANDS - 1]
+                }
+            },
             all_passes: Default::default(),
         }
     }
@@ -112,7 +104,7 @@ impl IirCrossover {
                     .take(num_bands - 1)
                     .enumerate()
                 {
-                    let (lp_samples, hp_samples) = crossover.process_lr24(samples);
+                    let (lp_samples, hp_samples) = crossover.process(samples);
 
                     // The low-pass result needs to have the same phase shift applied to it that
                     // higher bands would get
@@ -146,9 +138,7 @@ impl IirCrossover {
                     .zip(frequencies)
                     .take(num_bands - 1)
                 {
-                    let lp_coefs = BiquadCoefficients::lowpass(sample_rate, frequency, NEUTRAL_Q);
-                    let hp_coefs = BiquadCoefficients::highpass(sample_rate, frequency, NEUTRAL_Q);
-                    crossover.update_coefficients(lp_coefs, hp_coefs);
+                    crossover.update(sample_rate, frequency);
                 }
             }
         }
@@ -167,48 +157,6 @@ impl IirCrossover {
     }
 }
 
-impl Crossover {
-    /// Process left and right audio samples through two low-pass and two high-pass filter stages.
-    /// The resulting tuple contains the low-passed and the high-passed samples. Used for the
-    /// Linkwitz-Riley 24 dB/octave crossover.
-    pub fn process_lr24(&mut self, samples: f32x2) -> (f32x2, f32x2) {
-        let mut low_passed = samples;
-        for filter in &mut self.lp_filters[..2] {
-            low_passed = filter.process(low_passed)
-        }
-        let mut high_passed = samples;
-        for filter in &mut self.hp_filters[..2] {
-            high_passed = filter.process(high_passed)
-        }
-
-        (low_passed, high_passed)
-    }
-
-    /// Update the coefficients for all filters in the crossover.
-    pub fn update_coefficients(
-        &mut self,
-        lp_coefs: BiquadCoefficients<f32x2>,
-        hp_coefs: BiquadCoefficients<f32x2>,
-    ) {
-        for filter in &mut self.lp_filters {
-            filter.coefficients = lp_coefs;
-        }
-        for filter in &mut self.hp_filters {
-            filter.coefficients = hp_coefs;
-        }
-    }
-
-    /// Reset the internal filter state.
-    pub fn reset(&mut self) {
-        for filter in &mut self.lp_filters {
-            filter.reset();
-        }
-        for filter in &mut self.hp_filters {
-            filter.reset();
-        }
-    }
-}
-
 impl AllPassCascade {
     /// Compensate lower bands for the additional phase shift introduced in higher bands when using
     /// LR24 filters to split those bands.
@@ -257,7 +205,7 @@ impl AllPassCascade {
             // ```
             for target_crossover_idx in 0..crossover_idx {
                 self.ap_filters[target_crossover_idx][crossover_idx - target_crossover_idx - 1]
-                    .coefficients = ap_coefs;
+                    .set_coefficients(ap_coefs);
             }
         }
     }
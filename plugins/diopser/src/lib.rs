@@ -22,6 +22,7 @@ compile_error!("Compiling without SIMD support is currently not supported");
 use atomic_float::AtomicF32;
 use editor::SafeModeClamper;
 use nih_plug::prelude::*;
+use nih_plug::util::filter::{Biquad, BiquadCoefficients};
 use std::simd::f32x2;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -30,7 +31,6 @@ use crate::params::{DiopserParams, SpreadStyle};
 use crate::spectrum::{SpectrumInput, SpectrumOutput};
 
 mod editor;
-mod filter;
 mod params;
 mod spectrum;
 
@@ -49,7 +49,7 @@ pub struct Diopser {
     /// All of the all-pass filters, with vectorized coefficients so they can be calculated for
     /// multiple channels at once. [`DiopserParams::num_stages`] controls how many filters are
     /// actually active.
-    filters: [filter::Biquad<f32x2>; params::MAX_NUM_FILTERS],
+    filters: [Biquad<f32x2>; params::MAX_NUM_FILTERS],
     /// When the bypass parameter is toggled, this smoother fades between 0.0 and 1.0. This lets us
     /// crossfade the dry and the wet signal to avoid clicks. The smoothing target is set in a
     /// callback handler on the bypass parameter.
@@ -90,7 +90,7 @@ impl Default for Diopser {
 
             sample_rate,
 
-            filters: [filter::Biquad::default(); params::MAX_NUM_FILTERS],
+            filters: [Biquad::default(); params::MAX_NUM_FILTERS],
             bypass_smoother,
 
             should_update_filters,
@@ -327,8 +327,11 @@ impl Diopser {
             }
             .clamp(MIN_FREQUENCY, max_frequency);
 
-            self.filters[filter_idx].coefficients =
-                filter::BiquadCoefficients::allpass(sample_rate, filter_frequency, resonance);
+            self.filters[filter_idx].set_coefficients(BiquadCoefficients::allpass(
+                sample_rate,
+                filter_frequency,
+                resonance,
+            ));
             if reset_filters {
                 self.filters[filter_idx].reset();
             }
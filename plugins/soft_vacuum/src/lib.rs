@@ -19,9 +19,11 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use nih_plug::prelude::*;
+use nih_plug::util::oversampling::{
+    oversampling_factor_to_times, oversampling_times_to_factor, Oversampler, OversamplingFilter,
+};
 
 mod hard_vacuum;
-mod oversampling;
 
 /// The maximum number of samples to process at a time. Used to create scratch buffers for the
 /// oversampling.
@@ -42,12 +44,12 @@ struct SoftVacuum {
     /// Stores implementations of the Hard Vacuum algorithm for each channel, since each channel
     /// needs to maintain its own state.
     hard_vacuum_processors: Vec<hard_vacuum::HardVacuum>,
-    /// Oversampling for each channel.
-    oversamplers: Vec<oversampling::Lanczos3Oversampler>,
+    /// Oversampling for all channels.
+    oversampler: Oversampler,
     /// Oversampling for each channel's slew control signal. This is upsampled separately to make
     /// the oversampled algorithm sound similar to the regular, non oversampled version as the slews
     /// will necessarily be lower in the oversampled version.
-    slew_oversamplers: Vec<oversampling::Lanczos3Oversampler>,
+    slew_oversampler: Oversampler,
 
     /// Scratch buffers that the smoothed parameters can be rendered to. Allocated on the heap
     /// because Windows uses tiny stack sizes which may eventually cause problems in some hosts.
@@ -210,8 +212,8 @@ impl Default for SoftVacuum {
             params: Arc::new(SoftVacuumParams::default()),
 
             hard_vacuum_processors: Vec::new(),
-            oversamplers: Vec::new(),
-            slew_oversamplers: Vec::new(),
+            oversampler: new_oversampler(0),
+            slew_oversampler: new_oversampler(0),
 
             scratch_buffers: Box::default(),
         }
@@ -261,18 +263,13 @@ impl Plugin for SoftVacuum {
 
         self.hard_vacuum_processors
             .resize_with(num_channels, hard_vacuum::HardVacuum::default);
-        self.oversamplers.resize_with(num_channels, || {
-            oversampling::Lanczos3Oversampler::new(MAX_BLOCK_SIZE, MAX_OVERSAMPLING_FACTOR)
-        });
-        self.slew_oversamplers.resize_with(num_channels, || {
-            oversampling::Lanczos3Oversampler::new(MAX_BLOCK_SIZE, MAX_OVERSAMPLING_FACTOR)
-        });
-
-        if let Some(oversampler) = self.oversamplers.first() {
-            context.set_latency_samples(
-                oversampler.latency(self.params.oversampling_factor.value() as usize),
-            );
-        }
+        self.oversampler = new_oversampler(num_channels);
+        self.slew_oversampler = new_oversampler(num_channels);
+
+        context.set_latency_samples(
+            self.oversampler
+                .latency(self.params.oversampling_factor.value() as usize),
+        );
 
         true
     }
@@ -282,12 +279,8 @@ impl Plugin for SoftVacuum {
             hard_vacuum.reset();
         }
 
-        for oversampler in &mut self.oversamplers {
-            oversampler.reset();
-        }
-        for oversampler in &mut self.slew_oversamplers {
-            oversampler.reset();
-        }
+        self.oversampler.reset();
+        self.slew_oversampler.reset();
     }
 
     fn process(
@@ -301,9 +294,7 @@ impl Plugin for SoftVacuum {
 
         // If the oversampling factor parameter is changed then the host needs to know about the new
         // latency
-        if let Some(oversampler) = self.oversamplers.first() {
-            context.set_latency_samples(oversampler.latency(oversampling_factor));
-        }
+        context.set_latency_samples(self.oversampler.latency(oversampling_factor));
 
         for (_, block) in buffer.iter_blocks(MAX_BLOCK_SIZE) {
             let block_len = block.samples();
@@ -338,13 +329,10 @@ impl Plugin for SoftVacuum {
                 .smoothed
                 .next_block(dry_wet_ratio, upsampled_block_len);
 
-            for (block_channel, ((oversampler, slew_oversampler), hard_vacuum)) in
-                block.into_iter().zip(
-                    self.oversamplers
-                        .iter_mut()
-                        .zip(self.slew_oversamplers.iter_mut())
-                        .zip(self.hard_vacuum_processors.iter_mut()),
-                )
+            for (channel_idx, (block_channel, hard_vacuum)) in block
+                .into_iter()
+                .zip(self.hard_vacuum_processors.iter_mut())
+                .enumerate()
             {
                 // The slew signal is computed and oversampled first. This is then used as a control
                 // signal in the oversampled version of the algorithm so it sounds more similar to
@@ -355,33 +343,41 @@ impl Plugin for SoftVacuum {
                     *slew = hard_vacuum.compute_slew(*sample);
                 }
 
-                let upsampled_slews =
-                    slew_oversampler.upsample_only(&mut slews, oversampling_factor);
-
-                oversampler.process(block_channel, oversampling_factor, |upsampled| {
-                    assert!(upsampled.len() == upsampled_block_len);
-
-                    for (sample_idx, (sample, slew)) in
-                        upsampled.iter_mut().zip(upsampled_slews).enumerate()
-                    {
-                        // SAFETY: We already made sure that the blocks are equal in size. We could
-                        //         zip iterators instead but with six iterators that's already a bit
-                        //         too much without a first class way to zip more than two iterators
-                        //         together into a single tuple of iterators.
-                        let hard_vacuum_params = hard_vacuum::Params {
-                            drive: unsafe { *drive.get_unchecked(sample_idx) },
-                            warmth: unsafe { *warmth.get_unchecked(sample_idx) },
-                            aura: unsafe { *aura.get_unchecked(sample_idx) },
-                        };
-                        let output_gain = unsafe { *output_gain.get_unchecked(sample_idx) };
-                        let dry_wet_ratio = unsafe { *dry_wet_ratio.get_unchecked(sample_idx) };
-
-                        let distorted =
-                            hard_vacuum.process_with_slew(*sample, &hard_vacuum_params, *slew);
-                        *sample = (distorted * output_gain * dry_wet_ratio)
-                            + (*sample * (1.0 - dry_wet_ratio));
-                    }
-                });
+                let upsampled_slews = self.slew_oversampler.upsample_only(
+                    channel_idx,
+                    &mut slews[..block_len],
+                    oversampling_factor,
+                );
+
+                self.oversampler.process_channel(
+                    channel_idx,
+                    block_channel,
+                    oversampling_factor,
+                    |upsampled| {
+                        assert!(upsampled.len() == upsampled_block_len);
+
+                        for (sample_idx, (sample, slew)) in
+                            upsampled.iter_mut().zip(upsampled_slews).enumerate()
+                        {
+                            // SAFETY: We already made sure that the blocks are equal in size. We
+                            //         could zip iterators instead but with six iterators that's
+                            //         already a bit too much without a first class way to zip more
+                            //         than two iterators together into a single tuple of iterators.
+                            let hard_vacuum_params = hard_vacuum::Params {
+                                drive: unsafe { *drive.get_unchecked(sample_idx) },
+                                warmth: unsafe { *warmth.get_unchecked(sample_idx) },
+                                aura: unsafe { *aura.get_unchecked(sample_idx) },
+                            };
+                            let output_gain = unsafe { *output_gain.get_unchecked(sample_idx) };
+                            let dry_wet_ratio = unsafe { *dry_wet_ratio.get_unchecked(sample_idx) };
+
+                            let distorted =
+                                hard_vacuum.process_with_slew(*sample, &hard_vacuum_params, *slew);
+                            *sample = (distorted * output_gain * dry_wet_ratio)
+                                + (*sample * (1.0 - dry_wet_ratio));
+                        }
+                    },
+                );
             }
         }
 
@@ -389,13 +385,15 @@ impl Plugin for SoftVacuum {
     }
 }
 
-// Used in the conversion for the oversampling amount parameter
-const fn oversampling_factor_to_times(factor: usize) -> usize {
-    2usize.pow(factor as u32)
-}
-
-const fn oversampling_times_to_factor(times: usize) -> usize {
-    times.ilog2() as usize
+/// Create an oversampler for `num_channels` channels using this plugin's maximum block size and
+/// oversampling factor.
+fn new_oversampler(num_channels: usize) -> Oversampler {
+    Oversampler::new(
+        num_channels,
+        MAX_BLOCK_SIZE,
+        MAX_OVERSAMPLING_FACTOR,
+        OversamplingFilter::LinearPhase,
+    )
 }
 
 impl ClapPlugin for SoftVacuum {
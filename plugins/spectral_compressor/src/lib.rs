@@ -19,6 +19,7 @@ use atomic_float::AtomicF32;
 use crossbeam::atomic::AtomicCell;
 use editor::EditorMode;
 use nih_plug::prelude::*;
+use nih_plug::util::dry_wet::{DryWetMixer, MixingStyle};
 use nih_plug_vizia::ViziaState;
 use realfft::num_complex::Complex32;
 use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
@@ -29,7 +30,6 @@ use triple_buffer::TripleBuffer;
 mod analyzer;
 mod compressor_bank;
 mod curve;
-mod dry_wet_mixer;
 mod editor;
 
 const MIN_WINDOW_ORDER: usize = 6;
@@ -67,7 +67,7 @@ pub struct SpectralCompressor {
     /// helper. Allocated with a `MAX_WINDOW_SIZE` initial capacity.
     window_function: Vec<f32>,
     /// A mixer to mix the dry signal back into the processed signal with latency compensation.
-    dry_wet_mixer: dry_wet_mixer::DryWetMixer,
+    dry_wet_mixer: DryWetMixer,
     /// Spectral per-bin upwards and downwards compressors with soft-knee settings. This is where
     /// the magic happens.
     compressor_bank: compressor_bank::CompressorBank,
@@ -177,7 +177,7 @@ impl Default for SpectralCompressor {
             // These three will be set to the correct values in the initialize function
             stft: util::StftHelper::new(2, MAX_WINDOW_SIZE, 0),
             window_function: Vec::with_capacity(MAX_WINDOW_SIZE),
-            dry_wet_mixer: dry_wet_mixer::DryWetMixer::new(0, 0, 0),
+            dry_wet_mixer: DryWetMixer::new(0, 0, 0),
             compressor_bank,
 
             // This is initialized later since we don't want to do non-trivial computations before
@@ -500,8 +500,8 @@ impl Plugin for SpectralCompressor {
                 .smoothed
                 .next_step(buffer.samples() as u32),
             // The dry and wet signals are in phase, so we can do a linear mix
-            dry_wet_mixer::MixingStyle::Linear,
-            self.stft.latency_samples() as usize,
+            MixingStyle::Linear,
+            context.latency_samples(),
         );
 
         ProcessStatus::Normal
@@ -1,13 +1,57 @@
 //! Adapters and utilities for working with audio buffers.
 
+use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use crate::prelude::{Plugin, ProcessContext};
 
 mod blocks;
+mod event_blocks;
 mod samples;
 
 pub use blocks::{Block, BlockChannelsIter, BlocksIter};
+pub use event_blocks::{EventBlock, EventBlocksIter};
 pub use samples::{ChannelSamples, ChannelSamplesIter, SamplesIter};
 
+/// A floating point sample type audio buffers can contain. This is implemented for `f32`, which is
+/// what plugins process by default, and for `f64`, which is used when a plugin opts into 64-bit
+/// processing using [`Plugin::F64_PROCESSING`][crate::prelude::Plugin::F64_PROCESSING]. Generic
+/// DSP code can use this trait to work with both types.
+pub trait Sample:
+    Copy
+    + Default
+    + Debug
+    + PartialEq
+    + PartialOrd
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+{
+    /// The value `0.0`.
+    const ZERO: Self;
+    /// The value `1.0`.
+    const ONE: Self;
+
+    /// Convert an `f32` to this sample type.
+    fn from_f32(value: f32) -> Self;
+    /// Convert this sample to an `f32`. This may lose precision.
+    fn to_f32(self) -> f32;
+    /// Convert an `f64` to this sample type. This may lose precision.
+    fn from_f64(value: f64) -> Self;
+    /// Convert this sample to an `f64`.
+    fn to_f64(self) -> f64;
+}
+
 /// The audio buffers used during processing. This contains the output audio output buffers with the
 /// inputs already copied to the outputs. You can either use the iterator adapters to conveniently
 /// and efficiently iterate over the samples, or you can do your own thing using the raw audio
@@ -15,8 +59,12 @@ pub use samples::{ChannelSamples, ChannelSamplesIter, SamplesIter};
 ///
 /// TODO: This lifetime makes zero sense because you're going to need unsafe lifetime casts to use
 ///       this either way. Maybe just get rid of it in favor for raw pointers.
+///
+/// Buffers contain `f32` samples by default. Plugins that opt into 64-bit processing receive a
+/// `Buffer<f64>` in [`Plugin::process_f64()`][crate::prelude::Plugin::process_f64()] instead, which
+/// offers the same API.
 #[derive(Default)]
-pub struct Buffer<'a> {
+pub struct Buffer<'a, T = f32> {
     /// The number of samples contained within `output_slices`. This needs to be stored separately
     /// to be able to handle 0 channel IO for MIDI-only plugins.
     num_samples: usize,
@@ -28,10 +76,10 @@ pub struct Buffer<'a> {
     /// because this `Buffers` either cannot have the same lifetime as the separately stored output
     /// buffers, and it also cannot be stored in a field next to it because that would mean
     /// containing mutable references to data stored in a mutex.
-    output_slices: Vec<&'a mut [f32]>,
+    output_slices: Vec<&'a mut [T]>,
 }
 
-impl<'a> Buffer<'a> {
+impl<'a, T: Sample> Buffer<'a, T> {
     /// Returns the number of samples per channel in this buffer.
     #[inline]
     pub fn samples(&self) -> usize {
@@ -52,20 +100,20 @@ impl<'a> Buffer<'a> {
 
     /// Obtain the raw audio buffers.
     #[inline]
-    pub fn as_slice(&mut self) -> &mut [&'a mut [f32]] {
+    pub fn as_slice(&mut self) -> &mut [&'a mut [T]] {
         &mut self.output_slices
     }
 
     /// The same as [`as_slice()`][Self::as_slice()], but for a non-mutable reference. This is
     /// usually not needed.
     #[inline]
-    pub fn as_slice_immutable(&self) -> &[&'a mut [f32]] {
+    pub fn as_slice_immutable(&self) -> &[&'a mut [T]] {
         &self.output_slices
     }
 
     /// Iterate over the samples, returning a channel iterator for each sample.
     #[inline]
-    pub fn iter_samples<'slice>(&'slice mut self) -> SamplesIter<'slice, 'a> {
+    pub fn iter_samples<'slice>(&'slice mut self) -> SamplesIter<'slice, 'a, T> {
         SamplesIter {
             buffers: self.output_slices.as_mut_slice(),
             current_sample: 0,
@@ -99,7 +147,10 @@ impl<'a> Buffer<'a> {
     /// }
     /// ````
     #[inline]
-    pub fn iter_blocks<'slice>(&'slice mut self, max_block_size: usize) -> BlocksIter<'slice, 'a> {
+    pub fn iter_blocks<'slice>(
+        &'slice mut self,
+        max_block_size: usize,
+    ) -> BlocksIter<'slice, 'a, T> {
         BlocksIter {
             buffers: self.output_slices.as_mut_slice(),
             max_block_size,
@@ -108,6 +159,59 @@ impl<'a> Buffer<'a> {
         }
     }
 
+    /// Iterate over the buffer in blocks that are split at every note event's timing, with an
+    /// optional maximum block size. This is a sample accurate alternative to looping over
+    /// [`ProcessContext::next_event()`] by hand, and it's especially useful for polyphonic
+    /// synthesizers that render their voices in blocks. The iterator yields an
+    /// [`EventBlock::Event`] for every event, followed by an [`EventBlock::Block`] containing the
+    /// audio up until the next event. All events are yielded before the block that starts at the
+    /// event's timing.
+    ///
+    /// Parameter changes are not sent as note events. When
+    /// [`Plugin::SAMPLE_ACCURATE_AUTOMATION`] is enabled, the wrapper already splits the buffer at
+    /// parameter changes before calling the process function. Polyphonic modulation and
+    /// [`NoteEvent::MonoAutomation`][crate::prelude::NoteEvent::MonoAutomation] events are note
+    /// events, so blocks are also split on those.
+    ///
+    /// ```ignore
+    /// let mut blocks = buffer.iter_event_blocks(context, Some(64));
+    /// while let Some(event_block) = blocks.next() {
+    ///     match event_block {
+    ///         EventBlock::Event(NoteEvent::NoteOn { note, velocity, .. }) => { ... },
+    ///         EventBlock::Event(_) => (),
+    ///         EventBlock::Block(block_start, block) => {
+    ///             // Render the voices for `block`, and use `blocks.context()` to send events
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// The process context's [`next_event()`][ProcessContext::next_event()] function should not
+    /// be used while iterating, since the iterator already consumes the context's events.
+    #[inline]
+    pub fn iter_event_blocks<'slice, 'context, P, C>(
+        &'slice mut self,
+        context: &'context mut C,
+        max_block_size: Option<usize>,
+    ) -> EventBlocksIter<'slice, 'a, 'context, P, C, T>
+    where
+        P: Plugin,
+        C: ProcessContext<P>,
+    {
+        let next_event = context.next_event();
+
+        EventBlocksIter {
+            buffers: self.output_slices.as_mut_slice(),
+            context,
+            // A zero sample block size would cause the iterator to get stuck
+            max_block_size: max_block_size.map(|max_block_size| max_block_size.max(1)),
+            current_block_start: 0,
+            samples_end: self.samples(),
+            next_event,
+            _marker: PhantomData,
+        }
+    }
+
     /// Set the slices in the raw output slice vector. This vector needs to be resized to match the
     /// number of output channels during the plugin's initialization. Then during audio processing,
     /// these slices should be updated to point to the plugin's audio buffers. The `num_samples`
@@ -121,7 +225,7 @@ impl<'a> Buffer<'a> {
     pub unsafe fn set_slices(
         &mut self,
         num_samples: usize,
-        update: impl FnOnce(&mut Vec<&'a mut [f32]>),
+        update: impl FnOnce(&mut Vec<&'a mut [T]>),
     ) {
         self.num_samples = num_samples;
         update(&mut self.output_slices);
@@ -133,6 +237,79 @@ impl<'a> Buffer<'a> {
     }
 }
 
+impl Sample for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Sample for f64 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        value as f64
+    }
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// Run `f` on a buffer pointing to `channels`. All channels need to contain the same number of
+/// samples. This is used to test the DSP utilities that process entire buffers.
+#[cfg(test)]
+pub(crate) fn with_buffer<T: Sample>(
+    channels: &mut [impl AsMut<[T]>],
+    f: impl FnOnce(&mut Buffer<T>),
+) {
+    let num_samples = channels
+        .first_mut()
+        .map_or(0, |channel| channel.as_mut().len());
+    let mut buffer = Buffer::default();
+    unsafe {
+        buffer.set_slices(num_samples, |output_slices| {
+            *output_slices = channels
+                .iter_mut()
+                .map(|channel| channel.as_mut())
+                .collect();
+        })
+    };
+
+    f(&mut buffer);
+}
+
 #[cfg(any(miri, test))]
 mod miri {
     use super::*;
@@ -0,0 +1,369 @@
+//! A block iterator that splits the buffer on note events.
+
+use std::marker::PhantomData;
+
+use super::Block;
+use crate::prelude::{Plugin, PluginNoteEvent, ProcessContext};
+
+/// An iterator over a buffer that splits the buffer into blocks at every note event's timing. See
+/// [`Buffer::iter_event_blocks()`][super::Buffer::iter_event_blocks()]. Every block is preceded by
+/// the events that happen at the start of that block.
+///
+/// Blocks are only split on note events and the maximum block size, not on parameter changes.
+/// Parameter values never change during a single process call: when
+/// [`Plugin::SAMPLE_ACCURATE_AUTOMATION`] is enabled the wrapper already splits the buffer at every
+/// parameter change and calls the process function once for every part, and otherwise all of the
+/// buffer's parameter changes are applied before the process function is called. Every block
+/// yielded by this iterator thus already lies between two parameter changes.
+///
+/// The iterator holds on to the process context while iterating. Since the yielded items do not
+/// borrow from the iterator, the context can still be accessed through
+/// [`context()`][Self::context()] when iterating over the blocks using a `while let` loop.
+pub struct EventBlocksIter<'slice, 'sample: 'slice, 'context, P, C, T = f32>
+where
+    P: Plugin,
+    C: ProcessContext<P>,
+{
+    /// The raw output buffers.
+    pub(super) buffers: *mut [&'sample mut [T]],
+    pub(super) context: &'context mut C,
+    /// The maximum size of a block, or `None` if blocks should only be split on events.
+    pub(super) max_block_size: Option<usize>,
+    pub(super) current_block_start: usize,
+    /// The total number of samples in the buffer. This is stored separately so MIDI-only plugins
+    /// without any audio channels can still use this iterator to process their events.
+    pub(super) samples_end: usize,
+    /// The next event from `context`. This is used to determine where the current block ends.
+    pub(super) next_event: Option<PluginNoteEvent<P>>,
+    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [T]]>,
+}
+
+/// An item yielded by [`EventBlocksIter`]. All events happening at the start of a block are
+/// yielded before that block.
+pub enum EventBlock<'slice, 'sample: 'slice, P: Plugin, T = f32> {
+    /// A note event. The event happens at the start of the next [`Block`][Self::Block]. Its timing
+    /// is still relative to the start of the buffer.
+    Event(PluginNoteEvent<P>),
+    /// A block of audio that contains no events, along with the offset from the start of the
+    /// buffer.
+    Block(usize, Block<'slice, 'sample, T>),
+}
+
+impl<'slice, 'sample, 'context, P, C, T> EventBlocksIter<'slice, 'sample, 'context, P, C, T>
+where
+    P: Plugin,
+    C: ProcessContext<P>,
+{
+    /// Access the process context while iterating over the buffer, for instance to send note
+    /// events. The context's [`next_event()`][ProcessContext::next_event()] function should not be
+    /// called directly, as that would cause those events to be skipped by this iterator.
+    #[inline]
+    pub fn context(&mut self) -> &mut C {
+        self.context
+    }
+}
+
+impl<'slice, 'sample, 'context, P, C, T> Iterator
+    for EventBlocksIter<'slice, 'sample, 'context, P, C, T>
+where
+    P: Plugin,
+    C: ProcessContext<P>,
+{
+    type Item = EventBlock<'slice, 'sample, P, T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // Events at or before the start of the current block are yielded first. Once we're past
+        // the end of the buffer any events the host sent with out of bounds timings are yielded so
+        // they don't silently get lost.
+        match &self.next_event {
+            Some(event)
+                if event.timing() as usize <= self.current_block_start
+                    || self.current_block_start >= self.samples_end =>
+            {
+                let event = self.next_event.take();
+                self.next_event = self.context.next_event();
+
+                return event.map(EventBlock::Event);
+            }
+            _ => (),
+        }
+
+        if self.current_block_start >= self.samples_end {
+            return None;
+        }
+
+        let current_block_start = self.current_block_start;
+        let mut current_block_end = match self.max_block_size {
+            Some(max_block_size) => (current_block_start + max_block_size).min(self.samples_end),
+            None => self.samples_end,
+        };
+        if let Some(event) = &self.next_event {
+            current_block_end = current_block_end.min(event.timing() as usize);
+        }
+
+        let block = Block {
+            buffers: self.buffers,
+            current_block_start,
+            current_block_end,
+            _marker: self._marker,
+        };
+
+        self.current_block_start = current_block_end;
+
+        Some(EventBlock::Block(current_block_start, block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::buffer::with_buffer;
+    use crate::prelude::{
+        AudioIOLayout, Buffer, NoteEvent, ParamPtr, Params, PluginApi, ProcessStatus, Transport,
+    };
+
+    #[derive(Default)]
+    struct TestPlugin;
+
+    struct TestParams;
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            Vec::new()
+        }
+    }
+
+    impl Plugin for TestPlugin {
+        const NAME: &'static str = "Test Plugin";
+        const VENDOR: &'static str = "Test Vendor";
+        const URL: &'static str = "";
+        const EMAIL: &'static str = "";
+        const VERSION: &'static str = "0.1.0";
+
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[];
+
+        type SysExMessage = ();
+        type BackgroundTask = ();
+
+        fn params(&self) -> Arc<dyn Params> {
+            Arc::new(TestParams)
+        }
+
+        fn process(
+            &mut self,
+            _buffer: &mut Buffer,
+            _aux: &mut crate::prelude::AuxiliaryBuffers,
+            _context: &mut impl ProcessContext<Self>,
+        ) -> ProcessStatus {
+            ProcessStatus::Normal
+        }
+    }
+
+    /// A process context that only provides the events it was created with.
+    struct TestProcessContext {
+        events: VecDeque<PluginNoteEvent<TestPlugin>>,
+        transport: Transport,
+    }
+
+    impl ProcessContext<TestPlugin> for TestProcessContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Standalone
+        }
+
+        fn execute_background(&self, _task: ()) {}
+
+        fn execute_gui(&self, _task: ()) {}
+
+        fn transport(&self) -> &Transport {
+            &self.transport
+        }
+
+        fn next_event(&mut self) -> Option<PluginNoteEvent<TestPlugin>> {
+            self.events.pop_front()
+        }
+
+        fn send_event(&mut self, _event: PluginNoteEvent<TestPlugin>) {}
+
+        fn set_latency_samples(&self, _samples: u32) {}
+
+        fn latency_samples(&self) -> u32 {
+            0
+        }
+
+        fn set_current_voice_capacity(&self, _capacity: u32) {}
+
+        fn param_info_changed(&self) {}
+    }
+
+    /// A simplified version of [`EventBlock`] that can be compared.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Item {
+        /// A note on event's timing and note number.
+        Event(u32, u8),
+        /// A block's start offset and length.
+        Block(usize, usize),
+    }
+
+    fn note_on(timing: u32, note: u8) -> PluginNoteEvent<TestPlugin> {
+        NoteEvent::NoteOn {
+            timing,
+            voice_id: None,
+            channel: 0,
+            note,
+            velocity: 1.0,
+        }
+    }
+
+    /// Iterate over a single channel buffer with `num_samples` samples, and return the yielded
+    /// items. Every sample is set to the offset of the block containing it, which is checked
+    /// afterwards.
+    fn collect_event_blocks(
+        num_samples: usize,
+        events: Vec<PluginNoteEvent<TestPlugin>>,
+        max_block_size: Option<usize>,
+    ) -> Vec<Item> {
+        let mut context = TestProcessContext {
+            events: events.into(),
+            transport: Transport::new(44_100.0),
+        };
+        let mut samples = vec![-1.0f32; num_samples];
+        let mut items = Vec::new();
+        with_buffer(&mut [&mut samples], |buffer| {
+            for event_block in buffer.iter_event_blocks(&mut context, max_block_size) {
+                match event_block {
+                    EventBlock::Event(NoteEvent::NoteOn { timing, note, .. }) => {
+                        items.push(Item::Event(timing, note))
+                    }
+                    EventBlock::Event(event) => panic!("Unexpected event: {event:?}"),
+                    EventBlock::Block(block_start, mut block) => {
+                        block.get_mut(0).unwrap().fill(block_start as f32);
+                        items.push(Item::Block(block_start, block.samples()));
+                    }
+                }
+            }
+        });
+
+        for item in &items {
+            if let Item::Block(block_start, block_len) = *item {
+                assert!(samples[block_start..block_start + block_len]
+                    .iter()
+                    .all(|sample| *sample == block_start as f32));
+            }
+        }
+        assert!(samples.iter().all(|sample| *sample >= 0.0));
+
+        items
+    }
+
+    #[test]
+    fn no_events() {
+        assert_eq!(
+            collect_event_blocks(16, Vec::new(), None),
+            [Item::Block(0, 16)]
+        );
+    }
+
+    #[test]
+    fn events_at_start() {
+        assert_eq!(
+            collect_event_blocks(16, vec![note_on(0, 60), note_on(8, 62)], None),
+            [
+                Item::Event(0, 60),
+                Item::Block(0, 8),
+                Item::Event(8, 62),
+                Item::Block(8, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn simultaneous_events() {
+        assert_eq!(
+            collect_event_blocks(
+                16,
+                vec![note_on(4, 60), note_on(4, 64), note_on(4, 67)],
+                None
+            ),
+            [
+                Item::Block(0, 4),
+                Item::Event(4, 60),
+                Item::Event(4, 64),
+                Item::Event(4, 67),
+                Item::Block(4, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn events_past_end() {
+        assert_eq!(
+            collect_event_blocks(
+                16,
+                vec![note_on(12, 60), note_on(16, 62), note_on(20, 64)],
+                None
+            ),
+            [
+                Item::Block(0, 12),
+                Item::Event(12, 60),
+                Item::Block(12, 4),
+                Item::Event(16, 62),
+                Item::Event(20, 64),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_block_size() {
+        assert_eq!(
+            collect_event_blocks(10, vec![note_on(5, 60)], Some(4)),
+            [
+                Item::Block(0, 4),
+                Item::Block(4, 1),
+                Item::Event(5, 60),
+                Item::Block(5, 4),
+                Item::Block(9, 1),
+            ]
+        );
+
+        // A zero sample maximum is treated as a single sample
+        assert_eq!(
+            collect_event_blocks(2, vec![note_on(1, 60)], Some(0)),
+            [Item::Block(0, 1), Item::Event(1, 60), Item::Block(1, 1)]
+        );
+    }
+
+    #[test]
+    fn no_audio_channels() {
+        let mut context = TestProcessContext {
+            events: vec![note_on(0, 60), note_on(3, 62)].into(),
+            transport: Transport::new(44_100.0),
+        };
+        let mut buffer = Buffer::<f32>::default();
+        unsafe { buffer.set_slices(8, |_| ()) };
+
+        let mut items = Vec::new();
+        for event_block in buffer.iter_event_blocks(&mut context, None) {
+            match event_block {
+                EventBlock::Event(event) => items.push(Item::Event(event.timing(), 0)),
+                EventBlock::Block(block_start, block) => {
+                    assert_eq!(block.channels(), 0);
+                    items.push(Item::Block(block_start, block.samples()))
+                }
+            }
+        }
+
+        assert_eq!(
+            items,
+            [
+                Item::Event(0, 0),
+                Item::Block(0, 3),
+                Item::Event(3, 0),
+                Item::Block(3, 5),
+            ]
+        );
+    }
+}
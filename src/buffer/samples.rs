@@ -3,43 +3,45 @@
 use std::marker::PhantomData;
 
 #[cfg(feature = "simd")]
-use std::simd::{LaneCount, Simd, SupportedLaneCount};
+use std::simd::{LaneCount, Simd, SimdElement, SupportedLaneCount};
+
+use super::Sample;
 
 /// An iterator over all samples in a buffer or block, yielding iterators over each channel for
 /// every sample. This iteration order offers good cache locality for per-sample access.
-pub struct SamplesIter<'slice, 'sample: 'slice> {
+pub struct SamplesIter<'slice, 'sample: 'slice, T = f32> {
     /// The raw output buffers.
-    pub(super) buffers: *mut [&'sample mut [f32]],
+    pub(super) buffers: *mut [&'sample mut [T]],
     pub(super) current_sample: usize,
     /// The last sample index to iterate over plus one. Would be equal to `buffers.len()` when
     /// iterating over an entire buffer, but this can also be used to iterate over smaller blocks in
     /// a similar fashion.
     pub(super) samples_end: usize,
-    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [T]]>,
 }
 
 /// Can construct iterators over actual iterator over the channel data for a sample, yielded by
 /// [`SamplesIter`]. Can be turned into an iterator, or [`ChannelSamples::iter_mut()`] can be used
 /// to iterate over the channel data multiple times, or more efficiently you can use
 /// [`ChannelSamples::get_unchecked_mut()`] to do the same thing.
-pub struct ChannelSamples<'slice, 'sample: 'slice> {
+pub struct ChannelSamples<'slice, 'sample: 'slice, T = f32> {
     /// The raw output buffers.
-    pub(self) buffers: *mut [&'sample mut [f32]],
+    pub(self) buffers: *mut [&'sample mut [T]],
     pub(self) current_sample: usize,
-    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [T]]>,
 }
 
 /// The actual iterator over the channel data for a sample, yielded by [`ChannelSamples`].
-pub struct ChannelSamplesIter<'slice, 'sample: 'slice> {
+pub struct ChannelSamplesIter<'slice, 'sample: 'slice, T = f32> {
     /// The raw output buffers.
-    pub(self) buffers: *mut [&'sample mut [f32]],
+    pub(self) buffers: *mut [&'sample mut [T]],
     pub(self) current_sample: usize,
     pub(self) current_channel: usize,
-    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [T]]>,
 }
 
-impl<'slice, 'sample> Iterator for SamplesIter<'slice, 'sample> {
-    type Item = ChannelSamples<'slice, 'sample>;
+impl<'slice, 'sample, T> Iterator for SamplesIter<'slice, 'sample, T> {
+    type Item = ChannelSamples<'slice, 'sample, T>;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -67,9 +69,9 @@ impl<'slice, 'sample> Iterator for SamplesIter<'slice, 'sample> {
     }
 }
 
-impl<'slice, 'sample> IntoIterator for ChannelSamples<'slice, 'sample> {
-    type Item = &'sample mut f32;
-    type IntoIter = ChannelSamplesIter<'slice, 'sample>;
+impl<'slice, 'sample, T> IntoIterator for ChannelSamples<'slice, 'sample, T> {
+    type Item = &'sample mut T;
+    type IntoIter = ChannelSamplesIter<'slice, 'sample, T>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -82,8 +84,8 @@ impl<'slice, 'sample> IntoIterator for ChannelSamples<'slice, 'sample> {
     }
 }
 
-impl<'slice, 'sample> Iterator for ChannelSamplesIter<'slice, 'sample> {
-    type Item = &'sample mut f32;
+impl<'slice, 'sample, T> Iterator for ChannelSamplesIter<'slice, 'sample, T> {
+    type Item = &'sample mut T;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -113,10 +115,10 @@ impl<'slice, 'sample> Iterator for ChannelSamplesIter<'slice, 'sample> {
     }
 }
 
-impl ExactSizeIterator for SamplesIter<'_, '_> {}
-impl ExactSizeIterator for ChannelSamplesIter<'_, '_> {}
+impl<T> ExactSizeIterator for SamplesIter<'_, '_, T> {}
+impl<T> ExactSizeIterator for ChannelSamplesIter<'_, '_, T> {}
 
-impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
+impl<'slice, 'sample, T: Sample> ChannelSamples<'slice, 'sample, T> {
     /// Get the number of channels.
     #[allow(clippy::len_without_is_empty)]
     #[inline]
@@ -128,7 +130,7 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     /// you don't need to use this function as [`ChannelSamples`] already implements
     /// [`IntoIterator`].
     #[inline]
-    pub fn iter_mut(&mut self) -> ChannelSamplesIter<'slice, 'sample> {
+    pub fn iter_mut(&mut self) -> ChannelSamplesIter<'slice, 'sample, T> {
         ChannelSamplesIter {
             buffers: self.buffers,
             current_sample: self.current_sample,
@@ -140,7 +142,7 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     /// Access a sample by index. Useful when you would otherwise iterate over this 'Channels'
     /// iterator multiple times.
     #[inline]
-    pub fn get_mut(&mut self, channel_index: usize) -> Option<&mut f32> {
+    pub fn get_mut(&mut self, channel_index: usize) -> Option<&mut T> {
         // SAFETY: The sample bound has already been checked
         unsafe {
             Some(
@@ -157,7 +159,7 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     ///
     /// `channel_index` must be in the range `0..Self::len()`.
     #[inline]
-    pub unsafe fn get_unchecked_mut(&mut self, channel_index: usize) -> &mut f32 {
+    pub unsafe fn get_unchecked_mut(&mut self, channel_index: usize) -> &mut T {
         (*self.buffers)
             .get_unchecked_mut(channel_index)
             .get_unchecked_mut(self.current_sample)
@@ -168,12 +170,13 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     /// all values.
     #[cfg(feature = "simd")]
     #[inline]
-    pub fn to_simd<const LANES: usize>(&self) -> Simd<f32, LANES>
+    pub fn to_simd<const LANES: usize>(&self) -> Simd<T, LANES>
     where
+        T: SimdElement,
         LaneCount<LANES>: SupportedLaneCount,
     {
         let used_lanes = self.len().max(LANES);
-        let mut values = [0.0; LANES];
+        let mut values = [T::ZERO; LANES];
         for (channel_idx, value) in values.iter_mut().enumerate().take(used_lanes) {
             *value = unsafe {
                 *(*self.buffers)
@@ -193,11 +196,12 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     /// Undefined behavior if `LANES > channels.len()`.
     #[cfg(feature = "simd")]
     #[inline]
-    pub unsafe fn to_simd_unchecked<const LANES: usize>(&self) -> Simd<f32, LANES>
+    pub unsafe fn to_simd_unchecked<const LANES: usize>(&self) -> Simd<T, LANES>
     where
+        T: SimdElement,
         LaneCount<LANES>: SupportedLaneCount,
     {
-        let mut values = [0.0; LANES];
+        let mut values = [T::ZERO; LANES];
         for (channel_idx, value) in values.iter_mut().enumerate() {
             *value = *(*self.buffers)
                 .get_unchecked(channel_idx)
@@ -212,8 +216,9 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     #[cfg(feature = "simd")]
     #[allow(clippy::wrong_self_convention)]
     #[inline]
-    pub fn from_simd<const LANES: usize>(&mut self, vector: Simd<f32, LANES>)
+    pub fn from_simd<const LANES: usize>(&mut self, vector: Simd<T, LANES>)
     where
+        T: SimdElement,
         LaneCount<LANES>: SupportedLaneCount,
     {
         let used_lanes = self.len().max(LANES);
@@ -236,8 +241,9 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     #[cfg(feature = "simd")]
     #[allow(clippy::wrong_self_convention)]
     #[inline]
-    pub unsafe fn from_simd_unchecked<const LANES: usize>(&mut self, vector: Simd<f32, LANES>)
+    pub unsafe fn from_simd_unchecked<const LANES: usize>(&mut self, vector: Simd<T, LANES>)
     where
+        T: SimdElement,
         LaneCount<LANES>: SupportedLaneCount,
     {
         let values = vector.to_array();
@@ -3,7 +3,7 @@
 use std::sync::Arc;
 
 use super::PluginApi;
-use crate::prelude::{Param, ParamPtr, Plugin, PluginState};
+use crate::prelude::{MidiLearn, MidiMapping, Param, ParamPtr, Plugin, PluginState, StateSlots};
 
 /// Callbacks the plugin can make when the user interacts with its GUI such as updating parameter
 /// values. This is passed to the plugin during [`Editor::spawn()`][crate::prelude::Editor::spawn()]. All of
@@ -63,6 +63,46 @@ pub trait GuiContext: Send + Sync + 'static {
     /// host. If the plugin is currently processing audio, then the parameter values will be
     /// restored at the end of the current processing cycle.
     fn set_state(&self, state: PluginState);
+
+    /// Undo the last parameter change or state restore made from the editor. Parameter changes are
+    /// recorded per gesture, so dragging a slider and releasing it again is undone in a single
+    /// step. Changes made by the host, like automation, are not recorded. If the host supports
+    /// CLAP's undo extension, then this instead asks the host to undo its last change so the host's
+    /// undo history and the plugin's undo history don't get out of sync.
+    fn undo(&self);
+
+    /// Redo the last change undone using [`undo()`][Self::undo()].
+    fn redo(&self);
+
+    /// Get the plugin instance's MIDI learn manager. This can be used to arm MIDI learn for a
+    /// parameter and to inspect or modify the existing MIDI CC mappings. [`ParamSetter`] has a
+    /// couple convenience functions for the common cases.
+    fn midi_learn(&self) -> &MidiLearn;
+
+    /// Get the plugin instance's state slots. This can be used to show which slot is active. Use
+    /// [`switch_state_slot()`][Self::switch_state_slot()] and
+    /// [`copy_state_slot()`][Self::copy_state_slot()] to modify them.
+    fn state_slots(&self) -> &StateSlots;
+
+    /// Store the plugin's current state in the active slot and load the state stored in `slot`. If
+    /// the plugin is currently processing audio, then the new state is loaded at the start of the
+    /// next processing cycle. The parameters' smoothers are not reset and the plugin is not
+    /// reinitialized, so this can be done while audio is playing. Does nothing if `slot` is the
+    /// active slot or if it's out of range.
+    fn switch_state_slot(&self, slot: usize);
+
+    /// Copy the state stored in slot `from` to slot `to`. If `to` is the active slot, then the
+    /// copied state is loaded the same way as in
+    /// [`switch_state_slot()`][Self::switch_state_slot()].
+    fn copy_state_slot(&self, from: usize, to: usize);
+
+    /// Inform the host and the editor that one or more parameters' names, ranges, or enum variants
+    /// have been changed using functions like
+    /// [`FloatParam::set_name()`][crate::prelude::FloatParam::set_name()] or
+    /// [`EnumParam::set_variant_names()`][crate::prelude::EnumParam::set_variant_names()]. The
+    /// host will then rescan the parameters' information. If a parameter's step count has changed,
+    /// then this may cause audio playback to be restarted.
+    fn param_info_changed(&self);
 }
 
 /// An way to run background tasks from the plugin's GUI, equivalent to the
@@ -180,4 +220,32 @@ impl<'a> ParamSetter<'a> {
     pub fn end_set_parameter<P: Param>(&self, param: &P) {
         unsafe { self.raw_context.raw_end_set_parameter(param.as_ptr()) };
     }
+
+    /// Map the next MIDI CC the plugin receives to this parameter. This replaces the parameter's
+    /// existing MIDI mapping, if it has one. See [`MidiLearn`] for more information.
+    pub fn arm_midi_learn<P: Param>(&self, param: &P) {
+        self.raw_context.midi_learn().arm(param.as_ptr());
+    }
+
+    /// Cancel MIDI learn if it is armed for any parameter.
+    pub fn cancel_midi_learn(&self) {
+        self.raw_context.midi_learn().disarm();
+    }
+
+    /// Whether MIDI learn is currently armed for this parameter.
+    pub fn is_midi_learn_armed<P: Param>(&self, param: &P) -> bool {
+        self.raw_context.midi_learn().armed_param() == Some(param.as_ptr())
+    }
+
+    /// Get the parameter's MIDI mapping, if it has one.
+    pub fn midi_mapping<P: Param>(&self, param: &P) -> Option<MidiMapping> {
+        self.raw_context.midi_learn().mapping(param.as_ptr())
+    }
+
+    /// Remove the parameter's MIDI mapping, if it has one.
+    pub fn clear_midi_mapping<P: Param>(&self, param: &P) {
+        self.raw_context
+            .midi_learn()
+            .set_mapping(param.as_ptr(), None);
+    }
 }
@@ -73,6 +73,9 @@ pub trait ProcessContext<P: Plugin> {
     ///
     /// ProcessStatus::Normal
     /// ```
+    ///
+    /// [`Buffer::iter_event_blocks()`][crate::prelude::Buffer::iter_event_blocks()] implements this
+    /// loop for you, and it can also split the buffer into blocks with a maximum size.
     fn next_event(&mut self) -> Option<PluginNoteEvent<P>>;
 
     /// Send an event to the host. Only available when
@@ -84,6 +87,12 @@ pub trait ProcessContext<P: Plugin> {
     /// this may cause audio playback to be restarted.
     fn set_latency_samples(&self, samples: u32);
 
+    /// The plugin's current latency in samples, as last set through
+    /// [`set_latency_samples()`][Self::set_latency_samples()]. This can be passed to
+    /// [`DryWetMixer::mix_in_dry()`][crate::util::dry_wet::DryWetMixer::mix_in_dry()] to keep the
+    /// dry signal aligned with the latency reported to the host.
+    fn latency_samples(&self) -> u32;
+
     /// Set the current voice **capacity** for this plugin (so not the number of currently active
     /// voices). This may only be called if
     /// [`ClapPlugin::CLAP_POLY_MODULATION_CONFIG`][crate::prelude::ClapPlugin::CLAP_POLY_MODULATION_CONFIG]
@@ -92,6 +101,15 @@ pub trait ProcessContext<P: Plugin> {
     /// monophonic modulation when dropping the capacity down to 1.
     fn set_current_voice_capacity(&self, capacity: u32);
 
+    /// Inform the host and the editor that one or more parameters' names, ranges, or enum variants
+    /// have been changed using functions like
+    /// [`FloatParam::set_name()`][crate::prelude::FloatParam::set_name()] or
+    /// [`EnumParam::set_variant_names()`][crate::prelude::EnumParam::set_variant_names()]. The
+    /// host will then rescan the parameters' information. This is realtime-safe, and the actual
+    /// rescan is deferred to the main thread. If a parameter's step count has changed, then this
+    /// may cause audio playback to be restarted.
+    fn param_info_changed(&self);
+
     // TODO: Add this, this works similar to [GuiContext::set_parameter] but it adds the parameter
     //       change to a queue (or directly to the VST3 plugin's parameter output queues) instead of
     //       using main thread host automation (and all the locks involved there).
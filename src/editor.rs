@@ -69,6 +69,16 @@ pub trait Editor: Send {
     /// loaded.
     fn param_values_changed(&self);
 
+    /// Called whenever one or more parameters' names, ranges, or enum variants have been changed
+    /// while the editor is open. This happens after the plugin calls
+    /// [`GuiContext::param_info_changed()`][crate::prelude::GuiContext::param_info_changed()] or
+    /// [`ProcessContext::param_info_changed()`][crate::prelude::ProcessContext::param_info_changed()].
+    /// Since parameter values may also change as a result of this, the default implementation
+    /// simply calls [`param_values_changed()`][Self::param_values_changed()].
+    fn param_info_changed(&self) {
+        self.param_values_changed();
+    }
+
     // TODO: Reconsider adding a tick function here for the Linux `IRunLoop`. To keep this platform
     //       and API agnostic, add a way to ask the GuiContext if the wrapper already provides a
     //       tick function. If it does not, then the Editor implementation must handle this by
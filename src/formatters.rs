@@ -8,6 +8,7 @@
 use std::cmp::Ordering;
 use std::sync::Arc;
 
+use crate::params::time::{DivisionModifier, NoteDivision};
 use crate::util;
 
 // TODO: The v2s and s2v naming convention isn't ideal, but at least it's unambiguous. Is there a
@@ -255,6 +256,69 @@ pub fn s2v_f32_hz_then_khz() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
     })
 }
 
+/// Format a time in milliseconds as rounded milliseconds below one second, and as rounded seconds
+/// above that. This already includes the unit.
+pub fn v2s_f32_ms_then_s(digits: usize) -> Arc<dyn Fn(f32) -> String + Send + Sync> {
+    Arc::new(move |value| {
+        if value < 1000.0 {
+            format!("{value:.digits$} ms")
+        } else {
+            format!("{:.digits$} s", value / 1000.0, digits = digits.max(1))
+        }
+    })
+}
+
+/// Convert an input in the same format as that of [`v2s_f32_ms_then_s()`] to a time in
+/// milliseconds. Plain numbers without a unit are parsed as milliseconds.
+pub fn s2v_f32_ms_then_s() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
+    Arc::new(move |string| {
+        let string = string.trim();
+        let cleaned_string = string
+            .trim_end_matches([' ', 'm', 'M', 's', 'S'])
+            .parse()
+            .ok();
+        let lowercase_string = string.to_ascii_lowercase();
+        if lowercase_string.ends_with("ms") {
+            cleaned_string
+        } else if lowercase_string.ends_with('s') {
+            cleaned_string.map(|x| x * 1000.0)
+        } else {
+            cleaned_string
+        }
+    })
+}
+
+/// Format an index into [`NOTE_DIVISIONS`][crate::params::time::NOTE_DIVISIONS] as a note division
+/// like `1/4`, `1/8.` for a dotted eighth note, or `1/16T` for a sixteenth note triplet. Used by
+/// [`SyncedTimeParams`][crate::params::SyncedTimeParams].
+pub fn v2s_i32_note_division() -> Arc<dyn Fn(i32) -> String + Send + Sync> {
+    Arc::new(move |value| NoteDivision::from_index(value).to_string())
+}
+
+/// Parse a note division in the format of [`v2s_i32_note_division()`] to an index into
+/// [`NOTE_DIVISIONS`][crate::params::time::NOTE_DIVISIONS]. Dotted notes can also be written with a
+/// `d` suffix, and triplets with a lowercase `t`.
+pub fn s2v_i32_note_division() -> Arc<dyn Fn(&str) -> Option<i32> + Send + Sync> {
+    Arc::new(|string| {
+        let string = string.trim();
+        let (fraction, modifier) = if let Some(fraction) = string.strip_suffix(['.', 'd', 'D']) {
+            (fraction, DivisionModifier::Dotted)
+        } else if let Some(fraction) = string.strip_suffix(['t', 'T']) {
+            (fraction, DivisionModifier::Triplet)
+        } else {
+            (string, DivisionModifier::Straight)
+        };
+
+        let (numerator, denominator) = fraction.split_once('/')?;
+        NoteDivision {
+            numerator: numerator.trim().parse().ok()?,
+            denominator: denominator.trim().parse().ok()?,
+            modifier,
+        }
+        .index()
+    })
+}
+
 /// Format an order/power of two. Useful in conjunction with [`s2v_i32_power_of_two()`] to limit
 /// integer parameter ranges to be only powers of two.
 pub fn v2s_i32_power_of_two() -> Arc<dyn Fn(i32) -> String + Send + Sync> {
@@ -352,6 +416,32 @@ mod tests {
         assert_eq!("0.01", v2s(0.009));
     }
 
+    #[test]
+    fn note_division_roundtrip() {
+        let v2s = v2s_i32_note_division();
+        let s2v = s2v_i32_note_division();
+
+        for index in 0..crate::params::time::NOTE_DIVISIONS.len() as i32 {
+            assert_eq!(Some(index), s2v(&v2s(index)));
+        }
+
+        assert_eq!(s2v("1/8."), s2v("1/8d"));
+        assert_eq!(s2v("1/16T"), s2v(" 1/16t "));
+        assert_eq!(None, s2v("1/3"));
+    }
+
+    #[test]
+    fn ms_then_s_roundtrip() {
+        let v2s = v2s_f32_ms_then_s(2);
+        let s2v = s2v_f32_ms_then_s();
+
+        assert_eq!("250.00 ms", v2s(250.0));
+        assert_eq!("1.50 s", v2s(1500.0));
+        assert_eq!(Some(1500.0), s2v(&v2s(1500.0)));
+        assert_eq!(Some(250.0), s2v(&v2s(250.0)));
+        assert_eq!(Some(42.0), s2v("42"));
+    }
+
     // More of these validators could use tests, but this one in particular is tricky and I noticed
     // an issue where it didn't roundtrip correctly
     #[test]
@@ -5,6 +5,7 @@ use midi_consts::channel_event as midi;
 use self::sysex::SysExMessage;
 use crate::prelude::Plugin;
 
+pub mod learn;
 pub mod sysex;
 
 pub use midi_consts::channel_event::control_change;
@@ -646,6 +647,32 @@ impl<S: SysExMessage> NoteEvent<S> {
             NoteEvent::MidiSysEx { timing, .. } => *timing -= samples,
         }
     }
+
+    /// Overwrite this event's timing. Used in the standalone wrapper to assign timings to events
+    /// that were received before their position in the audio buffer was known.
+    #[cfg_attr(not(feature = "standalone"), allow(dead_code))]
+    pub(crate) fn set_timing(&mut self, new_timing: u32) {
+        match self {
+            NoteEvent::NoteOn { timing, .. } => *timing = new_timing,
+            NoteEvent::NoteOff { timing, .. } => *timing = new_timing,
+            NoteEvent::Choke { timing, .. } => *timing = new_timing,
+            NoteEvent::VoiceTerminated { timing, .. } => *timing = new_timing,
+            NoteEvent::PolyModulation { timing, .. } => *timing = new_timing,
+            NoteEvent::MonoAutomation { timing, .. } => *timing = new_timing,
+            NoteEvent::PolyPressure { timing, .. } => *timing = new_timing,
+            NoteEvent::PolyVolume { timing, .. } => *timing = new_timing,
+            NoteEvent::PolyPan { timing, .. } => *timing = new_timing,
+            NoteEvent::PolyTuning { timing, .. } => *timing = new_timing,
+            NoteEvent::PolyVibrato { timing, .. } => *timing = new_timing,
+            NoteEvent::PolyExpression { timing, .. } => *timing = new_timing,
+            NoteEvent::PolyBrightness { timing, .. } => *timing = new_timing,
+            NoteEvent::MidiChannelPressure { timing, .. } => *timing = new_timing,
+            NoteEvent::MidiPitchBend { timing, .. } => *timing = new_timing,
+            NoteEvent::MidiCC { timing, .. } => *timing = new_timing,
+            NoteEvent::MidiProgramChange { timing, .. } => *timing = new_timing,
+            NoteEvent::MidiSysEx { timing, .. } => *timing = new_timing,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,379 @@
+//! MIDI learn for a plugin's parameters. The plugin wrappers apply these mappings to incoming MIDI
+//! CC events before the plugin's `process()` function is called, so parameters can be controlled
+//! with a MIDI controller even in hosts that don't have a MIDI mapping feature of their own.
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::NoteEvent;
+use crate::prelude::ParamPtr;
+use crate::util::permit_alloc;
+
+/// A mapping from a MIDI CC to a parameter. These are created automatically when a CC is received
+/// while MIDI learn is armed for a parameter, and they can be changed afterwards through
+/// [`MidiLearn::set_mapping()`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MidiMapping {
+    /// The MIDI channel the CC needs to be received on, in `0..16`. If this is `None`, then the CC
+    /// is accepted on all channels.
+    pub channel: Option<u8>,
+    /// The control change number, in `0..128`.
+    pub cc: u8,
+    /// The normalized parameter value corresponding to a CC value of 0. This can be larger than
+    /// `max` to invert the mapping.
+    pub min: f32,
+    /// The normalized parameter value corresponding to a CC value of 127.
+    pub max: f32,
+    /// An exponent applied to the normalized CC value in [`MidiMappingMode::Absolute`] mode. `1.0`
+    /// results in a linear mapping, values above `1.0` give the lower part of the CC's range more
+    /// resolution, and values below `1.0` do the same for the upper part of the range.
+    pub curve: f32,
+    /// How the CC's values are interpreted.
+    pub mode: MidiMappingMode,
+}
+
+/// Determines how a [`MidiMapping`] interprets the CC's values. The relative modes are used by
+/// endless encoders, and they differ in how the encoder encodes the direction it was turned in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiMappingMode {
+    /// The CC's value is mapped directly to the parameter's value.
+    Absolute,
+    /// The CC's value is a two's complement offset. Values 1 through 63 increase the parameter's
+    /// value, and values 127 through 65 decrease it.
+    RelativeTwosComplement,
+    /// The CC's value is an offset from 64. Values 65 through 127 increase the parameter's value,
+    /// and values 63 through 0 decrease it.
+    RelativeBinaryOffset,
+    /// The CC's seventh bit indicates the direction. Values 1 through 63 increase the parameter's
+    /// value, and values 65 through 127 decrease it.
+    RelativeSignedBit,
+}
+
+/// Manages MIDI learn and the MIDI CC mappings for a plugin instance's parameters. Every wrapper
+/// owns one of these, and the plugin's editor can access it through
+/// [`GuiContext::midi_learn()`][crate::prelude::GuiContext::midi_learn()]. The mappings are stored
+/// in the [`PluginState`][crate::prelude::PluginState] together with the parameter values.
+///
+/// MIDI CCs are only received when the plugin's
+/// [`MIDI_INPUT`][crate::prelude::Plugin::MIDI_INPUT] is set to [`MidiConfig::MidiCCs`][super::MidiConfig::MidiCCs].
+/// Mapped CC events are still passed to the plugin.
+pub struct MidiLearn {
+    /// The parameter that will be mapped to the next CC that's received, if any.
+    armed: Mutex<Option<ParamPtr>>,
+    /// The current mappings. There is at most one mapping per parameter, but a single CC can
+    /// control multiple parameters. This is only locked for writing from the audio thread when a
+    /// new mapping is being learned.
+    mappings: RwLock<Vec<(ParamPtr, MidiMapping)>>,
+
+    /// Used to translate parameters to string IDs and back when saving and restoring the mappings.
+    param_id_to_ptr: HashMap<String, ParamPtr>,
+    param_ptr_to_id: HashMap<ParamPtr, String>,
+}
+
+impl Default for MidiMapping {
+    fn default() -> Self {
+        Self {
+            channel: None,
+            cc: 0,
+            min: 0.0,
+            max: 1.0,
+            curve: 1.0,
+            mode: MidiMappingMode::Absolute,
+        }
+    }
+}
+
+impl MidiMapping {
+    /// Create a linear absolute mapping for a CC on a specific channel, or on all channels if
+    /// `channel` is `None`.
+    pub fn new(channel: Option<u8>, cc: u8) -> Self {
+        Self {
+            channel,
+            cc,
+            ..Self::default()
+        }
+    }
+
+    /// Only use part of the parameter's normalized range.
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Apply an exponential curve to absolute CC values. See [`curve`][Self::curve].
+    pub fn with_curve(mut self, curve: f32) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    /// Change how the CC's values are interpreted.
+    pub fn with_mode(mut self, mode: MidiMappingMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Whether this mapping responds to a CC on a channel.
+    pub fn matches(&self, channel: u8, cc: u8) -> bool {
+        self.cc == cc && self.channel.map(|c| c == channel).unwrap_or(true)
+    }
+
+    /// Compute the parameter's new normalized value after receiving a CC with a normalized `value`.
+    /// `current` is the parameter's current unmodulated normalized value and `step_count` is the
+    /// parameter's step count, both of which are used for the relative modes.
+    pub fn apply(&self, value: f32, current: f32, step_count: Option<usize>) -> f32 {
+        let (lower, upper) = if self.min <= self.max {
+            (self.min, self.max)
+        } else {
+            (self.max, self.min)
+        };
+
+        let raw_value = (value.clamp(0.0, 1.0) * 127.0).round() as i32;
+        let delta = match self.mode {
+            MidiMappingMode::Absolute => {
+                let value = value.clamp(0.0, 1.0).powf(self.curve.max(f32::EPSILON));
+                return (self.min + ((self.max - self.min) * value)).clamp(lower, upper);
+            }
+            MidiMappingMode::RelativeTwosComplement if raw_value >= 64 => raw_value - 128,
+            MidiMappingMode::RelativeTwosComplement => raw_value,
+            MidiMappingMode::RelativeBinaryOffset => raw_value - 64,
+            MidiMappingMode::RelativeSignedBit if raw_value >= 64 => -(raw_value - 64),
+            MidiMappingMode::RelativeSignedBit => raw_value,
+        };
+
+        // Stepped parameters move by one step per encoder tick, since smaller increments would
+        // otherwise get lost to rounding
+        let step_size = match step_count {
+            Some(step_count) if step_count > 0 => 1.0 / step_count as f32,
+            _ => (upper - lower) / 127.0,
+        };
+
+        (current + (delta as f32 * step_size)).clamp(lower, upper)
+    }
+}
+
+impl MidiLearn {
+    /// Create a MIDI learn manager for a plugin's parameters, identified by their string IDs.
+    pub(crate) fn new<'a>(params: impl IntoIterator<Item = (&'a String, ParamPtr)>) -> Self {
+        let param_id_to_ptr: HashMap<String, ParamPtr> = params
+            .into_iter()
+            .map(|(param_id, param_ptr)| (param_id.clone(), param_ptr))
+            .collect();
+        let param_ptr_to_id = param_id_to_ptr
+            .iter()
+            .map(|(param_id, param_ptr)| (*param_ptr, param_id.clone()))
+            .collect();
+
+        Self {
+            armed: Mutex::new(None),
+            mappings: RwLock::new(Vec::new()),
+
+            param_id_to_ptr,
+            param_ptr_to_id,
+        }
+    }
+
+    /// Map the next MIDI CC that's received to `param`. This replaces the parameter's existing
+    /// mapping, if it has one. Arming MIDI learn for another parameter cancels this.
+    pub fn arm(&self, param: ParamPtr) {
+        if self.param_ptr_to_id.contains_key(&param) {
+            *self.armed.lock() = Some(param);
+        } else {
+            nih_debug_assert_failure!("Unknown parameter: {:?}", param);
+        }
+    }
+
+    /// Cancel MIDI learn if it is armed.
+    pub fn disarm(&self) {
+        *self.armed.lock() = None;
+    }
+
+    /// The parameter MIDI learn is currently armed for, if any.
+    pub fn armed_param(&self) -> Option<ParamPtr> {
+        *self.armed.lock()
+    }
+
+    /// Get the parameter's current MIDI mapping, if it has one.
+    pub fn mapping(&self, param: ParamPtr) -> Option<MidiMapping> {
+        self.mappings
+            .read()
+            .iter()
+            .find(|(p, _)| *p == param)
+            .map(|(_, mapping)| *mapping)
+    }
+
+    /// Get all current MIDI mappings.
+    pub fn mappings(&self) -> Vec<(ParamPtr, MidiMapping)> {
+        self.mappings.read().clone()
+    }
+
+    /// Replace the parameter's MIDI mapping, or remove it if `mapping` is `None`.
+    pub fn set_mapping(&self, param: ParamPtr, mapping: Option<MidiMapping>) {
+        if !self.param_ptr_to_id.contains_key(&param) {
+            nih_debug_assert_failure!("Unknown parameter: {:?}", param);
+            return;
+        }
+
+        let mut mappings = self.mappings.write();
+        mappings.retain(|(p, _)| *p != param);
+        if let Some(mapping) = mapping {
+            mappings.push((param, mapping));
+        }
+    }
+
+    /// Remove all MIDI mappings.
+    pub fn clear(&self) {
+        self.mappings.write().clear();
+    }
+
+    /// Get the current mappings keyed by the parameters' string IDs so they can be stored in a
+    /// [`PluginState`][crate::prelude::PluginState].
+    pub(crate) fn serialize(&self) -> BTreeMap<String, MidiMapping> {
+        self.mappings
+            .read()
+            .iter()
+            .filter_map(|(param_ptr, mapping)| {
+                Some((self.param_ptr_to_id.get(param_ptr)?.clone(), *mapping))
+            })
+            .collect()
+    }
+
+    /// Replace the current mappings with mappings restored from a
+    /// [`PluginState`][crate::prelude::PluginState].
+    pub(crate) fn deserialize(&self, mappings: &BTreeMap<String, MidiMapping>) {
+        let mappings: Vec<_> = mappings
+            .iter()
+            .filter_map(
+                |(param_id, mapping)| match self.param_id_to_ptr.get(param_id) {
+                    Some(param_ptr) => Some((*param_ptr, *mapping)),
+                    None => {
+                        nih_debug_assert_failure!(
+                            "Unknown parameter in MIDI mapping: {}",
+                            param_id
+                        );
+                        None
+                    }
+                },
+            )
+            .collect();
+
+        *self.mappings.write() = mappings;
+    }
+
+    /// Apply a note event to the mapped parameters. If MIDI learn is armed and this is a MIDI CC,
+    /// then a new mapping is created for it first. `set_parameter` is called with the new
+    /// normalized value for every parameter mapped to the CC. The wrapper is responsible for
+    /// setting the value and informing the host and the editor about the change.
+    ///
+    /// This is called from the audio thread and never blocks. If the GUI is changing the mappings
+    /// at the same time, the event is ignored.
+    pub(crate) fn handle_event<S>(
+        &self,
+        event: &NoteEvent<S>,
+        mut set_parameter: impl FnMut(ParamPtr, f32),
+    ) {
+        let (channel, cc, value) = match *event {
+            NoteEvent::MidiCC {
+                channel, cc, value, ..
+            } => (channel, cc, value),
+            _ => return,
+        };
+
+        if let Some(mut armed) = self.armed.try_lock() {
+            if let Some(param_ptr) = armed.take() {
+                match self.mappings.try_write() {
+                    Some(mut mappings) => {
+                        mappings.retain(|(p, _)| *p != param_ptr);
+                        // This may need to resize the vector, but it only happens once per learned
+                        // mapping
+                        permit_alloc(|| {
+                            mappings.push((param_ptr, MidiMapping::new(Some(channel), cc)))
+                        });
+                    }
+                    // Try again with the next CC
+                    None => *armed = Some(param_ptr),
+                }
+            }
+        }
+
+        let mappings = match self.mappings.try_read() {
+            Some(mappings) => mappings,
+            None => return,
+        };
+        for (param_ptr, mapping) in mappings.iter() {
+            if mapping.matches(channel, cc) {
+                let (current, step_count) = unsafe {
+                    (
+                        param_ptr.unmodulated_normalized_value(),
+                        param_ptr.step_count(),
+                    )
+                };
+
+                set_parameter(*param_ptr, mapping.apply(value, current, step_count));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cc_value(raw_value: u8) -> f32 {
+        raw_value as f32 / 127.0
+    }
+
+    #[test]
+    fn test_absolute_mapping() {
+        let mapping = MidiMapping::new(None, 1).with_range(0.25, 0.75);
+
+        assert_eq!(mapping.apply(cc_value(0), 0.5, None), 0.25);
+        assert_eq!(mapping.apply(cc_value(127), 0.5, None), 0.75);
+    }
+
+    #[test]
+    fn test_inverted_curved_mapping() {
+        let mapping = MidiMapping::new(None, 1)
+            .with_range(1.0, 0.0)
+            .with_curve(2.0);
+
+        assert_eq!(mapping.apply(cc_value(0), 0.5, None), 1.0);
+        assert_eq!(mapping.apply(0.5, 0.5, None), 0.75);
+        assert_eq!(mapping.apply(cc_value(127), 0.5, None), 0.0);
+    }
+
+    #[test]
+    fn test_relative_modes() {
+        let twos_complement =
+            MidiMapping::new(None, 1).with_mode(MidiMappingMode::RelativeTwosComplement);
+        let binary_offset =
+            MidiMapping::new(None, 1).with_mode(MidiMappingMode::RelativeBinaryOffset);
+        let signed_bit = MidiMapping::new(None, 1).with_mode(MidiMappingMode::RelativeSignedBit);
+
+        let step = 1.0 / 127.0;
+        assert_eq!(twos_complement.apply(cc_value(1), 0.5, None), 0.5 + step);
+        assert_eq!(twos_complement.apply(cc_value(127), 0.5, None), 0.5 - step);
+        assert_eq!(binary_offset.apply(cc_value(65), 0.5, None), 0.5 + step);
+        assert_eq!(binary_offset.apply(cc_value(63), 0.5, None), 0.5 - step);
+        assert_eq!(signed_bit.apply(cc_value(1), 0.5, None), 0.5 + step);
+        assert_eq!(signed_bit.apply(cc_value(65), 0.5, None), 0.5 - step);
+    }
+
+    #[test]
+    fn test_relative_stepped() {
+        let mapping = MidiMapping::new(None, 1).with_mode(MidiMappingMode::RelativeBinaryOffset);
+
+        assert_eq!(mapping.apply(cc_value(66), 0.0, Some(4)), 0.5);
+        assert_eq!(mapping.apply(cc_value(0), 0.5, Some(4)), 0.0);
+    }
+
+    #[test]
+    fn test_channel_matching() {
+        assert!(MidiMapping::new(None, 7).matches(3, 7));
+        assert!(MidiMapping::new(Some(3), 7).matches(3, 7));
+        assert!(!MidiMapping::new(Some(2), 7).matches(3, 7));
+        assert!(!MidiMapping::new(None, 8).matches(3, 7));
+    }
+}
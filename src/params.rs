@@ -9,6 +9,7 @@ use std::fmt::{Debug, Display};
 use std::sync::Arc;
 
 use self::internals::ParamPtr;
+use crate::wrapper::state::PluginState;
 
 // The proc-macro for deriving `Params`
 pub use nih_plug_derive::Params;
@@ -18,8 +19,11 @@ mod boolean;
 pub mod enums;
 mod float;
 mod integer;
+mod text;
+pub mod time;
 
 pub mod internals;
+pub mod migrate;
 pub mod persist;
 pub mod range;
 pub mod smoothing;
@@ -28,6 +32,8 @@ pub use boolean::BoolParam;
 pub use enums::EnumParam;
 pub use float::FloatParam;
 pub use integer::IntParam;
+pub use text::{PathParam, StringParam, TextParam, ValueSnapshot};
+pub use time::{NoteDivision, SyncedTimeParams};
 
 bitflags::bitflags! {
     /// Flags for controlling a parameter's behavior.
@@ -37,7 +43,8 @@ bitflags::bitflags! {
         /// When applied to a [`BoolParam`], this will cause the parameter to be linked to the
         /// host's bypass control. Only a single parameter can be marked as a bypass parameter. If
         /// you don't have a bypass parameter, then NIH-plug will add one for you. You will need to
-        /// implement this yourself if your plugin introduces latency.
+        /// implement this yourself if your plugin introduces latency, unless you let the wrapper
+        /// handle bypass through [`Plugin::BYPASS_POLICY`][crate::prelude::Plugin::BYPASS_POLICY].
         const BYPASS = 1 << 0;
         /// The parameter cannot be changed from an automation lane. The parameter can however still
         /// be manually changed by the user from either the plugin's own GUI or from the host's
@@ -77,8 +84,9 @@ pub trait Param: Display + Debug + sealed::Sealed {
     /// The plain parameter type.
     type Plain: PartialEq;
 
-    /// Get the human readable name for this parameter.
-    fn name(&self) -> &str;
+    /// Get the human readable name for this parameter. Names can be changed at runtime, so this
+    /// returns a reference counted snapshot of the current name.
+    fn name(&self) -> Arc<String>;
 
     /// Get the unit label for this parameter, if any.
     fn unit(&self) -> &'static str;
@@ -249,12 +257,27 @@ pub(crate) trait ParamMut: Param {
 /// with the `#[persist = "key"]` attribute containing types that can be serialized and deserialized
 /// with [Serde](https://serde.rs/).
 ///
+/// Non-automatable text values like script code or file paths can be stored in a [`StringParam`] or
+/// a [`PathParam`] field with this attribute. These also have change callbacks and can be read
+/// from the audio thread without blocking.
+///
+/// ## `#[persist_bytes = "key"]`
+///
+/// Works like `#[persist = "key"]`, but for
+/// [`PersistentBytesField`][persist::PersistentBytesField]s containing raw bytes, like a
+/// `Mutex<Vec<u8>>`. These are stored in [`PluginState::binary_fields`] instead of being converted
+/// to JSON strings, and states containing these fields are saved using a binary container format.
+/// This is much faster for larger amounts of data like audio samples or wavetables. Keys are shared
+/// with the `#[persist]` fields.
+///
 /// ## `#[nested]`, `#[nested(group_name = "group name")]`
 ///
 /// Finally, the `Params` object may include parameters from other objects. Setting a group name is
 /// optional, but some hosts can use this information to display the parameters in a tree structure.
 /// Parameter IDs and persisting keys still need to be **unique** when using nested parameter
-/// structs.
+/// structs. For structs without generic parameters, the derive macro checks this at compile time,
+/// including for IDs with `id_prefix` prefixes and array index suffixes. The same check also
+/// rejects IDs whose hashes collide, since CLAP and VST3 use these hashes to identify parameters.
 ///
 /// Take a look at the example gain example plugin to see how this is used.
 ///
@@ -275,6 +298,35 @@ pub(crate) trait ParamMut: Param {
 /// parameter will belong to the group `Foo {array_index + 1}`, and it will have the renamed
 /// parameter ID `bar_{array_index + 1}`. The same thing applies to persistent field keys.
 ///
+/// ## `#[old_ids("old_id", ...)]`, `#[old_keys("old_key", ...)]`
+///
+/// When a parameter's ID needs to change, the parameter's previous IDs can be listed using the
+/// `#[old_ids(...)]` attribute. Values and MIDI mappings stored under those IDs in an old
+/// [`PluginState`] will then be moved to the parameter's current ID before the state is loaded.
+/// The same can be done for `#[persist = "key"]` fields with the `#[old_keys(...)]` attribute. Old
+/// IDs and keys should not be reused for other parameters or fields. These attributes also work
+/// for nested parameter structs.
+///
+/// ## `#[migrate(before = "0.2.0", with = "function")]`
+///
+/// Transforms a parameter's or persistent field's stored value when loading a state that was saved
+/// with a plugin version older than `before`, as determined by [`PluginState::version`]. For
+/// parameters the function should have the signature `fn(ParamValue) -> ParamValue`, and for
+/// persistent fields it receives and returns the field's JSON representation as a `String`, or the
+/// field's raw bytes as a `Vec<u8>` for `#[persist_bytes]` fields. This attribute can be used
+/// multiple times on the same field, in which case the functions are applied in order. See
+/// [`migrate::version_older_than()`] for how versions are compared.
+///
+/// ```ignore
+/// #[id = "gain_db"]
+/// #[old_ids("gain")]
+/// #[migrate(before = "0.3.0", with = "gain_to_db")]
+/// pub gain: FloatParam,
+/// ```
+///
+/// These migrations are applied before
+/// [`Plugin::filter_state()`][crate::prelude::Plugin::filter_state()] is called.
+///
 /// # Safety
 ///
 /// This implementation is safe when using from the wrapper because the plugin's returned `Params`
@@ -311,6 +363,29 @@ pub unsafe trait Params: 'static + Send + Sync {
     /// [`persist::deserialize_field()`] under the hood.
     #[allow(unused_variables)]
     fn deserialize_fields(&self, serialized: &BTreeMap<String, String>) {}
+
+    /// Serialize all fields marked with `#[persist_bytes = "stable_name"]` into a hash map
+    /// containing those fields' raw bytes. This is the [`persist::PersistentBytesField`] equivalent
+    /// of [`serialize_fields()`][Self::serialize_fields()].
+    fn serialize_binary_fields(&self) -> BTreeMap<String, Vec<u8>> {
+        BTreeMap::new()
+    }
+
+    /// Restore all fields marked with `#[persist_bytes = "stable_name"]` from a hashmap created by
+    /// [`serialize_binary_fields()`][Self::serialize_binary_fields()]. This gets called right after
+    /// [`deserialize_fields()`][Self::deserialize_fields()] when the plugin's state is being
+    /// restored.
+    #[allow(unused_variables)]
+    fn deserialize_binary_fields(&self, serialized: &BTreeMap<String, Vec<u8>>) {}
+
+    /// Migrate parameter values and persistent fields in an old [`PluginState`] to the current
+    /// parameter IDs and field keys before the state is loaded. This gets called just before
+    /// [`Plugin::filter_state()`][crate::prelude::Plugin::filter_state()]. The derive macro
+    /// implements this using the `#[old_ids(...)]`, `#[old_keys(...)]`, and `#[migrate(...)]`
+    /// attributes, and it also migrates nested parameter structs. The functions in the [`migrate`]
+    /// module can be used to implement this manually.
+    #[allow(unused_variables)]
+    fn migrate_state(&self, state: &mut PluginState) {}
 }
 
 /// This may be useful when building generic UIs using nested `Params` objects.
@@ -326,4 +401,16 @@ unsafe impl<P: Params> Params for Arc<P> {
     fn deserialize_fields(&self, serialized: &BTreeMap<String, String>) {
         self.as_ref().deserialize_fields(serialized)
     }
+
+    fn serialize_binary_fields(&self) -> BTreeMap<String, Vec<u8>> {
+        self.as_ref().serialize_binary_fields()
+    }
+
+    fn deserialize_binary_fields(&self, serialized: &BTreeMap<String, Vec<u8>>) {
+        self.as_ref().deserialize_binary_fields(serialized)
+    }
+
+    fn migrate_state(&self, state: &mut PluginState) {
+        self.as_ref().migrate_state(state)
+    }
 }
@@ -1,5 +1,6 @@
 //! Simple boolean parameters.
 
+use arc_swap::ArcSwap;
 use atomic_float::AtomicF32;
 use std::fmt::{Debug, Display};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -35,8 +36,9 @@ pub struct BoolParam {
     /// thread.
     value_changed: Option<Arc<dyn Fn(bool) + Send + Sync>>,
 
-    /// The parameter's human readable display name.
-    name: String,
+    /// The parameter's human readable display name. This can be changed at runtime using
+    /// [`set_name()`][Self::set_name()].
+    name: ArcSwap<String>,
     /// If this parameter has been marked as polyphonically modulatable, then this will be a unique
     /// integer identifying the parameter. Because this value is determined by the plugin itself,
     /// the plugin can easily map
@@ -65,9 +67,9 @@ impl Debug for BoolParam {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // This uses the above `Display` instance to show the value
         if self.value.load(Ordering::Relaxed) != self.unmodulated_value.load(Ordering::Relaxed) {
-            write!(f, "{}: {} (modulated)", &self.name, &self)
+            write!(f, "{}: {} (modulated)", self.name(), &self)
         } else {
-            write!(f, "{}: {}", &self.name, &self)
+            write!(f, "{}: {}", self.name(), &self)
         }
     }
 }
@@ -78,8 +80,8 @@ impl super::Sealed for BoolParam {}
 impl Param for BoolParam {
     type Plain = bool;
 
-    fn name(&self) -> &str {
-        &self.name
+    fn name(&self) -> Arc<String> {
+        self.name.load_full()
     }
 
     fn unit(&self) -> &'static str {
@@ -241,13 +243,29 @@ impl BoolParam {
             flags: ParamFlags::default(),
             value_changed: None,
 
-            name: name.into(),
+            name: ArcSwap::from_pointee(name.into()),
             poly_modulation_id: None,
             value_to_string: None,
             string_to_value: None,
         }
     }
 
+    /// Change the parameter's default value and reset the parameter to it, while keeping the rest
+    /// of the parameter's configuration. Used for parameter wrappers like
+    /// [`SyncedTimeParams`][super::SyncedTimeParams] that create their parameters themselves.
+    pub(crate) fn with_default(mut self, default: bool) -> Self {
+        let normalized_value = if default { 1.0 } else { 0.0 };
+        self.value.store(default, Ordering::Relaxed);
+        self.normalized_value
+            .store(normalized_value, Ordering::Relaxed);
+        self.unmodulated_value.store(default, Ordering::Relaxed);
+        self.unmodulated_normalized_value
+            .store(normalized_value, Ordering::Relaxed);
+        self.default = default;
+
+        self
+    }
+
     /// The field's current plain value, after monophonic modulation has been applied. Equivalent to
     /// calling `param.plain_value()`.
     #[inline]
@@ -255,6 +273,16 @@ impl BoolParam {
         self.modulated_plain_value()
     }
 
+    /// Change the parameter's display name at runtime. The host and the editor won't pick up on
+    /// this change until you call
+    /// [`GuiContext::param_info_changed()`][crate::prelude::GuiContext::param_info_changed()] or
+    /// [`ProcessContext::param_info_changed()`][crate::prelude::ProcessContext::param_info_changed()].
+    ///
+    /// This allocates, so it should not be called from the audio thread.
+    pub fn set_name(&self, name: impl Into<String>) {
+        self.name.store(Arc::new(name.into()));
+    }
+
     /// Enable polyphonic modulation for this parameter. The ID is used to uniquely identify this
     /// parameter in [`NoteEvent::PolyModulation`][crate::prelude::NoteEvent::PolyModulation]
     /// events, and must thus be unique between _all_ polyphonically modulatable parameters. See the
@@ -304,7 +332,8 @@ impl BoolParam {
     /// Mark this parameter as a bypass parameter. Plugin hosts can integrate this parameter into
     /// their UI. Only a single [`BoolParam`] can be a bypass parameter, and NIH-plug will add one
     /// if you don't create one yourself. You will need to implement this yourself if your plugin
-    /// introduces latency.
+    /// introduces latency, unless you let the wrapper handle bypass through
+    /// [`Plugin::BYPASS_POLICY`][crate::prelude::Plugin::BYPASS_POLICY].
     pub fn make_bypass(mut self) -> Self {
         self.flags.insert(ParamFlags::BYPASS);
         self
@@ -1,5 +1,6 @@
 //! Enum parameters. `enum` is a keyword, so `enums` it is.
 
+use arc_swap::ArcSwap;
 use std::fmt::{Debug, Display};
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -85,8 +86,14 @@ pub struct EnumParam<T: Enum + PartialEq> {
 pub struct EnumParamInner {
     /// The integer parameter backing this enum parameter.
     pub(crate) inner: IntParam,
-    /// The human readable variant names, obtained from [Enum::variants()].
-    variants: &'static [&'static str],
+    /// The human readable variant names, initially obtained from [Enum::variants()]. These can be
+    /// changed at runtime using [`EnumParam::set_variant_names()`]. This may contain fewer names
+    /// than the enum has variants, in which case only the first `variants.len()` variants can be
+    /// selected.
+    variants: ArcSwap<Vec<String>>,
+    /// The number of variants in the enum, obtained from [Enum::variants()]. This is the upper
+    /// limit for the number of variant names.
+    num_enum_variants: usize,
     /// Stable identifiers for the enum variants, obtained from [Enum::ids()]. These are optional,
     /// but if they are set (they're either not set for any variant, or set for all variants) then
     /// these identifiers are used when saving enum parameter values to the state. Otherwise the
@@ -102,10 +109,11 @@ impl<T: Enum + PartialEq> Display for EnumParam<T> {
 
 impl Display for EnumParamInner {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variants = self.variants.load();
         write!(
             f,
             "{}",
-            self.variants[self.inner.modulated_plain_value() as usize]
+            variant_name(&variants, self.inner.modulated_plain_value())
         )
     }
 }
@@ -133,7 +141,7 @@ impl<T: Enum + PartialEq> super::Sealed for EnumParam<T> {}
 impl<T: Enum + PartialEq> Param for EnumParam<T> {
     type Plain = T;
 
-    fn name(&self) -> &str {
+    fn name(&self) -> Arc<String> {
         self.inner.name()
     }
 
@@ -216,7 +224,7 @@ impl super::Sealed for EnumParamInner {}
 impl Param for EnumParamInner {
     type Plain = i32;
 
-    fn name(&self) -> &str {
+    fn name(&self) -> Arc<String> {
         self.inner.name()
     }
 
@@ -267,12 +275,13 @@ impl Param for EnumParamInner {
 
     fn normalized_value_to_string(&self, normalized: f32, _include_unit: bool) -> String {
         let index = self.preview_plain(normalized);
-        self.variants[index as usize].to_string()
+        variant_name(&self.variants.load(), index).to_owned()
     }
 
     fn string_to_normalized_value(&self, string: &str) -> Option<f32> {
         let string = string.trim();
         self.variants
+            .load()
             .iter()
             .position(|variant| variant == &string)
             .map(|idx| self.preview_normalized(idx as i32))
@@ -350,7 +359,10 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
                         max: variants.len() as i32 - 1,
                     },
                 ),
-                variants,
+                variants: ArcSwap::from_pointee(
+                    variants.iter().map(|variant| variant.to_string()).collect(),
+                ),
+                num_enum_variants: variants.len(),
                 ids,
             },
             _marker: PhantomData,
@@ -363,6 +375,51 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
         self.modulated_plain_value()
     }
 
+    /// Change the parameter's display name at runtime. The host and the editor won't pick up on
+    /// this change until you call
+    /// [`GuiContext::param_info_changed()`][crate::prelude::GuiContext::param_info_changed()] or
+    /// [`ProcessContext::param_info_changed()`][crate::prelude::ProcessContext::param_info_changed()].
+    ///
+    /// This allocates, so it should not be called from the audio thread.
+    pub fn set_name(&self, name: impl Into<String>) {
+        self.inner.inner.set_name(name);
+    }
+
+    /// Replace the human readable variant names at runtime. The names correspond to `T`'s variants
+    /// in order. If fewer names than `T` has variants are passed, then only the first `names.len()`
+    /// variants can be selected, and the parameter's value is clamped to the last of those
+    /// variants. Passing [`Enum::variants()`] restores the original names and variants. This can
+    /// be used to, for instance, only offer the waveforms that are available in the current
+    /// configuration. As with [`set_name()`][Self::set_name()], the host and the editor need to be
+    /// notified about this change.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `names` is empty or contains more names than `T` has variants.
+    pub fn set_variant_names<S: Into<String>>(&self, names: impl IntoIterator<Item = S>) {
+        let names: Vec<String> = names.into_iter().map(|name| name.into()).collect();
+        assert!(
+            !names.is_empty() && names.len() <= self.inner.num_enum_variants,
+            "The number of variant names must be between 1 and the number of enum variants"
+        );
+
+        // The variant names are used to format the parameter's current value, so the range should
+        // never contain more values than there are names
+        let old_len = self.inner.len();
+        let new_len = names.len();
+        let new_range = IntRange::Linear {
+            min: 0,
+            max: new_len as i32 - 1,
+        };
+        if new_len < old_len {
+            self.inner.inner.set_range(new_range);
+            self.inner.variants.store(Arc::new(names));
+        } else {
+            self.inner.variants.store(Arc::new(names));
+            self.inner.inner.set_range(new_range);
+        }
+    }
+
     /// Enable polyphonic modulation for this parameter. The ID is used to uniquely identify this
     /// parameter in [`NoteEvent::PolyModulation`][crate::prelude::NoteEvent::PolyModulation]
     /// events, and must thus be unique between _all_ polyphonically modulatable parameters. See the
@@ -416,10 +473,12 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
 }
 
 impl EnumParamInner {
-    /// Get the number of variants for this enum.
+    /// Get the number of variants for this enum. This may be lower than the number of variants in
+    /// the original enum type if the variants have been restricted with
+    /// [`EnumParam::set_variant_names()`].
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
-        self.variants.len()
+        self.variants.load().len()
     }
 
     /// Get the stable ID for the parameter's current value according to
@@ -435,10 +494,11 @@ impl EnumParamInner {
     /// Set the parameter based on a serialized stable string identifier. Return whether the ID was
     /// known and the parameter was set.
     pub fn set_from_id(&self, id: &str) -> bool {
-        match self
-            .ids
-            .and_then(|ids| ids.iter().position(|candidate| *candidate == id))
-        {
+        match self.ids.and_then(|ids| {
+            ids[..self.len()]
+                .iter()
+                .position(|candidate| *candidate == id)
+        }) {
             Some(index) => {
                 self.set_plain_value(index as i32);
                 true
@@ -447,3 +507,117 @@ impl EnumParamInner {
         }
     }
 }
+
+/// Get the name for the variant at `index`. [`EnumParam::set_variant_names()`] replaces the names
+/// and the parameter's range separately, so another thread may still see a value from before the
+/// range was shrunk. In that case this returns the last name instead of indexing out of bounds.
+fn variant_name(variants: &[String], index: i32) -> &str {
+    &variants[(index.max(0) as usize).min(variants.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum Waveform {
+        Sine,
+        Triangle,
+        Saw,
+        Square,
+    }
+
+    impl Enum for Waveform {
+        fn variants() -> &'static [&'static str] {
+            &["Sine", "Triangle", "Saw", "Square"]
+        }
+
+        fn ids() -> Option<&'static [&'static str]> {
+            None
+        }
+
+        fn to_index(self) -> usize {
+            self as usize
+        }
+
+        fn from_index(index: usize) -> Self {
+            match index {
+                1 => Waveform::Triangle,
+                2 => Waveform::Saw,
+                3 => Waveform::Square,
+                _ => Waveform::Sine,
+            }
+        }
+    }
+
+    #[test]
+    fn set_name() {
+        let param = EnumParam::new("Waveform", Waveform::Saw);
+        let old_name = param.name();
+
+        param.set_name("Shape");
+        assert_eq!(param.name().as_str(), "Shape");
+        assert_eq!(old_name.as_str(), "Waveform");
+        assert_eq!(format!("{param:?}"), "Shape: Saw");
+    }
+
+    #[test]
+    fn rename_variants() {
+        let param = EnumParam::new("Waveform", Waveform::Saw);
+        param.set_variant_names(["Sin", "Tri", "Sawtooth", "Pulse"]);
+
+        assert_eq!(param.value(), Waveform::Saw);
+        assert_eq!(param.to_string(), "Sawtooth");
+        assert_eq!(param.step_count(), Some(3));
+        assert_eq!(param.string_to_normalized_value("Saw"), None);
+        approx::assert_relative_eq!(param.string_to_normalized_value("Pulse").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn restrict_variants() {
+        let param = EnumParam::new("Waveform", Waveform::Square);
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 1.0);
+
+        // The value is clamped to the last remaining variant, and the normalized value is
+        // recomputed for the smaller range
+        param.set_variant_names(["Sine", "Triangle"]);
+        assert_eq!(param.value(), Waveform::Triangle);
+        assert_eq!(param.to_string(), "Triangle");
+        assert_eq!(param.step_count(), Some(1));
+        assert_eq!(param.string_to_normalized_value("Saw"), None);
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 1.0);
+        approx::assert_relative_eq!(param.modulated_normalized_value(), 1.0);
+
+        param.set_plain_value(Waveform::Sine);
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 0.0);
+        param.set_plain_value(Waveform::Triangle);
+
+        // Restoring the original variants keeps the value, but the normalized value changes again
+        param.set_variant_names(Waveform::variants().iter().copied());
+        assert_eq!(param.value(), Waveform::Triangle);
+        assert_eq!(param.step_count(), Some(3));
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 1.0 / 3.0);
+        approx::assert_relative_eq!(param.modulated_normalized_value(), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn stale_value_after_restricting_variants() {
+        let param = EnumParam::new("Waveform", Waveform::Square);
+
+        // This is what another thread could observe while `set_variant_names()` is running: the
+        // names have already been replaced, but the value has not yet been clamped
+        param.inner.variants.store(Arc::new(vec![
+            String::from("Sine"),
+            String::from("Triangle"),
+        ]));
+        assert_eq!(param.to_string(), "Triangle");
+        assert_eq!(param.normalized_value_to_string(1.0, true), "Triangle");
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_many_variant_names() {
+        let param = EnumParam::new("Waveform", Waveform::Saw);
+        param.set_variant_names(["A", "B", "C", "D", "E"]);
+    }
+}
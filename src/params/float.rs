@@ -1,6 +1,8 @@
 //! Continuous (or discrete, with a step size) floating point parameters.
 
+use arc_swap::ArcSwap;
 use atomic_float::AtomicF32;
+use crossbeam::atomic::AtomicCell;
 use std::fmt::{Debug, Display};
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -45,14 +47,16 @@ pub struct FloatParam {
     /// TODO: We probably also want to pass the old value to this function.
     value_changed: Option<Arc<dyn Fn(f32) + Send + Sync>>,
 
-    /// The distribution of the parameter's values.
-    range: FloatRange,
+    /// The distribution of the parameter's values. This can be changed at runtime using
+    /// [`set_range()`][Self::set_range()].
+    range: AtomicCell<FloatRange>,
     /// The distance between discrete steps in this parameter. Mostly useful for quantizing GUI
     /// input. If this is set and if [`value_to_string`][Self::value_to_string] is not set, then
     /// this is also used when formatting the parameter. This must be a positive, nonzero number.
     step_size: Option<f32>,
-    /// The parameter's human readable display name.
-    name: String,
+    /// The parameter's human readable display name. This can be changed at runtime using
+    /// [`set_name()`][Self::set_name()].
+    name: ArcSwap<String>,
     /// The parameter value's unit, added after [`value_to_string`][Self::value_to_string] if that
     /// is set. NIH-plug will not automatically add a space before the unit.
     unit: &'static str,
@@ -90,9 +94,9 @@ impl Debug for FloatParam {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // This uses the above `Display` instance to show the value
         if self.modulated_plain_value() != self.unmodulated_plain_value() {
-            write!(f, "{}: {} (modulated)", &self.name, &self)
+            write!(f, "{}: {} (modulated)", self.name(), &self)
         } else {
-            write!(f, "{}: {}", &self.name, &self)
+            write!(f, "{}: {}", self.name(), &self)
         }
     }
 }
@@ -103,8 +107,8 @@ impl super::Sealed for FloatParam {}
 impl Param for FloatParam {
     type Plain = f32;
 
-    fn name(&self) -> &str {
-        &self.name
+    fn name(&self) -> Arc<String> {
+        self.name.load_full()
     }
 
     fn unit(&self) -> &'static str {
@@ -145,11 +149,11 @@ impl Param for FloatParam {
     }
 
     fn previous_step(&self, from: Self::Plain, finer: bool) -> Self::Plain {
-        self.range.previous_step(from, self.step_size, finer)
+        self.range.load().previous_step(from, self.step_size, finer)
     }
 
     fn next_step(&self, from: Self::Plain, finer: bool) -> Self::Plain {
-        self.range.next_step(from, self.step_size, finer)
+        self.range.load().next_step(from, self.step_size, finer)
     }
 
     fn normalized_value_to_string(&self, normalized: f32, include_unit: bool) -> String {
@@ -182,14 +186,15 @@ impl Param for FloatParam {
 
     #[inline]
     fn preview_normalized(&self, plain: Self::Plain) -> f32 {
-        self.range.normalize(plain)
+        self.range.load().normalize(plain)
     }
 
     #[inline]
     fn preview_plain(&self, normalized: f32) -> Self::Plain {
-        let value = self.range.unnormalize(normalized);
+        let range = self.range.load();
+        let value = range.unnormalize(normalized);
         match &self.step_size {
-            Some(step_size) => self.range.snap_to_step(value, *step_size as Self::Plain),
+            Some(step_size) => range.snap_to_step(value, *step_size as Self::Plain),
             None => value,
         }
     }
@@ -283,9 +288,9 @@ impl FloatParam {
             flags: ParamFlags::default(),
             value_changed: None,
 
-            range,
+            range: AtomicCell::new(range),
             step_size: None,
-            name: name.into(),
+            name: ArcSwap::from_pointee(name.into()),
             unit: "",
             poly_modulation_id: None,
             value_to_string: None,
@@ -303,7 +308,51 @@ impl FloatParam {
     /// The range of valid plain values for this parameter.
     #[inline]
     pub fn range(&self) -> FloatRange {
-        self.range
+        self.range.load()
+    }
+
+    /// Change the parameter's display name at runtime. The host and the editor won't pick up on
+    /// this change until you call
+    /// [`GuiContext::param_info_changed()`][crate::prelude::GuiContext::param_info_changed()] or
+    /// [`ProcessContext::param_info_changed()`][crate::prelude::ProcessContext::param_info_changed()].
+    ///
+    /// This allocates, so it should not be called from the audio thread.
+    pub fn set_name(&self, name: impl Into<String>) {
+        self.name.store(Arc::new(name.into()));
+    }
+
+    /// Change the parameter's range at runtime. The parameter's current plain value is kept as is,
+    /// clamped to the new range. As with [`set_name()`][Self::set_name()], the host and the editor
+    /// need to be notified about this change using
+    /// [`GuiContext::param_info_changed()`][crate::prelude::GuiContext::param_info_changed()] or
+    /// [`ProcessContext::param_info_changed()`][crate::prelude::ProcessContext::param_info_changed()].
+    /// Since the parameter's normalized value changes, any automation the host recorded for this
+    /// parameter will map to different plain values afterwards.
+    pub fn set_range(&self, range: FloatRange) {
+        range.assert_validity();
+        self.range.store(range);
+
+        // `set_plain_value()` only updates the normalized values if the plain value changed, so
+        // they may need to be recomputed manually
+        let plain = self.preview_plain(self.preview_normalized(self.unmodulated_plain_value()));
+        if self.set_plain_value(plain) {
+            // Otherwise the smoother would keep moving towards the old, out of range value. There's
+            // no sample rate to smooth with here, so the smoother jumps to the clamped value.
+            self.smoothed.reset(self.modulated_plain_value());
+        } else {
+            let unmodulated_normalized_value = self.preview_normalized(plain);
+            let modulation_offset = self.modulation_offset.load(Ordering::Relaxed);
+            let normalized_value = if modulation_offset == 0.0 {
+                unmodulated_normalized_value
+            } else {
+                (unmodulated_normalized_value + modulation_offset).clamp(0.0, 1.0)
+            };
+
+            self.normalized_value
+                .store(normalized_value, Ordering::Relaxed);
+            self.unmodulated_normalized_value
+                .store(unmodulated_normalized_value, Ordering::Relaxed);
+        }
     }
 
     /// Enable polyphonic modulation for this parameter. The ID is used to uniquely identify this
@@ -328,13 +377,11 @@ impl FloatParam {
     pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
         // Logarithmic smoothing will cause problems if the range goes through zero since then you
         // end up multiplying by zero
-        let goes_through_zero = match (&style, &self.range) {
-            (
-                SmoothingStyle::Logarithmic(_),
-                FloatRange::Linear { min, max }
-                | FloatRange::Skewed { min, max, .. }
-                | FloatRange::SymmetricalSkewed { min, max, .. },
-            ) => *min == 0.0 || *max == 0.0 || min.signum() != max.signum(),
+        let goes_through_zero = match &style {
+            SmoothingStyle::Logarithmic(_) => {
+                let (min, max) = self.range.load().bounds();
+                min == 0.0 || max == 0.0 || min.signum() != max.signum()
+            }
             _ => false,
         };
         nih_debug_assert!(
@@ -437,3 +484,68 @@ fn decimals_from_step_size(step_size: f32) -> usize {
 
     num_digits as usize
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_param() -> FloatParam {
+        FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 })
+    }
+
+    #[test]
+    fn set_name() {
+        let param = make_param();
+        let old_name = param.name();
+
+        param.set_name("Volume");
+        assert_eq!(param.name().as_str(), "Volume");
+        // Names that were handed out before are not affected
+        assert_eq!(old_name.as_str(), "Gain");
+        assert_eq!(format!("{param:?}"), "Volume: 0.5");
+    }
+
+    #[test]
+    fn set_range_keeps_value() {
+        let param = make_param();
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 0.5);
+
+        param.set_range(FloatRange::Linear { min: 0.0, max: 2.0 });
+        assert_eq!(param.value(), 0.5);
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 0.25);
+        approx::assert_relative_eq!(param.modulated_normalized_value(), 0.25);
+        approx::assert_relative_eq!(param.default_normalized_value(), 0.25);
+    }
+
+    #[test]
+    fn set_range_clamps_value() {
+        let param = make_param().with_smoother(SmoothingStyle::Linear(10.0));
+        param.update_smoother(1000.0, true);
+        param.set_plain_value(1.0);
+        param.update_smoother(1000.0, false);
+        assert!(param.smoothed.is_smoothing());
+
+        param.set_range(FloatRange::Linear { min: 0.0, max: 0.8 });
+        assert_eq!(param.value(), 0.8);
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 1.0);
+        approx::assert_relative_eq!(param.modulated_normalized_value(), 1.0);
+
+        // The smoother should not keep moving towards the old, out of range value
+        assert!(!param.smoothed.is_smoothing());
+        assert_eq!(param.smoothed.next(), 0.8);
+    }
+
+    #[test]
+    fn set_range_with_modulation() {
+        let param = make_param();
+        param.modulate_value(0.25);
+        assert_eq!(param.value(), 0.75);
+
+        // The modulation offset is applied in the normalized domain
+        param.set_range(FloatRange::Linear { min: 0.0, max: 2.0 });
+        assert_eq!(param.unmodulated_plain_value(), 0.5);
+        assert_eq!(param.value(), 1.0);
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 0.25);
+        approx::assert_relative_eq!(param.modulated_normalized_value(), 0.5);
+    }
+}
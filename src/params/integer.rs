@@ -1,6 +1,8 @@
 //! Stepped integer parameters.
 
+use arc_swap::ArcSwap;
 use atomic_float::AtomicF32;
+use crossbeam::atomic::AtomicCell;
 use std::fmt::{Debug, Display};
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
@@ -45,10 +47,12 @@ pub struct IntParam {
     /// TODO: We probably also want to pass the old value to this function.
     value_changed: Option<Arc<dyn Fn(i32) + Send + Sync>>,
 
-    /// The distribution of the parameter's values.
-    range: IntRange,
-    /// The parameter's human readable display name.
-    name: String,
+    /// The distribution of the parameter's values. This can be changed at runtime using
+    /// [`set_range()`][Self::set_range()].
+    range: AtomicCell<IntRange>,
+    /// The parameter's human readable display name. This can be changed at runtime using
+    /// [`set_name()`][Self::set_name()].
+    name: ArcSwap<String>,
     /// The parameter value's unit, added after `value_to_string` if that is set. NIH-plug will not
     /// automatically add a space before the unit.
     unit: &'static str,
@@ -82,9 +86,9 @@ impl Debug for IntParam {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // This uses the above `Display` instance to show the value
         if self.modulated_plain_value() != self.unmodulated_plain_value() {
-            write!(f, "{}: {} (modulated)", &self.name, &self)
+            write!(f, "{}: {} (modulated)", self.name(), &self)
         } else {
-            write!(f, "{}: {}", &self.name, &self)
+            write!(f, "{}: {}", self.name(), &self)
         }
     }
 }
@@ -95,8 +99,8 @@ impl super::Sealed for IntParam {}
 impl Param for IntParam {
     type Plain = i32;
 
-    fn name(&self) -> &str {
-        &self.name
+    fn name(&self) -> Arc<String> {
+        self.name.load_full()
     }
 
     fn unit(&self) -> &'static str {
@@ -133,15 +137,15 @@ impl Param for IntParam {
     }
 
     fn step_count(&self) -> Option<usize> {
-        Some(self.range.step_count())
+        Some(self.range.load().step_count())
     }
 
     fn previous_step(&self, from: Self::Plain, _finer: bool) -> Self::Plain {
-        self.range.previous_step(from)
+        self.range.load().previous_step(from)
     }
 
     fn next_step(&self, from: Self::Plain, _finer: bool) -> Self::Plain {
-        self.range.next_step(from)
+        self.range.load().next_step(from)
     }
 
     fn normalized_value_to_string(&self, normalized: f32, include_unit: bool) -> String {
@@ -166,12 +170,12 @@ impl Param for IntParam {
 
     #[inline]
     fn preview_normalized(&self, plain: Self::Plain) -> f32 {
-        self.range.normalize(plain)
+        self.range.load().normalize(plain)
     }
 
     #[inline]
     fn preview_plain(&self, normalized: f32) -> Self::Plain {
-        self.range.unnormalize(normalized)
+        self.range.load().unnormalize(normalized)
     }
 
     fn flags(&self) -> ParamFlags {
@@ -263,8 +267,8 @@ impl IntParam {
             flags: ParamFlags::default(),
             value_changed: None,
 
-            range,
-            name: name.into(),
+            range: AtomicCell::new(range),
+            name: ArcSwap::from_pointee(name.into()),
             unit: "",
             poly_modulation_id: None,
             value_to_string: None,
@@ -282,7 +286,50 @@ impl IntParam {
     /// The range of valid plain values for this parameter.
     #[inline]
     pub fn range(&self) -> IntRange {
-        self.range
+        self.range.load()
+    }
+
+    /// Change the parameter's display name at runtime. The host and the editor won't pick up on
+    /// this change until you call
+    /// [`GuiContext::param_info_changed()`][crate::prelude::GuiContext::param_info_changed()] or
+    /// [`ProcessContext::param_info_changed()`][crate::prelude::ProcessContext::param_info_changed()].
+    ///
+    /// This allocates, so it should not be called from the audio thread.
+    pub fn set_name(&self, name: impl Into<String>) {
+        self.name.store(Arc::new(name.into()));
+    }
+
+    /// Change the parameter's range at runtime. The parameter's current plain value is kept as is,
+    /// clamped to the new range. As with [`set_name()`][Self::set_name()], the host and the editor
+    /// need to be notified about this change. Changing the number of steps is a bigger change than
+    /// changing the parameter's name. CLAP hosts will only pick up on the new step count after the
+    /// plugin has been deactivated, and any automation the host recorded for this parameter will
+    /// map to different plain values afterwards.
+    pub fn set_range(&self, range: IntRange) {
+        range.assert_validity();
+        self.range.store(range);
+
+        // `set_plain_value()` only updates the normalized values if the plain value changed, so
+        // they may need to be recomputed manually
+        let plain = self.preview_plain(self.preview_normalized(self.unmodulated_plain_value()));
+        if self.set_plain_value(plain) {
+            // Otherwise the smoother would keep moving towards the old, out of range value. There's
+            // no sample rate to smooth with here, so the smoother jumps to the clamped value.
+            self.smoothed.reset(self.modulated_plain_value());
+        } else {
+            let unmodulated_normalized_value = self.preview_normalized(plain);
+            let modulation_offset = self.modulation_offset.load(Ordering::Relaxed);
+            let normalized_value = if modulation_offset == 0.0 {
+                unmodulated_normalized_value
+            } else {
+                (unmodulated_normalized_value + modulation_offset).clamp(0.0, 1.0)
+            };
+
+            self.normalized_value
+                .store(normalized_value, Ordering::Relaxed);
+            self.unmodulated_normalized_value
+                .store(unmodulated_normalized_value, Ordering::Relaxed);
+        }
     }
 
     /// Enable polyphonic modulation for this parameter. The ID is used to uniquely identify this
@@ -307,7 +354,7 @@ impl IntParam {
     pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
         // Logarithmic smoothing will cause problems if the range goes through zero since then you
         // end up multiplying by zero
-        let goes_through_zero = match (&style, &self.range) {
+        let goes_through_zero = match (&style, &self.range.load()) {
             (SmoothingStyle::Logarithmic(_), IntRange::Linear { min, max }) => {
                 *min == 0 || *max == 0 || min.signum() != max.signum()
             }
@@ -388,3 +435,54 @@ impl IntParam {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_param() -> IntParam {
+        IntParam::new("Voices", 4, IntRange::Linear { min: 0, max: 8 })
+    }
+
+    #[test]
+    fn set_name() {
+        let param = make_param();
+        let old_name = param.name();
+
+        param.set_name("Unison");
+        assert_eq!(param.name().as_str(), "Unison");
+        // Names that were handed out before are not affected
+        assert_eq!(old_name.as_str(), "Voices");
+        assert_eq!(format!("{param:?}"), "Unison: 4");
+    }
+
+    #[test]
+    fn set_range_keeps_value() {
+        let param = make_param();
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 0.5);
+
+        param.set_range(IntRange::Linear { min: 0, max: 16 });
+        assert_eq!(param.value(), 4);
+        assert_eq!(param.step_count(), Some(16));
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 0.25);
+        approx::assert_relative_eq!(param.modulated_normalized_value(), 0.25);
+    }
+
+    #[test]
+    fn set_range_clamps_value() {
+        let param = make_param().with_smoother(SmoothingStyle::Linear(10.0));
+        param.update_smoother(1000.0, true);
+        param.set_plain_value(8);
+        param.update_smoother(1000.0, false);
+        assert!(param.smoothed.is_smoothing());
+
+        param.set_range(IntRange::Linear { min: 0, max: 2 });
+        assert_eq!(param.value(), 2);
+        approx::assert_relative_eq!(param.unmodulated_normalized_value(), 1.0);
+        approx::assert_relative_eq!(param.modulated_normalized_value(), 1.0);
+
+        // The smoother should not keep moving towards the old, out of range value
+        assert!(!param.smoothed.is_smoothing());
+        assert_eq!(param.smoothed.next(), 2);
+    }
+}
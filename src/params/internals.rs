@@ -1,7 +1,11 @@
 //! Implementation details for the parameter management.
 
+use std::sync::Arc;
+
 use super::{Param, ParamFlags, ParamMut};
 
+pub mod static_ids;
+
 /// Internal pointers to parameters. This is an implementation detail used by the wrappers for type
 /// erasure.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -61,7 +65,7 @@ macro_rules! param_ptr_forward(
 );
 
 impl ParamPtr {
-    param_ptr_forward!(pub unsafe fn name(&self) -> &str);
+    param_ptr_forward!(pub unsafe fn name(&self) -> Arc<String>);
     param_ptr_forward!(pub unsafe fn unit(&self) -> &'static str);
     param_ptr_forward!(pub unsafe fn poly_modulation_id(&self) -> Option<u32>);
     param_ptr_forward!(pub unsafe fn modulated_normalized_value(&self) -> f32);
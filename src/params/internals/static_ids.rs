@@ -0,0 +1,356 @@
+//! Compile time parameter ID checks. The [`Params`][crate::prelude::Params] derive macro uses these
+//! to detect duplicate parameter IDs and parameter ID hash collisions across nested parameter
+//! structs, since the macro itself only knows about the IDs defined on a single struct. This is an
+//! implementation detail and should not be used directly.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// The maximum number of components a [`StaticParamId`] can consist of. Every level of prefixed or
+/// array nesting adds two components. IDs for more deeply nested parameters are not checked.
+const MAX_PARTS: usize = 16;
+/// The maximum length of a parameter ID when comparing two IDs with the same hash. Longer IDs are
+/// compared up to this length.
+const MAX_ID_LEN: usize = 256;
+
+/// A parameter ID that is known at compile time. This may contain ID prefixes and array index
+/// suffixes added by nested parameter structs, so it is stored as a list of components instead of
+/// a single string.
+#[derive(Debug, Clone, Copy)]
+pub struct StaticParamId {
+    parts: [IdPart; MAX_PARTS],
+    num_parts: usize,
+    /// Set when the parameter is nested too deeply to be represented. These IDs are not checked.
+    truncated: bool,
+}
+
+/// A component of a [`StaticParamId`].
+#[derive(Debug, Clone, Copy)]
+enum IdPart {
+    Str(&'static str),
+    /// An array index, formatted as a decimal number.
+    Index(usize),
+}
+
+/// Implemented by the `Params` derive macro for every non-generic parameters struct. This lists the
+/// IDs of all of the struct's parameters, including those from nested structs.
+pub trait StaticParamIds {
+    const PARAM_IDS: &'static [StaticParamId];
+}
+
+/// Used to get a type's [`StaticParamIds::PARAM_IDS`] from generated code without requiring the
+/// type to implement that trait. If the type does not implement the trait, for instance because
+/// it has a manual `Params` implementation, then the [`UnknownParamIds`] fallback returns an empty
+/// list instead.
+pub struct ParamIdsOf<T: ?Sized>(PhantomData<T>);
+
+impl<T: ?Sized + StaticParamIds> ParamIdsOf<T> {
+    pub const PARAM_IDS: &'static [StaticParamId] = T::PARAM_IDS;
+}
+
+/// The fallback for [`ParamIdsOf`]. This needs to be in scope for the fallback to work.
+pub trait UnknownParamIds {
+    const PARAM_IDS: &'static [StaticParamId] = &[];
+}
+
+impl<T: ?Sized> UnknownParamIds for ParamIdsOf<T> {}
+
+impl<P: StaticParamIds> StaticParamIds for Arc<P> {
+    const PARAM_IDS: &'static [StaticParamId] = P::PARAM_IDS;
+}
+
+impl StaticParamId {
+    const EMPTY: Self = Self {
+        parts: [IdPart::Str(""); MAX_PARTS],
+        num_parts: 0,
+        truncated: false,
+    };
+
+    /// A parameter ID defined using an `#[id = "..."]` attribute.
+    pub const fn new(id: &'static str) -> Self {
+        let mut result = Self::EMPTY;
+        result.parts[0] = IdPart::Str(id);
+        result.num_parts = 1;
+
+        result
+    }
+
+    /// Add an `{id_prefix}_` prefix to this ID, like `#[nested(id_prefix = "...")]` does.
+    const fn with_prefix(mut self, id_prefix: &'static str) -> Self {
+        if self.truncated || self.num_parts + 2 > MAX_PARTS {
+            self.truncated = true;
+            return self;
+        }
+
+        let mut idx = self.num_parts;
+        while idx > 0 {
+            idx -= 1;
+            self.parts[idx + 2] = self.parts[idx];
+        }
+        self.parts[0] = IdPart::Str(id_prefix);
+        self.parts[1] = IdPart::Str("_");
+        self.num_parts += 2;
+
+        self
+    }
+
+    /// Add an `_{array_idx}` suffix to this ID, like `#[nested(array)]` does.
+    const fn with_index(mut self, array_idx: usize) -> Self {
+        if self.truncated || self.num_parts + 2 > MAX_PARTS {
+            self.truncated = true;
+            return self;
+        }
+
+        self.parts[self.num_parts] = IdPart::Str("_");
+        self.parts[self.num_parts + 1] = IdPart::Index(array_idx);
+        self.num_parts += 2;
+
+        self
+    }
+
+    /// Compute the same hash as the one used by the wrappers to identify parameters.
+    const fn hash(&self) -> u32 {
+        let mut hash: u32 = 0;
+        let mut part_idx = 0;
+        while part_idx < self.num_parts {
+            match self.parts[part_idx] {
+                IdPart::Str(s) => {
+                    let bytes = s.as_bytes();
+                    let mut byte_idx = 0;
+                    while byte_idx < bytes.len() {
+                        hash = hash.wrapping_mul(31).wrapping_add(bytes[byte_idx] as u32);
+                        byte_idx += 1;
+                    }
+                }
+                IdPart::Index(index) => {
+                    let mut divisor = largest_power_of_ten(index);
+                    while divisor > 0 {
+                        let digit = (index / divisor) % 10;
+                        hash = hash
+                            .wrapping_mul(31)
+                            .wrapping_add(b'0' as u32 + digit as u32);
+                        divisor /= 10;
+                    }
+                }
+            }
+
+            part_idx += 1;
+        }
+
+        // This is also done in `hash_param_id()` since VST3 reserves the last bit
+        hash & !(1 << 31)
+    }
+
+    /// Write the full ID to a buffer. Returns the buffer and the ID's length, up to `MAX_ID_LEN`.
+    const fn render(&self) -> ([u8; MAX_ID_LEN], usize) {
+        let mut buffer = [0; MAX_ID_LEN];
+        let mut len = 0;
+        let mut part_idx = 0;
+        while part_idx < self.num_parts {
+            match self.parts[part_idx] {
+                IdPart::Str(s) => {
+                    let bytes = s.as_bytes();
+                    let mut byte_idx = 0;
+                    while byte_idx < bytes.len() && len < MAX_ID_LEN {
+                        buffer[len] = bytes[byte_idx];
+                        len += 1;
+                        byte_idx += 1;
+                    }
+                }
+                IdPart::Index(index) => {
+                    let mut divisor = largest_power_of_ten(index);
+                    while divisor > 0 && len < MAX_ID_LEN {
+                        buffer[len] = b'0' + ((index / divisor) % 10) as u8;
+                        len += 1;
+                        divisor /= 10;
+                    }
+                }
+            }
+
+            part_idx += 1;
+        }
+
+        (buffer, len)
+    }
+
+    /// Whether two IDs are equal after all prefixes and suffixes have been applied.
+    const fn equals(&self, other: &Self) -> bool {
+        let (buffer, len) = self.render();
+        let (other_buffer, other_len) = other.render();
+        if len != other_len {
+            return false;
+        }
+
+        let mut idx = 0;
+        while idx < len {
+            if buffer[idx] != other_buffer[idx] {
+                return false;
+            }
+            idx += 1;
+        }
+
+        true
+    }
+}
+
+/// Concatenate the parameter ID lists for a struct's fields. `N` must be the sum of the lists'
+/// lengths.
+pub const fn concat<const N: usize>(lists: &[&[StaticParamId]]) -> [StaticParamId; N] {
+    let mut result = [StaticParamId::EMPTY; N];
+    let mut len = 0;
+    let mut list_idx = 0;
+    while list_idx < lists.len() {
+        let list = lists[list_idx];
+        let mut idx = 0;
+        while idx < list.len() {
+            result[len] = list[idx];
+            len += 1;
+            idx += 1;
+        }
+
+        list_idx += 1;
+    }
+    assert!(len == N, "Incorrect parameter ID list length");
+
+    result
+}
+
+/// Add an ID prefix to all IDs from a `#[nested(id_prefix = "...")]` field. `N` must be the
+/// length of `ids`.
+pub const fn with_prefix<const N: usize>(
+    ids: &[StaticParamId],
+    id_prefix: &'static str,
+) -> [StaticParamId; N] {
+    let mut result = [StaticParamId::EMPTY; N];
+    let mut idx = 0;
+    while idx < N {
+        result[idx] = ids[idx].with_prefix(id_prefix);
+        idx += 1;
+    }
+
+    result
+}
+
+/// Add array index suffixes to the IDs from a `#[nested(array)]` field containing `array_len`
+/// parameter structs with the IDs from `ids`. Array indices start at 1. `N` must be
+/// `ids.len() * array_len`.
+pub const fn with_array_indices<const N: usize>(
+    ids: &[StaticParamId],
+    array_len: usize,
+) -> [StaticParamId; N] {
+    let mut result = [StaticParamId::EMPTY; N];
+    let mut array_idx = 0;
+    while array_idx < array_len {
+        let mut idx = 0;
+        while idx < ids.len() {
+            result[(array_idx * ids.len()) + idx] = ids[idx].with_index(array_idx + 1);
+            idx += 1;
+        }
+
+        array_idx += 1;
+    }
+
+    result
+}
+
+/// Compute the hashes for all IDs in `ids`, so [`check_ids()`] doesn't need to hash the same IDs
+/// over and over again. `N` must be the length of `ids`.
+pub const fn hashes<const N: usize>(ids: &[StaticParamId]) -> [u32; N] {
+    let mut result = [0; N];
+    let mut idx = 0;
+    while idx < N {
+        result[idx] = ids[idx].hash();
+        idx += 1;
+    }
+
+    result
+}
+
+/// Check the IDs in `ids[start..end]` against all IDs that come before them. `hashes` contains the
+/// IDs' hashes as computed by [`hashes()`]. The generated code calls this once per field, with the
+/// span of the field, so the compiler error points at the field that introduced the duplicate ID
+/// or hash collision.
+pub const fn check_ids(
+    ids: &[StaticParamId],
+    hashes: &[u32],
+    start: usize,
+    end: usize,
+    duplicate_message: &'static str,
+    hash_collision_message: &'static str,
+) {
+    let mut idx = start;
+    while idx < end {
+        if !ids[idx].truncated {
+            let mut other_idx = 0;
+            while other_idx < idx {
+                if !ids[other_idx].truncated && hashes[other_idx] == hashes[idx] {
+                    if ids[idx].equals(&ids[other_idx]) {
+                        panic!("{}", duplicate_message);
+                    } else {
+                        panic!("{}", hash_collision_message);
+                    }
+                }
+
+                other_idx += 1;
+            }
+        }
+
+        idx += 1;
+    }
+}
+
+/// The largest power of ten that's smaller than or equal to `n`, or 1 if `n` is 0.
+const fn largest_power_of_ten(n: usize) -> usize {
+    let mut result = 1;
+    while n / result >= 10 {
+        result *= 10;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wrapper::util::hash_param_id;
+
+    #[test]
+    fn hash_matches_wrapper() {
+        let id = StaticParamId::new("gain").with_index(12).with_prefix("osc");
+        assert_eq!(id.hash(), hash_param_id("osc_gain_12"));
+
+        let (buffer, len) = id.render();
+        assert_eq!(&buffer[..len], b"osc_gain_12");
+    }
+
+    #[test]
+    fn equality() {
+        let id = StaticParamId::new("a").with_index(1);
+        assert!(id.equals(&StaticParamId::new("a_1")));
+        assert!(!id.equals(&StaticParamId::new("a_11")));
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate")]
+    fn duplicate_ids() {
+        let ids = concat::<3>(&[
+            &[StaticParamId::new("foo_bar")],
+            &with_prefix::<2>(
+                &[StaticParamId::new("baz"), StaticParamId::new("bar")],
+                "foo",
+            ),
+        ]);
+        let hashes = hashes::<3>(&ids);
+        check_ids(&ids, &hashes, 0, 1, "duplicate", "hash collision");
+        check_ids(&ids, &hashes, 1, 3, "duplicate", "hash collision");
+    }
+
+    #[test]
+    #[should_panic(expected = "hash collision")]
+    fn hash_collisions() {
+        // These two IDs have the same hash
+        let ids = [StaticParamId::new("Aa"), StaticParamId::new("BB")];
+        let hashes = hashes::<2>(&ids);
+        check_ids(&ids, &hashes, 0, 2, "duplicate", "hash collision");
+    }
+}
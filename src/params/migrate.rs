@@ -0,0 +1,257 @@
+//! Helpers for migrating old plugin states to the current parameter IDs and persistent field keys.
+//! These are used by the code generated by the [`Params`][super::Params] derive macro for the
+//! `#[old_ids(...)]`, `#[old_keys(...)]`, and `#[migrate(...)]` attributes.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::wrapper::state::PluginState;
+
+/// Move a parameter's value stored under one of its old IDs to its current ID, if the state does
+/// not already contain a value for the current ID. The first old ID found in the state wins. All
+/// old IDs are removed from the state afterwards. MIDI mappings for the parameter are migrated the
+/// same way.
+pub fn rename_param(state: &mut PluginState, id: &str, old_ids: &[&str]) {
+    rename_key(&mut state.params, id, old_ids);
+    rename_key(&mut state.midi_mappings, id, old_ids);
+}
+
+/// The same as [`rename_param()`], but for a persistent field's key.
+pub fn rename_field(state: &mut PluginState, key: &str, old_keys: &[&str]) {
+    rename_key(&mut state.fields, key, old_keys);
+}
+
+/// The same as [`rename_field()`], but for a `#[persist_bytes]` field's key.
+pub fn rename_binary_field(state: &mut PluginState, key: &str, old_keys: &[&str]) {
+    rename_key(&mut state.binary_fields, key, old_keys);
+}
+
+/// Replace the value stored under `key` using `f`, if the map contains that key.
+pub fn map_value<V>(map: &mut BTreeMap<String, V>, key: &str, f: impl FnOnce(V) -> V) {
+    if let Some(value) = map.remove(key) {
+        map.insert(key.to_owned(), f(value));
+    }
+}
+
+/// Run `f` on the part of `state` whose parameter IDs, persistent field keys, and MIDI mappings
+/// start with `prefix`. The prefix is stripped before calling `f`, and it is added back
+/// afterwards. Used for `#[nested(id_prefix = "...")]` fields.
+pub fn migrate_prefixed(state: &mut PluginState, prefix: &str, f: impl FnOnce(&mut PluginState)) {
+    migrate_matching(
+        state,
+        |key| key.strip_prefix(prefix),
+        |key| format!("{}{}", prefix, key),
+        f,
+    )
+}
+
+/// The same as [`migrate_prefixed()`], but for keys ending with `suffix`. Used for
+/// `#[nested(array)]` fields.
+pub fn migrate_suffixed(state: &mut PluginState, suffix: &str, f: impl FnOnce(&mut PluginState)) {
+    migrate_matching(
+        state,
+        |key| key.strip_suffix(suffix),
+        |key| format!("{}{}", key, suffix),
+        f,
+    )
+}
+
+/// Whether `version` is older than `other`. Versions are compared component by component, where
+/// the components are separated by periods. The numeric parts of a component are compared as
+/// numbers, and any remaining text is compared alphabetically. A component with trailing text, like
+/// the `0-beta` in `1.0.0-beta`, is a pre-release and is thus older than the same component without
+/// any text. Missing components count as zero. An empty version, as found in very old states, is
+/// older than any other version.
+pub fn version_older_than(version: &str, other: &str) -> bool {
+    compare_versions(version, other) == Ordering::Less
+}
+
+fn compare_versions(version: &str, other: &str) -> Ordering {
+    match (version.is_empty(), other.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        (false, false) => (),
+    }
+
+    let mut components = version.split('.');
+    let mut other_components = other.split('.');
+    loop {
+        let (component, other_component) = match (components.next(), other_components.next()) {
+            (None, None) => return Ordering::Equal,
+            (component, other_component) => {
+                (component.unwrap_or("0"), other_component.unwrap_or("0"))
+            }
+        };
+
+        let (number, rest) = split_component(component);
+        let (other_number, other_rest) = split_component(other_component);
+        let ordering = number.cmp(&other_number).then_with(|| {
+            match (rest.is_empty(), other_rest.is_empty()) {
+                // Pre-releases come before the release without a suffix
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => rest.cmp(other_rest),
+            }
+        });
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Split a version component like `3-beta` into its leading number and the remaining text.
+fn split_component(component: &str) -> (u64, &str) {
+    let num_digits = component
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(component.len());
+    let (number, rest) = component.split_at(num_digits);
+
+    (number.parse().unwrap_or(0), rest)
+}
+
+fn rename_key<V>(map: &mut BTreeMap<String, V>, key: &str, old_keys: &[&str]) {
+    for old_key in old_keys {
+        if let Some(value) = map.remove(*old_key) {
+            if !map.contains_key(key) {
+                map.insert(key.to_owned(), value);
+            }
+        }
+    }
+}
+
+fn migrate_matching(
+    state: &mut PluginState,
+    strip: impl Fn(&str) -> Option<&str>,
+    restore: impl Fn(&str) -> String,
+    f: impl FnOnce(&mut PluginState),
+) {
+    let mut matching_state = PluginState {
+        version: state.version.clone(),
+        params: split_off_matching(&mut state.params, &strip),
+        fields: split_off_matching(&mut state.fields, &strip),
+        binary_fields: split_off_matching(&mut state.binary_fields, &strip),
+        midi_mappings: split_off_matching(&mut state.midi_mappings, &strip),
+        state_slots: None,
+    };
+
+    f(&mut matching_state);
+
+    extend_restored(&mut state.params, matching_state.params, &restore);
+    extend_restored(&mut state.fields, matching_state.fields, &restore);
+    extend_restored(
+        &mut state.binary_fields,
+        matching_state.binary_fields,
+        &restore,
+    );
+    extend_restored(
+        &mut state.midi_mappings,
+        matching_state.midi_mappings,
+        &restore,
+    );
+}
+
+/// Remove all entries whose keys match `strip` from `map`, and return them with the stripped keys.
+fn split_off_matching<V>(
+    map: &mut BTreeMap<String, V>,
+    strip: impl Fn(&str) -> Option<&str>,
+) -> BTreeMap<String, V> {
+    let matching_keys: Vec<String> = map
+        .keys()
+        .filter(|key| strip(key.as_str()).is_some())
+        .cloned()
+        .collect();
+
+    matching_keys
+        .into_iter()
+        .filter_map(|key| {
+            let value = map.remove(&key)?;
+            Some((strip(key.as_str())?.to_owned(), value))
+        })
+        .collect()
+}
+
+/// Add the entries from `matching` back to `map` after restoring their original keys.
+fn extend_restored<V>(
+    map: &mut BTreeMap<String, V>,
+    matching: BTreeMap<String, V>,
+    restore: impl Fn(&str) -> String,
+) {
+    map.extend(
+        matching
+            .into_iter()
+            .map(|(key, value)| (restore(key.as_str()), value)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wrapper::state::ParamValue;
+
+    fn state_with_params(version: &str, param_ids: &[&str]) -> PluginState {
+        PluginState {
+            version: version.to_owned(),
+            params: param_ids
+                .iter()
+                .map(|id| (id.to_string(), ParamValue::F32(1.0)))
+                .collect(),
+            fields: BTreeMap::new(),
+            binary_fields: BTreeMap::new(),
+            midi_mappings: BTreeMap::new(),
+            state_slots: None,
+        }
+    }
+
+    #[test]
+    fn version_comparison() {
+        assert!(version_older_than("0.1.0", "0.2.0"));
+        assert!(version_older_than("0.9.0", "0.10.0"));
+        assert!(version_older_than("1.2", "1.2.1"));
+        assert!(version_older_than("", "0.0.1"));
+        assert!(version_older_than("1.0.0-beta", "1.0.1"));
+        assert!(version_older_than("1.0.0-beta", "1.0.0"));
+        assert!(version_older_than("1.0.0-alpha", "1.0.0-beta"));
+        assert!(version_older_than("1.0-beta", "1.0.0"));
+        assert!(!version_older_than("1.0.0", "1.0.0-beta"));
+        assert!(!version_older_than("1.0.0", "1.0"));
+        assert!(!version_older_than("1.10.0", "1.9.0"));
+    }
+
+    #[test]
+    fn rename() {
+        let mut state = state_with_params("", &["old", "older"]);
+        rename_param(&mut state, "new", &["old", "older"]);
+        assert_eq!(
+            state.params.keys().collect::<Vec<_>>(),
+            [&String::from("new")]
+        );
+
+        // Values stored under the current ID are never overwritten
+        let mut state = state_with_params("", &["new", "old"]);
+        state
+            .params
+            .insert(String::from("new"), ParamValue::F32(2.0));
+        rename_param(&mut state, "new", &["old"]);
+        assert_eq!(state.params.len(), 1);
+        assert!(matches!(state.params["new"], ParamValue::F32(v) if v == 2.0));
+    }
+
+    #[test]
+    fn prefixed() {
+        let mut state = state_with_params("", &["foo_old", "bar_old", "other"]);
+        migrate_prefixed(&mut state, "foo_", |state| {
+            assert_eq!(state.params.len(), 1);
+            rename_param(state, "new", &["old"]);
+        });
+        assert_eq!(
+            state.params.keys().collect::<Vec<_>>(),
+            [
+                &String::from("bar_old"),
+                &String::from("foo_new"),
+                &String::from("other")
+            ]
+        );
+    }
+}
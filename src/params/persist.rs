@@ -217,6 +217,94 @@ where
 impl_persistent_arc!(crossbeam::atomic::AtomicCell<T>,
                      T: serde::Serialize + serde::Deserialize<'a> + Copy + Send);
 
+/// The raw bytes equivalent of [`PersistentField`]. These can be used with [`Params`][super::Params]'
+/// `#[persist_bytes = "..."]` attributes to store larger amounts of data like audio samples in the
+/// plugin's state without converting them to JSON first.
+///
+/// This should be implemented for some type with interior mutability containing a `Vec<u8>`.
+pub trait PersistentBytesField: Send + Sync {
+    /// Replace the stored bytes using interior mutability.
+    fn set_bytes(&self, new_bytes: Vec<u8>);
+
+    /// Get a reference to the stored bytes, and apply a function to it. This is used to serialize
+    /// the field.
+    fn map_bytes<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&[u8]) -> R;
+}
+
+impl<I: PersistentBytesField + ?Sized> PersistentBytesField for Arc<I> {
+    fn set_bytes(&self, new_bytes: Vec<u8>) {
+        self.as_ref().set_bytes(new_bytes);
+    }
+    fn map_bytes<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&[u8]) -> R,
+    {
+        self.as_ref().map_bytes(f)
+    }
+}
+
+impl PersistentBytesField for std::sync::RwLock<Vec<u8>> {
+    fn set_bytes(&self, new_bytes: Vec<u8>) {
+        *self.write().expect("Poisoned RwLock on write") = new_bytes;
+    }
+    fn map_bytes<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&[u8]) -> R,
+    {
+        f(&self.read().expect("Poisoned RwLock on read"))
+    }
+}
+
+impl PersistentBytesField for parking_lot::RwLock<Vec<u8>> {
+    fn set_bytes(&self, new_bytes: Vec<u8>) {
+        *self.write() = new_bytes;
+    }
+    fn map_bytes<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&[u8]) -> R,
+    {
+        f(&self.read())
+    }
+}
+
+impl PersistentBytesField for std::sync::Mutex<Vec<u8>> {
+    fn set_bytes(&self, new_bytes: Vec<u8>) {
+        *self.lock().expect("Poisoned Mutex") = new_bytes;
+    }
+    fn map_bytes<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&[u8]) -> R,
+    {
+        f(&self.lock().expect("Poisoned Mutex"))
+    }
+}
+
+impl PersistentBytesField for parking_lot::Mutex<Vec<u8>> {
+    fn set_bytes(&self, new_bytes: Vec<u8>) {
+        *self.lock() = new_bytes;
+    }
+    fn map_bytes<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&[u8]) -> R,
+    {
+        f(&self.lock())
+    }
+}
+
+impl PersistentBytesField for atomic_refcell::AtomicRefCell<Vec<u8>> {
+    fn set_bytes(&self, new_bytes: Vec<u8>) {
+        *self.borrow_mut() = new_bytes;
+    }
+    fn map_bytes<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&[u8]) -> R,
+    {
+        f(&self.borrow())
+    }
+}
+
 /// Can be used with the `#[serde(with = "nih_plug::params::internals::serialize_atomic_cell")]`
 /// attribute to serialize `AtomicCell<T>`s.
 pub mod serialize_atomic_cell {
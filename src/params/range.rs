@@ -22,6 +22,44 @@ pub enum FloatRange {
         factor: f32,
         center: f32,
     },
+    /// A logarithmic range where every doubling of the value takes up the same portion of the
+    /// range. This is what you want for frequency parameters. Both `min` and `max` need to be
+    /// positive.
+    Logarithmic { min: f32, max: f32 },
+    /// A piecewise linear mapping defined by a list of `(normalized, plain)` breakpoints. The first
+    /// breakpoint's normalized value needs to be 0.0 and the last breakpoint's normalized value
+    /// needs to be 1.0, and both the normalized and the plain values need to be strictly
+    /// increasing. This can be used for things like faders with different slopes in different
+    /// regions:
+    ///
+    /// ```
+    /// # use nih_plug::prelude::FloatRange;
+    /// // -60 dB to -20 dB takes up the first 25% of the fader, and -20 dB to +6 dB the rest
+    /// const FADER_RANGE: FloatRange = FloatRange::Piecewise {
+    ///     points: &[(0.0, -60.0), (0.25, -20.0), (1.0, 6.0)],
+    /// };
+    /// ```
+    Piecewise { points: &'static [(f32, f32)] },
+    /// A custom mapping between `min` and `max`. `normalize` receives a plain value that has
+    /// already been clamped to `[min, max]` and should return a value in `[0, 1]`, and
+    /// `unnormalize` should do the inverse. The functions should be monotonically increasing, and
+    /// they should map `min` to 0.0 and `max` to 1.0.
+    ///
+    /// ```
+    /// # use nih_plug::prelude::FloatRange;
+    /// const CUBIC_RANGE: FloatRange = FloatRange::Custom {
+    ///     min: 0.0,
+    ///     max: 8.0,
+    ///     normalize: |plain| (plain / 8.0).cbrt(),
+    ///     unnormalize: |normalized| normalized.powi(3) * 8.0,
+    /// };
+    /// ```
+    Custom {
+        min: f32,
+        max: f32,
+        normalize: fn(f32) -> f32,
+        unnormalize: fn(f32) -> f32,
+    },
     /// A reversed range that goes from high to low instead of from low to high.
     Reversed(&'static FloatRange),
 }
@@ -94,6 +132,32 @@ impl FloatRange {
                     (1.0 - inverted_scaled_proportion.powf(*factor)) * 0.5
                 }
             }
+            FloatRange::Logarithmic { min, max } => {
+                (plain.clamp(*min, *max) / min).ln() / (max / min).ln()
+            }
+            FloatRange::Piecewise { points } => {
+                let (min, max) = self.bounds();
+                let plain = plain.clamp(min, max);
+
+                // The segment containing `plain`. Values beyond the last breakpoint end up in the
+                // last segment.
+                let segment_idx = points
+                    .windows(2)
+                    .position(|segment| plain <= segment[1].1)
+                    .unwrap_or(points.len().saturating_sub(2));
+                let (start_normalized, start_plain) = points[segment_idx];
+                let (end_normalized, end_plain) = points[segment_idx + 1];
+
+                start_normalized
+                    + ((plain - start_plain) / (end_plain - start_plain))
+                        * (end_normalized - start_normalized)
+            }
+            FloatRange::Custom {
+                min,
+                max,
+                normalize,
+                ..
+            } => normalize(plain.clamp(*min, *max)).clamp(0.0, 1.0),
             FloatRange::Reversed(range) => 1.0 - range.normalize(plain),
         }
     }
@@ -126,6 +190,25 @@ impl FloatRange {
 
                 (skewed_proportion * (max - min)) + min
             }
+            FloatRange::Logarithmic { min, max } => min * (max / min).powf(normalized),
+            FloatRange::Piecewise { points } => {
+                let segment_idx = points
+                    .windows(2)
+                    .position(|segment| normalized <= segment[1].0)
+                    .unwrap_or(points.len().saturating_sub(2));
+                let (start_normalized, start_plain) = points[segment_idx];
+                let (end_normalized, end_plain) = points[segment_idx + 1];
+
+                start_plain
+                    + ((normalized - start_normalized) / (end_normalized - start_normalized))
+                        * (end_plain - start_plain)
+            }
+            FloatRange::Custom {
+                min,
+                max,
+                unnormalize,
+                ..
+            } => unnormalize(normalized).clamp(*min, *max),
             FloatRange::Reversed(range) => range.unnormalize(1.0 - normalized),
         }
     }
@@ -138,9 +221,9 @@ impl FloatRange {
         // range up into 50 segments, but if `self.step_size` would cause the range to be devided
         // into less than 50 segments then we'll use that.
         match self {
-            FloatRange::Linear { min, max }
-            | FloatRange::Skewed { min, max, .. }
-            | FloatRange::SymmetricalSkewed { min, max, .. } => {
+            FloatRange::Reversed(range) => range.next_step(from, step_size, finer),
+            _ => {
+                let (min, max) = self.bounds();
                 let normalized_naive_step_size = if finer { 0.005 } else { 0.02 };
                 let naive_step =
                     self.unnormalize(self.normalize(from) - normalized_naive_step_size);
@@ -153,9 +236,8 @@ impl FloatRange {
                     Some(step_size) => from - step_size,
                     None => naive_step,
                 }
-                .clamp(*min, *max)
+                .clamp(min, max)
             }
-            FloatRange::Reversed(range) => range.next_step(from, step_size, finer),
         }
     }
 
@@ -164,9 +246,9 @@ impl FloatRange {
     pub fn next_step(&self, from: f32, step_size: Option<f32>, finer: bool) -> f32 {
         // See above
         match self {
-            FloatRange::Linear { min, max }
-            | FloatRange::Skewed { min, max, .. }
-            | FloatRange::SymmetricalSkewed { min, max, .. } => {
+            FloatRange::Reversed(range) => range.previous_step(from, step_size, finer),
+            _ => {
+                let (min, max) = self.bounds();
                 let normalized_naive_step_size = if finer { 0.005 } else { 0.02 };
                 let naive_step =
                     self.unnormalize(self.normalize(from) + normalized_naive_step_size);
@@ -178,21 +260,31 @@ impl FloatRange {
                     Some(step_size) => from + step_size,
                     None => naive_step,
                 }
-                .clamp(*min, *max)
+                .clamp(min, max)
             }
-            FloatRange::Reversed(range) => range.previous_step(from, step_size, finer),
         }
     }
 
     /// Snap a value to a step size, clamping to the minimum and maximum value of the range.
     pub fn snap_to_step(&self, value: f32, step_size: f32) -> f32 {
+        let (min, max) = self.bounds();
+        ((value / step_size).round() * step_size).clamp(min, max)
+    }
+
+    /// The range's minimum and maximum plain values. For reversed ranges these are the wrapped
+    /// range's bounds, so the minimum is always less than the maximum.
+    pub(super) fn bounds(&self) -> (f32, f32) {
         match self {
             FloatRange::Linear { min, max }
             | FloatRange::Skewed { min, max, .. }
-            | FloatRange::SymmetricalSkewed { min, max, .. } => {
-                ((value / step_size).round() * step_size).clamp(*min, *max)
-            }
-            FloatRange::Reversed(range) => range.snap_to_step(value, step_size),
+            | FloatRange::SymmetricalSkewed { min, max, .. }
+            | FloatRange::Logarithmic { min, max }
+            | FloatRange::Custom { min, max, .. } => (*min, *max),
+            FloatRange::Piecewise { points } => match (points.first(), points.last()) {
+                (Some((_, min)), Some((_, max))) => (*min, *max),
+                _ => (0.0, 0.0),
+            },
+            FloatRange::Reversed(range) => range.bounds(),
         }
     }
 
@@ -211,6 +303,60 @@ impl FloatRange {
                     max
                 );
             }
+            FloatRange::Logarithmic { min, max } => {
+                nih_debug_assert!(
+                    *min > 0.0 && min < max,
+                    "The logarithmic range minimum ({}) needs to be positive and less than the \
+                     range maximum ({})",
+                    min,
+                    max
+                );
+            }
+            FloatRange::Piecewise { points } => {
+                nih_debug_assert!(
+                    points.len() >= 2,
+                    "Piecewise ranges need at least two breakpoints"
+                );
+                nih_debug_assert!(
+                    points.first().map(|(normalized, _)| *normalized) == Some(0.0)
+                        && points.last().map(|(normalized, _)| *normalized) == Some(1.0),
+                    "The first and last breakpoints of a piecewise range need to be at 0.0 and \
+                     1.0, got {:?}",
+                    points
+                );
+                nih_debug_assert!(
+                    points
+                        .windows(2)
+                        .all(|segment| segment[0].0 < segment[1].0 && segment[0].1 < segment[1].1),
+                    "The breakpoints of a piecewise range need to be strictly increasing, got {:?}",
+                    points
+                );
+            }
+            FloatRange::Custom {
+                min,
+                max,
+                normalize,
+                unnormalize,
+            } => {
+                nih_debug_assert!(
+                    min < max,
+                    "The range minimum ({}) needs to be less than the range maximum ({}) and they \
+                     cannot be equal",
+                    min,
+                    max
+                );
+                nih_debug_assert!(
+                    (normalize(*min) - 0.0).abs() < 1e-4 && (normalize(*max) - 1.0).abs() < 1e-4,
+                    "A custom range's normalize function needs to map the minimum to 0.0 and the \
+                     maximum to 1.0"
+                );
+                nih_debug_assert!(
+                    (unnormalize(0.0) - min).abs() < 1e-4 * (max - min)
+                        && (unnormalize(1.0) - max).abs() < 1e-4 * (max - min),
+                    "A custom range's unnormalize function needs to map 0.0 to the minimum and 1.0 \
+                     to the maximum"
+                );
+            }
             FloatRange::Reversed(range) => range.assert_validity(),
         }
     }
@@ -417,6 +563,102 @@ mod tests {
         }
     }
 
+    mod logarithmic {
+        use super::*;
+
+        const RANGE: FloatRange = FloatRange::Logarithmic {
+            min: 20.0,
+            max: 20_000.0,
+        };
+
+        #[test]
+        fn range_normalize_float() {
+            approx::assert_relative_eq!(RANGE.normalize(200.0), 1.0 / 3.0, epsilon = 1e-6);
+            assert_eq!(RANGE.normalize(10.0), 0.0);
+        }
+
+        #[test]
+        fn range_unnormalize_float() {
+            approx::assert_relative_eq!(RANGE.unnormalize(2.0 / 3.0), 2000.0, max_relative = 1e-5);
+        }
+
+        #[test]
+        fn steps() {
+            // The steps are evenly distributed in the normalized range, so the step from 1 kHz to
+            // 2 kHz is equally large as the step from 10 Hz to 20 Hz
+            let next = RANGE.next_step(1000.0, None, false);
+            approx::assert_relative_eq!(
+                RANGE.normalize(next) - RANGE.normalize(1000.0),
+                0.02,
+                epsilon = 1e-5
+            );
+            assert_eq!(RANGE.previous_step(20.0, None, false), 20.0);
+            assert_eq!(RANGE.snap_to_step(1.0, 10.0), 20.0);
+        }
+    }
+
+    mod piecewise {
+        use super::*;
+
+        const RANGE: FloatRange = FloatRange::Piecewise {
+            points: &[(0.0, -60.0), (0.25, -20.0), (1.0, 6.0)],
+        };
+
+        #[test]
+        fn range_normalize_float() {
+            assert_eq!(RANGE.normalize(-40.0), 0.125);
+            assert_eq!(RANGE.normalize(-20.0), 0.25);
+            assert_eq!(RANGE.normalize(6.0), 1.0);
+            assert_eq!(RANGE.normalize(100.0), 1.0);
+        }
+
+        #[test]
+        fn range_unnormalize_float() {
+            assert_eq!(RANGE.unnormalize(0.125), -40.0);
+            assert_eq!(RANGE.unnormalize(0.625), -7.0);
+            assert_eq!(RANGE.unnormalize(0.0), -60.0);
+        }
+
+        #[test]
+        fn steps() {
+            assert_eq!(RANGE.next_step(6.0, None, false), 6.0);
+            assert_eq!(RANGE.previous_step(-60.0, None, false), -60.0);
+            assert_eq!(RANGE.next_step(-20.0, Some(0.5), false), -19.5);
+            assert_eq!(RANGE.snap_to_step(-70.0, 0.5), -60.0);
+        }
+    }
+
+    mod custom {
+        use super::*;
+
+        const RANGE: FloatRange = FloatRange::Custom {
+            min: 0.0,
+            max: 8.0,
+            normalize: |plain| (plain / 8.0).cbrt(),
+            unnormalize: |normalized| normalized.powi(3) * 8.0,
+        };
+
+        #[test]
+        fn range_normalize_float() {
+            assert_eq!(RANGE.normalize(1.0), 0.5);
+            assert_eq!(RANGE.normalize(-1.0), 0.0);
+        }
+
+        #[test]
+        fn range_unnormalize_float() {
+            assert_eq!(RANGE.unnormalize(0.5), 1.0);
+            assert_eq!(RANGE.unnormalize(2.0), 8.0);
+        }
+
+        #[test]
+        fn reversed() {
+            const REVERSED_RANGE: FloatRange = FloatRange::Reversed(&RANGE);
+            assert_eq!(REVERSED_RANGE.normalize(1.0), 0.5);
+            assert_eq!(REVERSED_RANGE.unnormalize(1.0), 0.0);
+            assert_eq!(REVERSED_RANGE.next_step(0.0, None, false), 0.0);
+        }
+    }
+
     mod reversed_linear {
         use super::*;
 
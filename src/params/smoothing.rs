@@ -1,8 +1,10 @@
 //! Utilities to handle smoothing parameter changes over time.
 
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
 use std::sync::Arc;
 
+use atomic_float::AtomicF64;
+
 // Re-exported here because it's sued in `SmoothingStyle`.
 pub use atomic_float::AtomicF32;
 
@@ -13,7 +15,8 @@ pub enum SmoothingStyle {
     /// parameter that's used in an oversampled part of the plugin. The `Arc<AtomicF32>` indicates
     /// the oversampling amount, where `1.0` means no oversampling. This value can change at
     /// runtime, and it effectively scales the sample rate when computing new smoothing coefficients
-    /// when the parameter's value changes.
+    /// when the parameter's value changes. See
+    /// [`Oversampler`][crate::util::oversampling::Oversampler] for an example.
     OversamplingAware(Arc<AtomicF32>, &'static SmoothingStyle),
 
     /// No smoothing is applied. The parameter's `value` field contains the latest sample value
@@ -37,6 +40,26 @@ pub enum SmoothingStyle {
     /// This results in a smoother transition, with the caveat being that there will be a tiny jump
     /// at the end. Unlike the `Logarithmic` option, this does support crossing the zero value.
     Exponential(f32),
+    /// Smooth parameter changes using a critically damped second order filter. This is the same as
+    /// two `Exponential` smoothers in series. Unlike the other smoothing styles, the value's rate
+    /// of change is continuous, even when the target value changes while the smoother is still
+    /// smoothing. This avoids the audible kinks you would otherwise get with fast automation.
+    /// Like `Exponential`, this reaches 99.99% of the target value in the specified number of
+    /// milliseconds before snapping to the target value in the last step, and it can cross the
+    /// zero value.
+    CriticallyDamped(f32),
+    /// Move the current value towards the target value at a fixed rate, specified in units per
+    /// second. So with a rate of `100.0` a gain parameter in decibels will take 120 milliseconds to
+    /// move from -6 dB to +6 dB, and 60 milliseconds to move from -3 dB to +3 dB. Large jumps thus
+    /// take longer to reach than small adjustments.
+    SlewRateLimited(f32),
+    /// Linearly ramp to the target value over the time since the target value was last changed,
+    /// up to the specified number of milliseconds. When the host sends automation as a series of
+    /// value changes at regular intervals, this reconstructs the host's automation ramp instead of
+    /// lagging behind it with a fixed smoothing time. An isolated value change, for instance when
+    /// the user drags a knob after the parameter has not changed for a while, takes this many
+    /// milliseconds to reach.
+    HostRamp(f32),
 }
 
 /// A smoother, providing a smoothed value for each sample.
@@ -56,11 +79,18 @@ pub struct Smoother<T: Smoothable> {
     /// the specified tiem frame. This is also a floating point number to keep the smoothing
     /// uniform.
     ///
-    /// In the case of the `Exponential` smoothing style this is the coefficient `x` that the
-    /// previous sample is multiplied by.
+    /// In the case of the `Exponential` and `CriticallyDamped` smoothing styles this is the
+    /// coefficient `x` that the previous sample is multiplied by.
     step_size: AtomicF32,
     /// The value for the current sample. Always stored as floating point for obvious reasons.
     current: AtomicF32,
+    /// The output of the first filter stage for the `CriticallyDamped` smoothing style. This is
+    /// equal to `current` when the smoother is not smoothing.
+    intermediate: AtomicF32,
+    /// The number of samples produced since the target value was last set. This is used to
+    /// determine the ramp duration for the `HostRamp` smoothing style, and it is not updated for
+    /// the other styles.
+    samples_since_target: AtomicU32,
     /// The value we're smoothing towards
     target: T::Atomic,
 }
@@ -86,10 +116,43 @@ impl SmoothingStyle {
             }
 
             Self::None => 1,
-            Self::Linear(time) | Self::Logarithmic(time) | Self::Exponential(time) => {
+            Self::Linear(time)
+            | Self::Logarithmic(time)
+            | Self::Exponential(time)
+            | Self::CriticallyDamped(time)
+            | Self::HostRamp(time) => {
                 nih_debug_assert!(*time >= 0.0);
                 (sample_rate * time / 1000.0).round() as u32
             }
+            Self::SlewRateLimited(rate) => {
+                nih_debug_assert!(*rate > 0.0);
+                (sample_rate / rate).ceil() as u32
+            }
+        }
+    }
+
+    /// Compute the number of steps to get from `start` to `target`. This is the same as
+    /// [`num_steps()`][Self::num_steps()], except for the `SlewRateLimited` style where the number
+    /// of steps depends on the distance between the two values. For `SlewRateLimited`,
+    /// `num_steps()` returns the number of steps needed to change the value by one unit. For the
+    /// `HostRamp` style this returns the maximum number of steps, and the smoother will use the
+    /// time since the last value change instead if that is shorter.
+    #[inline]
+    pub fn num_steps_between(&self, sample_rate: f32, start: f32, target: f32) -> u32 {
+        nih_debug_assert!(sample_rate > 0.0);
+
+        match self {
+            Self::OversamplingAware(oversampling_times, style) => style.num_steps_between(
+                sample_rate * oversampling_times.load(Ordering::Relaxed),
+                start,
+                target,
+            ),
+
+            Self::SlewRateLimited(rate) => {
+                nih_debug_assert!(*rate > 0.0);
+                ((target - start).abs() * sample_rate / rate).ceil() as u32
+            }
+            _ => self.num_steps(sample_rate),
         }
     }
 
@@ -104,7 +167,9 @@ impl SmoothingStyle {
             Self::OversamplingAware(_, style) => style.step_size(start, target, num_steps),
 
             Self::None => 0.0,
-            Self::Linear(_) => (target - start) / (num_steps as f32),
+            Self::Linear(_) | Self::SlewRateLimited(_) | Self::HostRamp(_) => {
+                (target - start) / (num_steps as f32)
+            }
             Self::Logarithmic(_) => {
                 // We need to solve `start * (step_size ^ num_steps) = target` for `step_size`
                 nih_debug_assert_ne!(start, 0.0);
@@ -115,6 +180,11 @@ impl SmoothingStyle {
             // reaches 99.99% of the target value after `num_steps`. The smoother will snap to the
             // target value after that point.
             Self::Exponential(_) => 0.0001f64.powf((num_steps as f64).recip()) as f32,
+            // This is the coefficient for both filter stages. The step response of two identical
+            // one-pole filters in series is approximately `1 - (1 + x) * e^-x` for
+            // `x = num_steps * -ln(coefficient)`, which reaches 99.99% of the target value at
+            // `x ≈ 11.756`.
+            Self::CriticallyDamped(_) => (-CRITICALLY_DAMPED_DECAY / num_steps as f64).exp() as f32,
         }
     }
 
@@ -123,16 +193,43 @@ impl SmoothingStyle {
     /// never completely reach `target`, so you will need to snap to `target` yourself after
     /// computing the target number of steps.
     ///
+    /// The `CriticallyDamped` style needs to keep track of an additional value between steps, so
+    /// this function assumes that its first filter stage has already reached `target`. Use
+    /// [`next_with_state()`][Self::next_with_state()] instead to get the actual second order
+    /// response.
+    ///
     /// See the docstring on the [`SmoothingStyle::next_step()`] function for the formulas used.
     #[inline]
     pub fn next(&self, current: f32, target: f32, step_size: f32) -> f32 {
+        let mut state = target;
+        self.next_with_state(current, &mut state, target, step_size)
+    }
+
+    /// The same as [`next()`][Self::next()], but with an additional `state` value that's needed
+    /// for the `CriticallyDamped` smoothing style. `state` should initially be set to the same
+    /// value as `current`, and it should be reset to `target` when snapping to the target value.
+    /// It is unused for the other smoothing styles.
+    #[inline]
+    pub fn next_with_state(
+        &self,
+        current: f32,
+        state: &mut f32,
+        target: f32,
+        step_size: f32,
+    ) -> f32 {
         match self {
-            Self::OversamplingAware(_, style) => style.next(current, target, step_size),
+            Self::OversamplingAware(_, style) => {
+                style.next_with_state(current, state, target, step_size)
+            }
 
             Self::None => target,
-            Self::Linear(_) => current + step_size,
+            Self::Linear(_) | Self::SlewRateLimited(_) | Self::HostRamp(_) => current + step_size,
             Self::Logarithmic(_) => current * step_size,
             Self::Exponential(_) => (current * step_size) + (target * (1.0 - step_size)),
+            Self::CriticallyDamped(_) => {
+                *state = (*state * step_size) + (target * (1.0 - step_size));
+                (current * step_size) + (*state * (1.0 - step_size))
+            }
         }
     }
 
@@ -141,16 +238,38 @@ impl SmoothingStyle {
     /// `n` times to a value, but is more efficient to compute. `next_step()` with 1 step is
     /// equivalent to `step()`.
     ///
-    /// See the docstring on the [`SmoothingStyle::next_step()`] function for the formulas used.
+    /// Like [`next()`][Self::next()], this assumes that the `CriticallyDamped` style's first filter
+    /// stage has already reached `target`. Use
+    /// [`next_step_with_state()`][Self::next_step_with_state()] for the actual second order
+    /// response.
     #[inline]
     pub fn next_step(&self, current: f32, target: f32, step_size: f32, steps: u32) -> f32 {
+        let mut state = target;
+        self.next_step_with_state(current, &mut state, target, step_size, steps)
+    }
+
+    /// The same as [`next_step()`][Self::next_step()], but with the additional state used by
+    /// [`next_with_state()`][Self::next_with_state()].
+    #[inline]
+    pub fn next_step_with_state(
+        &self,
+        current: f32,
+        state: &mut f32,
+        target: f32,
+        step_size: f32,
+        steps: u32,
+    ) -> f32 {
         nih_debug_assert!(steps >= 1);
 
         match self {
-            Self::OversamplingAware(_, style) => style.next_step(current, target, step_size, steps),
+            Self::OversamplingAware(_, style) => {
+                style.next_step_with_state(current, state, target, step_size, steps)
+            }
 
             Self::None => target,
-            Self::Linear(_) => current + (step_size * steps as f32),
+            Self::Linear(_) | Self::SlewRateLimited(_) | Self::HostRamp(_) => {
+                current + (step_size * steps as f32)
+            }
             Self::Logarithmic(_) => current * (step_size.powi(steps as i32)),
             Self::Exponential(_) => {
                 // This is the same as calculating `current = (current * step_size) +
@@ -158,10 +277,36 @@ impl SmoothingStyle {
                 let coefficient = step_size.powi(steps as i32);
                 (current * coefficient) + (target * (1.0 - coefficient))
             }
+            Self::CriticallyDamped(_) => {
+                // The distance between the first stage and the target decays exponentially. The
+                // second stage's distance to the target is the sum of its own exponential decay and
+                // the first stage's distance filtered by the second stage, which works out to
+                // `steps * (1 - step_size) * step_size^steps` times the first stage's distance.
+                let coefficient = step_size.powi(steps as i32);
+                let state_distance = *state - target;
+                *state = target + (state_distance * coefficient);
+                target
+                    + ((current - target) * coefficient)
+                    + (state_distance * steps as f32 * (1.0 - step_size) * coefficient)
+            }
+        }
+    }
+
+    /// Whether this is the `HostRamp` style, possibly wrapped in an `OversamplingAware` style.
+    #[inline]
+    fn is_host_ramp(&self) -> bool {
+        match self {
+            Self::OversamplingAware(_, style) => style.is_host_ramp(),
+            Self::HostRamp(_) => true,
+            _ => false,
         }
     }
 }
 
+/// The value of `x` for which the step response of the `CriticallyDamped` smoothing style,
+/// `1 - (1 + x) * e^-x`, reaches 99.99%.
+const CRITICALLY_DAMPED_DECAY: f64 = 11.756371222495419;
+
 /// A type that can be smoothed. This exists just to avoid duplicate explicit implementations for
 /// the smoothers.
 pub trait Smoothable: Default + Clone + Copy {
@@ -185,6 +330,8 @@ impl<T: Smoothable> Default for Smoother<T> {
             steps_left: AtomicI32::new(0),
             step_size: Default::default(),
             current: AtomicF32::new(0.0),
+            intermediate: AtomicF32::new(0.0),
+            samples_since_target: AtomicU32::new(u32::MAX),
             target: Default::default(),
         }
     }
@@ -208,6 +355,8 @@ impl<T: Smoothable> Clone for Smoother<T> {
             steps_left: AtomicI32::new(self.steps_left.load(Ordering::Relaxed)),
             step_size: AtomicF32::new(self.step_size.load(Ordering::Relaxed)),
             current: AtomicF32::new(self.current.load(Ordering::Relaxed)),
+            intermediate: AtomicF32::new(self.intermediate.load(Ordering::Relaxed)),
+            samples_since_target: AtomicU32::new(self.samples_since_target.load(Ordering::Relaxed)),
             target: T::atomic_new(T::atomic_load(&self.target)),
         }
     }
@@ -253,18 +402,37 @@ impl<T: Smoothable> Smoother<T> {
     pub fn reset(&self, value: T) {
         T::atomic_store(&self.target, value);
         self.current.store(value.to_f32(), Ordering::Relaxed);
+        self.intermediate.store(value.to_f32(), Ordering::Relaxed);
         self.steps_left.store(0, Ordering::Relaxed);
+        self.samples_since_target.store(u32::MAX, Ordering::Relaxed);
     }
 
     /// Set the target value.
     pub fn set_target(&self, sample_rate: f32, target: T) {
         T::atomic_store(&self.target, target);
 
-        let steps_left = self.style.num_steps(sample_rate) as i32;
-        self.steps_left.store(steps_left, Ordering::Relaxed);
-
         let current = self.current.load(Ordering::Relaxed);
         let target_f32 = target.to_f32();
+
+        let mut num_steps = self
+            .style
+            .num_steps_between(sample_rate, current, target_f32);
+        if self.style.is_host_ramp() {
+            // The ramp should end when we expect the next value change to arrive, which is
+            // assumed to be the same amount of time as between the last two value changes
+            let samples_since_target = self.samples_since_target.swap(0, Ordering::Relaxed);
+            num_steps = num_steps.min(samples_since_target.max(1));
+        }
+        let steps_left = num_steps.min(i32::MAX as u32) as i32;
+
+        // The first filter stage of the `CriticallyDamped` style only needs to be preserved while
+        // the smoother is still smoothing, as that is what keeps the value's rate of change
+        // continuous
+        if self.steps_left.load(Ordering::Relaxed) <= 0 {
+            self.intermediate.store(current, Ordering::Relaxed);
+        }
+        self.steps_left.store(steps_left, Ordering::Relaxed);
+
         self.step_size.store(
             if steps_left > 0 {
                 self.style.step_size(current, target_f32, steps_left as u32)
@@ -282,6 +450,7 @@ impl<T: Smoothable> Smoother<T> {
     #[inline]
     pub fn next(&self) -> T {
         let target = T::atomic_load(&self.target);
+        self.count_samples(1);
 
         // NOTE: This used to be implemented in terms of `next_step()`, but this is more efficient
         //       for the common use case of single steps
@@ -295,14 +464,18 @@ impl<T: Smoothable> Smoother<T> {
             // possibility that we only have `n < steps` steps left. This is especially important
             // for the `Exponential` smoothing style, since that won't reach the target value
             // exactly.
+            let mut intermediate = self.intermediate.load(Ordering::Relaxed);
             let old_steps_left = self.steps_left.fetch_sub(1, Ordering::Relaxed);
             let new = if old_steps_left == 1 {
                 self.steps_left.store(0, Ordering::Relaxed);
+                intermediate = target_f32;
                 target_f32
             } else {
-                self.style.next(current, target_f32, step_size)
+                self.style
+                    .next_with_state(current, &mut intermediate, target_f32, step_size)
             };
             self.current.store(new, Ordering::Relaxed);
+            self.intermediate.store(intermediate, Ordering::Relaxed);
 
             T::from_f32(new)
         } else {
@@ -319,6 +492,7 @@ impl<T: Smoothable> Smoother<T> {
         nih_debug_assert_ne!(steps, 0);
 
         let target = T::atomic_load(&self.target);
+        self.count_samples(steps);
 
         if self.steps_left.load(Ordering::Relaxed) > 0 {
             let current = self.current.load(Ordering::Relaxed);
@@ -330,14 +504,23 @@ impl<T: Smoothable> Smoother<T> {
             // possibility that we only have `n < steps` steps left. This is especially important
             // for the `Exponential` smoothing style, since that won't reach the target value
             // exactly.
+            let mut intermediate = self.intermediate.load(Ordering::Relaxed);
             let old_steps_left = self.steps_left.fetch_sub(steps as i32, Ordering::Relaxed);
             let new = if old_steps_left <= steps as i32 {
                 self.steps_left.store(0, Ordering::Relaxed);
+                intermediate = target_f32;
                 target_f32
             } else {
-                self.style.next_step(current, target_f32, step_size, steps)
+                self.style.next_step_with_state(
+                    current,
+                    &mut intermediate,
+                    target_f32,
+                    step_size,
+                    steps,
+                )
             };
             self.current.store(new, Ordering::Relaxed);
+            self.intermediate.store(intermediate, Ordering::Relaxed);
 
             T::from_f32(new)
         } else {
@@ -353,6 +536,19 @@ impl<T: Smoothable> Smoother<T> {
         T::from_f32(self.current.load(Ordering::Relaxed))
     }
 
+    /// Keep track of the number of samples produced since the target value was last set. This is
+    /// only needed for the `HostRamp` smoothing style.
+    #[inline]
+    fn count_samples(&self, samples: u32) {
+        if self.style.is_host_ramp() {
+            let samples_since_target = self.samples_since_target.load(Ordering::Relaxed);
+            self.samples_since_target.store(
+                samples_since_target.saturating_add(samples),
+                Ordering::Relaxed,
+            );
+        }
+    }
+
     /// Produce smoothed values for an entire block of audio. This is useful when iterating the same
     /// block of audio multiple times. For instance when summing voices for a synthesizer.
     /// `block_values[..block_len]` will be filled with the smoothed values. This is simply a
@@ -369,6 +565,7 @@ impl<T: Smoothable> Smoother<T> {
     /// The same as [`next_block()`][Self::next_block()], but filling the entire slice.
     pub fn next_block_exact(&self, block_values: &mut [T]) {
         let target = T::atomic_load(&self.target);
+        self.count_samples(block_values.len() as u32);
 
         // `self.next()` will yield the current value if the parameter is no longer smoothing, but
         // it's a bit of a waste to continuously call that if only the first couple or none of the
@@ -379,6 +576,7 @@ impl<T: Smoothable> Smoother<T> {
         let num_smoothed_values = block_values.len().min(steps_left);
         if num_smoothed_values > 0 {
             let mut current = self.current.load(Ordering::Relaxed);
+            let mut intermediate = self.intermediate.load(Ordering::Relaxed);
             let target_f32 = target.to_f32();
             let step_size = self.step_size.load(Ordering::Relaxed);
 
@@ -386,17 +584,28 @@ impl<T: Smoothable> Smoother<T> {
                 // This is the same as calling `next()` `num_smoothed_values` times, but with some
                 // conditionals optimized out
                 block_values[..num_smoothed_values - 1].fill_with(|| {
-                    current = self.style.next(current, target_f32, step_size);
+                    current = self.style.next_with_state(
+                        current,
+                        &mut intermediate,
+                        target_f32,
+                        step_size,
+                    );
                     T::from_f32(current)
                 });
 
                 // In `next()` the last step snaps the value to the target value, so we'll do the
                 // same thing here
                 current = target_f32.to_f32();
+                intermediate = target_f32;
                 block_values[num_smoothed_values - 1] = target;
             } else {
                 block_values[..num_smoothed_values].fill_with(|| {
-                    current = self.style.next(current, target_f32, step_size);
+                    current = self.style.next_with_state(
+                        current,
+                        &mut intermediate,
+                        target_f32,
+                        step_size,
+                    );
                     T::from_f32(current)
                 });
             }
@@ -404,6 +613,7 @@ impl<T: Smoothable> Smoother<T> {
             block_values[num_smoothed_values..].fill(target);
 
             self.current.store(current, Ordering::Relaxed);
+            self.intermediate.store(intermediate, Ordering::Relaxed);
             self.steps_left
                 .fetch_sub(num_smoothed_values as i32, Ordering::Relaxed);
         } else {
@@ -435,11 +645,13 @@ impl<T: Smoothable> Smoother<T> {
         // This works exactly the same as `next_block_exact()`, except for the addition of the
         // mapping function
         let target_f32 = T::atomic_load(&self.target).to_f32();
+        self.count_samples(block_values.len() as u32);
 
         let steps_left = self.steps_left.load(Ordering::Relaxed) as usize;
         let num_smoothed_values = block_values.len().min(steps_left);
         if num_smoothed_values > 0 {
             let mut current = self.current.load(Ordering::Relaxed);
+            let mut intermediate = self.intermediate.load(Ordering::Relaxed);
             let step_size = self.step_size.load(Ordering::Relaxed);
 
             // See `next_block_exact()` for more details
@@ -449,11 +661,17 @@ impl<T: Smoothable> Smoother<T> {
                     .enumerate()
                     .take(num_smoothed_values - 1)
                 {
-                    current = self.style.next(current, target_f32, step_size);
+                    current = self.style.next_with_state(
+                        current,
+                        &mut intermediate,
+                        target_f32,
+                        step_size,
+                    );
                     *value = f(idx, current);
                 }
 
                 current = target_f32.to_f32();
+                intermediate = target_f32;
                 block_values[num_smoothed_values - 1] = f(num_smoothed_values - 1, target_f32);
             } else {
                 for (idx, value) in block_values
@@ -461,7 +679,12 @@ impl<T: Smoothable> Smoother<T> {
                     .enumerate()
                     .take(num_smoothed_values)
                 {
-                    current = self.style.next(current, target_f32, step_size);
+                    current = self.style.next_with_state(
+                        current,
+                        &mut intermediate,
+                        target_f32,
+                        step_size,
+                    );
                     *value = f(idx, current);
                 }
             }
@@ -475,6 +698,7 @@ impl<T: Smoothable> Smoother<T> {
             }
 
             self.current.store(current, Ordering::Relaxed);
+            self.intermediate.store(intermediate, Ordering::Relaxed);
             self.steps_left
                 .fetch_sub(num_smoothed_values as i32, Ordering::Relaxed);
         } else {
@@ -514,6 +738,37 @@ impl Smoothable for f32 {
     }
 }
 
+/// Smoothing is still computed at 32-bit precision, but this allows using a [`Smoother`] to
+/// produce values for [`Buffer<f64>`][crate::prelude::Buffer]s.
+impl Smoothable for f64 {
+    type Atomic = AtomicF64;
+
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        value as f64
+    }
+
+    #[inline]
+    fn atomic_new(value: Self) -> Self::Atomic {
+        AtomicF64::new(value)
+    }
+
+    #[inline]
+    fn atomic_load(this: &Self::Atomic) -> Self {
+        this.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn atomic_store(this: &Self::Atomic, value: Self) {
+        this.store(value, Ordering::Relaxed)
+    }
+}
+
 impl Smoothable for i32 {
     type Atomic = AtomicI32;
 
@@ -615,6 +870,20 @@ mod tests {
         assert_eq!(smoother.next(), 20.0);
     }
 
+    #[test]
+    fn linear_f64_smoothing() {
+        let smoother: Smoother<f64> = Smoother::new(SmoothingStyle::Linear(100.0));
+        smoother.reset(10.0);
+        assert_eq!(smoother.next(), 10.0);
+
+        smoother.set_target(100.0, 20.0);
+        for _ in 0..(10 - 2) {
+            smoother.next();
+        }
+        assert_ne!(smoother.next(), 20.0);
+        assert_eq!(smoother.next(), 20.0);
+    }
+
     #[test]
     fn linear_i32_smoothing() {
         let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Linear(100.0));
@@ -713,5 +982,142 @@ mod tests {
         assert_eq!(smoother.next(), 20);
     }
 
+    #[test]
+    fn critically_damped_f32_next_equivalence() {
+        let style = SmoothingStyle::CriticallyDamped(100.0);
+
+        let mut current = 0.4;
+        let mut state = 0.6;
+        let target = 0.8;
+        let steps = 15;
+        let step_size = style.step_size(current, target, steps);
+
+        let mut expected_state = state;
+        let expected_result =
+            style.next_step_with_state(current, &mut expected_state, target, step_size, steps);
+        for _ in 0..steps {
+            current = style.next_with_state(current, &mut state, target, step_size);
+        }
+
+        approx::assert_relative_eq!(current, expected_result, epsilon = 1e-5);
+        approx::assert_relative_eq!(state, expected_state, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn critically_damped_f32_smoothing() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::CriticallyDamped(100.0));
+        smoother.reset(10.0);
+        assert_eq!(smoother.next(), 10.0);
+
+        smoother.set_target(100.0, 20.0);
+        for _ in 0..(10 - 2) {
+            smoother.next();
+        }
+        assert_ne!(smoother.next(), 20.0);
+        assert_eq!(smoother.next(), 20.0);
+    }
+
+    #[test]
+    fn critically_damped_i32_smoothing() {
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::CriticallyDamped(100.0));
+        smoother.reset(0);
+        assert_eq!(smoother.next(), 0);
+
+        // The value is only 0.01% away from the target before snapping to it, so we need a large
+        // range to be able to test this with integers
+        smoother.set_target(100.0, 10000);
+        for _ in 0..(10 - 2) {
+            smoother.next();
+        }
+        assert_ne!(smoother.next(), 10000);
+        assert_eq!(smoother.next(), 10000);
+    }
+
+    /// Changing the target value while smoothing should not cause the value to suddenly change
+    /// direction.
+    #[test]
+    fn critically_damped_retarget_continuity() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::CriticallyDamped(1000.0));
+        smoother.reset(0.0);
+        smoother.set_target(1000.0, 1.0);
+        for _ in 0..200 {
+            smoother.next();
+        }
+
+        let previous = smoother.previous_value();
+        let slope = smoother.next() - previous;
+        smoother.set_target(1000.0, 0.0);
+        let new_slope = smoother.next() - (previous + slope);
+        assert!(new_slope > 0.0);
+        approx::assert_relative_eq!(new_slope, slope, max_relative = 0.1);
+    }
+
+    #[test]
+    fn slew_rate_limited_f32_smoothing() {
+        // At a 100 Hz sample rate this moves by one unit per sample
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::SlewRateLimited(100.0));
+        smoother.reset(10.0);
+        assert_eq!(smoother.next(), 10.0);
+
+        smoother.set_target(100.0, 20.0);
+        assert_eq!(smoother.steps_left(), 10);
+        approx::assert_relative_eq!(smoother.next(), 11.0, epsilon = 1e-5);
+
+        // Smaller changes take less time to reach
+        smoother.set_target(100.0, 15.0);
+        assert_eq!(smoother.steps_left(), 4);
+        approx::assert_relative_eq!(smoother.next(), 12.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn slew_rate_limited_i32_smoothing() {
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::SlewRateLimited(100.0));
+        smoother.reset(10);
+        assert_eq!(smoother.next(), 10);
+
+        smoother.set_target(100.0, 0);
+        let mut values = [0; 12];
+        smoother.next_block(&mut values, 12);
+        assert_eq!(values, [9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn host_ramp_f32_smoothing() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::HostRamp(100.0));
+        smoother.reset(10.0);
+
+        // An isolated value change uses the maximum ramp time
+        smoother.set_target(100.0, 20.0);
+        assert_eq!(smoother.steps_left(), 10);
+
+        // After that, the ramp lasts as long as the time between the last two value changes
+        let mut values = [0.0; 4];
+        smoother.next_block(&mut values, 4);
+        smoother.set_target(100.0, 30.0);
+        assert_eq!(smoother.steps_left(), 4);
+        assert_ne!(smoother.next_step(3), 30.0);
+        assert_eq!(smoother.next(), 30.0);
+
+        // But never longer than the maximum ramp time
+        for _ in 0..20 {
+            smoother.next();
+        }
+        smoother.set_target(100.0, 40.0);
+        assert_eq!(smoother.steps_left(), 10);
+    }
+
+    #[test]
+    fn host_ramp_i32_smoothing() {
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::HostRamp(100.0));
+        smoother.reset(0);
+        smoother.set_target(100.0, 10);
+        smoother.next_step(2);
+
+        smoother.set_target(100.0, 20);
+        let mut values = [0; 3];
+        smoother.next_block(&mut values, 3);
+        assert_eq!(values, [11, 20, 20]);
+    }
+
     // TODO: Tests for the exponential smoothing
 }
@@ -0,0 +1,450 @@
+//! Non-automatable string and path parameters. These are stored in the plugin's state like any
+//! other `#[persist = "key"]` field, but they also come with change callbacks and a realtime-safe
+//! way to read the current value from the audio thread.
+
+use parking_lot::{Mutex, RwLock};
+use std::fmt::{Debug, Display};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// A string value that can be edited from the plugin's editor and that's stored in the plugin's
+/// state, for things like script code or labels. Unlike the other parameter types this is not
+/// exposed to the host, so it cannot be automated. Add this to your [`Params`][super::Params]
+/// struct using the `#[persist = "key"]` attribute:
+///
+/// ```ignore
+/// #[derive(Params)]
+/// struct MyParams {
+///     #[persist = "script"]
+///     script: StringParam,
+/// }
+/// ```
+///
+/// The value can be read from the audio thread without locking or allocating by keeping a
+/// [`ValueSnapshot`] around and updating it with [`update_snapshot()`][Self::update_snapshot()].
+pub struct StringParam {
+    inner: TextValue<String>,
+}
+
+/// A file system path that can be edited from the plugin's editor and that's stored in the
+/// plugin's state. This works exactly like [`StringParam`], except that it stores a [`PathBuf`].
+/// The path is empty by default.
+pub struct PathParam {
+    inner: TextValue<PathBuf>,
+}
+
+/// A snapshot of a [`StringParam`]'s or a [`PathParam`]'s value. This dereferences to the value
+/// as it was when the snapshot was taken or last updated. Cloning and dropping snapshots is
+/// realtime-safe, since the parameter keeps old values alive until the last snapshot referring to
+/// them has been dropped.
+#[derive(Clone)]
+pub struct ValueSnapshot<T> {
+    value: Arc<T>,
+    /// The parameter's [`generation()`][StringParam::generation()] at the time `value` was
+    /// fetched.
+    generation: u32,
+}
+
+/// The shared implementation for [`StringParam`] and [`PathParam`].
+struct TextValue<T> {
+    /// The parameter's human readable display name.
+    name: String,
+    /// The current value. The write lock is only held for as long as it takes to swap the `Arc`.
+    current: RwLock<Arc<T>>,
+    /// Incremented every time the value changes. This lets readers check whether their snapshot
+    /// is still up to date without touching `current`.
+    generation: AtomicU32,
+    /// Values that have been replaced, but which may still be referenced by a [`ValueSnapshot`].
+    /// Holding on to these makes sure the last reference to a value is never dropped on the audio
+    /// thread. These are cleaned up the next time the value changes.
+    retired: Mutex<Vec<Arc<T>>>,
+    /// The value the parameter was created with.
+    default: T,
+    /// Optional callback for listening to value changes. This is called with the new value.
+    value_changed: Option<Arc<dyn Fn(&T) + Send + Sync>>,
+}
+
+impl<T> Deref for ValueSnapshot<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: Debug> Debug for ValueSnapshot<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&*self.value, f)
+    }
+}
+
+impl<T> ValueSnapshot<T> {
+    /// The parameter's generation at the time this snapshot was taken. See
+    /// [`StringParam::generation()`].
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T: Clone + PartialEq> TextValue<T> {
+    fn new(name: String, default: T) -> Self {
+        Self {
+            name,
+            current: RwLock::new(Arc::new(default.clone())),
+            generation: AtomicU32::new(0),
+            retired: Mutex::new(Vec::new()),
+            default,
+            value_changed: None,
+        }
+    }
+
+    fn snapshot(&self) -> ValueSnapshot<T> {
+        // The generation needs to be read while holding the lock, since `set()` increments it
+        // while holding the write lock
+        let current = self.current.read();
+        ValueSnapshot {
+            value: current.clone(),
+            generation: self.generation.load(Ordering::Acquire),
+        }
+    }
+
+    fn update_snapshot(&self, snapshot: &mut ValueSnapshot<T>) -> bool {
+        if snapshot.generation == self.generation.load(Ordering::Acquire) {
+            return false;
+        }
+
+        // If the value is being changed right now then we'll simply try again the next time
+        match self.current.try_read() {
+            Some(current) => {
+                // The old value in the snapshot is either the current value or a retired value,
+                // so this will never deallocate
+                snapshot.value = current.clone();
+                snapshot.generation = self.generation.load(Ordering::Acquire);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        f(&self.current.read())
+    }
+
+    fn set(&self, new_value: T) {
+        if **self.current.read() == new_value {
+            return;
+        }
+
+        let new_value = Arc::new(new_value);
+        let old_value = {
+            let mut current = self.current.write();
+            self.generation.fetch_add(1, Ordering::AcqRel);
+            std::mem::replace(&mut *current, new_value.clone())
+        };
+
+        // Any retired value that's only referenced from this list can no longer be obtained by a
+        // snapshot, so those can now be freed safely
+        {
+            let mut retired = self.retired.lock();
+            retired.push(old_value);
+            retired.retain(|value| Arc::strong_count(value) > 1);
+        }
+
+        if let Some(f) = &self.value_changed {
+            f(&new_value);
+        }
+    }
+}
+
+impl Display for StringParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.map(|value| f.write_str(value))
+    }
+}
+
+impl Debug for StringParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner
+            .map(|value| write!(f, "{}: {:?}", self.inner.name, value))
+    }
+}
+
+impl Display for PathParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.map(|value| write!(f, "{}", value.display()))
+    }
+}
+
+impl Debug for PathParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner
+            .map(|value| write!(f, "{}: {:?}", self.inner.name, value))
+    }
+}
+
+impl StringParam {
+    /// Build a new [`StringParam`]. Use the other associated functions to modify the behavior of
+    /// the parameter.
+    pub fn new(name: impl Into<String>, default: impl Into<String>) -> Self {
+        Self {
+            inner: TextValue::new(name.into(), default.into()),
+        }
+    }
+
+    /// Run a callback whenever this parameter's value changes, including when the plugin's state
+    /// is restored. The argument passed to this function is the parameter's new value. This
+    /// should not do anything expensive, and it can be run from both the GUI and the audio thread
+    /// since state can be restored while the plugin is processing audio.
+    pub fn with_callback(mut self, callback: Arc<dyn Fn(&str) + Send + Sync>) -> Self {
+        self.inner.value_changed = Some(Arc::new(move |value: &String| callback(value)));
+        self
+    }
+
+    /// The parameter's human readable display name.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// The parameter's default value.
+    pub fn default_value(&self) -> &str {
+        &self.inner.default
+    }
+
+    /// Get a copy of the parameter's current value. This allocates and briefly locks the value,
+    /// so on the audio thread you should use [`update_snapshot()`][Self::update_snapshot()]
+    /// instead.
+    pub fn value(&self) -> String {
+        self.inner.map(|value| value.clone())
+    }
+
+    /// Get a snapshot of the parameter's current value. This briefly locks the value, so this
+    /// should be called outside of the audio thread, for instance when initializing the plugin.
+    /// The snapshot can then be kept up to date from the audio thread using
+    /// [`update_snapshot()`][Self::update_snapshot()].
+    pub fn snapshot(&self) -> ValueSnapshot<String> {
+        self.inner.snapshot()
+    }
+
+    /// Update `snapshot` to the parameter's current value if the value has changed since the
+    /// snapshot was taken. Returns `true` if the snapshot was updated. This never blocks or
+    /// allocates, so it's safe to call from the audio thread. If the value is being changed at the
+    /// same time then the snapshot is left as is, and it will be updated during the next call.
+    pub fn update_snapshot(&self, snapshot: &mut ValueSnapshot<String>) -> bool {
+        self.inner.update_snapshot(snapshot)
+    }
+
+    /// A number that's incremented every time the parameter's value changes. Editors can compare
+    /// this to a previously stored generation to find out whether they need to reload the value,
+    /// since the value can also change when the host restores the plugin's state.
+    pub fn generation(&self) -> u32 {
+        self.inner.generation.load(Ordering::Acquire)
+    }
+
+    /// Change the parameter's value. The callback is only run when the value actually changed.
+    /// This allocates, so it must not be called from the audio thread.
+    pub fn set_value(&self, value: impl Into<String>) {
+        self.inner.set(value.into());
+    }
+}
+
+impl PathParam {
+    /// Build a new [`PathParam`] with an empty default path. Use the other associated functions
+    /// to modify the behavior of the parameter.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            inner: TextValue::new(name.into(), PathBuf::new()),
+        }
+    }
+
+    /// Build a new [`PathParam`] with a non-empty default path.
+    pub fn with_default(name: impl Into<String>, default: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: TextValue::new(name.into(), default.into()),
+        }
+    }
+
+    /// Run a callback whenever this parameter's value changes, including when the plugin's state
+    /// is restored. The argument passed to this function is the parameter's new value. This
+    /// should not do anything expensive, and it can be run from both the GUI and the audio thread
+    /// since state can be restored while the plugin is processing audio.
+    pub fn with_callback(mut self, callback: Arc<dyn Fn(&Path) + Send + Sync>) -> Self {
+        self.inner.value_changed = Some(Arc::new(move |value: &PathBuf| callback(value)));
+        self
+    }
+
+    /// The parameter's human readable display name.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// The parameter's default value.
+    pub fn default_value(&self) -> &Path {
+        &self.inner.default
+    }
+
+    /// Get a copy of the parameter's current value. See [`StringParam::value()`].
+    pub fn value(&self) -> PathBuf {
+        self.inner.map(|value| value.clone())
+    }
+
+    /// Get a snapshot of the parameter's current value. See [`StringParam::snapshot()`].
+    pub fn snapshot(&self) -> ValueSnapshot<PathBuf> {
+        self.inner.snapshot()
+    }
+
+    /// Update `snapshot` to the parameter's current value if the value has changed since the
+    /// snapshot was taken. This is realtime-safe. See [`StringParam::update_snapshot()`].
+    pub fn update_snapshot(&self, snapshot: &mut ValueSnapshot<PathBuf>) -> bool {
+        self.inner.update_snapshot(snapshot)
+    }
+
+    /// A number that's incremented every time the parameter's value changes. See
+    /// [`StringParam::generation()`].
+    pub fn generation(&self) -> u32 {
+        self.inner.generation.load(Ordering::Acquire)
+    }
+
+    /// Change the parameter's value. The callback is only run when the value actually changed.
+    /// This allocates, so it must not be called from the audio thread.
+    pub fn set_value(&self, value: impl Into<PathBuf>) {
+        self.inner.set(value.into());
+    }
+}
+
+/// Common functionality for [`StringParam`] and [`PathParam`] that lets GUI widgets edit either of
+/// them as text.
+pub trait TextParam: Display + Send + Sync {
+    /// The parameter's human readable display name.
+    fn name(&self) -> &str;
+
+    /// The parameter's current value as a string. For paths this uses a lossy conversion.
+    fn text(&self) -> String;
+
+    /// Set the parameter's value from a string. This allocates, so it must not be called from the
+    /// audio thread.
+    fn set_text(&self, text: &str);
+
+    /// A number that's incremented every time the parameter's value changes. Widgets can use this
+    /// to detect changes made outside of the editor, like when a preset gets loaded.
+    fn generation(&self) -> u32;
+}
+
+impl TextParam for StringParam {
+    fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    fn text(&self) -> String {
+        self.value()
+    }
+
+    fn set_text(&self, text: &str) {
+        self.set_value(text);
+    }
+
+    fn generation(&self) -> u32 {
+        self.generation()
+    }
+}
+
+impl TextParam for PathParam {
+    fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    fn text(&self) -> String {
+        self.inner.map(|path| path.to_string_lossy().into_owned())
+    }
+
+    fn set_text(&self, text: &str) {
+        self.set_value(text);
+    }
+
+    fn generation(&self) -> u32 {
+        self.generation()
+    }
+}
+
+impl super::persist::PersistentField<'_, String> for StringParam {
+    fn set(&self, new_value: String) {
+        self.inner.set(new_value);
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&String) -> R,
+    {
+        self.inner.map(f)
+    }
+}
+
+impl super::persist::PersistentField<'_, PathBuf> for PathParam {
+    fn set(&self, new_value: PathBuf) {
+        self.inner.set(new_value);
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&PathBuf) -> R,
+    {
+        self.inner.map(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn snapshot_follows_value() {
+        let param = StringParam::new("Script", "foo");
+        let mut snapshot = param.snapshot();
+        assert_eq!(&*snapshot, "foo");
+        assert!(!param.update_snapshot(&mut snapshot));
+
+        param.set_value("bar");
+        assert_eq!(&*snapshot, "foo");
+        assert!(param.update_snapshot(&mut snapshot));
+        assert_eq!(&*snapshot, "bar");
+        assert_eq!(snapshot.generation(), param.generation());
+    }
+
+    #[test]
+    fn retired_values_outlive_snapshots() {
+        let param = StringParam::new("Script", "foo");
+        let snapshot = param.snapshot();
+
+        param.set_value("bar");
+        param.set_value("baz");
+        // The first value is still referenced by the snapshot, the second one isn't
+        assert_eq!(param.inner.retired.lock().len(), 1);
+        assert_eq!(&*snapshot, "foo");
+
+        drop(snapshot);
+        param.set_value("qux");
+        assert_eq!(param.inner.retired.lock().len(), 0);
+    }
+
+    #[test]
+    fn callback_only_runs_on_change() {
+        let num_calls = Arc::new(AtomicUsize::new(0));
+        let param = PathParam::new("Sample").with_callback({
+            let num_calls = num_calls.clone();
+            Arc::new(move |_| {
+                num_calls.fetch_add(1, Ordering::Relaxed);
+            })
+        });
+
+        param.set_value("/tmp/foo.wav");
+        param.set_value("/tmp/foo.wav");
+        assert_eq!(num_calls.load(Ordering::Relaxed), 1);
+        assert_eq!(param.value(), Path::new("/tmp/foo.wav"));
+    }
+}
@@ -0,0 +1,332 @@
+//! A compound time parameter that can switch between a free time in milliseconds and a note
+//! division synced to the host's tempo. Useful for delay times, LFO rates, envelope times, and
+//! anything else that should optionally follow the project's tempo.
+
+use std::fmt::Display;
+
+use super::internals::ParamPtr;
+use super::range::{FloatRange, IntRange};
+use super::{BoolParam, FloatParam, IntParam, Param, Params};
+use crate::context::process::Transport;
+use crate::formatters;
+
+/// The tempo in beats per minute that's used to resolve synced times when the host doesn't report
+/// a tempo.
+pub const FALLBACK_TEMPO: f64 = 120.0;
+
+/// Whether a [`NoteDivision`] is a straight, a dotted, or a triplet note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivisionModifier {
+    /// A regular note.
+    Straight,
+    /// A dotted note, which is one and a half times as long as a regular note.
+    Dotted,
+    /// A triplet note, which is two thirds as long as a regular note.
+    Triplet,
+}
+
+/// A note length relative to a whole note, like `1/4` for a quarter note or `1/8.` for a dotted
+/// eighth note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteDivision {
+    /// The numerator of the fraction of a whole note.
+    pub numerator: u32,
+    /// The denominator of the fraction of a whole note.
+    pub denominator: u32,
+    /// Whether this is a dotted or a triplet note.
+    pub modifier: DivisionModifier,
+}
+
+/// All note divisions selectable with [`SyncedTimeParams::division`], sorted by length. The
+/// division parameter's value is an index into this table.
+pub const NOTE_DIVISIONS: [NoteDivision; 27] = [
+    NoteDivision::triplet(1, 64),
+    NoteDivision::straight(1, 64),
+    NoteDivision::triplet(1, 32),
+    NoteDivision::dotted(1, 64),
+    NoteDivision::straight(1, 32),
+    NoteDivision::triplet(1, 16),
+    NoteDivision::dotted(1, 32),
+    NoteDivision::straight(1, 16),
+    NoteDivision::triplet(1, 8),
+    NoteDivision::dotted(1, 16),
+    NoteDivision::straight(1, 8),
+    NoteDivision::triplet(1, 4),
+    NoteDivision::dotted(1, 8),
+    NoteDivision::straight(1, 4),
+    NoteDivision::triplet(1, 2),
+    NoteDivision::dotted(1, 4),
+    NoteDivision::straight(1, 2),
+    NoteDivision::triplet(1, 1),
+    NoteDivision::dotted(1, 2),
+    NoteDivision::straight(1, 1),
+    NoteDivision::triplet(2, 1),
+    NoteDivision::dotted(1, 1),
+    NoteDivision::straight(2, 1),
+    NoteDivision::triplet(4, 1),
+    NoteDivision::dotted(2, 1),
+    NoteDivision::straight(4, 1),
+    NoteDivision::dotted(4, 1),
+];
+
+/// A time that's either a free time in milliseconds or a note division synced to the host's tempo,
+/// made up of a [`BoolParam`] that switches between the two modes, a [`FloatParam`] for the free
+/// time, and an [`IntParam`] selecting one of the [`NOTE_DIVISIONS`]. Use
+/// [`samples()`][Self::samples()] or [`seconds()`][Self::seconds()] to get the time for the
+/// current mode.
+///
+/// The parameters have the IDs `sync`, `time`, and `division`. This should be added to your
+/// `Params` struct with the `#[nested(id_prefix = "...")]` attribute so multiple of these objects
+/// can coexist:
+///
+/// ```ignore
+/// #[derive(Params)]
+/// struct MyParams {
+///     #[nested(id_prefix = "delay", group = "Delay")]
+///     delay_time: SyncedTimeParams,
+/// }
+/// ```
+pub struct SyncedTimeParams {
+    /// Whether the time is synced to the host's tempo. When this is enabled the `division`
+    /// parameter is used, and the `time` parameter is used otherwise.
+    pub sync: BoolParam,
+    /// The free time in milliseconds.
+    pub time: FloatParam,
+    /// An index into [`NOTE_DIVISIONS`].
+    pub division: IntParam,
+}
+
+impl Display for NoteDivision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)?;
+        match self.modifier {
+            DivisionModifier::Straight => Ok(()),
+            DivisionModifier::Dotted => write!(f, "."),
+            DivisionModifier::Triplet => write!(f, "T"),
+        }
+    }
+}
+
+impl NoteDivision {
+    /// A regular `numerator/denominator` note.
+    pub const fn straight(numerator: u32, denominator: u32) -> Self {
+        Self {
+            numerator,
+            denominator,
+            modifier: DivisionModifier::Straight,
+        }
+    }
+
+    /// A dotted `numerator/denominator` note.
+    pub const fn dotted(numerator: u32, denominator: u32) -> Self {
+        Self {
+            numerator,
+            denominator,
+            modifier: DivisionModifier::Dotted,
+        }
+    }
+
+    /// A `numerator/denominator` triplet note.
+    pub const fn triplet(numerator: u32, denominator: u32) -> Self {
+        Self {
+            numerator,
+            denominator,
+            modifier: DivisionModifier::Triplet,
+        }
+    }
+
+    /// Get the division at `index` in [`NOTE_DIVISIONS`]. Out of bounds indices are clamped.
+    pub fn from_index(index: i32) -> Self {
+        NOTE_DIVISIONS[index.clamp(0, NOTE_DIVISIONS.len() as i32 - 1) as usize]
+    }
+
+    /// Get this division's index in [`NOTE_DIVISIONS`], if it's in there.
+    pub fn index(&self) -> Option<i32> {
+        NOTE_DIVISIONS
+            .iter()
+            .position(|division| division == self)
+            .map(|index| index as i32)
+    }
+
+    /// The division's length in quarter notes, or beats.
+    pub fn beats(&self) -> f64 {
+        let straight_beats = self.numerator as f64 * 4.0 / self.denominator as f64;
+        match self.modifier {
+            DivisionModifier::Straight => straight_beats,
+            DivisionModifier::Dotted => straight_beats * 1.5,
+            DivisionModifier::Triplet => straight_beats * (2.0 / 3.0),
+        }
+    }
+
+    /// The division's length in seconds at `tempo` beats per minute.
+    pub fn seconds(&self, tempo: f64) -> f64 {
+        self.beats() * 60.0 / tempo
+    }
+}
+
+unsafe impl Params for SyncedTimeParams {
+    fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+        vec![
+            (String::from("sync"), self.sync.as_ptr(), String::new()),
+            (String::from("time"), self.time.as_ptr(), String::new()),
+            (
+                String::from("division"),
+                self.division.as_ptr(),
+                String::new(),
+            ),
+        ]
+    }
+}
+
+impl SyncedTimeParams {
+    /// Create a new synced time parameter. The parameters are called `{name} Sync`, `{name}`, and
+    /// `{name} Division`. `time_range` is the free time's range in milliseconds. The parameter is
+    /// not synced by default, use [`with_sync_default()`][Self::with_sync_default()] to change
+    /// that. `default_division` must be one of the [`NOTE_DIVISIONS`].
+    pub fn new(
+        name: &str,
+        default_time_ms: f32,
+        time_range: FloatRange,
+        default_division: NoteDivision,
+    ) -> Self {
+        let default_division_idx = default_division.index();
+        nih_debug_assert!(
+            default_division_idx.is_some(),
+            "{default_division} is not one of the available note divisions"
+        );
+
+        Self {
+            sync: BoolParam::new(format!("{name} Sync"), false),
+            time: FloatParam::new(name, default_time_ms, time_range)
+                .with_value_to_string(formatters::v2s_f32_ms_then_s(2))
+                .with_string_to_value(formatters::s2v_f32_ms_then_s()),
+            division: IntParam::new(
+                format!("{name} Division"),
+                default_division_idx.unwrap_or_else(|| {
+                    NoteDivision::straight(1, 4)
+                        .index()
+                        .expect("1/4 is always a valid note division")
+                }),
+                IntRange::Linear {
+                    min: 0,
+                    max: NOTE_DIVISIONS.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(formatters::v2s_i32_note_division())
+            .with_string_to_value(formatters::s2v_i32_note_division()),
+        }
+    }
+
+    /// Change whether the parameter is synced to the host's tempo by default. Any other changes
+    /// made to the `sync` parameter, like callbacks or flags, are kept.
+    pub fn with_sync_default(mut self, synced: bool) -> Self {
+        self.sync = self.sync.with_default(synced);
+        self
+    }
+
+    /// Whether the time is currently synced to the host's tempo.
+    pub fn is_synced(&self) -> bool {
+        self.sync.value()
+    }
+
+    /// The currently selected note division. This is also returned when the time is not synced.
+    pub fn division(&self) -> NoteDivision {
+        NoteDivision::from_index(self.division.value())
+    }
+
+    /// Resolve the time to seconds. Synced times use `tempo` in beats per minute, or
+    /// [`FALLBACK_TEMPO`] if the host does not report a tempo. This uses the parameters' current
+    /// unsmoothed values.
+    pub fn seconds(&self, tempo: Option<f64>) -> f32 {
+        if self.is_synced() {
+            self.division().seconds(tempo.unwrap_or(FALLBACK_TEMPO)) as f32
+        } else {
+            self.time.value() / 1000.0
+        }
+    }
+
+    /// Resolve the time to a (fractional) number of samples using the transport's tempo and sample
+    /// rate. See [`seconds()`][Self::seconds()].
+    pub fn samples(&self, transport: &Transport) -> f32 {
+        self.seconds(transport.tempo) * transport.sample_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::params::{ParamFlags, ParamMut};
+
+    #[test]
+    fn divisions_are_sorted() {
+        for pair in NOTE_DIVISIONS.windows(2) {
+            assert!(
+                pair[0].beats() < pair[1].beats(),
+                "{} should be shorter than {}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_to_samples() {
+        let params = SyncedTimeParams::new(
+            "Delay",
+            250.0,
+            FloatRange::Linear {
+                min: 1.0,
+                max: 5000.0,
+            },
+            NoteDivision::dotted(1, 8),
+        );
+        let mut transport = Transport::new(48000.0);
+        assert_eq!(params.samples(&transport), 12000.0);
+
+        params.sync.set_plain_value(true);
+        transport.tempo = Some(90.0);
+        // A dotted eighth note is 0.75 beats, and a beat at 90 BPM lasts 2/3 seconds
+        assert_eq!(params.samples(&transport), 24000.0);
+
+        // Without a tempo this falls back to 120 BPM
+        transport.tempo = None;
+        assert_eq!(params.samples(&transport), 18000.0);
+    }
+
+    #[test]
+    fn sync_default() {
+        let sync_changes = Arc::new(AtomicUsize::new(0));
+        let mut params = SyncedTimeParams::new(
+            "Delay",
+            250.0,
+            FloatRange::Linear {
+                min: 1.0,
+                max: 5000.0,
+            },
+            NoteDivision::straight(1, 4),
+        );
+        params.sync = params
+            .sync
+            .with_callback({
+                let sync_changes = sync_changes.clone();
+                Arc::new(move |_| {
+                    sync_changes.fetch_add(1, Ordering::Relaxed);
+                })
+            })
+            .non_automatable();
+        let params = params.with_sync_default(true);
+
+        assert!(params.is_synced());
+        assert!(params.sync.default_plain_value());
+        assert_eq!(params.sync.unmodulated_normalized_value(), 1.0);
+        assert_eq!(params.sync.name().as_str(), "Delay Sync");
+
+        // The callback and flags set before should not have been lost
+        assert!(params.sync.flags().contains(ParamFlags::NON_AUTOMATABLE));
+        params.sync.set_plain_value(false);
+        assert_eq!(sync_changes.load(Ordering::Relaxed), 1);
+    }
+}
@@ -117,6 +117,32 @@ pub trait Plugin: Default + Send + 'static {
     /// to do offline processing.
     const HARD_REALTIME_ONLY: bool = false;
 
+    /// If enabled, the plugin will ask the host to process audio at 64-bit precision. Hosts that
+    /// support this will then call [`process_f64()`][Self::process_f64()] with `f64` buffers
+    /// instead of calling [`process()`][Self::process()]. Since hosts are free to keep processing
+    /// audio at 32-bit precision, the plugin still needs to implement `process()`. Both functions
+    /// can forward to a single implementation that's generic over the
+    /// [`Sample`][crate::prelude::Sample] type. This is supported by the CLAP and VST3 wrappers.
+    /// The standalone wrapper always processes audio at 32-bit precision.
+    const F64_PROCESSING: bool = false;
+
+    /// Whether the wrapper should implement bypass for the plugin. By default the plugin is
+    /// responsible for handling its own
+    /// [bypass parameter][crate::prelude::BoolParam::make_bypass()]. With any other
+    /// [`BypassPolicy`], the wrapper crossfades between the plugin's output and the plugin's main
+    /// input whenever the bypass parameter changes. The input is delayed by the latency the plugin
+    /// currently reports so the dry signal stays aligned with the processed signal, and the plugin
+    /// doesn't need to do anything itself. This requires the plugin to have a bypass parameter.
+    const BYPASS_POLICY: BypassPolicy = BypassPolicy::Manual;
+
+    /// The number of state snapshots the user can switch between for A/B comparisons. The editor
+    /// can switch and copy these through
+    /// [`GuiContext::switch_state_slot()`][crate::prelude::GuiContext::switch_state_slot()] and
+    /// [`GuiContext::copy_state_slot()`][crate::prelude::GuiContext::copy_state_slot()], and the
+    /// snapshots are stored as part of the plugin's state. See
+    /// [`StateSlots`][crate::prelude::StateSlots] for more information.
+    const STATE_SLOTS: usize = 2;
+
     /// The plugin's SysEx message type if it supports sending or receiving MIDI SysEx messages, or
     /// `()` if it does not. This type can be a struct or enum wrapping around one or more message
     /// types, and the [`SysExMessage`] trait is then used to convert between this type and basic
@@ -173,6 +199,10 @@ pub trait Plugin: Default + Send + 'static {
     /// with default values that would otherwise change the sound of a preset. Keep in mind that
     /// automation may still be broken in the first two use cases.
     ///
+    /// Simple parameter ID renames and value transformations can also be declared on the `Params`
+    /// struct using the `#[old_ids(...)]` and `#[migrate(...)]` attributes. Those migrations have
+    /// already been applied when this function is called. See [`Params`] for more information.
+    ///
     /// # Note
     ///
     /// This is an advanced feature that the vast majority of plugins won't need to implement.
@@ -243,6 +273,23 @@ pub trait Plugin: Default + Send + 'static {
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus;
 
+    /// The same as [`process()`][Self::process()], but for audio processed at 64-bit precision.
+    /// This is only called when [`F64_PROCESSING`][Self::F64_PROCESSING] is enabled and the host
+    /// agreed to process audio at that precision. The buffers have the same API as their 32-bit
+    /// counterparts.
+    fn process_f64(
+        &mut self,
+        buffer: &mut Buffer<f64>,
+        aux: &mut AuxiliaryBuffers<f64>,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        nih_debug_assert_failure!(
+            "'Plugin::F64_PROCESSING' is enabled, but 'Plugin::process_f64()' is not implemented"
+        );
+
+        ProcessStatus::Error("64-bit processing is not implemented")
+    }
+
     /// Called when the plugin is deactivated. The host will call
     /// [`initialize()`][Self::initialize()] again before the plugin resumes processing audio. These
     /// two functions will not be called when the host only temporarily stops processing audio. You
@@ -254,6 +301,24 @@ pub trait Plugin: Default + Send + 'static {
     fn deactivate(&mut self) {}
 }
 
+/// Determines how the wrapper handles the plugin's bypass parameter. See
+/// [`Plugin::BYPASS_POLICY`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BypassPolicy {
+    /// The plugin implements bypass itself. The wrapper only uses the bypass parameter to link it
+    /// to the host's bypass control.
+    Manual,
+    /// The wrapper crossfades to the latency compensated dry signal when the plugin is bypassed,
+    /// but it keeps calling the plugin's process function while bypassed. This keeps the plugin's
+    /// state up to date, so there won't be any transients when the bypass is disabled again.
+    KeepProcessing,
+    /// The same as [`KeepProcessing`][Self::KeepProcessing], but the process function is no
+    /// longer called after the crossfade to the dry signal has finished. This saves CPU while the
+    /// plugin is bypassed. The plugin's [`reset()`][Plugin::reset()] function is called before
+    /// processing resumes so it does not pick up where it left off.
+    SkipProcessing,
+}
+
 /// Indicates the current situation after the plugin has processed audio.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessStatus {
@@ -8,7 +8,10 @@ pub use crate::nih_export_clap;
 #[cfg(feature = "vst3")]
 pub use crate::nih_export_vst3;
 #[cfg(feature = "standalone")]
-pub use crate::wrapper::standalone::{nih_export_standalone, nih_export_standalone_with_args};
+pub use crate::wrapper::standalone::{
+    nih_export_standalone, nih_export_standalone_with_args,
+    nih_export_standalone_with_status_callback, StandaloneError, StandaloneStatus,
+};
 
 pub use crate::formatters;
 pub use crate::util;
@@ -16,7 +19,7 @@ pub use crate::util;
 pub use crate::audio_setup::{
     new_nonzero_u32, AudioIOLayout, AuxiliaryBuffers, BufferConfig, PortNames, ProcessMode,
 };
-pub use crate::buffer::Buffer;
+pub use crate::buffer::{Buffer, EventBlock, Sample};
 pub use crate::context::gui::{AsyncExecutor, GuiContext, ParamSetter};
 pub use crate::context::init::InitContext;
 pub use crate::context::process::{ProcessContext, Transport};
@@ -26,19 +29,25 @@ pub use crate::context::remote_controls::{
 pub use crate::context::PluginApi;
 // This also includes the derive macro
 pub use crate::editor::{Editor, ParentWindowHandle};
+pub use crate::midi::learn::{MidiLearn, MidiMapping, MidiMappingMode};
 pub use crate::midi::sysex::SysExMessage;
 pub use crate::midi::{control_change, MidiConfig, NoteEvent, PluginNoteEvent};
 pub use crate::params::enums::{Enum, EnumParam};
 pub use crate::params::internals::ParamPtr;
 pub use crate::params::range::{FloatRange, IntRange};
 pub use crate::params::smoothing::{AtomicF32, Smoothable, Smoother, SmoothingStyle};
+pub use crate::params::time::{DivisionModifier, NoteDivision, SyncedTimeParams};
 pub use crate::params::Params;
-pub use crate::params::{BoolParam, FloatParam, IntParam, Param, ParamFlags};
+pub use crate::params::{
+    BoolParam, FloatParam, IntParam, Param, ParamFlags, PathParam, StringParam, TextParam,
+    ValueSnapshot,
+};
 pub use crate::plugin::clap::{ClapPlugin, PolyModulationConfig};
 #[cfg(feature = "vst3")]
 pub use crate::plugin::vst3::Vst3Plugin;
-pub use crate::plugin::{Plugin, ProcessStatus, TaskExecutor};
+pub use crate::plugin::{BypassPolicy, Plugin, ProcessStatus, TaskExecutor};
+pub use crate::presets::{Preset, PresetMetadata};
 pub use crate::wrapper::clap::features::ClapFeature;
-pub use crate::wrapper::state::PluginState;
+pub use crate::wrapper::state::{ParamValue, PluginState, StateSlots};
 #[cfg(feature = "vst3")]
 pub use crate::wrapper::vst3::subcategories::Vst3SubCategory;
@@ -0,0 +1,612 @@
+//! User preset files. Presets are stored as JSON files containing the preset's name, author, tags,
+//! the plugin they belong to, and the plugin's [`PluginState`]. Presets with binary fields instead
+//! store their state using [`PluginState::to_binary()`] after the JSON metadata. The same preset
+//! files can be used by the CLAP, VST3, and standalone versions of a plugin.
+//!
+//! These functions are meant to be used from a plugin's editor together with
+//! [`GuiContext::get_state()`] and [`GuiContext::set_state()`]:
+//!
+//! ```ignore
+//! let preset_dir = presets::user_preset_dir::<MyPlugin>().unwrap();
+//! let preset = Preset::from_context::<MyPlugin>("Big Bass", context);
+//! preset.write(&presets::preset_path(&preset_dir, &preset.metadata.name))?;
+//!
+//! for entry in presets::scan_presets::<MyPlugin>(&preset_dir) {
+//!     println!("{}", entry.metadata.name);
+//! }
+//! ```
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::prelude::{GuiContext, Plugin, PluginState};
+use crate::wrapper::state::has_binary_fields;
+
+/// The file extension used for preset files, without the leading period.
+pub const PRESET_FILE_EXTENSION: &str = "nihpreset";
+
+/// The current version of the preset file format. Preset files with a newer version cannot be
+/// loaded.
+const PRESET_FORMAT_VERSION: u32 = 1;
+
+/// The bytes preset files with a binary state start with. These files contain the magic bytes, the
+/// length of the JSON encoded [`PresetFileHeader`] as a little-endian `u64`, the header's JSON
+/// data, and finally the state encoded using [`PluginState::to_binary()`]. Other preset files are
+/// stored as plain JSON.
+const BINARY_PRESET_MAGIC: &[u8; 8] = b"NIHPRSET";
+
+/// A preset's metadata. This is everything stored in the preset file except for the plugin state,
+/// and it is what [`scan_presets()`] returns for every preset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresetMetadata {
+    /// The preset's name as shown in a preset browser.
+    pub name: String,
+    /// The preset's author. This may be empty.
+    #[serde(default)]
+    pub author: String,
+    /// Free form tags or categories, like `bass` or `pad`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The plugin this preset was saved with. See [`plugin_id()`].
+    pub plugin_id: String,
+    /// The version of the plugin this preset was saved with, from [`Plugin::VERSION`].
+    pub plugin_version: String,
+}
+
+/// A preset containing a plugin's state along with its metadata.
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub metadata: PresetMetadata,
+    /// The plugin's state. The preset's MIDI mappings and state slots are never stored.
+    pub state: PluginState,
+}
+
+/// A preset file found by [`scan_presets()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresetEntry {
+    /// The path to the preset file. This can be passed to [`Preset::read()`] to load the preset.
+    pub path: PathBuf,
+    pub metadata: PresetMetadata,
+}
+
+/// Filters the results from [`scan_presets()`]. All of the conditions need to match for a preset to
+/// be included. The default filter matches every preset.
+#[derive(Debug, Clone, Default)]
+pub struct PresetFilter {
+    /// Only include presets whose name, author, or tags contain this text. Matching is done case
+    /// insensitively. Ignored if empty.
+    pub query: String,
+    /// Only include presets that have all of these tags. Tags are matched case insensitively.
+    pub tags: Vec<String>,
+    /// Only include presets from this author, if set.
+    pub author: Option<String>,
+}
+
+/// The preset file's contents.
+#[derive(Serialize, Deserialize)]
+struct PresetFile<M, S> {
+    preset_format: u32,
+    metadata: M,
+    state: S,
+}
+
+/// Only the preset file's metadata. The state is skipped while scanning presets.
+#[derive(Serialize, Deserialize)]
+struct PresetFileHeader<M = PresetMetadata> {
+    preset_format: u32,
+    metadata: M,
+}
+
+/// An ID for plugin `P` that is the same for the CLAP, VST3, and standalone versions of the plugin.
+/// This consists of the plugin's vendor and name, so changing either of those makes existing
+/// presets incompatible with the plugin.
+pub fn plugin_id<P: Plugin>() -> String {
+    format!("{}/{}", P::VENDOR, P::NAME)
+}
+
+/// The directory user presets for plugin `P` should be stored in. Returns `None` if the user's
+/// data directory could not be determined. The directory may not exist yet.
+///
+/// - On Linux and the BSDs this is `$XDG_DATA_HOME/<vendor>/<plugin name>/presets`, or
+///   `~/.local/share/<vendor>/<plugin name>/presets` if `$XDG_DATA_HOME` is not set.
+/// - On macOS this is `~/Library/Application Support/<vendor>/<plugin name>/Presets`.
+/// - On Windows this is `%APPDATA%\<vendor>\<plugin name>\Presets`.
+pub fn user_preset_dir<P: Plugin>() -> Option<PathBuf> {
+    let presets_dir_name = if cfg!(any(target_os = "macos", target_os = "windows")) {
+        "Presets"
+    } else {
+        "presets"
+    };
+
+    user_data_dir().map(|dir| {
+        dir.join(sanitize_file_name(P::VENDOR))
+            .join(sanitize_file_name(P::NAME))
+            .join(presets_dir_name)
+    })
+}
+
+/// The path a preset called `name` would be stored at in `dir`. Characters that cannot be used in
+/// file names are replaced.
+pub fn preset_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!(
+        "{}.{}",
+        sanitize_file_name(name),
+        PRESET_FILE_EXTENSION
+    ))
+}
+
+/// Recursively find all presets for plugin `P` in `dir`. Presets for other plugins and files that
+/// could not be read are skipped. The results are sorted by the presets' names.
+pub fn scan_presets<P: Plugin>(dir: &Path) -> Vec<PresetEntry> {
+    let plugin_id = plugin_id::<P>();
+
+    let mut presets = Vec::new();
+    scan_dir(dir, &plugin_id, &mut presets);
+    presets.sort_by_cached_key(|entry| entry.metadata.name.to_lowercase());
+
+    presets
+}
+
+impl PresetMetadata {
+    /// Metadata for a new preset for plugin `P` with no author or tags.
+    pub fn new<P: Plugin>(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            author: String::new(),
+            tags: Vec::new(),
+            plugin_id: plugin_id::<P>(),
+            plugin_version: String::from(P::VERSION),
+        }
+    }
+
+    /// Whether this preset was saved by plugin `P`.
+    pub fn is_for<P: Plugin>(&self) -> bool {
+        self.plugin_id == plugin_id::<P>()
+    }
+}
+
+impl Preset {
+    /// Create a preset for plugin `P` from a state object.
+    pub fn new<P: Plugin>(name: impl Into<String>, state: PluginState) -> Self {
+        Self {
+            metadata: PresetMetadata::new::<P>(name),
+            state: PluginState {
+                midi_mappings: BTreeMap::new(),
+                state_slots: None,
+                ..state
+            },
+        }
+    }
+
+    /// Create a preset from the plugin's current state. See [`GuiContext::get_state()`].
+    pub fn from_context<P: Plugin>(name: impl Into<String>, context: &dyn GuiContext) -> Self {
+        Self::new::<P>(name, context.get_state())
+    }
+
+    /// Read a preset file.
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = fs::read(path)
+            .with_context(|| format!("Could not read the preset file at '{}'", path.display()))?;
+        let (preset_format, metadata, state) = match contents.strip_prefix(BINARY_PRESET_MAGIC) {
+            Some(mut remaining) => {
+                let header: PresetFileHeader = read_binary_header(&mut remaining)?;
+                let state = PluginState::from_binary(remaining);
+                (header.preset_format, header.metadata, state)
+            }
+            None => serde_json::from_slice(&contents)
+                .map(|file: PresetFile<PresetMetadata, PluginState>| {
+                    (file.preset_format, file.metadata, Ok(file.state))
+                })
+                .with_context(|| {
+                    format!("Could not parse the preset file at '{}'", path.display())
+                })?,
+        };
+        // The version is checked before the state so newer presets result in a useful error
+        check_format_version(preset_format)?;
+        let state = state
+            .with_context(|| format!("Could not parse the preset file at '{}'", path.display()))?;
+
+        Ok(Self { metadata, state })
+    }
+
+    /// Write this preset to a file, creating the parent directories if necessary. Existing files
+    /// are overwritten.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Could not create '{}'", parent.display()))?;
+        }
+
+        // Binary fields would be stored as huge JSON arrays otherwise
+        let contents = if has_binary_fields(&self.state) {
+            let header = serde_json::to_vec_pretty(&PresetFileHeader {
+                preset_format: PRESET_FORMAT_VERSION,
+                metadata: &self.metadata,
+            })
+            .context("Could not format the preset as JSON")?;
+            let state = self
+                .state
+                .to_binary()
+                .context("Could not encode the preset's state")?;
+
+            let mut contents =
+                Vec::with_capacity(BINARY_PRESET_MAGIC.len() + 8 + header.len() + state.len());
+            contents.extend_from_slice(BINARY_PRESET_MAGIC);
+            contents.extend_from_slice(&(header.len() as u64).to_le_bytes());
+            contents.extend_from_slice(&header);
+            contents.extend_from_slice(&state);
+            contents
+        } else {
+            serde_json::to_vec_pretty(&PresetFile {
+                preset_format: PRESET_FORMAT_VERSION,
+                metadata: &self.metadata,
+                state: &self.state,
+            })
+            .context("Could not format the preset as JSON")?
+        };
+        fs::write(path, contents)
+            .with_context(|| format!("Could not write the preset file to '{}'", path.display()))
+    }
+
+    /// Load this preset's state into plugin `P` using [`GuiContext::set_state()`]. Fails without
+    /// changing anything if the preset was saved by another plugin. Old presets are migrated the
+    /// same way as old plugin states. The plugin's MIDI mappings are kept as is.
+    pub fn load<P: Plugin>(self, context: &dyn GuiContext) -> Result<()> {
+        if !self.metadata.is_for::<P>() {
+            bail!(
+                "The preset '{}' belongs to '{}', not to '{}'",
+                self.metadata.name,
+                self.metadata.plugin_id,
+                plugin_id::<P>()
+            );
+        }
+
+        // Loading a state would otherwise also clear the MIDI mappings
+        context.set_state(PluginState {
+            midi_mappings: context.midi_learn().serialize(),
+            ..self.state
+        });
+
+        Ok(())
+    }
+}
+
+impl PresetFilter {
+    /// Whether a preset's metadata matches this filter.
+    pub fn matches(&self, metadata: &PresetMetadata) -> bool {
+        let query = self.query.to_lowercase();
+        let matches_query = query.is_empty()
+            || metadata.name.to_lowercase().contains(&query)
+            || metadata.author.to_lowercase().contains(&query)
+            || metadata
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(&query));
+        let matches_tags = self.tags.iter().all(|tag| {
+            metadata
+                .tags
+                .iter()
+                .any(|preset_tag| preset_tag.eq_ignore_ascii_case(tag))
+        });
+        let matches_author = match &self.author {
+            Some(author) => &metadata.author == author,
+            None => true,
+        };
+
+        matches_query && matches_tags && matches_author
+    }
+
+    /// Only keep the presets matching this filter.
+    pub fn filter<'a>(
+        &'a self,
+        presets: &'a [PresetEntry],
+    ) -> impl Iterator<Item = &'a PresetEntry> + 'a {
+        presets
+            .iter()
+            .filter(move |entry| self.matches(&entry.metadata))
+    }
+}
+
+fn check_format_version(preset_format: u32) -> Result<()> {
+    if preset_format > PRESET_FORMAT_VERSION {
+        bail!("Unsupported preset format version {preset_format}, try updating the plugin");
+    }
+
+    Ok(())
+}
+
+/// Read only the header from a preset file, without parsing the state.
+fn read_header(path: &Path) -> Result<PresetFileHeader> {
+    let mut file = File::open(path).context("Could not read the file")?;
+    let mut magic = Vec::with_capacity(BINARY_PRESET_MAGIC.len());
+    (&mut file)
+        .take(BINARY_PRESET_MAGIC.len() as u64)
+        .read_to_end(&mut magic)
+        .context("Could not read the file")?;
+
+    if magic == BINARY_PRESET_MAGIC {
+        let mut header_len = [0; 8];
+        file.read_exact(&mut header_len)
+            .context("Could not read the file")?;
+        let mut header = Vec::new();
+        file.take(u64::from_le_bytes(header_len))
+            .read_to_end(&mut header)
+            .context("Could not read the file")?;
+
+        serde_json::from_slice(&header).context("Could not parse the file")
+    } else {
+        let mut contents = magic;
+        file.read_to_end(&mut contents)
+            .context("Could not read the file")?;
+
+        serde_json::from_slice(&contents).context("Could not parse the file")
+    }
+}
+
+/// Parse the JSON header of a binary preset file. `remaining` should start right after the magic
+/// bytes, and it is advanced to the start of the binary state.
+fn read_binary_header(remaining: &mut &[u8]) -> Result<PresetFileHeader> {
+    let header_len = remaining
+        .get(..8)
+        .context("Unexpected end of the preset file")?;
+    let header_len = u64::from_le_bytes(header_len.try_into().unwrap()) as usize;
+    let header = remaining
+        .get(8..8usize.saturating_add(header_len))
+        .context("Unexpected end of the preset file")?;
+    let header = serde_json::from_slice(header).context("Could not parse the preset's metadata")?;
+    *remaining = &remaining[8 + header_len..];
+
+    Ok(header)
+}
+
+fn scan_dir(dir: &Path, plugin_id: &str, presets: &mut Vec<PresetEntry>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            nih_trace!("Could not scan '{}' for presets: {}", dir.display(), err);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            scan_dir(&path, plugin_id, presets);
+            continue;
+        }
+        // Following symlinked directories could recurse forever if the links form a loop.
+        // `DirEntry::file_type()` doesn't follow symlinks, but `Path::is_dir()` does.
+        if file_type.is_symlink() && path.is_dir() {
+            nih_trace!(
+                "Not following the symlinked directory at '{}'",
+                path.display()
+            );
+            continue;
+        }
+        if path.extension().and_then(|extension| extension.to_str()) != Some(PRESET_FILE_EXTENSION)
+        {
+            continue;
+        }
+
+        let header = read_header(&path).and_then(|header| {
+            check_format_version(header.preset_format)?;
+            Ok(header)
+        });
+        match header {
+            Ok(header) if header.metadata.plugin_id == plugin_id => presets.push(PresetEntry {
+                path,
+                metadata: header.metadata,
+            }),
+            Ok(_) => (),
+            Err(err) => nih_warn!("Skipping preset '{}': {:#}", path.display(), err),
+        }
+    }
+}
+
+/// The directory per-user application data is stored in.
+fn user_data_dir() -> Option<PathBuf> {
+    // Relative paths should be ignored according to the XDG Base Directory specification
+    let absolute_env_dir = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "macos")] {
+            absolute_env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+        } else if #[cfg(target_os = "windows")] {
+            absolute_env_dir("APPDATA")
+        } else {
+            absolute_env_dir("XDG_DATA_HOME")
+                .or_else(|| absolute_env_dir("HOME").map(|home| home.join(".local").join("share")))
+        }
+    }
+}
+
+/// Replace characters that are not allowed in file names on some platforms.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // Names like `..` would otherwise escape the directory
+    if sanitized.trim_matches('.').trim().is_empty() {
+        String::from("_")
+    } else {
+        sanitized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(name: &str, author: &str, tags: &[&str]) -> PresetMetadata {
+        PresetMetadata {
+            name: String::from(name),
+            author: String::from(author),
+            tags: tags.iter().map(|tag| String::from(*tag)).collect(),
+            plugin_id: String::from("Vendor/Plugin"),
+            plugin_version: String::from("0.1.0"),
+        }
+    }
+
+    #[test]
+    fn filter() {
+        let bass = metadata("Big Bass", "Alice", &["Bass", "Mono"]);
+        let pad = metadata("Soft Pad", "Bob", &["pad"]);
+
+        let filter = PresetFilter {
+            query: String::from("bass"),
+            ..Default::default()
+        };
+        assert!(filter.matches(&bass));
+        assert!(!filter.matches(&pad));
+
+        let filter = PresetFilter {
+            tags: vec![String::from("PAD")],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&bass));
+        assert!(filter.matches(&pad));
+
+        let filter = PresetFilter {
+            author: Some(String::from("Alice")),
+            ..Default::default()
+        };
+        assert!(filter.matches(&bass));
+        assert!(!filter.matches(&pad));
+    }
+
+    #[test]
+    fn sanitize() {
+        assert_eq!(sanitize_file_name("A/B: C?"), "A_B_ C_");
+        assert_eq!(sanitize_file_name(".."), "_");
+    }
+
+    #[test]
+    fn write_and_read() {
+        let dir = std::env::temp_dir().join(format!("nih-plug-presets-{}", std::process::id()));
+        let preset = Preset {
+            metadata: metadata("Init", "", &[]),
+            state: PluginState {
+                version: String::from("0.1.0"),
+                params: BTreeMap::new(),
+                fields: BTreeMap::from([(String::from("foo"), String::from("\"bar\""))]),
+                binary_fields: BTreeMap::new(),
+                midi_mappings: BTreeMap::new(),
+                state_slots: None,
+            },
+        };
+
+        let path = preset_path(&dir, &preset.metadata.name);
+        preset.write(&path).unwrap();
+        let read_preset = Preset::read(&path).unwrap();
+        let header: PresetFileHeader = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(read_preset.metadata, preset.metadata);
+        assert_eq!(read_preset.state.fields["foo"], "\"bar\"");
+        assert_eq!(header.metadata, preset.metadata);
+    }
+
+    #[test]
+    fn write_and_read_binary() {
+        let dir =
+            std::env::temp_dir().join(format!("nih-plug-binary-presets-{}", std::process::id()));
+        let sample: Vec<u8> = (0..=255).cycle().take(1 << 20).collect();
+        let preset = Preset {
+            metadata: metadata("Sampler", "", &["keys"]),
+            state: PluginState {
+                version: String::from("0.1.0"),
+                params: BTreeMap::new(),
+                fields: BTreeMap::from([(String::from("foo"), String::from("\"bar\""))]),
+                binary_fields: BTreeMap::from([(String::from("sample"), sample.clone())]),
+                midi_mappings: BTreeMap::new(),
+                state_slots: None,
+            },
+        };
+
+        let path = preset_path(&dir, &preset.metadata.name);
+        preset.write(&path).unwrap();
+        let contents = fs::read(&path).unwrap();
+        let read_preset = Preset::read(&path).unwrap();
+        let header = read_header(&path).unwrap();
+        let mut presets = Vec::new();
+        scan_dir(&dir, "Vendor/Plugin", &mut presets);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(contents.starts_with(BINARY_PRESET_MAGIC));
+        assert!(contents.len() < sample.len() + 1024);
+        assert_eq!(read_preset.metadata, preset.metadata);
+        assert_eq!(read_preset.state.fields["foo"], "\"bar\"");
+        assert_eq!(read_preset.state.binary_fields["sample"], sample);
+        assert_eq!(header.metadata, preset.metadata);
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].metadata, preset.metadata);
+    }
+
+    #[test]
+    fn truncated_binary_preset() {
+        let dir =
+            std::env::temp_dir().join(format!("nih-plug-truncated-presets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Broken.nihpreset");
+        let mut contents = BINARY_PRESET_MAGIC.to_vec();
+        contents.extend_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&path, contents).unwrap();
+
+        let read_result = Preset::read(&path);
+        let mut presets = Vec::new();
+        scan_dir(&dir, "Vendor/Plugin", &mut presets);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(read_result.is_err());
+        assert!(presets.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scan_symlink_loop() {
+        let dir =
+            std::env::temp_dir().join(format!("nih-plug-symlink-presets-{}", std::process::id()));
+        let sub_dir = dir.join("Bass");
+        fs::create_dir_all(&sub_dir).unwrap();
+        std::os::unix::fs::symlink(&dir, sub_dir.join("loop")).unwrap();
+
+        let preset = Preset {
+            metadata: metadata("Big Bass", "", &[]),
+            state: PluginState {
+                version: String::from("0.1.0"),
+                params: BTreeMap::new(),
+                fields: BTreeMap::new(),
+                binary_fields: BTreeMap::new(),
+                midi_mappings: BTreeMap::new(),
+                state_slots: None,
+            },
+        };
+        preset
+            .write(&preset_path(&sub_dir, &preset.metadata.name))
+            .unwrap();
+
+        let mut presets = Vec::new();
+        scan_dir(&dir, "Vendor/Plugin", &mut presets);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].metadata, preset.metadata);
+    }
+}
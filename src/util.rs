@@ -1,5 +1,12 @@
 //! General conversion functions and utilities.
 
+#[cfg(feature = "convolution")]
+pub mod convolution;
+pub mod delay;
+pub mod dry_wet;
+pub mod filter;
+pub mod oversampling;
+pub mod phase_vocoder;
 mod stft;
 pub mod window;
 
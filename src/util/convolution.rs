@@ -0,0 +1,598 @@
+//! Uniformly partitioned FFT convolution for long impulse responses, like those used for cabinet
+//! simulation and convolution reverbs. This module is only available when the `convolution`
+//! feature is enabled.
+
+use crossbeam::channel::{self, Receiver, Sender};
+use realfft::num_complex::Complex32;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+use crate::buffer::Buffer;
+
+/// A multichannel convolution engine using uniformly partitioned overlap-save convolution. The
+/// impulse response is split into partitions of `partition_size` samples, and every time
+/// `partition_size` input samples have been gathered, the input's spectrum is multiplied with
+/// every partition's spectrum using a frequency domain delay line. This makes the cost of
+/// convolving with long impulse responses roughly linear in the impulse response's length, and it
+/// introduces exactly `partition_size` samples of latency. That amount should be reported to the
+/// host using [`latency_samples()`][Self::latency_samples()].
+///
+/// The impulse response is loaded through an [`ImpulseResponseLoader`] obtained from
+/// [`loader()`][Self::loader()]. Loading an impulse response allocates and performs FFTs, so it
+/// should be done from a background task or from the plugin's `initialize()` function. The
+/// convolver picks up the new impulse response at the start of the next
+/// [`process()`][Self::process()] call and crossfades to it over one partition. Impulse responses
+/// are never allocated or deallocated on the audio thread. Until an impulse response has been
+/// loaded, the convolver outputs silence.
+///
+/// # Example
+///
+/// ```ignore
+/// // In `initialize()`, or when the user selects an impulse response file in a background task:
+/// self.convolver.loader().load(&[left_ir, right_ir]);
+///
+/// // In `process()`:
+/// self.convolver.process(buffer);
+/// ```
+pub struct Convolver {
+    /// The number of samples in each of the impulse response's partitions. The FFTs operate on
+    /// twice this many samples.
+    partition_size: usize,
+    /// The maximum number of partitions an impulse response may contain. Longer impulse responses
+    /// are truncated when they are loaded.
+    max_num_partitions: usize,
+
+    r2c_plan: Arc<dyn RealToComplex<f32>>,
+    c2r_plan: Arc<dyn ComplexToReal<f32>>,
+
+    /// The impulse response the convolver is currently using.
+    impulse_response: ImpulseResponse,
+    /// The impulse response the convolver is crossfading away from, if an impulse response was
+    /// swapped in during the last partition. Once the crossfade is finished this is sent back to
+    /// the loader so it can be deallocated there.
+    previous_impulse_response: Option<ImpulseResponse>,
+
+    /// New impulse responses sent by the [`ImpulseResponseLoader`]s. The sender and the receiver
+    /// are stored here so new loaders can be created at any time.
+    new_impulse_responses: (Sender<ImpulseResponse>, Receiver<ImpulseResponse>),
+    /// Impulse responses that are no longer used are sent back to the loaders through this channel
+    /// so they don't get deallocated on the audio thread.
+    retired_impulse_responses: (Sender<ImpulseResponse>, Receiver<ImpulseResponse>),
+
+    /// The input for the next FFT for every channel. The first half contains the previous
+    /// partition's input, and the second half is filled with the current partition's input.
+    input_buffers: Vec<Vec<f32>>,
+    /// The output computed at the end of the last partition for every channel. This is written back
+    /// to the buffer while the next partition's input is being gathered.
+    output_buffers: Vec<Vec<f32>>,
+    /// The current position in `output_buffers`, and in the second half of `input_buffers`.
+    current_pos: usize,
+
+    /// The frequency domain delay line containing the spectra of the last `max_num_partitions`
+    /// input partitions. Indexed by `[channel_idx][slot_idx][bin_idx]`.
+    input_spectra: Vec<Vec<Vec<Complex32>>>,
+    /// The slot in `input_spectra` the most recent partition's spectrum was written to.
+    current_slot: usize,
+
+    /// Scratch buffers for the FFT operations, and for accumulating the products of the input
+    /// spectra and the impulse response spectra.
+    real_scratch_buffer: Vec<f32>,
+    previous_real_scratch_buffer: Vec<f32>,
+    complex_scratch_buffer: Vec<Complex32>,
+    fft_scratch_buffer: Vec<Complex32>,
+}
+
+/// Prepares impulse responses for a [`Convolver`] and sends them to it. This can be cloned and
+/// moved to other threads. Impulse responses the convolver no longer needs are deallocated by the
+/// loader the next time it loads an impulse response.
+#[derive(Clone)]
+pub struct ImpulseResponseLoader {
+    num_channels: usize,
+    partition_size: usize,
+    max_num_partitions: usize,
+    r2c_plan: Arc<dyn RealToComplex<f32>>,
+
+    new_impulse_responses: (Sender<ImpulseResponse>, Receiver<ImpulseResponse>),
+    retired_impulse_responses: Receiver<ImpulseResponse>,
+}
+
+/// An impulse response split into partitions, stored in the frequency domain.
+#[derive(Default)]
+struct ImpulseResponse {
+    /// The spectra of the zero padded partitions, normalized for the inverse FFT. Indexed by
+    /// `[channel_idx][partition_idx][bin_idx]`. Contains either a single channel that is used for
+    /// all of the convolver's channels, or one channel per convolver channel.
+    partitions: Vec<Vec<Vec<Complex32>>>,
+}
+
+impl Convolver {
+    /// Create a convolver for `num_channels` channels that splits impulse responses into partitions
+    /// of `partition_size` samples. This is also the convolver's latency. Impulse responses longer
+    /// than `max_impulse_response_len` samples are truncated. This allocates, so it should be
+    /// called from the plugin's `initialize()` function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `partition_size` or `max_impulse_response_len` is zero.
+    pub fn new(
+        num_channels: usize,
+        partition_size: usize,
+        max_impulse_response_len: usize,
+    ) -> Self {
+        assert_ne!(partition_size, 0);
+        assert_ne!(max_impulse_response_len, 0);
+
+        let fft_size = partition_size * 2;
+        let num_bins = fft_size / 2 + 1;
+        let max_num_partitions = (max_impulse_response_len + partition_size - 1) / partition_size;
+
+        let mut planner = RealFftPlanner::<f32>::new();
+        let r2c_plan = planner.plan_fft_forward(fft_size);
+        let c2r_plan = planner.plan_fft_inverse(fft_size);
+        let fft_scratch_len = r2c_plan.get_scratch_len().max(c2r_plan.get_scratch_len());
+
+        // The loaders only ever add a single impulse response to the channel at a time. The
+        // retired impulse response channel needs some headroom since the loader may drain it
+        // right before the convolver retires the previous impulse response.
+        let new_impulse_responses = channel::bounded(1);
+        let retired_impulse_responses = channel::bounded(2);
+
+        Self {
+            partition_size,
+            max_num_partitions,
+
+            r2c_plan,
+            c2r_plan,
+
+            impulse_response: ImpulseResponse::default(),
+            previous_impulse_response: None,
+
+            new_impulse_responses,
+            retired_impulse_responses,
+
+            input_buffers: vec![vec![0.0; fft_size]; num_channels],
+            output_buffers: vec![vec![0.0; partition_size]; num_channels],
+            current_pos: 0,
+
+            input_spectra: vec![
+                vec![vec![Complex32::default(); num_bins]; max_num_partitions];
+                num_channels
+            ],
+            current_slot: 0,
+
+            real_scratch_buffer: vec![0.0; fft_size],
+            previous_real_scratch_buffer: vec![0.0; fft_size],
+            complex_scratch_buffer: vec![Complex32::default(); num_bins],
+            fft_scratch_buffer: vec![Complex32::default(); fft_scratch_len],
+        }
+    }
+
+    /// Get a loader for sending new impulse responses to this convolver.
+    pub fn loader(&self) -> ImpulseResponseLoader {
+        ImpulseResponseLoader {
+            num_channels: self.input_buffers.len(),
+            partition_size: self.partition_size,
+            max_num_partitions: self.max_num_partitions,
+            r2c_plan: self.r2c_plan.clone(),
+
+            new_impulse_responses: self.new_impulse_responses.clone(),
+            retired_impulse_responses: self.retired_impulse_responses.1.clone(),
+        }
+    }
+
+    /// The amount of latency introduced by the convolver, in samples. This is equal to the
+    /// partition size.
+    pub fn latency_samples(&self) -> u32 {
+        self.partition_size as u32
+    }
+
+    /// Clear the convolver's buffers. The current impulse response is kept.
+    pub fn reset(&mut self) {
+        for buffer in self
+            .input_buffers
+            .iter_mut()
+            .chain(self.output_buffers.iter_mut())
+        {
+            buffer.fill(0.0);
+        }
+        for spectrum in self.input_spectra.iter_mut().flatten() {
+            spectrum.fill(Complex32::default());
+        }
+        self.current_pos = 0;
+        self.current_slot = 0;
+
+        if let Some(previous_impulse_response) = self.previous_impulse_response.take() {
+            self.retire_impulse_response(previous_impulse_response);
+        }
+    }
+
+    /// Convolve the buffer with the current impulse response. The output is delayed by
+    /// [`latency_samples()`][Self::latency_samples()] samples. If a new impulse response has been
+    /// loaded since the last call, then this will start crossfading to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer's channel count does not match the convolver's channel count.
+    pub fn process(&mut self, buffer: &mut Buffer) {
+        assert_eq!(buffer.channels(), self.input_buffers.len());
+
+        self.receive_impulse_response();
+
+        let num_samples = buffer.samples();
+        let mut already_processed_samples = 0;
+        while already_processed_samples < num_samples {
+            let samples_to_process = (self.partition_size - self.current_pos)
+                .min(num_samples - already_processed_samples);
+
+            // The input is written to the second half of the input buffer while the output from the
+            // last partition is copied to the buffer
+            let buffer_range =
+                already_processed_samples..already_processed_samples + samples_to_process;
+            let partition_range = self.current_pos..self.current_pos + samples_to_process;
+            for ((channel, input_buffer), output_buffer) in buffer
+                .as_slice()
+                .iter_mut()
+                .zip(self.input_buffers.iter_mut())
+                .zip(self.output_buffers.iter())
+            {
+                input_buffer[self.partition_size..][partition_range.clone()]
+                    .copy_from_slice(&channel[buffer_range.clone()]);
+                channel[buffer_range.clone()]
+                    .copy_from_slice(&output_buffer[partition_range.clone()]);
+            }
+
+            already_processed_samples += samples_to_process;
+            self.current_pos += samples_to_process;
+            if self.current_pos == self.partition_size {
+                self.process_partition();
+                self.current_pos = 0;
+            }
+        }
+    }
+
+    /// Swap in a new impulse response if a loader has sent one. This only happens when the previous
+    /// impulse response can be sent back to the loader afterwards, so it never gets deallocated
+    /// here.
+    fn receive_impulse_response(&mut self) {
+        if self.previous_impulse_response.is_some() || self.retired_impulse_responses.0.is_full() {
+            return;
+        }
+
+        if let Ok(impulse_response) = self.new_impulse_responses.1.try_recv() {
+            let previous_impulse_response =
+                std::mem::replace(&mut self.impulse_response, impulse_response);
+
+            // There's no need to crossfade away from silence
+            if previous_impulse_response.partitions.is_empty() {
+                self.retire_impulse_response(previous_impulse_response);
+            } else {
+                self.previous_impulse_response = Some(previous_impulse_response);
+            }
+        }
+    }
+
+    /// Send an impulse response that's no longer used back to the loaders.
+    fn retire_impulse_response(&mut self, impulse_response: ImpulseResponse) {
+        // The channel was checked for capacity before the impulse response was swapped in
+        let result = self.retired_impulse_responses.0.try_send(impulse_response);
+        nih_debug_assert!(result.is_ok());
+    }
+
+    /// Compute the next partition's output for all channels after a full partition of input has
+    /// been gathered.
+    fn process_partition(&mut self) {
+        let num_slots = self.max_num_partitions;
+        self.current_slot = (self.current_slot + 1) % num_slots;
+
+        for (channel_idx, (input_buffer, output_buffer)) in self
+            .input_buffers
+            .iter_mut()
+            .zip(self.output_buffers.iter_mut())
+            .enumerate()
+        {
+            // The FFT overwrites its input, so the input buffer needs to be copied first
+            let input_spectra = &mut self.input_spectra[channel_idx];
+            self.real_scratch_buffer.copy_from_slice(input_buffer);
+            self.r2c_plan
+                .process_with_scratch(
+                    &mut self.real_scratch_buffer,
+                    &mut input_spectra[self.current_slot],
+                    &mut self.fft_scratch_buffer,
+                )
+                .unwrap();
+            input_buffer.copy_within(self.partition_size.., 0);
+
+            convolve_partition(
+                &self.impulse_response,
+                channel_idx,
+                input_spectra,
+                self.current_slot,
+                &*self.c2r_plan,
+                &mut self.complex_scratch_buffer,
+                &mut self.real_scratch_buffer,
+                &mut self.fft_scratch_buffer,
+            );
+
+            // With overlap-save convolution only the second half of the IFFT's output is valid
+            match &self.previous_impulse_response {
+                Some(previous_impulse_response) => {
+                    convolve_partition(
+                        previous_impulse_response,
+                        channel_idx,
+                        input_spectra,
+                        self.current_slot,
+                        &*self.c2r_plan,
+                        &mut self.complex_scratch_buffer,
+                        &mut self.previous_real_scratch_buffer,
+                        &mut self.fft_scratch_buffer,
+                    );
+
+                    let crossfade_step = (self.partition_size as f32).recip();
+                    for (sample_idx, ((output_sample, sample), previous_sample)) in output_buffer
+                        .iter_mut()
+                        .zip(&self.real_scratch_buffer[self.partition_size..])
+                        .zip(&self.previous_real_scratch_buffer[self.partition_size..])
+                        .enumerate()
+                    {
+                        let t = (sample_idx + 1) as f32 * crossfade_step;
+                        *output_sample = (sample * t) + (previous_sample * (1.0 - t));
+                    }
+                }
+                None => {
+                    output_buffer.copy_from_slice(&self.real_scratch_buffer[self.partition_size..])
+                }
+            }
+        }
+
+        if let Some(previous_impulse_response) = self.previous_impulse_response.take() {
+            self.retire_impulse_response(previous_impulse_response);
+        }
+    }
+}
+
+impl ImpulseResponseLoader {
+    /// Prepare an impulse response and send it to the [`Convolver`]. `impulse_response` contains
+    /// either a single channel that is used for all of the convolver's channels, or one channel for
+    /// every channel. The channels may have different lengths. Impulse responses that are longer
+    /// than the maximum length the convolver was created with are truncated. If the convolver has
+    /// not yet picked up the last impulse response sent by this or another loader, then that
+    /// impulse response is replaced.
+    ///
+    /// This allocates and performs FFTs, so it should not be called from the audio thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of channels does not match.
+    pub fn load<S: AsRef<[f32]>>(&self, impulse_response: &[S]) {
+        assert!(impulse_response.len() == 1 || impulse_response.len() == self.num_channels);
+
+        // Impulse responses the convolver no longer uses are deallocated here
+        while self.retired_impulse_responses.try_recv().is_ok() {}
+
+        let fft_size = self.partition_size * 2;
+        let gain_compensation = (fft_size as f32).recip();
+        let mut real_scratch_buffer = vec![0.0; fft_size];
+        let mut fft_scratch_buffer = vec![Complex32::default(); self.r2c_plan.get_scratch_len()];
+        let partitions = impulse_response
+            .iter()
+            .map(|channel| {
+                let channel = channel.as_ref();
+                if channel.len() > self.max_num_partitions * self.partition_size {
+                    nih_debug_assert_failure!(
+                        "The impulse response is longer than the convolver's maximum length, it \
+                         will be truncated"
+                    );
+                }
+
+                channel
+                    .chunks(self.partition_size)
+                    .take(self.max_num_partitions)
+                    .map(|partition| {
+                        // Each partition is zero padded to the FFT size
+                        real_scratch_buffer.fill(0.0);
+                        for (scratch_sample, sample) in
+                            real_scratch_buffer.iter_mut().zip(partition)
+                        {
+                            *scratch_sample = sample * gain_compensation;
+                        }
+
+                        let mut spectrum = self.r2c_plan.make_output_vec();
+                        self.r2c_plan
+                            .process_with_scratch(
+                                &mut real_scratch_buffer,
+                                &mut spectrum,
+                                &mut fft_scratch_buffer,
+                            )
+                            .unwrap();
+
+                        spectrum
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // If the convolver hasn't picked up the last impulse response yet, then that one is
+        // replaced. Otherwise the channel has room for the new impulse response. Multiple loaders
+        // racing each other may still cause the channel to be full, in which case the last loader
+        // wins.
+        let mut impulse_response = ImpulseResponse { partitions };
+        while let Err(err) = self.new_impulse_responses.0.try_send(impulse_response) {
+            impulse_response = err.into_inner();
+            let _ = self.new_impulse_responses.1.try_recv();
+        }
+    }
+}
+
+/// Multiply the spectra in the frequency domain delay line with the impulse response's partitions
+/// for channel `channel_idx`, and perform the inverse FFT on the sum. The result is written to
+/// `real_output`. This is a free function so it can be used with the convolver's fields while
+/// other fields are borrowed mutably.
+#[allow(clippy::too_many_arguments)]
+fn convolve_partition(
+    impulse_response: &ImpulseResponse,
+    channel_idx: usize,
+    input_spectra: &[Vec<Complex32>],
+    current_slot: usize,
+    c2r_plan: &dyn ComplexToReal<f32>,
+    complex_scratch_buffer: &mut [Complex32],
+    real_output: &mut [f32],
+    fft_scratch_buffer: &mut [Complex32],
+) {
+    complex_scratch_buffer.fill(Complex32::default());
+
+    // Mono impulse responses are used for all channels
+    if let Some(partitions) = impulse_response
+        .partitions
+        .get(channel_idx)
+        .or_else(|| impulse_response.partitions.first())
+    {
+        // The first partition gets multiplied with the most recent input, the second partition
+        // with the input from one partition ago, and so on
+        let num_slots = input_spectra.len();
+        for (partition_idx, partition) in partitions.iter().enumerate() {
+            let slot = (current_slot + num_slots - partition_idx) % num_slots;
+            for ((output_bin, input_bin), partition_bin) in complex_scratch_buffer
+                .iter_mut()
+                .zip(&input_spectra[slot])
+                .zip(partition)
+            {
+                *output_bin += input_bin * partition_bin;
+            }
+        }
+    }
+
+    // These should already be zero, but rounding errors could otherwise cause the inverse FFT to
+    // return an error
+    let num_bins = complex_scratch_buffer.len();
+    complex_scratch_buffer[0].im = 0.0;
+    complex_scratch_buffer[num_bins - 1].im = 0.0;
+
+    c2r_plan
+        .process_with_scratch(complex_scratch_buffer, real_output, fft_scratch_buffer)
+        .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_buffer;
+
+    /// Run the input through the convolver using irregular buffer sizes.
+    fn process(convolver: &mut Convolver, input: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        let mut output = vec![Vec::new(); input.len()];
+        let mut num_processed_samples = 0;
+        for buffer_size in [1, 7, 64, 13, 100].into_iter().cycle() {
+            if num_processed_samples >= input[0].len() {
+                break;
+            }
+
+            let buffer_size = buffer_size.min(input[0].len() - num_processed_samples);
+            let mut block: Vec<Vec<f32>> = input
+                .iter()
+                .map(|channel| {
+                    channel[num_processed_samples..num_processed_samples + buffer_size].to_vec()
+                })
+                .collect();
+            with_buffer(&mut block, |buffer| convolver.process(buffer));
+
+            for (output_channel, block_channel) in output.iter_mut().zip(block) {
+                output_channel.extend(block_channel);
+            }
+            num_processed_samples += buffer_size;
+        }
+
+        output
+    }
+
+    /// Direct convolution, delayed by `latency` samples.
+    fn convolve_direct(input: &[f32], impulse_response: &[f32], latency: usize) -> Vec<f32> {
+        (0..input.len())
+            .map(|sample_idx| {
+                (0..impulse_response.len())
+                    .filter_map(|tap_idx| {
+                        let input_idx = sample_idx.checked_sub(latency + tap_idx)?;
+                        Some(input[input_idx] * impulse_response[tap_idx])
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_direct_convolution() {
+        const PARTITION_SIZE: usize = 32;
+
+        let input: Vec<Vec<f32>> = (0..2)
+            .map(|channel_idx| {
+                (0..1000)
+                    .map(|idx| ((idx * (channel_idx + 3)) as f32 * 0.37).sin())
+                    .collect()
+            })
+            .collect();
+        let impulse_response: Vec<Vec<f32>> = (0..2)
+            .map(|channel_idx| {
+                (0..150 + channel_idx * 20)
+                    .map(|idx: usize| (idx as f32 * 0.91).cos() * 0.99f32.powi(idx as i32))
+                    .collect()
+            })
+            .collect();
+
+        let mut convolver = Convolver::new(2, PARTITION_SIZE, 256);
+        convolver.loader().load(&impulse_response);
+        let output = process(&mut convolver, &input);
+
+        for ((output, input), impulse_response) in output.iter().zip(&input).zip(&impulse_response)
+        {
+            let expected = convolve_direct(input, impulse_response, PARTITION_SIZE);
+            for (output_sample, expected_sample) in output.iter().zip(expected) {
+                approx::assert_relative_eq!(*output_sample, expected_sample, epsilon = 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn mono_impulse_response() {
+        let input = vec![vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]; 2];
+
+        let mut convolver = Convolver::new(2, 2, 4);
+        convolver.loader().load(&[[0.5, 0.25, 0.125]]);
+        let output = process(&mut convolver, &input);
+
+        for channel in output {
+            for (output_sample, expected_sample) in channel
+                .iter()
+                .zip([0.0, 0.0, 0.5, 0.25, 0.125, 0.0, 0.0, 0.0])
+            {
+                approx::assert_relative_eq!(*output_sample, expected_sample, epsilon = 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn swapping_impulse_responses_crossfades() {
+        const PARTITION_SIZE: usize = 16;
+
+        let mut convolver = Convolver::new(1, PARTITION_SIZE, 16);
+        let loader = convolver.loader();
+        let input = vec![vec![1.0; PARTITION_SIZE * 4]];
+
+        loader.load(&[[1.0]]);
+        let output = process(&mut convolver, &input);
+        approx::assert_relative_eq!(*output[0].last().unwrap(), 1.0, epsilon = 1e-6);
+
+        // The output should smoothly move from the old to the new gain over one partition after
+        // one partition of latency
+        loader.load(&[[-1.0]]);
+        let output = process(&mut convolver, &input);
+        for sample in &output[0][..PARTITION_SIZE] {
+            approx::assert_relative_eq!(*sample, 1.0, epsilon = 1e-6);
+        }
+        for sample_pair in output[0][PARTITION_SIZE..PARTITION_SIZE * 2].windows(2) {
+            assert!(sample_pair[1] < sample_pair[0]);
+            assert!((sample_pair[1] - sample_pair[0]).abs() < 0.2);
+        }
+        for sample in &output[0][PARTITION_SIZE * 2..] {
+            approx::assert_relative_eq!(*sample, -1.0, epsilon = 1e-6);
+        }
+    }
+}
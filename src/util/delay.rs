@@ -0,0 +1,200 @@
+//! A multichannel delay line for latency compensation.
+
+use crate::buffer::{Buffer, Sample};
+
+/// A multichannel delay line with a fixed maximum delay. All channels share the same write
+/// position, so the delay line is always written to and read from in blocks of audio containing
+/// all channels. This can for instance be used to delay a dry signal or a sidechain input by the
+/// same amount of latency the main signal path introduces. Reading and writing does not allocate,
+/// and the delay time can be changed at any point up to the maximum delay set when creating the
+/// delay line.
+///
+/// The sample type defaults to `f32`, but the delay line can also be used with `f64` buffers.
+#[derive(Debug, Default)]
+pub struct DelayLine<T = f32> {
+    /// The ring buffers for the delay line. This is indexed by `[channel_idx][sample_idx]`, with
+    /// the size set to the maximum delay plus the maximum block size rounded up to the next power
+    /// of two.
+    buffers: Vec<Vec<T>>,
+    /// The position in the ring buffers where the next samples should be written to. This is
+    /// incremented after writing. When reading, the starting read position is determined by
+    /// subtracting the block's length and the delay from this position.
+    next_write_position: usize,
+
+    /// The maximum delay in samples. Reads with larger delays are rejected.
+    max_delay: usize,
+    /// The maximum block size. Larger blocks are rejected.
+    max_block_size: usize,
+}
+
+impl<T: Sample> DelayLine<T> {
+    /// Create a delay line for `num_channels` channels that can delay blocks of up to
+    /// `max_block_size` samples by up to `max_delay` samples.
+    pub fn new(num_channels: usize, max_block_size: usize, max_delay: usize) -> Self {
+        let mut delay_line = Self::default();
+        delay_line.resize(num_channels, max_block_size, max_delay);
+
+        delay_line
+    }
+
+    /// Resize the internal buffers to fit new parameters. This also clears the delay line. This
+    /// allocates, so it should be called from a plugin's `initialize()` function.
+    pub fn resize(&mut self, num_channels: usize, max_block_size: usize, max_delay: usize) {
+        let buffer_len = (max_block_size + max_delay).next_power_of_two();
+
+        self.buffers.resize_with(num_channels, Vec::new);
+        for buffer in &mut self.buffers {
+            buffer.resize(buffer_len, T::ZERO);
+            buffer.fill(T::ZERO);
+        }
+        self.next_write_position = 0;
+
+        self.max_delay = max_delay;
+        self.max_block_size = max_block_size;
+    }
+
+    /// Clear out the buffers.
+    pub fn reset(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.fill(T::ZERO);
+        }
+        self.next_write_position = 0;
+    }
+
+    /// The number of channels this delay line was configured for.
+    pub fn num_channels(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// The maximum delay in samples this delay line was configured for.
+    pub fn max_delay(&self) -> usize {
+        self.max_delay
+    }
+
+    /// The maximum block size this delay line was configured for.
+    pub fn max_block_size(&self) -> usize {
+        self.max_block_size
+    }
+
+    /// Write a block of audio to the delay line. This advances the write position by the buffer's
+    /// length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is larger than the maximum block size or if the channel counts don't
+    /// match.
+    pub fn write(&mut self, buffer: &Buffer<T>) {
+        if self.buffers.is_empty() {
+            return;
+        }
+
+        assert_eq!(buffer.channels(), self.buffers.len());
+        assert!(buffer.samples() <= self.max_block_size);
+        let buffer_len = self.buffers[0].len();
+
+        let num_samples_before_wrap = buffer.samples().min(buffer_len - self.next_write_position);
+        let num_samples_after_wrap = buffer.samples() - num_samples_before_wrap;
+
+        for (buffer_channel, delay_line) in buffer
+            .as_slice_immutable()
+            .iter()
+            .zip(self.buffers.iter_mut())
+        {
+            delay_line
+                [self.next_write_position..self.next_write_position + num_samples_before_wrap]
+                .copy_from_slice(&buffer_channel[..num_samples_before_wrap]);
+            delay_line[..num_samples_after_wrap]
+                .copy_from_slice(&buffer_channel[num_samples_before_wrap..]);
+        }
+
+        self.next_write_position = (self.next_write_position + buffer.samples()) % buffer_len;
+    }
+
+    /// Read the last `output.len()` samples written to the delay line for channel `channel_idx`,
+    /// delayed by `delay` samples. With a delay of zero this returns the samples that were just
+    /// written using [`write()`][Self::write()].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` is larger than the maximum block size, if the delay is larger than the
+    /// maximum delay, or if the channel index is out of bounds.
+    pub fn read(&self, channel_idx: usize, delay: usize, output: &mut [T]) {
+        assert!(output.len() <= self.max_block_size);
+        assert!(delay <= self.max_delay);
+        let delay_line = &self.buffers[channel_idx];
+        let buffer_len = delay_line.len();
+
+        let read_position =
+            (self.next_write_position + buffer_len - output.len() - delay) % buffer_len;
+        let num_samples_before_wrap = output.len().min(buffer_len - read_position);
+        let num_samples_after_wrap = output.len() - num_samples_before_wrap;
+
+        output[..num_samples_before_wrap]
+            .copy_from_slice(&delay_line[read_position..read_position + num_samples_before_wrap]);
+        output[num_samples_before_wrap..].copy_from_slice(&delay_line[..num_samples_after_wrap]);
+    }
+
+    /// Delay a buffer in place by `delay` samples. This is the same as calling
+    /// [`write()`][Self::write()] followed by a [`read()`][Self::read()] into every channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is larger than the maximum block size, if the delay is larger than the
+    /// maximum delay, or if the channel counts don't match.
+    pub fn process(&mut self, buffer: &mut Buffer<T>, delay: usize) {
+        self.write(buffer);
+        for (channel_idx, channel) in buffer.as_slice().iter_mut().enumerate() {
+            self.read(channel_idx, delay, channel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_buffer;
+
+    #[test]
+    fn delay_across_wraps() {
+        const DELAY: usize = 5;
+
+        // The ring buffer is 16 samples long, so this wraps around several times
+        let mut delay_line = DelayLine::new(1, 8, DELAY);
+        let mut input_idx = 0;
+        for block_size in [3, 8, 1, 7, 8, 5, 8, 2] {
+            let mut block: Vec<f32> = (input_idx..input_idx + block_size)
+                .map(|idx| idx as f32 + 1.0)
+                .collect();
+            with_buffer(&mut [&mut block], |buffer| {
+                delay_line.process(buffer, DELAY)
+            });
+
+            for (sample_idx, sample) in block.iter().enumerate() {
+                let expected = (input_idx + sample_idx)
+                    .checked_sub(DELAY)
+                    .map(|idx| idx as f32 + 1.0)
+                    .unwrap_or(0.0);
+                assert_eq!(*sample, expected);
+            }
+
+            input_idx += block_size;
+        }
+    }
+
+    #[test]
+    fn read_multiple_delays() {
+        let mut delay_line = DelayLine::new(1, 4, 8);
+        for block_idx in 0..4 {
+            let mut block: Vec<f32> = (0..4).map(|idx| (block_idx * 4 + idx) as f32).collect();
+            with_buffer(&mut [&mut block], |buffer| delay_line.write(buffer));
+        }
+
+        let mut output = [0.0; 4];
+        delay_line.read(0, 0, &mut output);
+        assert_eq!(output, [12.0, 13.0, 14.0, 15.0]);
+        delay_line.read(0, 3, &mut output);
+        assert_eq!(output, [9.0, 10.0, 11.0, 12.0]);
+        delay_line.read(0, 8, &mut output[..2]);
+        assert_eq!(output[..2], [6.0, 7.0]);
+    }
+}
@@ -0,0 +1,256 @@
+//! A dry/wet mixer with latency compensation.
+
+use super::delay::DelayLine;
+use crate::buffer::Buffer;
+
+/// The number of samples the [`DryWetMixer`] takes to crossfade between the old and the new dry
+/// signal delay when the latency changes. This is about 5 milliseconds at 48 kHz.
+pub const LATENCY_CROSSFADE_LENGTH: usize = 256;
+
+/// A dry/wet mixer with latency compensation that operates on entire buffers. The dry signal is
+/// written to a delay line at the start of the process function, and it is mixed back in after
+/// processing delayed by the plugin's current latency so the dry and wet signals stay
+/// phase-aligned.
+///
+/// The mixer keeps track of the latency it was last called with. When that changes, it crossfades
+/// from the old to the new dry signal delay over [`LATENCY_CROSSFADE_LENGTH`] samples instead of
+/// jumping to a different position in the delay line, which would otherwise cause a click. Passing
+/// [`ProcessContext::latency_samples()`][crate::prelude::ProcessContext::latency_samples()] as
+/// the latency makes the mixer follow whatever latency the plugin has reported to the host.
+///
+/// # Example
+///
+/// ```ignore
+/// fn process(
+///     &mut self,
+///     buffer: &mut Buffer,
+///     _aux: &mut AuxiliaryBuffers,
+///     context: &mut impl ProcessContext<Self>,
+/// ) -> ProcessStatus {
+///     self.dry_wet_mixer.write_dry(buffer);
+///
+///     // Process the signal, possibly calling `context.set_latency_samples()`...
+///
+///     self.dry_wet_mixer.mix_in_dry(
+///         buffer,
+///         self.params.dry_wet_ratio.value(),
+///         MixingStyle::EqualPower,
+///         context.latency_samples(),
+///     );
+///
+///     ProcessStatus::Normal
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct DryWetMixer {
+    /// The delay line for the latency compensation.
+    delay_line: DelayLine,
+
+    /// The latency the dry signal is currently being delayed by.
+    latency: usize,
+    /// Whether `latency` has been set since the mixer was last reset. The first latency after a
+    /// reset is used as is without crossfading.
+    latency_initialized: bool,
+    /// The latency the dry signal was delayed by before the last latency change. While
+    /// `crossfade_samples_remaining` is non-zero, the dry signal is crossfaded from this delay to
+    /// `latency`.
+    previous_latency: usize,
+    /// The number of samples left in the current latency crossfade.
+    crossfade_samples_remaining: usize,
+
+    /// Scratch buffers for reading the dry signal at the current and at the previous latency.
+    /// These have the same size as the maximum block size.
+    dry_scratch_buffer: Vec<f32>,
+    previous_dry_scratch_buffer: Vec<f32>,
+}
+
+/// The mixing style for the [`DryWetMixer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixingStyle {
+    /// Linearly interpolate between the dry and wet signals. This keeps the sum at a constant
+    /// level for fully correlated signals.
+    Linear,
+    /// Use square root gains so the summed power stays constant for uncorrelated signals.
+    EqualPower,
+}
+
+impl DryWetMixer {
+    /// Set up the mixer for the given parameters. `max_latency` is the largest latency in samples
+    /// that will be passed to [`mix_in_dry()`][Self::mix_in_dry()].
+    pub fn new(num_channels: usize, max_block_size: usize, max_latency: usize) -> Self {
+        let mut mixer = Self::default();
+        mixer.resize(num_channels, max_block_size, max_latency);
+
+        mixer
+    }
+
+    /// Resize the internal buffers to fit new parameters. This also resets the mixer. This
+    /// allocates, so it should be called from a plugin's `initialize()` function.
+    pub fn resize(&mut self, num_channels: usize, max_block_size: usize, max_latency: usize) {
+        self.delay_line
+            .resize(num_channels, max_block_size, max_latency);
+        self.dry_scratch_buffer.resize(max_block_size, 0.0);
+        self.previous_dry_scratch_buffer.resize(max_block_size, 0.0);
+
+        self.reset();
+    }
+
+    /// Clear out the buffers. The next call to [`mix_in_dry()`][Self::mix_in_dry()] will
+    /// immediately use the new latency without crossfading.
+    pub fn reset(&mut self) {
+        self.delay_line.reset();
+        self.latency_initialized = false;
+        self.crossfade_samples_remaining = 0;
+    }
+
+    /// Write the dry signal into the buffer. This should be called at the start of the process
+    /// function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is larger than the maximum block size or if the channel counts don't
+    /// match.
+    pub fn write_dry(&mut self, buffer: &Buffer) {
+        self.delay_line.write(buffer);
+    }
+
+    /// Mix the dry signal into the buffer. The ratio is a `[0, 1]` value where 0 results in an
+    /// all-dry signal, and 1 results in an all-wet signal. The dry signal is delayed by `latency`
+    /// samples. If this is different from the latency used in the previous call, then the dry
+    /// signal is crossfaded to the new delay. This should be called at the end of the process
+    /// function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is larger than the maximum block size, if the latency is larger than
+    /// the maximum latency, or if the channel counts don't match.
+    pub fn mix_in_dry(
+        &mut self,
+        buffer: &mut Buffer,
+        ratio: f32,
+        style: MixingStyle,
+        latency: u32,
+    ) {
+        let latency = latency as usize;
+        if !self.latency_initialized {
+            self.latency = latency;
+            self.previous_latency = latency;
+            self.latency_initialized = true;
+        } else if latency != self.latency {
+            // If the latency changes during a crossfade then this restarts the crossfade from the
+            // latency it was fading to. That causes a small discontinuity, but that's still much
+            // better than jumping directly to the new latency.
+            self.previous_latency = self.latency;
+            self.latency = latency;
+            self.crossfade_samples_remaining = LATENCY_CROSSFADE_LENGTH;
+        }
+
+        let num_samples = buffer.samples();
+        let crossfade_samples_remaining = self.crossfade_samples_remaining;
+        self.crossfade_samples_remaining = crossfade_samples_remaining.saturating_sub(num_samples);
+        if buffer.channels() == 0 {
+            return;
+        }
+
+        let ratio = ratio.clamp(0.0, 1.0);
+        if ratio == 1.0 {
+            return;
+        }
+        let (wet_t, dry_t) = match style {
+            MixingStyle::Linear => (ratio, 1.0 - ratio),
+            MixingStyle::EqualPower => (ratio.sqrt(), (1.0 - ratio).sqrt()),
+        };
+
+        assert_eq!(buffer.channels(), self.delay_line.num_channels());
+        let dry = &mut self.dry_scratch_buffer[..num_samples];
+        let previous_dry = &mut self.previous_dry_scratch_buffer[..num_samples];
+        for (channel_idx, buffer_channel) in buffer.as_slice().iter_mut().enumerate() {
+            self.delay_line.read(channel_idx, self.latency, dry);
+
+            if crossfade_samples_remaining > 0 {
+                self.delay_line
+                    .read(channel_idx, self.previous_latency, previous_dry);
+
+                let crossfade_step = (LATENCY_CROSSFADE_LENGTH as f32).recip();
+                for (sample_idx, (dry_sample, previous_dry_sample)) in
+                    dry.iter_mut().zip(previous_dry.iter()).enumerate()
+                {
+                    let new_t = if sample_idx < crossfade_samples_remaining {
+                        1.0 - ((crossfade_samples_remaining - sample_idx) as f32 * crossfade_step)
+                    } else {
+                        1.0
+                    };
+
+                    *dry_sample = (*dry_sample * new_t) + (previous_dry_sample * (1.0 - new_t));
+                }
+            }
+
+            if ratio == 0.0 {
+                buffer_channel.copy_from_slice(dry);
+            } else {
+                for (buffer_sample, dry_sample) in buffer_channel.iter_mut().zip(dry.iter()) {
+                    *buffer_sample = (*buffer_sample * wet_t) + (dry_sample * dry_t);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_buffer;
+
+    #[test]
+    fn dry_signal_is_latency_compensated() {
+        const LATENCY: u32 = 10;
+
+        let mut mixer = DryWetMixer::new(1, 16, 32);
+
+        let mut block = [0.0f32; 16];
+        block[0] = 1.0;
+        with_buffer(&mut [&mut block], |buffer| {
+            mixer.write_dry(buffer);
+            // The plugin would normally introduce `LATENCY` samples of latency here, so the wet
+            // signal is still silent
+            buffer.as_slice()[0].fill(0.0);
+            mixer.mix_in_dry(buffer, 0.5, MixingStyle::Linear, LATENCY);
+        });
+
+        let mut expected = [0.0f32; 16];
+        expected[LATENCY as usize] = 0.5;
+        assert_eq!(block, expected);
+    }
+
+    #[test]
+    fn latency_change_crossfades() {
+        let mut mixer = DryWetMixer::new(1, 64, 128);
+
+        // With a constant input, changing the latency should not change the output at all
+        let mut max_difference = 0.0f32;
+        let mut previous_sample = None;
+        for block_idx in 0..16 {
+            let latency = if block_idx < 8 { 0 } else { 100 };
+            let mut block: Vec<f32> = (0..64)
+                .map(|sample_idx| ((block_idx * 64 + sample_idx) as f32 * 0.01).sin())
+                .collect();
+            with_buffer(&mut [&mut block], |buffer| {
+                mixer.write_dry(buffer);
+                mixer.mix_in_dry(buffer, 0.0, MixingStyle::Linear, latency);
+            });
+
+            for sample in block {
+                if let Some(previous_sample) = previous_sample {
+                    let difference: f32 = sample - previous_sample;
+                    max_difference = max_difference.max(difference.abs());
+                }
+                previous_sample = Some(sample);
+            }
+        }
+
+        // The sine wave itself changes by at most 0.01 per sample. Jumping to the new latency
+        // without a crossfade would cause a step of up to 2.0.
+        assert!(max_difference < 0.02, "Maximum step was {max_difference}");
+        assert_eq!(mixer.crossfade_samples_remaining, 0);
+    }
+}
@@ -0,0 +1,116 @@
+//! Allocation-free filters and filter design helpers. This contains RBJ cookbook biquads, a
+//! trapezoidal integrated state variable filter, Linkwitz-Riley crossovers, and helpers for
+//! designing linear-phase FIR filters.
+//!
+//! The IIR filters are generic over [`SimdType`]. This is either a plain `f32`, or when the `simd`
+//! feature is enabled a [`std::simd::Simd`] vector of `f32`s. The latter can be used to process
+//! multiple channels at once, for instance by using
+//! [`ChannelSamples::to_simd()`][crate::buffer::ChannelSamples::to_simd()] to load a stereo sample
+//! into an `f32x2`. Coefficients are always computed as scalars and then splatted to all lanes.
+//!
+//! Changing the coefficients while audio is playing may result in zipper noise. To avoid that,
+//! [`SmoothedBiquad`] and [`Svf`] can interpolate between the old and the new coefficients over a
+//! number of samples using their `set_coefficients_smoothed()` functions. The plain [`Biquad`]
+//! doesn't do this, which keeps it cheap to cascade in crossovers and filter banks.
+
+use std::ops::{Add, Mul, Sub};
+#[cfg(feature = "simd")]
+use std::simd::{LaneCount, Simd, SupportedLaneCount};
+
+mod biquad;
+mod crossover;
+mod fir;
+mod svf;
+
+pub use biquad::{Biquad, BiquadCoefficients, SmoothedBiquad};
+pub use crossover::{LinkwitzRileyAllpass, LinkwitzRileyCrossover, LinkwitzRileyOrder};
+pub use fir::{FirCoefficients, FirFilter};
+pub use svf::{Svf, SvfCoefficients};
+
+/// The Q value for a second order Butterworth filter, i.e. a filter with a maximally flat passband
+/// and 3 dB of attenuation at the cutoff frequency.
+pub const NEUTRAL_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Either an `f32` or some SIMD vector type of `f32`s that can be used with the filters in this
+/// module.
+pub trait SimdType:
+    Mul<Output = Self> + Sub<Output = Self> + Add<Output = Self> + Copy + Sized
+{
+    fn from_f32(value: f32) -> Self;
+}
+
+impl SimdType for f32 {
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+}
+
+#[cfg(feature = "simd")]
+impl<const LANES: usize> SimdType for Simd<f32, LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        Simd::splat(value)
+    }
+}
+
+/// Linear interpolation between two sets of filter coefficients. Shared between the different
+/// filter types to implement coefficient smoothing.
+#[derive(Debug, Clone, Copy)]
+struct CoefficientRamp<C> {
+    /// The amount each coefficient changes every sample.
+    step: C,
+    /// The coefficients the filter will have once the ramp is finished. These are assigned at the
+    /// last step to avoid accumulating rounding errors.
+    target: C,
+    /// The number of samples left until `target` is reached.
+    steps_left: u32,
+}
+
+/// Filter coefficients that can be linearly interpolated.
+trait InterpolatableCoefficients: Copy {
+    /// Compute the per-sample difference needed to go from `self` to `target` in `num_steps` steps.
+    fn step_towards(&self, target: &Self, num_steps: u32) -> Self;
+    /// Add a previously computed step to these coefficients.
+    fn add_step(&self, step: &Self) -> Self;
+}
+
+impl<C: InterpolatableCoefficients> CoefficientRamp<C> {
+    /// A ramp that has already reached `coefficients`.
+    fn finished(coefficients: C) -> Self {
+        Self {
+            step: coefficients,
+            target: coefficients,
+            steps_left: 0,
+        }
+    }
+
+    /// Start a new ramp from `current` to `target` that takes `num_steps` samples.
+    fn new(current: &C, target: C, num_steps: u32) -> Self {
+        if num_steps == 0 {
+            return Self::finished(target);
+        }
+
+        Self {
+            step: current.step_towards(&target, num_steps),
+            target,
+            steps_left: num_steps,
+        }
+    }
+
+    /// Advance `coefficients` by one step if the ramp has not yet finished.
+    #[inline]
+    fn next(&mut self, coefficients: &mut C) {
+        if self.steps_left > 0 {
+            self.steps_left -= 1;
+            *coefficients = if self.steps_left == 0 {
+                self.target
+            } else {
+                coefficients.add_step(&self.step)
+            };
+        }
+    }
+}
@@ -0,0 +1,526 @@
+//! RBJ cookbook biquad filters.
+
+use std::f32::consts;
+
+use super::{CoefficientRamp, InterpolatableCoefficients, SimdType};
+
+/// A simple biquad filter with functions for generating coefficients for the filter types from the
+/// RBJ audio EQ cookbook. Changing the coefficients takes effect immediately. Use
+/// [`SmoothedBiquad`] instead to interpolate between the old and the new coefficients.
+///
+/// Based on <https://en.wikipedia.org/wiki/Digital_biquad_filter#Transposed_direct_forms>.
+///
+/// The type parameter T  should be either an `f32` or a SIMD type.
+#[derive(Clone, Copy, Debug)]
+pub struct Biquad<T> {
+    coefficients: BiquadCoefficients<T>,
+    s1: T,
+    s2: T,
+}
+
+/// A [`Biquad`] that can linearly interpolate between its old and new coefficients to avoid zipper
+/// noise when the filter's parameters are automated. This is a separate type so the plain
+/// [`Biquad`] does not need to check for coefficient changes for every sample.
+///
+/// The type parameter T  should be either an `f32` or a SIMD type.
+#[derive(Clone, Copy, Debug)]
+pub struct SmoothedBiquad<T> {
+    biquad: Biquad<T>,
+    /// Used to interpolate between the old and new coefficients when using
+    /// [`set_coefficients_smoothed()`][Self::set_coefficients_smoothed()].
+    ramp: CoefficientRamp<BiquadCoefficients<T>>,
+}
+
+/// The coefficients `[b0, b1, b2, a1, a2]` for [`Biquad`]. These coefficients are all
+/// prenormalized, i.e. they have been divided by `a0`.
+///
+/// The type parameter T  should be either an `f32` or a SIMD type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiquadCoefficients<T> {
+    pub b0: T,
+    pub b1: T,
+    pub b2: T,
+    pub a1: T,
+    pub a2: T,
+}
+
+impl<T: SimdType> Default for Biquad<T> {
+    /// Before setting constants the filter should just act as an identity function.
+    fn default() -> Self {
+        Self::new(BiquadCoefficients::identity())
+    }
+}
+
+impl<T: SimdType> Default for SmoothedBiquad<T> {
+    /// Before setting constants the filter should just act as an identity function.
+    fn default() -> Self {
+        Self::new(BiquadCoefficients::identity())
+    }
+}
+
+impl<T: SimdType> Biquad<T> {
+    /// Create a new biquad filter using the specified coefficients.
+    pub fn new(coefficients: BiquadCoefficients<T>) -> Self {
+        Self {
+            coefficients,
+            s1: T::from_f32(0.0),
+            s2: T::from_f32(0.0),
+        }
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: T) -> T {
+        let result = self.coefficients.b0 * sample + self.s1;
+
+        self.s1 = self.coefficients.b1 * sample - self.coefficients.a1 * result + self.s2;
+        self.s2 = self.coefficients.b2 * sample - self.coefficients.a2 * result;
+
+        result
+    }
+
+    /// The filter's current coefficients.
+    pub fn coefficients(&self) -> &BiquadCoefficients<T> {
+        &self.coefficients
+    }
+
+    /// Change the filter's coefficients.
+    pub fn set_coefficients(&mut self, coefficients: BiquadCoefficients<T>) {
+        self.coefficients = coefficients;
+    }
+
+    /// Reset the state to zero, useful after making making large, non-interpolatable changes to the
+    /// filter coefficients.
+    pub fn reset(&mut self) {
+        self.s1 = T::from_f32(0.0);
+        self.s2 = T::from_f32(0.0);
+    }
+}
+
+impl<T: SimdType> SmoothedBiquad<T> {
+    /// Create a new biquad filter using the specified coefficients.
+    pub fn new(coefficients: BiquadCoefficients<T>) -> Self {
+        Self {
+            biquad: Biquad::new(coefficients),
+            ramp: CoefficientRamp::finished(coefficients),
+        }
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: T) -> T {
+        self.ramp.next(&mut self.biquad.coefficients);
+
+        self.biquad.process(sample)
+    }
+
+    /// The filter's current coefficients. These may be in between two sets of coefficients if the
+    /// coefficients are currently being smoothed.
+    pub fn coefficients(&self) -> &BiquadCoefficients<T> {
+        self.biquad.coefficients()
+    }
+
+    /// Immediately change the filter's coefficients. This cancels any ongoing coefficient
+    /// smoothing.
+    pub fn set_coefficients(&mut self, coefficients: BiquadCoefficients<T>) {
+        self.biquad.set_coefficients(coefficients);
+        self.ramp = CoefficientRamp::finished(coefficients);
+    }
+
+    /// Linearly interpolate from the filter's current coefficients to `coefficients` over the next
+    /// `num_steps` calls to [`process()`][Self::process()]. For small changes this is always
+    /// stable, but interpolating between very different coefficients over a long time may cause
+    /// the filter to briefly pass through unstable coefficients.
+    pub fn set_coefficients_smoothed(
+        &mut self,
+        coefficients: BiquadCoefficients<T>,
+        num_steps: u32,
+    ) {
+        self.ramp = CoefficientRamp::new(self.biquad.coefficients(), coefficients, num_steps);
+        if num_steps == 0 {
+            self.biquad.set_coefficients(coefficients);
+        }
+    }
+
+    /// Whether the coefficients are currently being smoothed.
+    pub fn is_smoothing(&self) -> bool {
+        self.ramp.steps_left > 0
+    }
+
+    /// Reset the state to zero, useful after making making large, non-interpolatable changes to the
+    /// filter coefficients. This does not affect ongoing coefficient smoothing.
+    pub fn reset(&mut self) {
+        self.biquad.reset();
+    }
+}
+
+impl<T: SimdType> InterpolatableCoefficients for BiquadCoefficients<T> {
+    fn step_towards(&self, target: &Self, num_steps: u32) -> Self {
+        let scale = T::from_f32((num_steps as f32).recip());
+
+        Self {
+            b0: (target.b0 - self.b0) * scale,
+            b1: (target.b1 - self.b1) * scale,
+            b2: (target.b2 - self.b2) * scale,
+            a1: (target.a1 - self.a1) * scale,
+            a2: (target.a2 - self.a2) * scale,
+        }
+    }
+
+    #[inline]
+    fn add_step(&self, step: &Self) -> Self {
+        Self {
+            b0: self.b0 + step.b0,
+            b1: self.b1 + step.b1,
+            b2: self.b2 + step.b2,
+            a1: self.a1 + step.a1,
+            a2: self.a2 + step.a2,
+        }
+    }
+}
+
+impl<T: SimdType> BiquadCoefficients<T> {
+    /// Convert scalar coefficients into the correct vector type.
+    pub fn from_f32s(scalar: BiquadCoefficients<f32>) -> Self {
+        Self {
+            b0: T::from_f32(scalar.b0),
+            b1: T::from_f32(scalar.b1),
+            b2: T::from_f32(scalar.b2),
+            a1: T::from_f32(scalar.a1),
+            a2: T::from_f32(scalar.a2),
+        }
+    }
+
+    /// Filter coefficients that would cause the sound to be passed through as is.
+    pub fn identity() -> Self {
+        Self::from_f32s(BiquadCoefficients {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+        })
+    }
+
+    /// Compute the coefficients for a low-pass filter.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn lowpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_and_alpha(sample_rate, frequency, q);
+
+        Self::from_unnormalized(
+            (1.0 - cos_omega0) / 2.0,
+            1.0 - cos_omega0,
+            (1.0 - cos_omega0) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_omega0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Compute the coefficients for a high-pass filter.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn highpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_and_alpha(sample_rate, frequency, q);
+
+        Self::from_unnormalized(
+            (1.0 + cos_omega0) / 2.0,
+            -(1.0 + cos_omega0),
+            (1.0 + cos_omega0) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_omega0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Compute the coefficients for a band-pass filter with a constant 0 dB peak gain.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn bandpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_and_alpha(sample_rate, frequency, q);
+
+        Self::from_unnormalized(
+            alpha,
+            0.0,
+            -alpha,
+            1.0 + alpha,
+            -2.0 * cos_omega0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Compute the coefficients for a notch filter.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn notch(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_and_alpha(sample_rate, frequency, q);
+
+        Self::from_unnormalized(
+            1.0,
+            -2.0 * cos_omega0,
+            1.0,
+            1.0 + alpha,
+            -2.0 * cos_omega0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Compute the coefficients for an all-pass filter.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn allpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_and_alpha(sample_rate, frequency, q);
+
+        Self::from_unnormalized(
+            1.0 - alpha,
+            -2.0 * cos_omega0,
+            1.0 + alpha,
+            1.0 + alpha,
+            -2.0 * cos_omega0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Compute the coefficients for a peaking/bell filter that boosts or cuts the frequencies
+    /// around `frequency` by `gain_db` decibels.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn peaking(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_and_alpha(sample_rate, frequency, q);
+        let a = 10.0f32.powf(gain_db / 40.0);
+
+        Self::from_unnormalized(
+            1.0 + alpha * a,
+            -2.0 * cos_omega0,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos_omega0,
+            1.0 - alpha / a,
+        )
+    }
+
+    /// Compute the coefficients for a low-shelf filter that boosts or cuts the frequencies below
+    /// `frequency` by `gain_db` decibels. A `q` value of [`NEUTRAL_Q`][super::NEUTRAL_Q]
+    /// corresponds to the steepest slope without any overshoot.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn low_shelf(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_and_alpha(sample_rate, frequency, q);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let two_sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        Self::from_unnormalized(
+            a * ((a + 1.0) - (a - 1.0) * cos_omega0 + two_sqrt_a_alpha),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos_omega0),
+            a * ((a + 1.0) - (a - 1.0) * cos_omega0 - two_sqrt_a_alpha),
+            (a + 1.0) + (a - 1.0) * cos_omega0 + two_sqrt_a_alpha,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos_omega0),
+            (a + 1.0) + (a - 1.0) * cos_omega0 - two_sqrt_a_alpha,
+        )
+    }
+
+    /// Compute the coefficients for a high-shelf filter that boosts or cuts the frequencies above
+    /// `frequency` by `gain_db` decibels. A `q` value of [`NEUTRAL_Q`][super::NEUTRAL_Q]
+    /// corresponds to the steepest slope without any overshoot.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn high_shelf(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let (cos_omega0, alpha) = omega0_and_alpha(sample_rate, frequency, q);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let two_sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        Self::from_unnormalized(
+            a * ((a + 1.0) + (a - 1.0) * cos_omega0 + two_sqrt_a_alpha),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_omega0),
+            a * ((a + 1.0) + (a - 1.0) * cos_omega0 - two_sqrt_a_alpha),
+            (a + 1.0) - (a - 1.0) * cos_omega0 + two_sqrt_a_alpha,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos_omega0),
+            (a + 1.0) - (a - 1.0) * cos_omega0 - two_sqrt_a_alpha,
+        )
+    }
+
+    /// Compute the coefficients for a first order low-pass filter. This is still a biquad, so it
+    /// can be cascaded with second order sections.
+    pub fn first_order_lowpass(sample_rate: f32, frequency: f32) -> Self {
+        let k = first_order_k(sample_rate, frequency);
+
+        Self::from_unnormalized(k, k, 0.0, k + 1.0, k - 1.0, 0.0)
+    }
+
+    /// Compute the coefficients for a first order high-pass filter. This is still a biquad, so it
+    /// can be cascaded with second order sections.
+    pub fn first_order_highpass(sample_rate: f32, frequency: f32) -> Self {
+        let k = first_order_k(sample_rate, frequency);
+
+        Self::from_unnormalized(1.0, -1.0, 0.0, k + 1.0, k - 1.0, 0.0)
+    }
+
+    /// Compute the coefficients for a first order all-pass filter. This is still a biquad, so it
+    /// can be cascaded with second order sections.
+    pub fn first_order_allpass(sample_rate: f32, frequency: f32) -> Self {
+        let k = first_order_k(sample_rate, frequency);
+
+        Self::from_unnormalized(k - 1.0, k + 1.0, 0.0, k + 1.0, k - 1.0, 0.0)
+    }
+
+    /// Prenormalize the coefficients by dividing them by `a0`.
+    fn from_unnormalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        let a0_recip = a0.recip();
+
+        Self::from_f32s(BiquadCoefficients {
+            b0: b0 * a0_recip,
+            b1: b1 * a0_recip,
+            b2: b2 * a0_recip,
+            a1: a1 * a0_recip,
+            a2: a2 * a0_recip,
+        })
+    }
+}
+
+impl BiquadCoefficients<f32> {
+    /// Compute the filter's magnitude response at `frequency`. Useful for drawing frequency
+    /// response curves in a GUI.
+    pub fn magnitude_response(&self, sample_rate: f32, frequency: f32) -> f32 {
+        let omega = consts::TAU * (frequency / sample_rate);
+        let (sin_omega, cos_omega) = omega.sin_cos();
+        let (sin_2omega, cos_2omega) = (2.0 * omega).sin_cos();
+
+        // This evaluates `H(z)` at `z = e^(j * omega)`, with
+        // `z^-n = cos(n * omega) - j * sin(n * omega)`
+        let numerator_re = self.b0 + self.b1 * cos_omega + self.b2 * cos_2omega;
+        let numerator_im = -(self.b1 * sin_omega + self.b2 * sin_2omega);
+        let denominator_re = 1.0 + self.a1 * cos_omega + self.a2 * cos_2omega;
+        let denominator_im = -(self.a1 * sin_omega + self.a2 * sin_2omega);
+
+        (numerator_re.hypot(numerator_im)) / (denominator_re.hypot(denominator_im))
+    }
+}
+
+/// Compute the `cos(omega0)` and `alpha` terms used in all of the RBJ cookbook formulas.
+fn omega0_and_alpha(sample_rate: f32, frequency: f32, q: f32) -> (f32, f32) {
+    nih_debug_assert!(sample_rate > 0.0);
+    nih_debug_assert!(frequency > 0.0);
+    nih_debug_assert!(frequency < sample_rate / 2.0);
+    nih_debug_assert!(q > 0.0);
+
+    let omega0 = consts::TAU * (frequency / sample_rate);
+    let (sin_omega0, cos_omega0) = omega0.sin_cos();
+    let alpha = sin_omega0 / (2.0 * q);
+
+    (cos_omega0, alpha)
+}
+
+/// The bilinear transform's prewarped `tan(omega0 / 2)` term for first order filters.
+fn first_order_k(sample_rate: f32, frequency: f32) -> f32 {
+    nih_debug_assert!(sample_rate > 0.0);
+    nih_debug_assert!(frequency > 0.0);
+    nih_debug_assert!(frequency < sample_rate / 2.0);
+
+    (consts::PI * (frequency / sample_rate)).tan()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    #[test]
+    fn lowpass_response() {
+        let coefficients =
+            BiquadCoefficients::lowpass(SAMPLE_RATE, 1_000.0, super::super::NEUTRAL_Q);
+
+        approx::assert_relative_eq!(
+            coefficients.magnitude_response(SAMPLE_RATE, 1.0),
+            1.0,
+            epsilon = 1e-4
+        );
+        approx::assert_relative_eq!(
+            coefficients.magnitude_response(SAMPLE_RATE, 1_000.0),
+            std::f32::consts::FRAC_1_SQRT_2,
+            epsilon = 1e-4
+        );
+        assert!(coefficients.magnitude_response(SAMPLE_RATE, 10_000.0) < 0.02);
+    }
+
+    #[test]
+    fn shelf_and_peaking_gain() {
+        let gain = 10.0f32.powf(6.0 / 20.0);
+
+        let low_shelf =
+            BiquadCoefficients::low_shelf(SAMPLE_RATE, 500.0, super::super::NEUTRAL_Q, 6.0);
+        approx::assert_relative_eq!(
+            low_shelf.magnitude_response(SAMPLE_RATE, 1.0),
+            gain,
+            epsilon = 1e-3
+        );
+        approx::assert_relative_eq!(
+            low_shelf.magnitude_response(SAMPLE_RATE, 20_000.0),
+            1.0,
+            epsilon = 1e-2
+        );
+
+        let high_shelf =
+            BiquadCoefficients::high_shelf(SAMPLE_RATE, 500.0, super::super::NEUTRAL_Q, 6.0);
+        approx::assert_relative_eq!(
+            high_shelf.magnitude_response(SAMPLE_RATE, 1.0),
+            1.0,
+            epsilon = 1e-3
+        );
+        approx::assert_relative_eq!(
+            high_shelf.magnitude_response(SAMPLE_RATE, 20_000.0),
+            gain,
+            epsilon = 1e-2
+        );
+
+        let peaking = BiquadCoefficients::peaking(SAMPLE_RATE, 1_000.0, 1.0, 6.0);
+        approx::assert_relative_eq!(
+            peaking.magnitude_response(SAMPLE_RATE, 1_000.0),
+            gain,
+            epsilon = 1e-3
+        );
+    }
+
+    #[test]
+    fn allpass_response() {
+        let coefficients = BiquadCoefficients::allpass(SAMPLE_RATE, 1_000.0, 2.0);
+        let first_order = BiquadCoefficients::first_order_allpass(SAMPLE_RATE, 1_000.0);
+        for frequency in [20.0, 200.0, 1_000.0, 5_000.0, 20_000.0] {
+            approx::assert_relative_eq!(
+                coefficients.magnitude_response(SAMPLE_RATE, frequency),
+                1.0,
+                epsilon = 1e-4
+            );
+            approx::assert_relative_eq!(
+                first_order.magnitude_response(SAMPLE_RATE, frequency),
+                1.0,
+                epsilon = 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn smoothed_coefficients() {
+        let from = BiquadCoefficients::lowpass(SAMPLE_RATE, 1_000.0, 1.0);
+        let to = BiquadCoefficients::lowpass(SAMPLE_RATE, 2_000.0, 1.0);
+
+        let mut biquad = SmoothedBiquad::new(from);
+        biquad.set_coefficients_smoothed(to, 10);
+        for _ in 0..5 {
+            biquad.process(0.0);
+        }
+        assert!(biquad.is_smoothing());
+        approx::assert_relative_eq!(
+            biquad.coefficients().b0,
+            (from.b0 + to.b0) / 2.0,
+            epsilon = 1e-6
+        );
+
+        for _ in 0..5 {
+            biquad.process(0.0);
+        }
+        assert!(!biquad.is_smoothing());
+        assert_eq!(*biquad.coefficients(), to);
+    }
+}
@@ -0,0 +1,253 @@
+//! Linkwitz-Riley crossovers built from cascaded biquads.
+
+use super::{Biquad, BiquadCoefficients, SimdType, NEUTRAL_Q};
+
+/// The Q values for the two second order sections of a fourth order Butterworth filter. These are
+/// `1 / (2 * cos(pi / 8))` and `1 / (2 * cos(3 * pi / 8))`.
+const BUTTERWORTH_4_QS: [f32; 2] = [0.541_196_1, 1.306_563];
+
+/// The slope of a [`LinkwitzRileyCrossover`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkwitzRileyOrder {
+    /// 12 dB/octave slopes. Uses a single second order section per band. The high-pass output is
+    /// inverted so the two bands sum to a flat response.
+    Lr12,
+    /// 24 dB/octave slopes. Stacks two Butterworth-style (i.e. $q = \frac{\sqrt{2}}{2}$) filters
+    /// per band.
+    Lr24,
+    /// 48 dB/octave slopes. Stacks two fourth order Butterworth filters per band, for a total of
+    /// four second order sections.
+    Lr48,
+}
+
+/// A two-way Linkwitz-Riley crossover. The low-pass and high-pass outputs sum to an all-pass
+/// filter with a flat magnitude response. The phase response of that sum is available as a
+/// standalone filter through [`LinkwitzRileyAllpass`].
+///
+/// The type parameter T  should be either an `f32` or a SIMD type.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkwitzRileyCrossover<T> {
+    order: LinkwitzRileyOrder,
+
+    /// Filters for the low-pass section of the crossover. Not all filters may be used depending on
+    /// the crossover's order.
+    lp_filters: [Biquad<T>; 4],
+    /// Filters for the high-pass section of the crossover. Not all filters may be used depending on
+    /// the crossover's order.
+    hp_filters: [Biquad<T>; 4],
+}
+
+/// An all-pass filter with the same phase response as the summed outputs of a
+/// [`LinkwitzRileyCrossover`] with the same order and frequency. This is needed to build multiband
+/// crossovers. When splitting a signal into more than two bands by repeatedly splitting the
+/// high-pass output of the previous crossover, the lower bands need to be run through the all-pass
+/// filters for all of the higher crossover frequencies so all bands stay in phase and still sum to
+/// a flat response.
+///
+/// The type parameter T  should be either an `f32` or a SIMD type.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkwitzRileyAllpass<T> {
+    order: LinkwitzRileyOrder,
+
+    /// Not all filters may be used depending on the order.
+    filters: [Biquad<T>; 2],
+}
+
+impl LinkwitzRileyOrder {
+    /// The number of second order sections used per band.
+    fn num_sections(&self) -> usize {
+        match self {
+            LinkwitzRileyOrder::Lr12 => 1,
+            LinkwitzRileyOrder::Lr24 => 2,
+            LinkwitzRileyOrder::Lr48 => 4,
+        }
+    }
+
+    /// The number of second order sections needed for the matching all-pass filter.
+    fn num_allpass_sections(&self) -> usize {
+        match self {
+            LinkwitzRileyOrder::Lr12 | LinkwitzRileyOrder::Lr24 => 1,
+            LinkwitzRileyOrder::Lr48 => 2,
+        }
+    }
+
+    /// The Q value for the `section_idx`th section of the low-pass and high-pass filters.
+    fn section_q(&self, section_idx: usize) -> f32 {
+        match self {
+            // Two identical first order filters in series are the same as a single second order
+            // filter with a Q of 0.5
+            LinkwitzRileyOrder::Lr12 => 0.5,
+            LinkwitzRileyOrder::Lr24 => NEUTRAL_Q,
+            LinkwitzRileyOrder::Lr48 => BUTTERWORTH_4_QS[section_idx % 2],
+        }
+    }
+}
+
+impl<T: SimdType> LinkwitzRileyCrossover<T> {
+    /// Create a new crossover. All filters will be configured to pass audio through as is.
+    /// [`update()`][Self::update()] needs to be called first to set up the filters.
+    pub fn new(order: LinkwitzRileyOrder) -> Self {
+        Self {
+            order,
+            lp_filters: [Biquad::default(); 4],
+            hp_filters: [Biquad::default(); 4],
+        }
+    }
+
+    /// The crossover's order.
+    pub fn order(&self) -> LinkwitzRileyOrder {
+        self.order
+    }
+
+    /// Change the crossover's order. This resets the filters, and [`update()`][Self::update()]
+    /// needs to be called again afterwards.
+    pub fn set_order(&mut self, order: LinkwitzRileyOrder) {
+        self.order = order;
+        self.reset();
+    }
+
+    /// Split `sample` into a low-passed and a high-passed version. The resulting tuple contains the
+    /// low-passed and the high-passed samples, in that order.
+    #[inline]
+    pub fn process(&mut self, sample: T) -> (T, T) {
+        let num_sections = self.order.num_sections();
+
+        let mut low_passed = sample;
+        for filter in &mut self.lp_filters[..num_sections] {
+            low_passed = filter.process(low_passed);
+        }
+        let mut high_passed = sample;
+        for filter in &mut self.hp_filters[..num_sections] {
+            high_passed = filter.process(high_passed);
+        }
+
+        if self.order == LinkwitzRileyOrder::Lr12 {
+            high_passed = high_passed * T::from_f32(-1.0);
+        }
+
+        (low_passed, high_passed)
+    }
+
+    /// Set the crossover frequency.
+    pub fn update(&mut self, sample_rate: f32, frequency: f32) {
+        for (section_idx, (lp_filter, hp_filter)) in self
+            .lp_filters
+            .iter_mut()
+            .zip(self.hp_filters.iter_mut())
+            .take(self.order.num_sections())
+            .enumerate()
+        {
+            let q = self.order.section_q(section_idx);
+            lp_filter.set_coefficients(BiquadCoefficients::lowpass(sample_rate, frequency, q));
+            hp_filter.set_coefficients(BiquadCoefficients::highpass(sample_rate, frequency, q));
+        }
+    }
+
+    /// Reset the internal filter state.
+    pub fn reset(&mut self) {
+        for filter in self.lp_filters.iter_mut().chain(self.hp_filters.iter_mut()) {
+            filter.reset();
+        }
+    }
+}
+
+impl<T: SimdType> LinkwitzRileyAllpass<T> {
+    /// Create a new all-pass filter. The filter will be configured to pass audio through as is.
+    /// [`update()`][Self::update()] needs to be called first to set up the filter.
+    pub fn new(order: LinkwitzRileyOrder) -> Self {
+        Self {
+            order,
+            filters: [Biquad::default(); 2],
+        }
+    }
+
+    /// The filter's order.
+    pub fn order(&self) -> LinkwitzRileyOrder {
+        self.order
+    }
+
+    /// Change the filter's order. This resets the filter, and [`update()`][Self::update()] needs
+    /// to be called again afterwards.
+    pub fn set_order(&mut self, order: LinkwitzRileyOrder) {
+        self.order = order;
+        self.reset();
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: T) -> T {
+        let mut result = sample;
+        for filter in &mut self.filters[..self.order.num_allpass_sections()] {
+            result = filter.process(result);
+        }
+
+        result
+    }
+
+    /// Set the crossover frequency this all-pass filter should match.
+    pub fn update(&mut self, sample_rate: f32, frequency: f32) {
+        match self.order {
+            // The sum of an LR12 crossover's outputs is a first order all-pass filter
+            LinkwitzRileyOrder::Lr12 => self.filters[0].set_coefficients(
+                BiquadCoefficients::first_order_allpass(sample_rate, frequency),
+            ),
+            // While for the higher orders this is an all-pass filter with the same Q values as
+            // _one_ of the two stacked Butterworth filters
+            LinkwitzRileyOrder::Lr24 => self.filters[0].set_coefficients(
+                BiquadCoefficients::allpass(sample_rate, frequency, NEUTRAL_Q),
+            ),
+            LinkwitzRileyOrder::Lr48 => {
+                for (filter, q) in self.filters.iter_mut().zip(BUTTERWORTH_4_QS) {
+                    filter.set_coefficients(BiquadCoefficients::allpass(sample_rate, frequency, q));
+                }
+            }
+        }
+    }
+
+    /// Reset the internal filter state.
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    /// The summed outputs of the crossover should be identical to the output of the matching
+    /// all-pass filter.
+    fn test_sum_matches_allpass(order: LinkwitzRileyOrder) {
+        let mut crossover = LinkwitzRileyCrossover::new(order);
+        crossover.update(SAMPLE_RATE, 1_000.0);
+        let mut allpass = LinkwitzRileyAllpass::new(order);
+        allpass.update(SAMPLE_RATE, 1_000.0);
+
+        for i in 0..1024 {
+            // Some noise-like signal with a lot of high and low frequency content
+            let sample = ((i * 7919) % 257) as f32 / 128.0 - 1.0;
+
+            let (low, high) = crossover.process(sample);
+            let allpassed = allpass.process(sample);
+            approx::assert_relative_eq!(low + high, allpassed, epsilon = 1e-4);
+        }
+    }
+
+    #[test]
+    fn lr12_sum() {
+        test_sum_matches_allpass(LinkwitzRileyOrder::Lr12);
+    }
+
+    #[test]
+    fn lr24_sum() {
+        test_sum_matches_allpass(LinkwitzRileyOrder::Lr24);
+    }
+
+    #[test]
+    fn lr48_sum() {
+        test_sum_matches_allpass(LinkwitzRileyOrder::Lr48);
+    }
+}
@@ -0,0 +1,295 @@
+//! Linear-phase FIR filter design and direct-form FIR filtering.
+
+use std::f32::consts;
+
+use super::{Biquad, BiquadCoefficients, SimdType};
+
+/// Coefficients for a (linear-phase) FIR filter. This struct includes ways to design the filter.
+/// `N` is the number of taps/coefficients and should be odd for linear-phase filters. A
+/// linear-phase filter designed using these functions introduces `N / 2` samples of latency.
+#[repr(transparent)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirCoefficients<const N: usize>(pub [f32; N]);
+
+/// A direct-form FIR filter with `N` taps. Direct convolution is only efficient for relatively
+/// short filters. Longer filters should use FFT convolution instead.
+///
+/// The type parameter T  should be either an `f32` or a SIMD type.
+#[derive(Debug, Clone)]
+pub struct FirFilter<const N: usize, T = f32> {
+    pub coefficients: FirCoefficients<N>,
+
+    /// The last `N` input samples. `history_pos` points to the oldest sample, which will be
+    /// overwritten by the next sample.
+    history: [T; N],
+    history_pos: usize,
+}
+
+impl<const N: usize> Default for FirCoefficients<N> {
+    fn default() -> Self {
+        // Initialize this to a delay with the same amount of latency as we'd introduce with our
+        // linear-phase filters
+        let mut coefficients = [0.0; N];
+        coefficients[N / 2] = 1.0;
+
+        Self(coefficients)
+    }
+}
+
+impl<const N: usize, T: SimdType> Default for FirFilter<N, T> {
+    fn default() -> Self {
+        Self::new(FirCoefficients::default())
+    }
+}
+
+impl<const N: usize, T: SimdType> FirFilter<N, T> {
+    /// Create a new FIR filter using the specified coefficients.
+    pub fn new(coefficients: FirCoefficients<N>) -> Self {
+        Self {
+            coefficients,
+
+            history: [T::from_f32(0.0); N],
+            history_pos: 0,
+        }
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: T) -> T {
+        self.history[self.history_pos] = sample;
+        self.history_pos += 1;
+        if self.history_pos == N {
+            self.history_pos = 0;
+        }
+
+        // `history_pos` now points to the oldest sample, so the newest sample gets multiplied by
+        // the first coefficient and the oldest sample by the last one. Splitting the history at
+        // that point avoids having to wrap the index around for every tap.
+        let (newest, oldest) = self.history.split_at(self.history_pos);
+        let mut result = T::from_f32(0.0);
+        for (sample, coefficient) in oldest
+            .iter()
+            .chain(newest.iter())
+            .zip(self.coefficients.0.iter().rev())
+        {
+            result = result + *sample * T::from_f32(*coefficient);
+        }
+
+        result
+    }
+
+    /// Reset the filter's history to zero.
+    pub fn reset(&mut self) {
+        self.history = [T::from_f32(0.0); N];
+        self.history_pos = 0;
+    }
+}
+
+impl<const N: usize> FirCoefficients<N> {
+    /// The latency introduced by a linear-phase filter with these many taps, in samples.
+    pub const fn latency() -> u32 {
+        (N / 2) as u32
+    }
+
+    /// Design a linear-phase low-pass filter by applying a Blackman window to a sinc function. The
+    /// result is normalized to have unity gain at DC. The transition band's width is inversely
+    /// proportional to the number of taps.
+    pub fn windowed_sinc_lowpass(sample_rate: f32, frequency: f32) -> Self {
+        nih_debug_assert!(sample_rate > 0.0);
+        nih_debug_assert!(frequency > 0.0);
+        nih_debug_assert!(frequency < sample_rate / 2.0);
+        nih_debug_assert!(N % 2 == 1);
+
+        let center_idx = N / 2;
+        let normalized_frequency = frequency / sample_rate;
+
+        let mut coefficients = [0.0; N];
+        let blackman_scale_1 = (2.0 * consts::PI) / (N - 1) as f32;
+        let blackman_scale_2 = blackman_scale_1 * 2.0;
+        for (sample_idx, coefficient) in coefficients.iter_mut().enumerate() {
+            let x = sample_idx as f32 - center_idx as f32;
+            let sinc = if x == 0.0 {
+                2.0 * normalized_frequency
+            } else {
+                (2.0 * consts::PI * normalized_frequency * x).sin() / (consts::PI * x)
+            };
+
+            // Adopted from `nih_plug::util::window`
+            let cos_1 = (blackman_scale_1 * sample_idx as f32).cos();
+            let cos_2 = (blackman_scale_2 * sample_idx as f32).cos();
+            *coefficient = sinc * (0.42 - (0.5 * cos_1) + (0.08 * cos_2));
+        }
+
+        let mut result = Self(coefficients);
+        result.normalize();
+
+        result
+    }
+
+    /// Design a linear-phase high-pass filter. This is the spectral inversion of
+    /// [`windowed_sinc_lowpass()`][Self::windowed_sinc_lowpass()].
+    pub fn windowed_sinc_highpass(sample_rate: f32, frequency: f32) -> Self {
+        Self::windowed_sinc_lowpass(sample_rate, frequency).spectral_inversion()
+    }
+
+    /// A somewhat crude but very functional and relatively fast way create linear phase FIR
+    /// **low-pass** filter that matches the frequency response of a fourth order biquad low-pass
+    /// filter. As in, this matches the frequency response magnitudes of applying those biquads to a
+    /// signal twice. This only works for low-pass filters, as the function normalizes the result to
+    /// hae unity gain at the DC bin. The algorithm works as follows:
+    ///
+    /// - An impulse function (so all zeroes except for the first element) of length `FILTER_LEN / 2
+    ///   + 1` is filtered with the biquad.
+    /// - The biquad's state is reset, and the impulse response is filtered in the opposite
+    ///   direction.
+    /// - At this point the bidirectionally filtered impulse response contains the **right** half of
+    ///   a truncated linear phase FIR kernel.
+    ///
+    /// Since the FIR filter will be a symmetrical version of this impulse response, we can optimize
+    /// the post-processing work slightly by windowing and normalizing this bidirectionally filtered
+    /// impulse response instead.
+    ///
+    /// - A half Blackman window is applied to the impulse response. Since this is the right half,
+    ///   this starts at unity gain for the first sample and then tapers off towards the right.
+    /// - The impulse response is then normalized such that the final linear-phase FIR kernel has a
+    ///   sum of 1.0. Since it will be symmetrical around the IRs first sample, the would-be final
+    ///   sum can be computed as `ir.sum() * 2 - ir[0]`.
+    ///
+    /// Lastly the linear phase FIR filter simply needs to be constructed from this right half:
+    ///
+    /// - This bidirectionally filtered impulse response is then reversed, and placed at the start
+    ///   of the `FILTER_LEN` size FIR coefficient array.
+    /// - The non-reversed bidirectionally filtered impulse response is copied to the second half of
+    ///   the coefficients. (one of the copies doesn't need to include the centermost coefficient)
+    ///
+    /// The corresponding high-pass filter can be computed through
+    /// [`spectral_inversion()`][Self::spectral_inversion()].
+    pub fn design_fourth_order_linear_phase_low_pass_from_biquad(
+        biquad_coefs: BiquadCoefficients<f32>,
+    ) -> Self {
+        nih_debug_assert!(N % 2 == 1);
+
+        // A single tap can only pass the signal through as is, and the indexing below assumes
+        // there are samples on both sides of the center
+        if N == 1 {
+            return Self::default();
+        }
+
+        // Rust doesn't allow you to define this as a constant
+        let center_idx = N / 2;
+
+        // We'll start with an impulse (at exactly half of this odd sized buffer)...
+        let mut impulse_response = [0.0; N];
+        impulse_response[center_idx] = 1.0;
+
+        // ...and filter that in both directions
+        let mut biquad = Biquad::new(biquad_coefs);
+        for sample in impulse_response.iter_mut().skip(center_idx - 1) {
+            *sample = biquad.process(*sample);
+        }
+
+        biquad.reset();
+        for sample in impulse_response.iter_mut().skip(center_idx - 1).rev() {
+            *sample = biquad.process(*sample);
+        }
+
+        // Now the right half of `impulse_response` contains a truncated right half of the
+        // linear-phase FIR filter. We can apply the window function here, and then fianlly
+        // normalize it so that the the final FIR filter kernel sums to 1.
+
+        // Adopted from `nih_plug::util::window`. We only end up applying the right half of the
+        // window, starting at the top of the window.
+        let blackman_scale_1 = (2.0 * consts::PI) / (N - 1) as f32;
+        let blackman_scale_2 = blackman_scale_1 * 2.0;
+        for (sample_idx, sample) in impulse_response.iter_mut().enumerate().skip(center_idx - 1) {
+            let cos_1 = (blackman_scale_1 * sample_idx as f32).cos();
+            let cos_2 = (blackman_scale_2 * sample_idx as f32).cos();
+            *sample *= 0.42 - (0.5 * cos_1) + (0.08 * cos_2);
+        }
+
+        // Since this final filter will be symmetrical around `impulse_response[CENTER_IDX]`, we
+        // can simply normalize based on that fact:
+        let would_be_impulse_response_sum = (impulse_response.iter().skip(center_idx).sum::<f32>()
+            * 2.0)
+            - impulse_response[center_idx];
+        let would_be_impulse_response_recip = would_be_impulse_response_sum.recip();
+        for sample in &mut impulse_response {
+            *sample *= would_be_impulse_response_recip;
+        }
+
+        // And finally we can simply copy the right half of the filter kernel to the left half
+        // around the `CENTER_IDX`.
+        for source_idx in center_idx + 1..N {
+            let target_idx = center_idx - (source_idx - center_idx);
+            impulse_response[target_idx] = impulse_response[source_idx];
+        }
+
+        Self(impulse_response)
+    }
+
+    /// Turn a linear-phase low-pass filter into the matching high-pass filter, or the other way
+    /// around, by subtracting it from a delayed impulse.
+    pub fn spectral_inversion(&self) -> Self {
+        let mut coefficients = self.0.map(|coefficient| -coefficient);
+        coefficients[N / 2] += 1.0;
+
+        Self(coefficients)
+    }
+
+    /// Normalize the coefficients so they sum to 1.0, giving the filter unity gain at DC.
+    pub fn normalize(&mut self) {
+        let sum_recip = self.0.iter().sum::<f32>().recip();
+        for coefficient in &mut self.0 {
+            *coefficient *= sum_recip;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windowed_sinc_symmetry_and_gain() {
+        let lowpass = FirCoefficients::<63>::windowed_sinc_lowpass(44_100.0, 5_000.0);
+        for (left, right) in lowpass.0.iter().zip(lowpass.0.iter().rev()) {
+            approx::assert_relative_eq!(left, right, epsilon = 1e-6);
+        }
+        approx::assert_relative_eq!(lowpass.0.iter().sum::<f32>(), 1.0, epsilon = 1e-5);
+
+        // The high-pass filter should remove DC entirely
+        let highpass = FirCoefficients::<63>::windowed_sinc_highpass(44_100.0, 5_000.0);
+        approx::assert_relative_eq!(highpass.0.iter().sum::<f32>(), 0.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn fourth_order_from_biquad() {
+        let biquad_coefs = BiquadCoefficients::lowpass(44_100.0, 1_000.0, super::super::NEUTRAL_Q);
+        let lowpass = FirCoefficients::<31>::design_fourth_order_linear_phase_low_pass_from_biquad(
+            biquad_coefs,
+        );
+        for (left, right) in lowpass.0.iter().zip(lowpass.0.iter().rev()) {
+            approx::assert_relative_eq!(left, right, epsilon = 1e-6);
+        }
+        approx::assert_relative_eq!(lowpass.0.iter().sum::<f32>(), 1.0, epsilon = 1e-5);
+
+        let single_tap =
+            FirCoefficients::<1>::design_fourth_order_linear_phase_low_pass_from_biquad(
+                biquad_coefs,
+            );
+        assert_eq!(single_tap.0, [1.0]);
+    }
+
+    #[test]
+    fn fir_filter_impulse_response() {
+        let coefficients = FirCoefficients([1.0, 2.0, 3.0, 4.0, 5.0]);
+        let mut filter: FirFilter<5> = FirFilter::new(coefficients);
+
+        let mut impulse_response = [0.0; 7];
+        for (i, sample) in impulse_response.iter_mut().enumerate() {
+            *sample = filter.process(if i == 0 { 1.0 } else { 0.0 });
+        }
+
+        assert_eq!(impulse_response, [1.0, 2.0, 3.0, 4.0, 5.0, 0.0, 0.0]);
+    }
+}
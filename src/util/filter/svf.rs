@@ -0,0 +1,335 @@
+//! A trapezoidal integrated state variable filter.
+
+use std::f32::consts;
+
+use super::{CoefficientRamp, InterpolatableCoefficients, SimdType};
+
+/// A trapezoidal integrated state variable filter based on Andrew Simper's (Cytomic) derivation.
+/// Unlike the [`Biquad`][super::Biquad], this filter's coefficients can be modulated quickly
+/// without causing instability or large transients, which makes it a good choice for filters with
+/// heavily automated or modulated cutoff frequencies.
+///
+/// Based on <https://cytomic.com/files/dsp/SvfLinearTrapOptimised2.pdf>.
+///
+/// The type parameter T  should be either an `f32` or a SIMD type.
+#[derive(Clone, Copy, Debug)]
+pub struct Svf<T> {
+    coefficients: SvfCoefficients<T>,
+    /// Used to interpolate between the old and new coefficients when using
+    /// [`set_coefficients_smoothed()`][Self::set_coefficients_smoothed()].
+    ramp: CoefficientRamp<SvfCoefficients<T>>,
+    ic1eq: T,
+    ic2eq: T,
+}
+
+/// The coefficients for [`Svf`]. `a1`, `a2`, and `a3` determine the filter's cutoff frequency and
+/// resonance, while `m0`, `m1`, and `m2` mix the input, band-pass, and low-pass outputs together to
+/// form the different filter types.
+///
+/// The type parameter T  should be either an `f32` or a SIMD type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvfCoefficients<T> {
+    pub a1: T,
+    pub a2: T,
+    pub a3: T,
+    pub m0: T,
+    pub m1: T,
+    pub m2: T,
+}
+
+impl<T: SimdType> Default for Svf<T> {
+    /// Before setting constants the filter should just act as an identity function.
+    fn default() -> Self {
+        Self::new(SvfCoefficients::identity())
+    }
+}
+
+impl<T: SimdType> Svf<T> {
+    /// Create a new state variable filter using the specified coefficients.
+    pub fn new(coefficients: SvfCoefficients<T>) -> Self {
+        Self {
+            coefficients,
+            ramp: CoefficientRamp::finished(coefficients),
+            ic1eq: T::from_f32(0.0),
+            ic2eq: T::from_f32(0.0),
+        }
+    }
+
+    /// Process a single sample.
+    #[inline]
+    pub fn process(&mut self, sample: T) -> T {
+        self.ramp.next(&mut self.coefficients);
+
+        let SvfCoefficients {
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
+        } = self.coefficients;
+
+        let v3 = sample - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = T::from_f32(2.0) * v1 - self.ic1eq;
+        self.ic2eq = T::from_f32(2.0) * v2 - self.ic2eq;
+
+        m0 * sample + m1 * v1 + m2 * v2
+    }
+
+    /// The filter's current coefficients. These may be in between two sets of coefficients if the
+    /// coefficients are currently being smoothed.
+    pub fn coefficients(&self) -> &SvfCoefficients<T> {
+        &self.coefficients
+    }
+
+    /// Immediately change the filter's coefficients. This cancels any ongoing coefficient
+    /// smoothing.
+    pub fn set_coefficients(&mut self, coefficients: SvfCoefficients<T>) {
+        self.coefficients = coefficients;
+        self.ramp = CoefficientRamp::finished(coefficients);
+    }
+
+    /// Linearly interpolate from the filter's current coefficients to `coefficients` over the next
+    /// `num_steps` calls to [`process()`][Self::process()]. This avoids zipper noise when the
+    /// filter's parameters are automated. The filter stays stable while its coefficients are being
+    /// interpolated.
+    pub fn set_coefficients_smoothed(&mut self, coefficients: SvfCoefficients<T>, num_steps: u32) {
+        self.ramp = CoefficientRamp::new(&self.coefficients, coefficients, num_steps);
+        if num_steps == 0 {
+            self.coefficients = coefficients;
+        }
+    }
+
+    /// Whether the coefficients are currently being smoothed.
+    pub fn is_smoothing(&self) -> bool {
+        self.ramp.steps_left > 0
+    }
+
+    /// Reset the state to zero.
+    pub fn reset(&mut self) {
+        self.ic1eq = T::from_f32(0.0);
+        self.ic2eq = T::from_f32(0.0);
+    }
+}
+
+impl<T: SimdType> InterpolatableCoefficients for SvfCoefficients<T> {
+    fn step_towards(&self, target: &Self, num_steps: u32) -> Self {
+        let scale = T::from_f32((num_steps as f32).recip());
+
+        Self {
+            a1: (target.a1 - self.a1) * scale,
+            a2: (target.a2 - self.a2) * scale,
+            a3: (target.a3 - self.a3) * scale,
+            m0: (target.m0 - self.m0) * scale,
+            m1: (target.m1 - self.m1) * scale,
+            m2: (target.m2 - self.m2) * scale,
+        }
+    }
+
+    #[inline]
+    fn add_step(&self, step: &Self) -> Self {
+        Self {
+            a1: self.a1 + step.a1,
+            a2: self.a2 + step.a2,
+            a3: self.a3 + step.a3,
+            m0: self.m0 + step.m0,
+            m1: self.m1 + step.m1,
+            m2: self.m2 + step.m2,
+        }
+    }
+}
+
+impl<T: SimdType> SvfCoefficients<T> {
+    /// Convert scalar coefficients into the correct vector type.
+    pub fn from_f32s(scalar: SvfCoefficients<f32>) -> Self {
+        Self {
+            a1: T::from_f32(scalar.a1),
+            a2: T::from_f32(scalar.a2),
+            a3: T::from_f32(scalar.a3),
+            m0: T::from_f32(scalar.m0),
+            m1: T::from_f32(scalar.m1),
+            m2: T::from_f32(scalar.m2),
+        }
+    }
+
+    /// Filter coefficients that would cause the sound to be passed through as is.
+    pub fn identity() -> Self {
+        Self::from_f32s(SvfCoefficients {
+            a1: 1.0,
+            a2: 0.0,
+            a3: 0.0,
+            m0: 1.0,
+            m1: 0.0,
+            m2: 0.0,
+        })
+    }
+
+    /// Compute the coefficients for a low-pass filter.
+    pub fn lowpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let g = g(sample_rate, frequency);
+        let k = k(q);
+
+        Self::from_g_k(g, k, 0.0, 0.0, 1.0)
+    }
+
+    /// Compute the coefficients for a high-pass filter.
+    pub fn highpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let g = g(sample_rate, frequency);
+        let k = k(q);
+
+        Self::from_g_k(g, k, 1.0, -k, -1.0)
+    }
+
+    /// Compute the coefficients for a band-pass filter with a constant 0 dB peak gain.
+    pub fn bandpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let g = g(sample_rate, frequency);
+        let k = k(q);
+
+        Self::from_g_k(g, k, 0.0, k, 0.0)
+    }
+
+    /// Compute the coefficients for a notch filter.
+    pub fn notch(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let g = g(sample_rate, frequency);
+        let k = k(q);
+
+        Self::from_g_k(g, k, 1.0, -k, 0.0)
+    }
+
+    /// Compute the coefficients for an all-pass filter.
+    pub fn allpass(sample_rate: f32, frequency: f32, q: f32) -> Self {
+        let g = g(sample_rate, frequency);
+        let k = k(q);
+
+        Self::from_g_k(g, k, 1.0, -2.0 * k, 0.0)
+    }
+
+    /// Compute the coefficients for a peaking/bell filter that boosts or cuts the frequencies
+    /// around `frequency` by `gain_db` decibels.
+    pub fn peaking(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let g = g(sample_rate, frequency);
+        let k = 1.0 / (q * a);
+
+        Self::from_g_k(g, k, 1.0, k * (a * a - 1.0), 0.0)
+    }
+
+    /// Compute the coefficients for a low-shelf filter that boosts or cuts the frequencies below
+    /// `frequency` by `gain_db` decibels.
+    pub fn low_shelf(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let g = g(sample_rate, frequency) / a.sqrt();
+        let k = k(q);
+
+        Self::from_g_k(g, k, 1.0, k * (a - 1.0), a * a - 1.0)
+    }
+
+    /// Compute the coefficients for a high-shelf filter that boosts or cuts the frequencies above
+    /// `frequency` by `gain_db` decibels.
+    pub fn high_shelf(sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let g = g(sample_rate, frequency) * a.sqrt();
+        let k = k(q);
+
+        Self::from_g_k(g, k, a * a, k * (1.0 - a) * a, 1.0 - a * a)
+    }
+
+    /// Compute the filter coefficients from the prewarped cutoff `g`, the damping `k`, and the
+    /// output mixing coefficients.
+    fn from_g_k(g: f32, k: f32, m0: f32, m1: f32, m2: f32) -> Self {
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        Self::from_f32s(SvfCoefficients {
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
+        })
+    }
+}
+
+/// The prewarped cutoff frequency used in all of the filter designs.
+fn g(sample_rate: f32, frequency: f32) -> f32 {
+    nih_debug_assert!(sample_rate > 0.0);
+    nih_debug_assert!(frequency > 0.0);
+    nih_debug_assert!(frequency < sample_rate / 2.0);
+
+    (consts::PI * (frequency / sample_rate)).tan()
+}
+
+/// The damping coefficient corresponding to a Q value.
+fn k(q: f32) -> f32 {
+    nih_debug_assert!(q > 0.0);
+
+    q.recip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.0;
+
+    /// Measure the filter's gain at a frequency by running a sine wave through it and comparing
+    /// the peak amplitudes after the filter has settled.
+    fn measure_gain(coefficients: SvfCoefficients<f32>, frequency: f32) -> f32 {
+        let mut filter = Svf::new(coefficients);
+
+        let mut peak = 0.0f32;
+        for i in 0..(SAMPLE_RATE as usize / 2) {
+            let sample = (consts::TAU * frequency * (i as f32 / SAMPLE_RATE)).sin();
+            let output = filter.process(sample);
+            if i > SAMPLE_RATE as usize / 4 {
+                peak = peak.max(output.abs());
+            }
+        }
+
+        peak
+    }
+
+    #[test]
+    fn lowpass_response() {
+        let coefficients = SvfCoefficients::lowpass(SAMPLE_RATE, 1_000.0, super::super::NEUTRAL_Q);
+
+        approx::assert_relative_eq!(measure_gain(coefficients, 50.0), 1.0, epsilon = 1e-2);
+        approx::assert_relative_eq!(
+            measure_gain(coefficients, 1_000.0),
+            std::f32::consts::FRAC_1_SQRT_2,
+            epsilon = 1e-2
+        );
+        assert!(measure_gain(coefficients, 10_000.0) < 0.02);
+    }
+
+    #[test]
+    fn peaking_gain() {
+        let coefficients = SvfCoefficients::peaking(SAMPLE_RATE, 1_000.0, 1.0, -6.0);
+
+        approx::assert_relative_eq!(
+            measure_gain(coefficients, 1_000.0),
+            10.0f32.powf(-6.0 / 20.0),
+            epsilon = 1e-2
+        );
+        approx::assert_relative_eq!(measure_gain(coefficients, 20.0), 1.0, epsilon = 1e-2);
+    }
+
+    #[test]
+    fn smoothed_coefficients() {
+        let from = SvfCoefficients::lowpass(SAMPLE_RATE, 1_000.0, 1.0);
+        let to = SvfCoefficients::highpass(SAMPLE_RATE, 2_000.0, 1.0);
+
+        let mut filter = Svf::new(from);
+        filter.set_coefficients_smoothed(to, 4);
+        for _ in 0..4 {
+            assert!(filter.is_smoothing());
+            filter.process(1.0);
+        }
+        assert!(!filter.is_smoothing());
+        assert_eq!(*filter.coefficients(), to);
+    }
+}
@@ -0,0 +1,1107 @@
+//! Multi-stage, multichannel oversampling with either linear-phase or minimum-phase filters.
+
+use crate::buffer::Block;
+
+/// The 2-logarithm of the maximum supported oversampling amount. 16x oversampling corresponds to
+/// factor 4.
+pub const MAX_OVERSAMPLING_FACTOR: usize = 4;
+
+/// The kernel used in the linear-phase oversampling stages. Specified here as a constant since it
+/// is a constant. Precomputed since compile-time floating point arithmetic is still unstable.
+///
+/// Computed using:
+///
+/// ```python
+/// LANCZOS_A = 3
+///
+/// x = np.arange(-LANCZOS_A * 2 + 1, LANCZOS_A * 2) / 2
+/// np.sinc(x) * np.sinc(x / LANCZOS_A)
+/// ```
+///
+/// Note the `+1` at the start of the range and the lack of `+1` at the (exclusive) end of the
+/// range. This is because we can ommit the first and last point because they are always zero.
+const LANCZOS3_UPSAMPLING_KERNEL: [f32; 11] = [
+    0.02431708,
+    -0.0,
+    -0.13509491,
+    0.0,
+    0.6079271,
+    1.0,
+    0.6079271,
+    0.0,
+    -0.13509491,
+    -0.0,
+    0.02431708,
+];
+
+/// `LANCZOS3_UPSAMPLING_KERNEL` divided by two, used for downsampling so that upsampling followed
+/// by downsampling results in unity gain.
+const LANCZOS3_DOWNSAMPLING_KERNEL: [f32; 11] = [
+    0.01215854,
+    -0.0,
+    -0.06754746,
+    0.0,
+    0.30396355,
+    0.5,
+    0.30396355,
+    0.0,
+    -0.06754746,
+    -0.0,
+    0.01215854,
+];
+
+/// The latency introduced by the two filter kernels defined above, in samples.
+const LANZCOS3_KERNEL_LATENCY: usize = LANCZOS3_UPSAMPLING_KERNEL.len() / 2;
+
+/// The allpass coefficients for the polyphase IIR halfband filters used in the minimum-phase
+/// oversampling stages, indexed by the stage number. The coefficients with an even index form the
+/// first allpass path, and the coefficients with an odd index form the second path. Later stages
+/// only need to filter out the images of an already band limited signal, so they can get away with
+/// a much wider transition band and thus far fewer coefficients.
+///
+/// Computed using the elliptic halfband design from Laurent de Soras' HIIR library
+/// (`hiir::PolyphaseIir2Designer::compute_coefs_spec_order_tbw()`) with the following number of
+/// coefficients and transition bandwidths, relative to the stage's oversampled sample rate. This
+/// results in the listed stopband attenuation:
+///
+/// | Stage | Coefficients | Transition bandwidth | Stopband attenuation |
+/// | ----- | ------------ | -------------------- | -------------------- |
+/// | 2x    | 8            | 0.01                 | 69.1 dB              |
+/// | 4x    | 4            | 0.13                 | 79.0 dB              |
+/// | 8x    | 3            | 0.19                 | 74.2 dB              |
+/// | 16x   | 3            | 0.22                 | 81.6 dB              |
+const ALLPASS_HALFBAND_COEFFICIENTS: [&[f32]; MAX_OVERSAMPLING_FACTOR] = [
+    &[
+        0.07711508, 0.26596853, 0.48207062, 0.66510415, 0.79682046, 0.8841015, 0.94125146,
+        0.9820054,
+    ],
+    &[0.06701349, 0.24687676, 0.49912912, 0.80959845],
+    &[0.08430115, 0.32325, 0.7157712],
+    &[0.0763827, 0.302082, 0.6987825],
+];
+
+/// The type of anti-aliasing filter used by an [`Oversampler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversamplingFilter {
+    /// Linear-phase FIR halfband filters based on a Lanczos kernel with `a = 3`. These don't
+    /// introduce any phase distortion, but they have a steeper latency cost. The reported latency
+    /// is exact.
+    LinearPhase,
+    /// Minimum-phase polyphase IIR halfband filters. These have a much lower latency and a steeper
+    /// transition band than the linear-phase filters, at the cost of introducing some phase
+    /// distortion near the Nyquist frequency. Since the group delay of these filters is not
+    /// constant, the reported latency is the filters' group delay at DC, rounded to the nearest
+    /// integer.
+    MinimumPhase,
+}
+
+/// A multichannel, multi-stage oversampler. Each stage doubles the sample rate, so oversampling
+/// factors between 2x and 16x can be used. The oversampling factor is passed to the processing
+/// functions as the 2-logarithm of the oversampling amount (1x oversampling, aka do nothing = 0, 2x
+/// oversampling = 1, 4x oversampling = 2, etc.), so it can change at runtime without allocating.
+/// Use [`latency()`][Self::latency()] to report the oversampler's latency to the host whenever the
+/// factor changes.
+///
+/// Smoothed parameters that are used in the oversampled part of the plugin should use
+/// [`SmoothingStyle::OversamplingAware`][crate::params::smoothing::SmoothingStyle::OversamplingAware]
+/// so they keep their timing regardless of the oversampling amount. The atomic used with that
+/// smoothing style should be updated with [`oversampling_factor_to_times()`] whenever the
+/// oversampling factor changes, for instance from the oversampling factor parameter's callback:
+///
+/// ```ignore
+/// let oversampling_times = Arc::new(AtomicF32::new(1.0));
+///
+/// drive: FloatParam::new("Drive", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+///     .with_smoother(SmoothingStyle::OversamplingAware(
+///         oversampling_times.clone(),
+///         &SmoothingStyle::Linear(10.0),
+///     )),
+/// oversampling_factor: IntParam::new(
+///     "Oversampling",
+///     0,
+///     IntRange::Linear { min: 0, max: MAX_OVERSAMPLING_FACTOR as i32 },
+/// )
+/// .with_callback(Arc::new(move |new_factor| {
+///     oversampling_times.store(
+///         oversampling_factor_to_times(new_factor as usize) as f32,
+///         Ordering::Relaxed,
+///     );
+/// })),
+/// ```
+///
+/// The smoothers then need to be advanced once for every oversampled sample, for instance by
+/// calling [`Smoother::next_block()`][crate::params::smoothing::Smoother::next_block()] with the
+/// upsampled block length.
+#[derive(Debug)]
+pub struct Oversampler {
+    /// The filter used in every oversampling stage.
+    filter: OversamplingFilter,
+    /// The number of samples at the base sample rate that can be processed at once.
+    maximum_block_size: usize,
+
+    /// The state used for each oversampling stage, for every channel. Also contains stages that are
+    /// not being used, so the number of stages can change without allocating. The oversampling
+    /// factor passed to the process functions determines how many of these are actually used.
+    channels: Vec<Vec<Stage>>,
+
+    /// The oversampler's latency. Precomputed for each possible number of active stages.
+    latencies: Vec<u32>,
+}
+
+/// A single 2x oversampling stage for a single channel. Contains the filter state used in the
+/// upsampling and downsampling parts of the stage, and a scratch buffer containing the upsampled
+/// signal.
+#[derive(Debug, Clone)]
+struct Stage {
+    /// The amount of oversampling that happens at this stage. Will be 2 for the first stage, 4 for
+    /// the second stage, 8 for the third stage, and so forth. Used to calculate the stage's effect
+    /// on the oversampling's latency.
+    oversampling_amount: usize,
+
+    filter: StageFilter,
+
+    scratch_buffer: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+enum StageFilter {
+    LinearPhase(Lanczos3Filter),
+    MinimumPhase(AllpassHalfbandFilter),
+}
+
+/// The filter state for a linear-phase stage. Contains the ring buffers and current position in
+/// that ringbuffer used for convolving the filter with the inputs in the upsampling and
+/// downsampling parts of the stage.
+#[derive(Debug, Clone)]
+struct Lanczos3Filter {
+    /// These ring buffers contain `LANCZOS3_UPSAMPLING_KERNEL.len()` samples. The upsampling ring
+    /// buffer contains room to delay the signal further to make sure the _total_
+    /// (upsampling+downsampling) latency imposed on the signal is divisible by the stage's
+    /// oversampling amount. That is needed to avoid fractional latency.
+    upsampling_rb: Vec<f32>,
+    upsampling_write_pos: usize,
+    /// The additional delay for the upsampling needed to make this stage impose an integer amount
+    /// of latency. The stage's _total_ (upsampling+downsampling) latency needs to be divisible by
+    /// the stage's oversampling amount.
+    additional_upsampling_latency: usize,
+
+    /// No additional latency needs to be imposed for the downsampling, so to keep things simple
+    /// this doesn't add any additional delay.
+    downsampling_rb: [f32; LANCZOS3_DOWNSAMPLING_KERNEL.len()],
+    downsampling_write_pos: usize,
+}
+
+/// The filter state for a minimum-phase stage. This is a polyphase halfband filter made out of two
+/// parallel chains of first order allpass filters. Both chains run at the stage's input sample
+/// rate, so the filter's cost is only half that of a regular IIR filter running at the oversampled
+/// rate.
+#[derive(Debug, Clone)]
+struct AllpassHalfbandFilter {
+    /// The coefficients for this stage, taken from `ALLPASS_HALFBAND_COEFFICIENTS`.
+    coefficients: &'static [f32],
+
+    /// The state for each of the allpass filters used during upsampling, matching
+    /// `coefficients`.
+    upsampling_state: Vec<AllpassState>,
+    /// The state for each of the allpass filters used during downsampling, matching
+    /// `coefficients`.
+    downsampling_state: Vec<AllpassState>,
+}
+
+/// The previous input and output for a first order allpass filter.
+#[derive(Debug, Default, Clone, Copy)]
+struct AllpassState {
+    x1: f32,
+    y1: f32,
+}
+
+impl Oversampler {
+    /// Create a new oversampler for `num_channels` channels that can oversample to up to the
+    /// specified oversampling factor, or the 2-logarithm of the oversampling amount. 1x
+    /// oversampling (aka, do nothing) = 0, 2x oversampling = 1, 4x oversampling = 2, etc. The
+    /// actual amount of oversampling stages used is passed to the process functions, and must be
+    /// set to `max_factor` or lower. The maximum block size is the number of samples at the base
+    /// sample rate that can be processed at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_factor` is larger than [`MAX_OVERSAMPLING_FACTOR`].
+    pub fn new(
+        num_channels: usize,
+        maximum_block_size: usize,
+        max_factor: usize,
+        filter: OversamplingFilter,
+    ) -> Self {
+        assert!(
+            max_factor <= MAX_OVERSAMPLING_FACTOR,
+            "Oversampling factors above {MAX_OVERSAMPLING_FACTOR} are not supported"
+        );
+
+        let stages: Vec<Stage> = (0..max_factor)
+            .map(|stage_number| Stage::new(maximum_block_size, stage_number, filter))
+            .collect();
+
+        // Since the number of active oversampling stages is passed to the process function, we also
+        // need to know the effective latencies of all possible oversampling settings in advance.
+        // The minimum-phase stages have fractional latencies, so the total is only rounded at the
+        // end.
+        let latencies = stages
+            .iter()
+            .map(|stage| stage.effective_latency())
+            .scan(0.0, |total_latency, latency| {
+                *total_latency += latency;
+                Some(total_latency.round() as u32)
+            })
+            .collect();
+
+        Self {
+            filter,
+            maximum_block_size,
+
+            channels: vec![stages; num_channels],
+
+            latencies,
+        }
+    }
+
+    /// The filter used by this oversampler.
+    pub fn filter(&self) -> OversamplingFilter {
+        self.filter
+    }
+
+    /// The number of channels this oversampler was created for.
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// The maximum oversampling factor this oversampler was created for.
+    pub fn max_factor(&self) -> usize {
+        self.latencies.len()
+    }
+
+    /// Reset the oversampling filters to their initial states.
+    pub fn reset(&mut self) {
+        for stage in self.channels.iter_mut().flatten() {
+            stage.reset();
+        }
+    }
+
+    /// Get the latency in samples for the given oversampling factor. Fractional latency is
+    /// automatically avoided for the linear-phase filters. For the minimum-phase filters this is
+    /// the filters' group delay at DC, rounded to the nearest integer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor > max_factor`.
+    pub fn latency(&self, factor: usize) -> u32 {
+        if factor == 0 {
+            0
+        } else {
+            self.latencies[factor - 1]
+        }
+    }
+
+    /// Upsample every channel in `block` using the specified oversampling factor, process the
+    /// upsampled channels using `f`, and then downsample them again and write the results back to
+    /// `block` with a [`latency()`][Self::latency()] sample delay. `f` receives the channel's index
+    /// and the upsampled version of that channel, which contains `block.samples() *
+    /// oversampling_factor_to_times(factor)` samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor > max_factor`, if `block` contains more channels than the oversampler was
+    /// created for, or if `block`'s length is longer than the maximum block size.
+    pub fn process_block(
+        &mut self,
+        block: &mut Block,
+        factor: usize,
+        mut f: impl FnMut(usize, &mut [f32]),
+    ) {
+        assert!(
+            block.channels() <= self.channels.len(),
+            "The block contains more channels than the oversampler was created for"
+        );
+
+        for (channel_idx, channel) in block.iter_mut().enumerate() {
+            self.process_channel(channel_idx, channel, factor, |upsampled| {
+                f(channel_idx, upsampled)
+            });
+        }
+    }
+
+    /// Upsample a single channel using the specified oversampling factor, process the upsampled
+    /// version using `f`, and then downsample it again and write the results back to `block` with
+    /// a [`latency()`][Self::latency()] sample delay.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor > max_factor`, if `channel_idx` is out of bounds, or if `block`'s length
+    /// is longer than the maximum block size.
+    pub fn process_channel(
+        &mut self,
+        channel_idx: usize,
+        block: &mut [f32],
+        factor: usize,
+        f: impl FnOnce(&mut [f32]),
+    ) {
+        assert!(factor <= self.max_factor());
+
+        // This is the 1x oversampling case, this should also modify the block to be consistent
+        if factor == 0 {
+            f(block);
+            return;
+        }
+
+        assert!(
+            block.len() <= self.maximum_block_size,
+            "The block's size exceeds the maximum block size"
+        );
+
+        let stages = &mut self.channels[channel_idx];
+        let upsampled = upsample_from(stages, block, factor);
+        f(upsampled);
+        downsample_to(stages, block, factor)
+    }
+
+    /// An upsample-only version of [`process_channel()`][Self::process_channel()] that returns the
+    /// upsampled version of the signal that would normally be passed to the process callback.
+    /// Useful for upsampling control signals. The channel's state is separate from the state used
+    /// by the process functions, so this should use its own channel index or its own oversampler.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor > max_factor`, if `channel_idx` is out of bounds, or if `block`'s length
+    /// is longer than the maximum block size.
+    pub fn upsample_only<'a>(
+        &'a mut self,
+        channel_idx: usize,
+        block: &'a mut [f32],
+        factor: usize,
+    ) -> &'a mut [f32] {
+        assert!(factor <= self.max_factor());
+
+        // This is the 1x oversampling case, this should also modify the block to be consistent
+        if factor == 0 {
+            return block;
+        }
+
+        assert!(
+            block.len() <= self.maximum_block_size,
+            "The block's size exceeds the maximum block size"
+        );
+
+        upsample_from(&mut self.channels[channel_idx], block, factor)
+    }
+}
+
+/// Convert an oversampling factor, or the 2-logarithm of the oversampling amount, to the
+/// oversampling amount. Factor 0 corresponds to 1x oversampling, 1 to 2x oversampling, etc.
+pub const fn oversampling_factor_to_times(factor: usize) -> usize {
+    2usize.pow(factor as u32)
+}
+
+/// The inverse of [`oversampling_factor_to_times()`].
+pub const fn oversampling_times_to_factor(times: usize) -> usize {
+    times.ilog2() as usize
+}
+
+/// Upsample `block` through `factor` oversampling stages. Returns a reference to the oversampled
+/// output stored in the last stage's scratch buffer **with the correct length**. This is a multiple
+/// of `block`'s length, which may be shorter than the entire scratch buffer's length if `block` is
+/// shorter than the configured maximum block length.
+///
+/// # Panics
+///
+/// Panics if `block`'s length is longer than the maximum block size, if the number of
+/// oversampling is smaller than `factor`, or if `factor` is zero. This is already checked for in
+/// the process functions.
+fn upsample_from<'a>(stages: &'a mut [Stage], block: &[f32], factor: usize) -> &'a mut [f32] {
+    assert_ne!(factor, 0);
+    assert!(factor <= stages.len());
+
+    // The first stage is upsampled from `block`, and everything after that is upsampled from the
+    // stage preceeding it
+    stages[0].upsample_from(block);
+
+    let mut previous_upsampled_block_len = block.len() * 2;
+    for to_stage_idx in 1..factor {
+        // This requires splitting the slice so we can borrow the from-stage immutably and the
+        // to-stage mutably at the same time
+        let ([.., from], [to, ..]) = stages.split_at_mut(to_stage_idx) else {
+            unreachable!()
+        };
+
+        to.upsample_from(&from.scratch_buffer[..previous_upsampled_block_len]);
+        previous_upsampled_block_len *= 2;
+    }
+
+    &mut stages[factor - 1].scratch_buffer[..previous_upsampled_block_len]
+}
+
+/// Downsample starting from the `factor`th oversampling stage, writing the results from
+/// downsampling the first stage to `block`. `block`'s actual length is taken into account to
+/// compute the length of the oversampled blocks.
+///
+/// # Panics
+///
+/// Panics if `block`'s length is longer than the maximum block size, if the number of
+/// oversampling is smaller than `factor`, or if `factor` is zero. This is already checked for in
+/// the process functions.
+fn downsample_to(stages: &mut [Stage], block: &mut [f32], factor: usize) {
+    assert_ne!(factor, 0);
+    assert!(factor <= stages.len());
+
+    // This is the reverse of `upsample_from`. Starting from the last stage, the oversampling stages
+    // are downsampled to the previous stage and then the first stage is downsampled to `block`.
+    let mut next_downsampled_block_len = block.len() * 2usize.pow(factor as u32 - 1);
+    for to_stage_idx in (1..factor).rev() {
+        // This requires splitting the slice so we can borrow the from-stage immutably and the
+        // to-stage mutably at the same time
+        let ([.., to], [from, ..]) = stages.split_at_mut(to_stage_idx) else {
+            unreachable!()
+        };
+
+        from.downsample_to(&mut to.scratch_buffer[..next_downsampled_block_len]);
+        next_downsampled_block_len /= 2;
+    }
+
+    // And then the first stage downsamples to `block`
+    assert_eq!(next_downsampled_block_len, block.len());
+    stages[0].downsample_to(block);
+}
+
+impl Stage {
+    /// Create a `stage_number`th oversampling stage, where `stage_number` is this stage's
+    /// zero-based index in a list of stages. Stage 0 handles the 2x oversampling, stage 1 handles
+    /// the 4x oversampling, stage 2 handles the 8x oversampling, etc.. This is used to make sure
+    /// the stage's effect on the total latency is always an integer amount for the linear-phase
+    /// filters, and to pick the filter coefficients for the minimum-phase filters.
+    ///
+    /// The maximum block size is used to allocate enough scratch space for oversampling that many
+    /// samples *at the base sample rate*. The scratch buffer's size automatically takes the stage
+    /// number into account.
+    pub fn new(maximum_block_size: usize, stage_number: usize, filter: OversamplingFilter) -> Self {
+        let oversampling_amount = 2usize.pow(stage_number as u32 + 1);
+
+        let filter = match filter {
+            OversamplingFilter::LinearPhase => {
+                StageFilter::LinearPhase(Lanczos3Filter::new(oversampling_amount))
+            }
+            OversamplingFilter::MinimumPhase => StageFilter::MinimumPhase(
+                AllpassHalfbandFilter::new(ALLPASS_HALFBAND_COEFFICIENTS[stage_number]),
+            ),
+        };
+
+        Self {
+            oversampling_amount,
+
+            filter,
+
+            scratch_buffer: vec![0.0; maximum_block_size * oversampling_amount],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        match &mut self.filter {
+            StageFilter::LinearPhase(filter) => filter.reset(),
+            StageFilter::MinimumPhase(filter) => filter.reset(),
+        }
+    }
+
+    /// The stage's effect on the oversampling's latency as a whole. This is already divided by the
+    /// stage's oversampling amount. This is always an integer for the linear-phase filters.
+    pub fn effective_latency(&self) -> f32 {
+        let stage_latency = match &self.filter {
+            StageFilter::LinearPhase(filter) => filter.latency() as f32,
+            StageFilter::MinimumPhase(filter) => filter.latency(),
+        };
+
+        stage_latency / self.oversampling_amount as f32
+    }
+
+    /// Upsample `block` 2x and write the results to this stage's scratch buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block`'s times two exceeds the scratch buffer's size.
+    pub fn upsample_from(&mut self, block: &[f32]) {
+        let output_length = block.len() * 2;
+        assert!(output_length <= self.scratch_buffer.len());
+
+        let output = &mut self.scratch_buffer[..output_length];
+        match &mut self.filter {
+            StageFilter::LinearPhase(filter) => filter.upsample(block, output),
+            StageFilter::MinimumPhase(filter) => filter.upsample(block, output),
+        }
+    }
+
+    /// Downsample this stage's scratch buffer by a factor of two, writing the results to `block`.
+    /// `block`'s actual length is taken into account to compute the length of the oversampled
+    /// block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block`'s divided by two exceeds the scratch buffer's size.
+    pub fn downsample_to(&mut self, block: &mut [f32]) {
+        let input_length = block.len() * 2;
+        assert!(input_length <= self.scratch_buffer.len());
+
+        let input = &self.scratch_buffer[..input_length];
+        match &mut self.filter {
+            StageFilter::LinearPhase(filter) => filter.downsample(input, block),
+            StageFilter::MinimumPhase(filter) => filter.downsample(input, block),
+        }
+    }
+}
+
+impl Lanczos3Filter {
+    /// Create the filter state for a stage with the specified oversampling amount.
+    pub fn new(oversampling_amount: usize) -> Self {
+        // In theory we would only need to delay one of these, but we'll distribute the delay
+        // cleanly
+        assert!(LANCZOS3_UPSAMPLING_KERNEL.len() == LANCZOS3_DOWNSAMPLING_KERNEL.len());
+        assert!(LANCZOS3_UPSAMPLING_KERNEL.len() % 2 == 1);
+
+        // This is the latency of the upsampling and downsampling filter, at the base sample rate.
+        // Because this stage's filtering happens at a higher sample rate (`oversampling_amount`
+        // times the base sample rate), we need to make sure that the delay imposed _on this higher
+        // sample rate_ results in an integer amount of latency at the base sample rate. To do that,
+        // the delay needs to be divisible by `oversampling_amount`. This extra delay is only
+        // applied to the upsampling part to keep the downsampling simpler.
+        let uncompensated_stage_latency = LANZCOS3_KERNEL_LATENCY + LANZCOS3_KERNEL_LATENCY;
+
+        // Say the oversampling amount is 4, then an uncompensated stage latency of 8 results in 0
+        // additional samples of delay, 9 in 3, 10 in 2, 11 in 1, 12 in 0, etc. This is added to the
+        // upsampling filter.
+        let additional_delay_required = (-(uncompensated_stage_latency as isize))
+            .rem_euclid(oversampling_amount as isize)
+            as usize;
+
+        Self {
+            upsampling_rb: vec![0.0; LANCZOS3_UPSAMPLING_KERNEL.len() + additional_delay_required],
+            upsampling_write_pos: 0,
+            additional_upsampling_latency: additional_delay_required,
+
+            downsampling_rb: [0.0; LANCZOS3_DOWNSAMPLING_KERNEL.len()],
+            downsampling_write_pos: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        // Resetting the positions is not needed, but it also doesn't hurt
+        self.upsampling_rb.fill(0.0);
+        self.upsampling_write_pos = 0;
+
+        self.downsampling_rb.fill(0.0);
+        self.downsampling_write_pos = 0;
+    }
+
+    /// The total (upsampling+downsampling) latency of this filter, in samples at the stage's
+    /// oversampled sample rate.
+    pub fn latency(&self) -> usize {
+        LANZCOS3_KERNEL_LATENCY + LANZCOS3_KERNEL_LATENCY + self.additional_upsampling_latency
+    }
+
+    /// Upsample `input` 2x and write the results to `output`, which is twice as long as `input`.
+    pub fn upsample(&mut self, input: &[f32], output: &mut [f32]) {
+        nih_debug_assert_eq!(output.len(), input.len() * 2);
+
+        // We'll first zero-stuff the input, and then run that through the lanczos halfband filter
+        for (input_sample_idx, input_sample) in input.iter().enumerate() {
+            let output_sample_idx = input_sample_idx * 2;
+            output[output_sample_idx] = *input_sample;
+            output[output_sample_idx + 1] = 0.0;
+        }
+
+        // The zero-stuffed input is now run through the lanczos filter, which is a windowed sinc
+        // filter where every even tap has a value of zero. That means that if the filter is
+        // centered on a non-zero sample, the output must be equal to that sample and we can thus
+        // skip the convolution step entirely. Another important consideration is that we are
+        // imposing an additional `self.additional_upsampling_latency` samples of delay on the input
+        // to make sure the effective latency of the oversampling is always an integer amount.
+        let mut direct_read_pos =
+            (self.upsampling_write_pos + LANZCOS3_KERNEL_LATENCY) % self.upsampling_rb.len();
+        for (output_sample_idx, output_sample) in output.iter_mut().enumerate() {
+            // For a more intuitive description, imagine that `self.additional_upsampling_latency`
+            // is 2, and `self.upsampling_write_pos` is currently 0. For an 11-tap filter (like the
+            // lanczos3 kernel with the zero points removed from both ends), the situation after
+            // this statement would look like this:
+            //
+            // [n, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+            //  ^-- self.upsampling_write_pos
+            self.upsampling_rb[self.upsampling_write_pos] = *output_sample;
+
+            // The read/write head position needs to be incremented before filtering so that the
+            // just-added sample becomes the last sample in the ring buffer (if the additional
+            // latency/delay is 0)
+            self.upsampling_write_pos += 1;
+            if self.upsampling_write_pos == self.upsampling_rb.len() {
+                self.upsampling_write_pos = 0;
+            }
+
+            direct_read_pos += 1;
+            if direct_read_pos == self.upsampling_rb.len() {
+                direct_read_pos = 0;
+            }
+
+            // We can now read starting from the new `self.upsampling_write_pos`. This will cause
+            // the output to be delayed by `self.additional_upsampling_latency` samples. The range
+            // used for convolution is visualized below. It in this example it takes 2 additional
+            // iterations of this loop before sample `n` is considered again. Even output samples
+            // can directly be read from the ring buffer without convolution at the visualized
+            // offset.
+            //
+            // [n, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+            //     ^--------------^---------------^
+            //                    └- direct_read_position
+            //
+            // NOTE: 'Even samples' is considered from the perspective of a zero latency filter. In
+            //       this case the evenness of the filter's latency also needs to be considered. If
+            //       it's odd then the direct reading should also happen for odd indexed samples.
+            *output_sample = if output_sample_idx % 2 == (LANZCOS3_KERNEL_LATENCY % 2) {
+                nih_debug_assert_eq!(
+                    self.upsampling_rb[(direct_read_pos + self.upsampling_rb.len() - 1)
+                        % self.upsampling_rb.len()],
+                    0.0
+                );
+                nih_debug_assert_eq!(
+                    self.upsampling_rb[(direct_read_pos + 1) % self.upsampling_rb.len()],
+                    0.0
+                );
+
+                self.upsampling_rb[direct_read_pos]
+            } else {
+                convolve_rb(
+                    &self.upsampling_rb,
+                    &LANCZOS3_UPSAMPLING_KERNEL,
+                    self.upsampling_write_pos,
+                )
+            };
+        }
+    }
+
+    /// Downsample `input` by a factor of two and write the results to `output`, which is half as
+    /// long as `input`.
+    pub fn downsample(&mut self, input: &[f32], output: &mut [f32]) {
+        nih_debug_assert_eq!(input.len(), output.len() * 2);
+
+        // The additional delay to make the latency integer has already been taken into account in
+        // the upsampling part, so the downsampling is more straightforward
+        for (input_sample_idx, input_sample) in input.iter().enumerate() {
+            self.downsampling_rb[self.downsampling_write_pos] = *input_sample;
+
+            // The read/write head position needs to be incremented before filtering so that the
+            // just-added sample becomes the last sample in the ring buffer
+            self.downsampling_write_pos += 1;
+            if self.downsampling_write_pos == LANCZOS3_DOWNSAMPLING_KERNEL.len() {
+                self.downsampling_write_pos = 0;
+            }
+
+            // Because downsampling by a factor of two is filtering followed by decimation (where
+            // you take every even sample), we only need to compute the filtered output for the even
+            // samples. This is similar to how we only need to filter half the samples in the
+            // upsampling step.
+            if input_sample_idx % 2 == 0 {
+                let output_sample_idx = input_sample_idx / 2;
+                output[output_sample_idx] = convolve_rb(
+                    &self.downsampling_rb,
+                    // NOTE: This is `LANCZOS3_UPSAMPLING_KERNEL`, but with a factor two gain
+                    //       decrease to compensate for the 2x gain increase that happened during
+                    //       the upsampling
+                    &LANCZOS3_DOWNSAMPLING_KERNEL,
+                    self.downsampling_write_pos,
+                )
+            }
+        }
+    }
+}
+
+impl AllpassHalfbandFilter {
+    /// Create the filter state for a stage using the specified allpass coefficients.
+    pub fn new(coefficients: &'static [f32]) -> Self {
+        Self {
+            coefficients,
+
+            upsampling_state: vec![AllpassState::default(); coefficients.len()],
+            downsampling_state: vec![AllpassState::default(); coefficients.len()],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.upsampling_state.fill(AllpassState::default());
+        self.downsampling_state.fill(AllpassState::default());
+    }
+
+    /// The total (upsampling+downsampling) group delay at DC of this filter, in samples at the
+    /// stage's oversampled sample rate. This is not an integer.
+    pub fn latency(&self) -> f32 {
+        // A first order allpass filter with coefficient `a` has a group delay of
+        // `(1 - a) / (1 + a)` samples at DC. The allpass filters run at half the oversampled sample
+        // rate, so this needs to be doubled. At DC the two paths are in phase, so the halfband
+        // filter's group delay is the average of the two paths' group delays. Both the upsampling
+        // and the downsampling filters add that amount of delay, and the single sample offset
+        // between the two paths cancels out between the two. That leaves the sum of both paths'
+        // group delays.
+        self.coefficients
+            .iter()
+            .map(|a| 2.0 * (1.0 - a) / (1.0 + a))
+            .sum()
+    }
+
+    /// Upsample `input` 2x and write the results to `output`, which is twice as long as `input`.
+    pub fn upsample(&mut self, input: &[f32], output: &mut [f32]) {
+        nih_debug_assert_eq!(output.len(), input.len() * 2);
+
+        // Every input sample is fed to both allpass paths, and the two outputs are interleaved.
+        // This is equivalent to zero-stuffing the input and then filtering it with the halfband
+        // filter at the oversampled rate, with the zero-stuffing's 2x gain reduction compensated.
+        for (input_sample, output_samples) in input.iter().zip(output.chunks_exact_mut(2)) {
+            let (even, odd) = process_allpass_paths(
+                self.coefficients,
+                &mut self.upsampling_state,
+                *input_sample,
+                *input_sample,
+            );
+
+            output_samples[0] = even;
+            output_samples[1] = odd;
+        }
+    }
+
+    /// Downsample `input` by a factor of two and write the results to `output`, which is half as
+    /// long as `input`.
+    pub fn downsample(&mut self, input: &[f32], output: &mut [f32]) {
+        nih_debug_assert_eq!(input.len(), output.len() * 2);
+
+        // This is the reverse of the upsampling. Every pair of input samples is split between the
+        // two allpass paths, and the averaged result is the filtered and decimated output.
+        for (input_samples, output_sample) in input.chunks_exact(2).zip(output.iter_mut()) {
+            let (even, odd) = process_allpass_paths(
+                self.coefficients,
+                &mut self.downsampling_state,
+                input_samples[1],
+                input_samples[0],
+            );
+
+            *output_sample = (even + odd) * 0.5;
+        }
+    }
+}
+
+/// Run `even` and `odd` through the two allpass paths formed by `coefficients`. The coefficients
+/// with an even index are applied to `even`, and the coefficients with an odd index are applied to
+/// `odd`.
+#[inline]
+fn process_allpass_paths(
+    coefficients: &[f32],
+    states: &mut [AllpassState],
+    mut even: f32,
+    mut odd: f32,
+) -> (f32, f32) {
+    nih_debug_assert_eq!(coefficients.len(), states.len());
+
+    for (coefficient_idx, (coefficient, state)) in
+        coefficients.iter().zip(states.iter_mut()).enumerate()
+    {
+        let sample = if coefficient_idx % 2 == 0 {
+            &mut even
+        } else {
+            &mut odd
+        };
+
+        // This is the first order allpass filter `(a + z^-1) / (1 + a * z^-1)`
+        let output = coefficient * (*sample - state.y1) + state.x1;
+        state.x1 = *sample;
+        state.y1 = output;
+
+        *sample = output;
+    }
+
+    (even, odd)
+}
+
+/// Convolve `input_ring_buffer` with `kernel`, with `input_ring_buffer` rotated so that it starts
+/// at `ring_buffer_pos` and then wraps back around to the start.
+///
+/// # Panics
+///
+/// Assumes `input_ring_buffer` and `kernel` have the same length. May panic if they don't.
+fn convolve_rb(input_ring_buffer: &[f32], kernel: &[f32], ring_buffer_pos: usize) -> f32 {
+    let mut total = 0.0;
+
+    nih_debug_assert!(input_ring_buffer.len() >= kernel.len());
+
+    // This is straightforward convolution. Could be implemented much more efficiently, but for our
+    // 11-tap filter this works fine
+    let num_samples_until_wraparound =
+        (input_ring_buffer.len() - ring_buffer_pos).min(kernel.len());
+    for (read_pos_offset, kernel_sample) in kernel
+        .iter()
+        .rev()
+        .take(num_samples_until_wraparound)
+        .enumerate()
+    {
+        total += kernel_sample * input_ring_buffer[ring_buffer_pos + read_pos_offset];
+    }
+
+    for (read_pos, kernel_sample) in kernel
+        .iter()
+        .rev()
+        // Needs to happen before the `enumerate`
+        .skip(num_samples_until_wraparound)
+        .enumerate()
+    {
+        total += kernel_sample * input_ring_buffer[read_pos];
+    }
+
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod convolve_rb {
+        use super::*;
+
+        #[test]
+        fn test_with_wrap() {
+            let input_rb = [1.0, 2.0, -3.0, 4.0];
+            let kernel = [1.0, 2.0, -0.0, -1.0];
+            let input_pos = 2;
+
+            // This should be `(-3.0 * -1.0) + (4.0 * 0.0) + (1.0 * 2.0) + (2.0 * 1.0) = 7.0`
+            let result = convolve_rb(&input_rb, &kernel, input_pos);
+            assert_eq!(result, 7.0);
+        }
+
+        #[test]
+        fn test_no_wrap() {
+            let input_rb = [1.0, 2.0, -3.0, 4.0];
+            let kernel = [1.0, 2.0, 0.0, -1.0];
+            let input_pos = 0;
+
+            // This should be `(1.0 * -1.0) + (2.0 * 0.0) + (-3.0 * 2.0) + (4.0 * 1.0) = 7.0`
+            let result = convolve_rb(&input_rb, &kernel, input_pos);
+            assert_eq!(result, -3.0);
+        }
+    }
+
+    mod oversampling {
+        use super::*;
+        use crate::buffer::with_buffer;
+
+        fn argmax(iter: impl IntoIterator<Item = f32>) -> usize {
+            iter.into_iter()
+                .enumerate()
+                .max_by(|(_, value_a), (_, value_b)| value_a.total_cmp(value_b))
+                .unwrap()
+                .0
+        }
+
+        /// Makes sure that the reported latency is correct and is (more or less) an integer value
+        fn test_latency(oversampling_factor: usize) {
+            let mut delta_impulse = [0.0f32; 64];
+            delta_impulse[0] = 1.0;
+
+            let mut oversampler = Oversampler::new(
+                1,
+                delta_impulse.len(),
+                oversampling_factor,
+                OversamplingFilter::LinearPhase,
+            );
+
+            let reported_latency = oversampler.latency(oversampling_factor) as usize;
+            assert!(
+                delta_impulse.len() > reported_latency,
+                "The delta impulse array is too small to test the latency at oversampling factor \
+                 {oversampling_factor}, this is an error with the test case"
+            );
+
+            oversampler.process_channel(0, &mut delta_impulse, oversampling_factor, |_| ());
+
+            let new_impulse_idx = argmax(delta_impulse);
+            assert_eq!(new_impulse_idx, reported_latency);
+
+            // The latency should also not be fractional
+            assert!(delta_impulse[new_impulse_idx] > delta_impulse[new_impulse_idx - 1]);
+            assert!(delta_impulse[new_impulse_idx] > delta_impulse[new_impulse_idx + 1]);
+        }
+
+        /// Checks whether the output matches the input when compensating for the latency. Also
+        /// applies a gain offset to make sure the process callback actually works. The
+        /// minimum-phase filters' latency is only exact at DC, so a lower frequency is used for
+        /// those.
+        fn test_sine_output(
+            oversampling_factor: usize,
+            filter: OversamplingFilter,
+            frequency: f32,
+        ) {
+            // The gain applied to the oversampled version
+            const GAIN: f32 = 2.0;
+
+            let mut input = [0.0f32; 128];
+            for (i, sample) in input.iter_mut().enumerate() {
+                *sample = (i as f32 * (frequency * 2.0 * std::f32::consts::PI)).sin();
+            }
+
+            let mut output = input;
+            let mut oversampler = Oversampler::new(1, output.len(), oversampling_factor, filter);
+            oversampler.process_channel(0, &mut output, oversampling_factor, |upsampled| {
+                for sample in upsampled {
+                    *sample *= GAIN;
+                }
+            });
+
+            let reported_latency = oversampler.latency(oversampling_factor) as usize;
+            for (input_sample_idx, input_sample) in input
+                .into_iter()
+                .enumerate()
+                // The minimum-phase filters need some time to settle
+                .skip(32)
+                .take(input.len() - reported_latency - 32)
+            {
+                let output_sample_idx = input_sample_idx + reported_latency;
+                let output_sample = output[output_sample_idx];
+
+                // There can be quite a big difference between the input and output thanks to the
+                // filter's ringing
+                approx::assert_relative_eq!(input_sample * GAIN, output_sample, epsilon = 0.1);
+            }
+        }
+
+        /// Makes sure every channel in a block gets processed with its own state.
+        #[test]
+        fn process_block_multichannel() {
+            let mut channels = [[0.0f32; 32], [0.0f32; 32]];
+            channels[0][0] = 1.0;
+            channels[1][4] = 1.0;
+
+            let mut oversampler = Oversampler::new(2, 32, 2, OversamplingFilter::LinearPhase);
+            let reported_latency = oversampler.latency(2) as usize;
+            with_buffer(&mut channels, |buffer| {
+                for (_, mut block) in buffer.iter_blocks(32) {
+                    oversampler.process_block(&mut block, 2, |channel_idx, upsampled| {
+                        assert_eq!(upsampled.len(), 32 * 4);
+
+                        for sample in upsampled {
+                            *sample *= (channel_idx + 1) as f32;
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(argmax(channels[0]), reported_latency);
+            assert_eq!(argmax(channels[1]), reported_latency + 4);
+            approx::assert_relative_eq!(
+                channels[1][reported_latency + 4],
+                2.0 * channels[0][reported_latency],
+                epsilon = 1e-5
+            );
+        }
+
+        #[test]
+        fn latency_2x() {
+            test_latency(1);
+        }
+
+        #[test]
+        fn latency_4x() {
+            test_latency(2);
+        }
+
+        #[test]
+        fn latency_8x() {
+            test_latency(3);
+        }
+
+        #[test]
+        fn latency_16x() {
+            test_latency(4);
+        }
+
+        #[test]
+        fn sine_output_2x() {
+            test_sine_output(1, OversamplingFilter::LinearPhase, 0.125);
+        }
+
+        #[test]
+        fn sine_output_4x() {
+            test_sine_output(2, OversamplingFilter::LinearPhase, 0.125);
+        }
+
+        #[test]
+        fn sine_output_8x() {
+            test_sine_output(3, OversamplingFilter::LinearPhase, 0.125);
+        }
+
+        #[test]
+        fn sine_output_16x() {
+            test_sine_output(4, OversamplingFilter::LinearPhase, 0.125);
+        }
+
+        #[test]
+        fn minimum_phase_sine_output_2x() {
+            test_sine_output(1, OversamplingFilter::MinimumPhase, 0.01);
+        }
+
+        #[test]
+        fn minimum_phase_sine_output_4x() {
+            test_sine_output(2, OversamplingFilter::MinimumPhase, 0.01);
+        }
+
+        #[test]
+        fn minimum_phase_sine_output_8x() {
+            test_sine_output(3, OversamplingFilter::MinimumPhase, 0.01);
+        }
+
+        #[test]
+        fn minimum_phase_sine_output_16x() {
+            test_sine_output(4, OversamplingFilter::MinimumPhase, 0.01);
+        }
+
+        /// The stopband attenuation of the minimum-phase stages should match the numbers listed in
+        /// the documentation for [`ALLPASS_HALFBAND_COEFFICIENTS`].
+        #[test]
+        fn minimum_phase_stopband_attenuation() {
+            // The transition bandwidth and stopband attenuation in decibels for every stage
+            let specs = [(0.01, 69.1), (0.13, 79.0), (0.19, 74.2), (0.22, 81.6)];
+            for (coefficients, (transition_bandwidth, attenuation_db)) in
+                ALLPASS_HALFBAND_COEFFICIENTS.iter().zip(specs)
+            {
+                let stopband_start = 0.25 + (transition_bandwidth / 2.0);
+                let max_gain = (0..=1000)
+                    .map(|i| {
+                        let frequency =
+                            stopband_start + ((0.5 - stopband_start) * i as f64 / 1000.0);
+                        halfband_magnitude_response(coefficients, frequency)
+                    })
+                    .fold(0.0, f64::max);
+
+                // The documented numbers are rounded to a single decimal
+                assert!(-20.0 * max_gain.log10() >= attenuation_db - 0.05);
+            }
+        }
+
+        /// The magnitude response of a polyphase IIR halfband filter at `frequency`, relative to
+        /// the filter's sample rate. Both allpass paths have unity gain, so only their phase
+        /// difference matters.
+        fn halfband_magnitude_response(coefficients: &[f32], frequency: f64) -> f64 {
+            let omega = std::f64::consts::TAU * frequency;
+            let (sin_2omega, cos_2omega) = (2.0 * omega).sin_cos();
+
+            // The phase response of the `(a + z^-2) / (1 + a * z^-2)` allpass sections
+            let mut path_phases = [0.0, 0.0];
+            for (coefficient_idx, &coefficient) in coefficients.iter().enumerate() {
+                let a = coefficient as f64;
+                path_phases[coefficient_idx % 2] += (-sin_2omega).atan2(a + cos_2omega)
+                    - (-a * sin_2omega).atan2(1.0 + a * cos_2omega);
+            }
+
+            // The second path is delayed by one sample
+            ((path_phases[0] - path_phases[1] + omega) / 2.0)
+                .cos()
+                .abs()
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Phase vocoder building blocks for pitch shifting and time stretching effects built on top of
+//! [`StftHelper`][super::StftHelper].
+
+use std::f32;
+
+/// Keeps track of the phases needed to implement a phase vocoder. A phase vocoder estimates the
+/// true frequency of the sinusoid in each FFT bin from the phase difference between two
+/// consecutive STFT frames, and it resynthesizes sinusoids at (modified) frequencies by
+/// accumulating phase advances between frames. Frequencies are expressed in fractional bins, which
+/// can be converted to and from Hertz using [`bin_to_frequency()`] and [`frequency_to_bin()`].
+///
+/// This only deals with the phases, so it works with any FFT library. Use something like
+/// `Complex32::arg()` to get the phases from an FFT's output, and `Complex32::from_polar()` to
+/// combine the synthesized phases with the bins' magnitudes before the inverse FFT.
+///
+/// # Example
+///
+/// A (very basic) octave up pitch shifter:
+///
+/// ```ignore
+/// // `self.magnitudes` and `self.phases` were computed from the FFT's output
+/// self.phase_vocoder.analyze(channel_idx, &self.phases, &mut self.frequencies);
+///
+/// self.shifted_magnitudes.fill(0.0);
+/// self.shifted_frequencies.fill(0.0);
+/// for bin_idx in 0..self.frequencies.len() / 2 {
+///     self.shifted_magnitudes[bin_idx * 2] += self.magnitudes[bin_idx];
+///     self.shifted_frequencies[bin_idx * 2] = self.frequencies[bin_idx] * 2.0;
+/// }
+///
+/// self.phase_vocoder.synthesize(channel_idx, &self.shifted_frequencies, &mut self.phases);
+/// // `self.shifted_magnitudes` and `self.phases` can now be converted back to complex numbers
+/// // for the inverse FFT
+/// ```
+#[derive(Debug, Default)]
+pub struct PhaseVocoder {
+    /// The phases from the last analyzed frame for every channel, indexed by
+    /// `[channel_idx][bin_idx]`.
+    previous_phases: Vec<Vec<f32>>,
+    /// The phases that were last output by [`synthesize()`][Self::synthesize()] for every channel,
+    /// indexed by `[channel_idx][bin_idx]`.
+    accumulated_phases: Vec<Vec<f32>>,
+
+    /// The size of the FFT window, in samples.
+    window_size: usize,
+    /// The number of samples between two consecutive STFT frames.
+    hop_size: usize,
+}
+
+impl PhaseVocoder {
+    /// Create a phase vocoder for `num_channels` channels of a real valued FFT with the specified
+    /// window size and hop size. If the plugin uses an overlap amount then the hop size is the
+    /// window size divided by the overlap amount.
+    pub fn new(num_channels: usize, window_size: usize, hop_size: usize) -> Self {
+        let mut phase_vocoder = Self::default();
+        phase_vocoder.resize(num_channels, window_size, hop_size);
+
+        phase_vocoder
+    }
+
+    /// Change the number of channels, the window size, or the hop size. This also resets the phase
+    /// vocoder. This only allocates when the number of channels or the window size grow, so
+    /// switching to smaller window sizes can be done from the audio thread. Reducing the number of
+    /// channels deallocates the removed channels' buffers, so that should not be done from the
+    /// audio thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window_size` or `hop_size` is zero.
+    pub fn resize(&mut self, num_channels: usize, window_size: usize, hop_size: usize) {
+        assert_ne!(window_size, 0);
+        assert_ne!(hop_size, 0);
+
+        let num_bins = window_size / 2 + 1;
+        for phases in [&mut self.previous_phases, &mut self.accumulated_phases] {
+            phases.resize_with(num_channels, Vec::new);
+            for channel_phases in phases.iter_mut() {
+                channel_phases.resize(num_bins, 0.0);
+            }
+        }
+
+        self.window_size = window_size;
+        self.hop_size = hop_size;
+
+        self.reset();
+    }
+
+    /// Clear the stored phases.
+    pub fn reset(&mut self) {
+        for channel_phases in self
+            .previous_phases
+            .iter_mut()
+            .chain(self.accumulated_phases.iter_mut())
+        {
+            channel_phases.fill(0.0);
+        }
+    }
+
+    /// The number of bins the phase vocoder expects, which is `window_size / 2 + 1`.
+    pub fn num_bins(&self) -> usize {
+        self.window_size / 2 + 1
+    }
+
+    /// Estimate the true frequency of each bin for the next frame of channel `channel_idx`.
+    /// `phases` contains the phases of the frame's FFT bins in radians. The estimated frequencies
+    /// are written to `frequencies` as fractional bin indices. For a stationary sinusoid these are
+    /// exact, even if the sinusoid's frequency falls between two bins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel index is out of bounds, or if the slices don't contain exactly
+    /// [`num_bins()`][Self::num_bins()] elements.
+    pub fn analyze(&mut self, channel_idx: usize, phases: &[f32], frequencies: &mut [f32]) {
+        let previous_phases = &mut self.previous_phases[channel_idx];
+        assert_eq!(phases.len(), previous_phases.len());
+        assert_eq!(frequencies.len(), previous_phases.len());
+
+        let phase_advance_per_bin = expected_phase_advance(1.0, self.window_size, self.hop_size);
+        for (bin_idx, ((phase, previous_phase), frequency)) in phases
+            .iter()
+            .zip(previous_phases.iter_mut())
+            .zip(frequencies.iter_mut())
+            .enumerate()
+        {
+            // The deviation from the phase advance expected for a sinusoid at exactly the bin's
+            // center frequency tells us how far the actual frequency is from that center
+            let expected_advance = bin_idx as f32 * phase_advance_per_bin;
+            let deviation = wrap_phase(phase - *previous_phase - expected_advance);
+            *frequency = bin_idx as f32 + (deviation / phase_advance_per_bin);
+
+            *previous_phase = *phase;
+        }
+    }
+
+    /// Advance the synthesis phases for channel `channel_idx` by one hop using the frequencies in
+    /// `frequencies`, expressed as fractional bin indices. These are typically the frequencies
+    /// returned by [`analyze()`][Self::analyze()] after moving them to different bins and/or
+    /// scaling them. The resulting phases are written to `phases`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel index is out of bounds, or if the slices don't contain exactly
+    /// [`num_bins()`][Self::num_bins()] elements.
+    pub fn synthesize(&mut self, channel_idx: usize, frequencies: &[f32], phases: &mut [f32]) {
+        let accumulated_phases = &mut self.accumulated_phases[channel_idx];
+        assert_eq!(frequencies.len(), accumulated_phases.len());
+        assert_eq!(phases.len(), accumulated_phases.len());
+
+        let phase_advance_per_bin = expected_phase_advance(1.0, self.window_size, self.hop_size);
+        for ((frequency, accumulated_phase), phase) in frequencies
+            .iter()
+            .zip(accumulated_phases.iter_mut())
+            .zip(phases.iter_mut())
+        {
+            // Wrapping keeps the phases from growing large enough to lose precision
+            *accumulated_phase = wrap_phase(*accumulated_phase + frequency * phase_advance_per_bin);
+            *phase = *accumulated_phase;
+        }
+    }
+}
+
+/// The phase in radians a sinusoid at `bin` (which may be fractional) advances by between two
+/// consecutive STFT frames.
+#[inline]
+pub fn expected_phase_advance(bin: f32, window_size: usize, hop_size: usize) -> f32 {
+    f32::consts::TAU * bin * hop_size as f32 / window_size as f32
+}
+
+/// Wrap a phase in radians to the `[-pi, pi]` range.
+#[inline]
+pub fn wrap_phase(phase: f32) -> f32 {
+    phase - (f32::consts::TAU * (phase / f32::consts::TAU).round())
+}
+
+/// Convert a (fractional) bin index to a frequency in Hertz.
+#[inline]
+pub fn bin_to_frequency(bin: f32, window_size: usize, sample_rate: f32) -> f32 {
+    bin * sample_rate / window_size as f32
+}
+
+/// Convert a frequency in Hertz to a fractional bin index.
+#[inline]
+pub fn frequency_to_bin(frequency: f32, window_size: usize, sample_rate: f32) -> f32 {
+    frequency * window_size as f32 / sample_rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW_SIZE: usize = 64;
+    const HOP_SIZE: usize = 16;
+
+    /// The phases of a sinusoid at the fractional bin `bin` for the frame starting at
+    /// `frame_start`. Only the bins near the sinusoid matter for these tests, and for those bins
+    /// the phase of a windowed frame is the sinusoid's phase at the frame's start plus a constant
+    /// offset.
+    fn sinusoid_phases(bin: f32, frame_start: usize) -> Vec<f32> {
+        let phase = f32::consts::TAU * bin * frame_start as f32 / WINDOW_SIZE as f32;
+        vec![wrap_phase(phase + 0.3); WINDOW_SIZE / 2 + 1]
+    }
+
+    #[test]
+    fn analyze_estimates_fractional_frequencies() {
+        const BIN: f32 = 5.3;
+
+        let mut phase_vocoder = PhaseVocoder::new(1, WINDOW_SIZE, HOP_SIZE);
+        let mut frequencies = vec![0.0; phase_vocoder.num_bins()];
+        phase_vocoder.analyze(0, &sinusoid_phases(BIN, 0), &mut frequencies);
+        phase_vocoder.analyze(0, &sinusoid_phases(BIN, HOP_SIZE), &mut frequencies);
+
+        // Bins close enough to the sinusoid should pick up its exact frequency
+        for bin_idx in 4..=6 {
+            approx::assert_relative_eq!(frequencies[bin_idx], BIN, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn synthesize_accumulates_phase() {
+        const BIN: f32 = 5.3;
+
+        let mut phase_vocoder = PhaseVocoder::new(1, WINDOW_SIZE, HOP_SIZE);
+        let frequencies = vec![BIN; phase_vocoder.num_bins()];
+        let mut phases = vec![0.0; phase_vocoder.num_bins()];
+        for frame_idx in 1..10 {
+            phase_vocoder.synthesize(0, &frequencies, &mut phases);
+
+            let expected = wrap_phase(
+                f32::consts::TAU * BIN * (frame_idx * HOP_SIZE) as f32 / WINDOW_SIZE as f32,
+            );
+            approx::assert_relative_eq!(wrap_phase(phases[0] - expected), 0.0, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn frequency_bin_conversion() {
+        approx::assert_relative_eq!(bin_to_frequency(16.0, 1024, 48000.0), 750.0);
+        approx::assert_relative_eq!(frequency_to_bin(750.0, 1024, 48000.0), 16.0);
+    }
+}
@@ -2,10 +2,10 @@
 
 use std::cmp;
 
-use crate::buffer::{Block, Buffer};
+use crate::buffer::{Block, Buffer, Sample};
 
-/// Some buffer that can be used with the [`StftHelper`].
-pub trait StftInput {
+/// Some buffer that can be used with the [`StftHelper`]. `T` is the buffer's sample type.
+pub trait StftInput<T: Sample = f32> {
     /// The number of samples in this input.
     fn num_samples(&self) -> usize;
 
@@ -13,46 +13,54 @@ pub trait StftInput {
     fn num_channels(&self) -> usize;
 
     /// Index the buffer without any bounds checks.
-    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> f32;
+    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> T;
 }
 
 /// The same as [`StftInput`], but with support for writing results back to the buffer
-pub trait StftInputMut: StftInput {
+pub trait StftInputMut<T: Sample = f32>: StftInput<T> {
     /// Get a mutable reference to a sample in the buffer without any bounds checks.
-    unsafe fn get_sample_unchecked_mut(&mut self, channel: usize, sample_idx: usize) -> &mut f32;
+    unsafe fn get_sample_unchecked_mut(&mut self, channel: usize, sample_idx: usize) -> &mut T;
 }
 
 /// Process the input buffer in equal sized blocks, running a callback on each block to transform
 /// the block and then writing back the results from the previous block to the buffer. This
 /// introduces latency equal to the size of the block.
 ///
+/// The distance between two consecutive blocks can either be specified as an overlap amount, in
+/// which case the hop size is the block size divided by the overlap amount, or directly as a hop
+/// size using the `*_with_hop_size()` functions. The hop size does not need to divide the block
+/// size.
+///
 /// Additional inputs can be processed by setting the `NUM_SIDECHAIN_INPUTS` constant. These buffers
 /// will not be written to, so they are purely used for analysis. These sidechain inputs will have
-/// the same number of channels as the main input.
+/// the same number of channels as the main input. The helper works with `f32` samples by default,
+/// but it can also be used with `f64` buffers by setting `T` to `f64`.
 ///
 /// TODO: Better name?
 /// TODO: We may need something like this purely for analysis, e.g. for showing spectrums in a GUI.
 ///       Figure out the cleanest way to adapt this for the non-processing use case.
-pub struct StftHelper<const NUM_SIDECHAIN_INPUTS: usize = 0> {
+pub struct StftHelper<const NUM_SIDECHAIN_INPUTS: usize = 0, T: Sample = f32> {
     // These ring buffers store the input samples and the already processed output produced by
     // adding overlapping windows. Whenever we reach a new overlapping window, we'll write the
     // already calculated outputs to the main buffer passed to the process function and then process
     // a new block.
-    main_input_ring_buffers: Vec<Vec<f32>>,
-    main_output_ring_buffers: Vec<Vec<f32>>,
-    sidechain_ring_buffers: [Vec<Vec<f32>>; NUM_SIDECHAIN_INPUTS],
+    main_input_ring_buffers: Vec<Vec<T>>,
+    main_output_ring_buffers: Vec<Vec<T>>,
+    sidechain_ring_buffers: [Vec<Vec<T>>; NUM_SIDECHAIN_INPUTS],
 
     /// Results from the ring buffers are copied to this scratch buffer before being passed to the
     /// plugin. Needed to handle overlap.
-    scratch_buffer: Vec<f32>,
+    scratch_buffer: Vec<T>,
     /// If padding is used, then this will contain the previous iteration's values from the padding
     /// values in `scratch_buffer` (`scratch_buffer[(scratch_buffer.len() - padding -
     /// 1)..scratch_buffer.len()]`). This is then added to the ring buffer in the next iteration.
-    padding_buffers: Vec<Vec<f32>>,
+    padding_buffers: Vec<Vec<T>>,
 
-    /// The current position in our ring buffers. Whenever this wraps around to 0, we'll process
-    /// a block.
+    /// The current position in our ring buffers.
     current_pos: usize,
+    /// The number of samples that have been added to the ring buffers since the last block was
+    /// processed. A new block is processed whenever this reaches the hop size.
+    samples_since_last_block: usize,
     /// If padding is used, then this much extra capacity has been added to the buffers.
     padding: usize,
 }
@@ -60,7 +68,7 @@ pub struct StftHelper<const NUM_SIDECHAIN_INPUTS: usize = 0> {
 /// Marker struct for the version without sidechaining.
 struct NoSidechain;
 
-impl StftInput for Buffer<'_> {
+impl<T: Sample> StftInput<T> for Buffer<'_, T> {
     #[inline]
     fn num_samples(&self) -> usize {
         self.samples()
@@ -72,7 +80,7 @@ impl StftInput for Buffer<'_> {
     }
 
     #[inline]
-    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> f32 {
+    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> T {
         *self
             .as_slice_immutable()
             .get_unchecked(channel)
@@ -80,16 +88,16 @@ impl StftInput for Buffer<'_> {
     }
 }
 
-impl StftInputMut for Buffer<'_> {
+impl<T: Sample> StftInputMut<T> for Buffer<'_, T> {
     #[inline]
-    unsafe fn get_sample_unchecked_mut(&mut self, channel: usize, sample_idx: usize) -> &mut f32 {
+    unsafe fn get_sample_unchecked_mut(&mut self, channel: usize, sample_idx: usize) -> &mut T {
         self.as_slice()
             .get_unchecked_mut(channel)
             .get_unchecked_mut(sample_idx)
     }
 }
 
-impl StftInput for Block<'_, '_> {
+impl<T: Sample> StftInput<T> for Block<'_, '_, T> {
     #[inline]
     fn num_samples(&self) -> usize {
         self.samples()
@@ -101,20 +109,20 @@ impl StftInput for Block<'_, '_> {
     }
 
     #[inline]
-    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> f32 {
+    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> T {
         *self.get_unchecked(channel).get_unchecked(sample_idx)
     }
 }
 
-impl StftInputMut for Block<'_, '_> {
+impl<T: Sample> StftInputMut<T> for Block<'_, '_, T> {
     #[inline]
-    unsafe fn get_sample_unchecked_mut(&mut self, channel: usize, sample_idx: usize) -> &mut f32 {
+    unsafe fn get_sample_unchecked_mut(&mut self, channel: usize, sample_idx: usize) -> &mut T {
         self.get_unchecked_mut(channel)
             .get_unchecked_mut(sample_idx)
     }
 }
 
-impl StftInput for [&[f32]] {
+impl<T: Sample> StftInput<T> for [&[T]] {
     #[inline]
     fn num_samples(&self) -> usize {
         if self.is_empty() {
@@ -130,12 +138,12 @@ impl StftInput for [&[f32]] {
     }
 
     #[inline]
-    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> f32 {
+    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> T {
         *self.get_unchecked(channel).get_unchecked(sample_idx)
     }
 }
 
-impl StftInput for [&mut [f32]] {
+impl<T: Sample> StftInput<T> for [&mut [T]] {
     #[inline]
     fn num_samples(&self) -> usize {
         if self.is_empty() {
@@ -151,20 +159,20 @@ impl StftInput for [&mut [f32]] {
     }
 
     #[inline]
-    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> f32 {
+    unsafe fn get_sample_unchecked(&self, channel: usize, sample_idx: usize) -> T {
         *self.get_unchecked(channel).get_unchecked(sample_idx)
     }
 }
 
-impl StftInputMut for [&mut [f32]] {
+impl<T: Sample> StftInputMut<T> for [&mut [T]] {
     #[inline]
-    unsafe fn get_sample_unchecked_mut(&mut self, channel: usize, sample_idx: usize) -> &mut f32 {
+    unsafe fn get_sample_unchecked_mut(&mut self, channel: usize, sample_idx: usize) -> &mut T {
         self.get_unchecked_mut(channel)
             .get_unchecked_mut(sample_idx)
     }
 }
 
-impl StftInput for NoSidechain {
+impl<T: Sample> StftInput<T> for NoSidechain {
     fn num_samples(&self) -> usize {
         0
     }
@@ -173,12 +181,12 @@ impl StftInput for NoSidechain {
         0
     }
 
-    unsafe fn get_sample_unchecked(&self, _channel: usize, _sample_idx: usize) -> f32 {
-        0.0
+    unsafe fn get_sample_unchecked(&self, _channel: usize, _sample_idx: usize) -> T {
+        T::ZERO
     }
 }
 
-impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
+impl<const NUM_SIDECHAIN_INPUTS: usize, T: Sample> StftHelper<NUM_SIDECHAIN_INPUTS, T> {
     /// Initialize the [`StftHelper`] for [`Buffer`]s with the specified number of channels and the
     /// given maximum block size. When the option is set, then every yielded sample buffer will have
     /// this many zero samples appended at the end of the block. Call
@@ -196,18 +204,19 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
         assert_ne!(max_block_size, 0);
 
         Self {
-            main_input_ring_buffers: vec![vec![0.0; max_block_size]; num_channels],
-            main_output_ring_buffers: vec![vec![0.0; max_block_size]; num_channels],
+            main_input_ring_buffers: vec![vec![T::ZERO; max_block_size]; num_channels],
+            main_output_ring_buffers: vec![vec![T::ZERO; max_block_size]; num_channels],
             // Kinda hacky way to initialize an array of non-copy types
             sidechain_ring_buffers: [(); NUM_SIDECHAIN_INPUTS]
-                .map(|_| vec![vec![0.0; max_block_size]; num_channels]),
+                .map(|_| vec![vec![T::ZERO; max_block_size]; num_channels]),
 
             // When padding is used this scratch buffer will have a bunch of zeroes added to it
             // after copying a block of audio to it
-            scratch_buffer: vec![0.0; max_block_size + max_padding],
-            padding_buffers: vec![vec![0.0; max_padding]; num_channels],
+            scratch_buffer: vec![T::ZERO; max_block_size + max_padding],
+            padding_buffers: vec![vec![T::ZERO; max_padding]; num_channels],
 
             current_pos: 0,
+            samples_since_last_block: 0,
             padding: max_padding,
         }
     }
@@ -289,15 +298,40 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
         &mut self,
         main_buffer: &mut M,
         overlap_times: usize,
+        process_cb: F,
+    ) where
+        M: StftInputMut<T>,
+        F: FnMut(usize, &mut [T]),
+    {
+        assert!(overlap_times > 0);
+
+        let hop_size = self.main_input_ring_buffers[0].len() / overlap_times;
+        self.process_overlap_add_with_hop_size(main_buffer, hop_size, process_cb);
+    }
+
+    /// The same as [`process_overlap_add()`][Self::process_overlap_add()], but processing a new
+    /// block every `hop_size` samples instead of using a fixed amount of overlap. The hop size does
+    /// not need to divide the block size. Keep in mind that the window function and the gain
+    /// compensation need to sum to a constant at this hop size for the output to be free of
+    /// amplitude modulation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hop_size` is zero or if it is larger than the block size, in addition to the
+    /// conditions mentioned in [`process_overlap_add()`][Self::process_overlap_add()].
+    pub fn process_overlap_add_with_hop_size<M, F>(
+        &mut self,
+        main_buffer: &mut M,
+        hop_size: usize,
         mut process_cb: F,
     ) where
-        M: StftInputMut,
-        F: FnMut(usize, &mut [f32]),
+        M: StftInputMut<T>,
+        F: FnMut(usize, &mut [T]),
     {
-        self.process_overlap_add_sidechain(
+        self.process_overlap_add_sidechain_with_hop_size(
             main_buffer,
             [&NoSidechain; NUM_SIDECHAIN_INPUTS],
-            overlap_times,
+            hop_size,
             |channel_idx, sidechain_idx, real_fft_scratch_buffer| {
                 if sidechain_idx.is_none() {
                     process_cb(channel_idx, real_fft_scratch_buffer);
@@ -316,31 +350,58 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
         main_buffer: &mut M,
         sidechain_buffers: [&S; NUM_SIDECHAIN_INPUTS],
         overlap_times: usize,
+        process_cb: F,
+    ) where
+        M: StftInputMut<T>,
+        S: StftInput<T>,
+        F: FnMut(usize, Option<usize>, &mut [T]),
+    {
+        assert!(overlap_times > 0);
+
+        let hop_size = self.main_input_ring_buffers[0].len() / overlap_times;
+        self.process_overlap_add_sidechain_with_hop_size(
+            main_buffer,
+            sidechain_buffers,
+            hop_size,
+            process_cb,
+        );
+    }
+
+    /// The same as [`process_overlap_add_sidechain()`][Self::process_overlap_add_sidechain()], but
+    /// with an arbitrary hop size like in
+    /// [`process_overlap_add_with_hop_size()`][Self::process_overlap_add_with_hop_size()].
+    pub fn process_overlap_add_sidechain_with_hop_size<M, S, F>(
+        &mut self,
+        main_buffer: &mut M,
+        sidechain_buffers: [&S; NUM_SIDECHAIN_INPUTS],
+        hop_size: usize,
         mut process_cb: F,
     ) where
-        M: StftInputMut,
-        S: StftInput,
-        F: FnMut(usize, Option<usize>, &mut [f32]),
+        M: StftInputMut<T>,
+        S: StftInput<T>,
+        F: FnMut(usize, Option<usize>, &mut [T]),
     {
         assert_eq!(
             main_buffer.num_channels(),
             self.main_input_ring_buffers.len()
         );
-        assert!(overlap_times > 0);
+        let block_size = self.main_input_ring_buffers[0].len();
+        assert!(hop_size > 0 && hop_size <= block_size);
 
         // We'll copy samples from `*_buffer` into `*_ring_buffers` while simultaneously copying
         // already processed samples from `main_ring_buffers` in into `main_buffer`
         let main_buffer_len = main_buffer.num_samples();
         let num_channels = main_buffer.num_channels();
-        let block_size = self.main_input_ring_buffers[0].len();
-        let window_interval = (block_size / overlap_times) as i32;
         let mut already_processed_samples = 0;
         while already_processed_samples < main_buffer_len {
             let remaining_samples = main_buffer_len - already_processed_samples;
-            let samples_until_next_window = ((window_interval - self.current_pos as i32 - 1)
-                .rem_euclid(window_interval)
-                + 1) as usize;
-            let samples_to_process = samples_until_next_window.min(remaining_samples);
+            let samples_until_next_window = hop_size - (self.samples_since_last_block % hop_size);
+            // If the hop size does not divide the block size, then the ring buffers may wrap around
+            // before the next window
+            let samples_until_wrap = block_size - self.current_pos;
+            let samples_to_process = samples_until_next_window
+                .min(samples_until_wrap)
+                .min(remaining_samples);
 
             // Copy the input from `main_buffer` to the ring buffer while copying last block's
             // result from the buffer to `main_buffer`
@@ -368,7 +429,7 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
                     *input_ring_buffer_sample = *sample;
                     *sample = *output_ring_buffer_sample;
                     // Very important, or else we'll overlap-add ourselves into a feedback hell
-                    *output_ring_buffer_sample = 0.0;
+                    *output_ring_buffer_sample = T::ZERO;
                 }
             }
 
@@ -397,11 +458,14 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
 
             already_processed_samples += samples_to_process;
             self.current_pos = (self.current_pos + samples_to_process) % block_size;
+            self.samples_since_last_block += samples_to_process;
 
             // At this point we either have `already_processed_samples == main_buffer_len`, or
-            // `self.current_pos % window_interval == 0`. If it's the latter, then we can process a
-            // new block.
+            // we've added another `hop_size` samples to the ring buffers. If it's the latter, then
+            // we can process a new block.
             if samples_to_process == samples_until_next_window {
+                self.samples_since_last_block = 0;
+
                 // Because we're processing in smaller windows, the input ring buffers sadly does
                 // not always contain the full contiguous range we're interested in because they map
                 // wrap around. Because premade FFT algorithms typically can't handle this, we'll
@@ -419,7 +483,7 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
                             sidechain_ring_buffer,
                         );
                         if self.padding > 0 {
-                            self.scratch_buffer[block_size..].fill(0.0);
+                            self.scratch_buffer[block_size..].fill(T::ZERO);
                         }
 
                         process_cb(channel_idx, Some(sidechain_idx), &mut self.scratch_buffer);
@@ -439,7 +503,7 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
                         input_ring_buffer,
                     );
                     if self.padding > 0 {
-                        self.scratch_buffer[block_size..].fill(0.0);
+                        self.scratch_buffer[block_size..].fill(T::ZERO);
                     }
 
                     process_cb(channel_idx, None, &mut self.scratch_buffer);
@@ -463,7 +527,7 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
                         padding_buffer.copy_within(padding_to_copy.., 0);
 
                         // And we obviously don't want this to feedback
-                        padding_buffer[self.padding - padding_to_copy..].fill(0.0);
+                        padding_buffer[self.padding - padding_to_copy..].fill(T::ZERO);
                     }
 
                     // The actual overlap-add part of the equation
@@ -493,30 +557,44 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
     /// STFT part. `buffer` will only ever be read from. This can be useful for providing FFT data
     /// for a spectrum analyzer in a plugin GUI. These is still a delay to the analysis equal to the
     /// block size.
-    pub fn process_analyze_only<B, F>(
+    pub fn process_analyze_only<B, F>(&mut self, buffer: &B, overlap_times: usize, analyze_cb: F)
+    where
+        B: StftInput<T>,
+        F: FnMut(usize, &mut [T]),
+    {
+        assert!(overlap_times > 0);
+
+        let hop_size = self.main_input_ring_buffers[0].len() / overlap_times;
+        self.process_analyze_only_with_hop_size(buffer, hop_size, analyze_cb);
+    }
+
+    /// The same as [`process_analyze_only()`][Self::process_analyze_only()], but with an arbitrary
+    /// hop size like in
+    /// [`process_overlap_add_with_hop_size()`][Self::process_overlap_add_with_hop_size()].
+    pub fn process_analyze_only_with_hop_size<B, F>(
         &mut self,
         buffer: &B,
-        overlap_times: usize,
+        hop_size: usize,
         mut analyze_cb: F,
     ) where
-        B: StftInput,
-        F: FnMut(usize, &mut [f32]),
+        B: StftInput<T>,
+        F: FnMut(usize, &mut [T]),
     {
         assert_eq!(buffer.num_channels(), self.main_input_ring_buffers.len());
-        assert!(overlap_times > 0);
+        let block_size = self.main_input_ring_buffers[0].len();
+        assert!(hop_size > 0 && hop_size <= block_size);
 
-        // See `process_overlap_add_sidechain` for an annotated version
+        // See `process_overlap_add_sidechain_with_hop_size` for an annotated version
         let main_buffer_len = buffer.num_samples();
         let num_channels = buffer.num_channels();
-        let block_size = self.main_input_ring_buffers[0].len();
-        let window_interval = (block_size / overlap_times) as i32;
         let mut already_processed_samples = 0;
         while already_processed_samples < main_buffer_len {
             let remaining_samples = main_buffer_len - already_processed_samples;
-            let samples_until_next_window = ((window_interval - self.current_pos as i32 - 1)
-                .rem_euclid(window_interval)
-                + 1) as usize;
-            let samples_to_process = samples_until_next_window.min(remaining_samples);
+            let samples_until_next_window = hop_size - (self.samples_since_last_block % hop_size);
+            let samples_until_wrap = block_size - self.current_pos;
+            let samples_to_process = samples_until_next_window
+                .min(samples_until_wrap)
+                .min(remaining_samples);
 
             for sample_offset in 0..samples_to_process {
                 for channel_idx in 0..num_channels {
@@ -537,8 +615,11 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
 
             already_processed_samples += samples_to_process;
             self.current_pos = (self.current_pos + samples_to_process) % block_size;
+            self.samples_since_last_block += samples_to_process;
 
             if samples_to_process == samples_until_next_window {
+                self.samples_since_last_block = 0;
+
                 for (channel_idx, input_ring_buffer) in
                     self.main_input_ring_buffers.iter().enumerate()
                 {
@@ -548,7 +629,7 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
                         input_ring_buffer,
                     );
                     if self.padding > 0 {
-                        self.scratch_buffer[block_size..].fill(0.0);
+                        self.scratch_buffer[block_size..].fill(T::ZERO);
                     }
 
                     analyze_cb(channel_idx, &mut self.scratch_buffer);
@@ -559,29 +640,31 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
 
     fn update_buffers(&mut self, block_size: usize) {
         for main_ring_buffer in &mut self.main_input_ring_buffers {
-            main_ring_buffer.resize(block_size, 0.0);
-            main_ring_buffer.fill(0.0);
+            main_ring_buffer.resize(block_size, T::ZERO);
+            main_ring_buffer.fill(T::ZERO);
         }
         for main_ring_buffer in &mut self.main_output_ring_buffers {
-            main_ring_buffer.resize(block_size, 0.0);
-            main_ring_buffer.fill(0.0);
+            main_ring_buffer.resize(block_size, T::ZERO);
+            main_ring_buffer.fill(T::ZERO);
         }
         for sidechain_ring_buffers in &mut self.sidechain_ring_buffers {
             for sidechain_ring_buffer in sidechain_ring_buffers {
-                sidechain_ring_buffer.resize(block_size, 0.0);
-                sidechain_ring_buffer.fill(0.0);
+                sidechain_ring_buffer.resize(block_size, T::ZERO);
+                sidechain_ring_buffer.fill(T::ZERO);
             }
         }
-        self.scratch_buffer.resize(block_size + self.padding, 0.0);
-        self.scratch_buffer.fill(0.0);
+        self.scratch_buffer
+            .resize(block_size + self.padding, T::ZERO);
+        self.scratch_buffer.fill(T::ZERO);
 
         for padding_buffer in &mut self.padding_buffers {
             // In case this changed since the last call, like in `set_padding()`
-            padding_buffer.resize(self.padding, 0.0);
-            padding_buffer.fill(0.0);
+            padding_buffer.resize(self.padding, T::ZERO);
+            padding_buffer.fill(T::ZERO);
         }
 
         self.current_pos = 0;
+        self.samples_since_last_block = 0;
     }
 }
 
@@ -589,10 +672,10 @@ impl<const NUM_SIDECHAIN_INPUTS: usize> StftHelper<NUM_SIDECHAIN_INPUTS> {
 /// the current position. This is a free function because you cannot pass an immutable reference to
 /// a field from `&self` to a `&mut self` method.
 #[inline]
-fn copy_ring_to_scratch_buffer(
-    scratch_buffer: &mut [f32],
+fn copy_ring_to_scratch_buffer<T: Sample>(
+    scratch_buffer: &mut [T],
     current_pos: usize,
-    ring_buffer: &[f32],
+    ring_buffer: &[T],
 ) {
     let block_size = ring_buffer.len();
     let num_copy_before_wrap = block_size - current_pos;
@@ -603,7 +686,11 @@ fn copy_ring_to_scratch_buffer(
 /// Add data from the scratch buffer to the specified ring buffer. When writing samples from this
 /// ring buffer back to the host's outputs they must be cleared to prevent infinite feedback.
 #[inline]
-fn add_scratch_to_ring_buffer(scratch_buffer: &[f32], current_pos: usize, ring_buffer: &mut [f32]) {
+fn add_scratch_to_ring_buffer<T: Sample>(
+    scratch_buffer: &[T],
+    current_pos: usize,
+    ring_buffer: &mut [T],
+) {
     // TODO: This could also use some SIMD
     let block_size = ring_buffer.len();
     let num_copy_before_wrap = block_size - current_pos;
@@ -620,3 +707,91 @@ fn add_scratch_to_ring_buffer(scratch_buffer: &[f32], current_pos: usize, ring_b
         *ring_sample += *scratch_sample;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::with_buffer;
+
+    #[test]
+    fn hop_size_matches_overlap() {
+        let input: Vec<f32> = (0..100).map(|idx| (idx as f32 * 0.1).sin()).collect();
+
+        let mut overlap_output = input.clone();
+        let mut overlap_stft = StftHelper::<0>::new(1, 16, 0);
+        with_buffer(&mut [&mut overlap_output], |buffer| {
+            overlap_stft.process_overlap_add(buffer, 4, |_, _| ())
+        });
+
+        let mut hop_size_output = input;
+        let mut hop_size_stft = StftHelper::<0>::new(1, 16, 0);
+        with_buffer(&mut [&mut hop_size_output], |buffer| {
+            hop_size_stft.process_overlap_add_with_hop_size(buffer, 4, |_, _| ())
+        });
+
+        assert_eq!(overlap_output, hop_size_output);
+    }
+
+    #[test]
+    fn analyze_with_uneven_hop_size() {
+        const BLOCK_SIZE: usize = 8;
+        const HOP_SIZE: usize = 3;
+
+        let mut stft = StftHelper::<0>::new(1, BLOCK_SIZE, 0);
+        let mut num_processed_samples = 0;
+        let mut num_blocks = 0;
+        for buffer_size in [2, 7, 1, 5, 11, 4] {
+            let mut input: Vec<f32> = (num_processed_samples..num_processed_samples + buffer_size)
+                .map(|idx| idx as f32 + 1.0)
+                .collect();
+            with_buffer(&mut [&mut input], |buffer| {
+                stft.process_analyze_only_with_hop_size(buffer, HOP_SIZE, |_, block| {
+                    num_blocks += 1;
+
+                    // Every block should contain the last `BLOCK_SIZE` samples up to the current
+                    // hop
+                    let last_sample = (num_blocks * HOP_SIZE) as f32;
+                    for (sample_idx, sample) in block.iter().enumerate() {
+                        let expected =
+                            (last_sample - (BLOCK_SIZE - 1 - sample_idx) as f32).max(0.0);
+                        assert_eq!(*sample, expected);
+                    }
+                })
+            });
+
+            num_processed_samples += buffer_size;
+        }
+
+        assert_eq!(num_blocks, num_processed_samples / HOP_SIZE);
+    }
+
+    #[test]
+    fn overlap_add_with_uneven_hop_size() {
+        const BLOCK_SIZE: usize = 8;
+        const HOP_SIZE: usize = 3;
+
+        let mut stft = StftHelper::<0>::new(1, BLOCK_SIZE, 0);
+        let mut output = Vec::new();
+        for buffer_size in [5, 8, 1, 7, 3, 6] {
+            let mut block = vec![1.0f32; buffer_size];
+            with_buffer(&mut [&mut block], |buffer| {
+                stft.process_overlap_add_with_hop_size(buffer, HOP_SIZE, |_, _| ())
+            });
+
+            output.extend(block);
+        }
+
+        // After one block of latency, every output sample is the sum of all windows that contained
+        // the corresponding input sample
+        for (sample_idx, sample) in output.iter().enumerate() {
+            let expected = if sample_idx < BLOCK_SIZE {
+                0
+            } else {
+                (sample_idx - BLOCK_SIZE + 1..=sample_idx)
+                    .filter(|window_end| window_end % HOP_SIZE == 0)
+                    .count()
+            };
+            assert_eq!(*sample, expected as f32, "Mismatch at sample {sample_idx}");
+        }
+    }
+}
@@ -1,6 +1,13 @@
 //! Windowing functions, useful in conjunction with [`StftHelper`][super::StftHelper].
 
 use std::f32;
+#[cfg(feature = "simd")]
+use std::simd::f32x8;
+
+/// The number of samples processed at once in [`multiply_with_window()`] when the `simd` feature
+/// is enabled.
+#[cfg(feature = "simd")]
+const SIMD_LANES: usize = 8;
 
 /// A Blackman window function with the 'standard' coefficients.
 ///
@@ -12,7 +19,7 @@ pub fn blackman(size: usize) -> Vec<f32> {
     window
 }
 
-/// The same as [`blackman()`], but filling an existing slice instead. asfasdf
+/// The same as [`blackman()`], but filling an existing slice instead.
 pub fn blackman_in_place(window: &mut [f32]) {
     let size = window.len();
 
@@ -25,6 +32,104 @@ pub fn blackman_in_place(window: &mut [f32]) {
     }
 }
 
+/// A four term Blackman-Harris window function. This has much lower sidelobes than the regular
+/// Blackman window at the cost of a wider main lobe.
+///
+/// <https://en.wikipedia.org/wiki/Window_function#Blackman%E2%80%93Harris_window>
+pub fn blackman_harris(size: usize) -> Vec<f32> {
+    let mut window = vec![0.0; size];
+    blackman_harris_in_place(&mut window);
+
+    window
+}
+
+/// The same as [`blackman_harris()`], but filling an existing slice instead.
+pub fn blackman_harris_in_place(window: &mut [f32]) {
+    cosine_sum_in_place(window, &[0.35875, 0.48829, 0.14128, 0.01168]);
+}
+
+/// A flat top window function. This has a very wide main lobe with almost no ripple, which makes it
+/// useful for measuring the amplitudes of sinusoids even when they fall between two FFT bins.
+///
+/// <https://en.wikipedia.org/wiki/Window_function#Flat_top_window>
+pub fn flat_top(size: usize) -> Vec<f32> {
+    let mut window = vec![0.0; size];
+    flat_top_in_place(&mut window);
+
+    window
+}
+
+/// The same as [`flat_top()`], but filling an existing slice instead.
+pub fn flat_top_in_place(window: &mut [f32]) {
+    cosine_sum_in_place(
+        window,
+        &[
+            0.21557895,
+            0.41663158,
+            0.27726316,
+            0.083578947,
+            0.006947368,
+        ],
+    );
+}
+
+/// A Gaussian window function. `sigma` is the standard deviation relative to half of the window's
+/// size, and it should be at most 0.5. Smaller values result in a narrower window.
+///
+/// <https://en.wikipedia.org/wiki/Window_function#Gaussian_window>
+pub fn gaussian(size: usize, sigma: f32) -> Vec<f32> {
+    let mut window = vec![0.0; size];
+    gaussian_in_place(&mut window, sigma);
+
+    window
+}
+
+/// The same as [`gaussian()`], but filling an existing slice instead.
+pub fn gaussian_in_place(window: &mut [f32], sigma: f32) {
+    if fill_trivial_window(window) {
+        return;
+    }
+
+    let size = window.len();
+
+    let center = (size - 1) as f32 / 2.0;
+    let scale = (sigma * center).recip();
+    for (i, sample) in window.iter_mut().enumerate() {
+        let x = (i as f32 - center) * scale;
+        *sample = (-0.5 * x * x).exp();
+    }
+}
+
+/// A Kaiser window function. `beta` controls the trade-off between the width of the main lobe and
+/// the sidelobe level. A beta of 0 results in a rectangular window, and higher values result in
+/// wider main lobes and lower sidelobes. A beta of around 8.6 is comparable to a Blackman window.
+///
+/// <https://en.wikipedia.org/wiki/Kaiser_window>
+pub fn kaiser(size: usize, beta: f32) -> Vec<f32> {
+    let mut window = vec![0.0; size];
+    kaiser_in_place(&mut window, beta);
+
+    window
+}
+
+/// The same as [`kaiser()`], but filling an existing slice instead.
+pub fn kaiser_in_place(window: &mut [f32], beta: f32) {
+    if fill_trivial_window(window) {
+        return;
+    }
+
+    let size = window.len();
+
+    // The Bessel function grows very quickly, so this is computed at double precision
+    let beta = beta as f64;
+    let scale = 2.0 / (size - 1) as f64;
+    let normalization = bessel_i0(beta).recip();
+    for (i, sample) in window.iter_mut().enumerate() {
+        let x = (i as f64 * scale) - 1.0;
+        *sample = (bessel_i0(beta * (1.0 - (x * x)).max(0.0).sqrt()) * normalization) as f32;
+    }
+}
+
 /// A Hann window function.
 ///
 /// <https://en.wikipedia.org/wiki/Hann_function>
@@ -48,11 +153,141 @@ pub fn hann_in_place(window: &mut [f32]) {
     }
 }
 
-/// Multiply a buffer with a window function.
+/// Multiply a buffer with a window function. If the buffer is longer than the window function,
+/// then only the first `window_function.len()` samples are multiplied. This uses SIMD when the
+/// `simd` feature is enabled.
 #[inline]
 pub fn multiply_with_window(buffer: &mut [f32], window_function: &[f32]) {
-    // TODO: ALso use SIMD here if available
+    let len = buffer.len().min(window_function.len());
+    let (buffer, window_function) = (&mut buffer[..len], &window_function[..len]);
+
+    #[cfg(feature = "simd")]
+    let (buffer, window_function) = {
+        let mut buffer_chunks = buffer.chunks_exact_mut(SIMD_LANES);
+        let mut window_chunks = window_function.chunks_exact(SIMD_LANES);
+        for (buffer_chunk, window_chunk) in (&mut buffer_chunks).zip(&mut window_chunks) {
+            let product = f32x8::from_slice(buffer_chunk) * f32x8::from_slice(window_chunk);
+            product.copy_to_slice(buffer_chunk);
+        }
+
+        (buffer_chunks.into_remainder(), window_chunks.remainder())
+    };
+
     for (sample, window_sample) in buffer.iter_mut().zip(window_function) {
         *sample *= window_sample;
     }
 }
+
+/// Fill a window with a generalized cosine window with the specified coefficients. The signs of the
+/// terms alternate, so all coefficients should be positive.
+fn cosine_sum_in_place(window: &mut [f32], coefficients: &[f32]) {
+    if fill_trivial_window(window) {
+        return;
+    }
+
+    let size = window.len();
+
+    let scale = (2.0 * f32::consts::PI) / (size - 1) as f32;
+    for (i, sample) in window.iter_mut().enumerate() {
+        let mut value = 0.0;
+        for (term_idx, coefficient) in coefficients.iter().enumerate() {
+            let term = coefficient * (scale * (term_idx * i) as f32).cos();
+            if term_idx % 2 == 0 {
+                value += term;
+            } else {
+                value -= term;
+            }
+        }
+
+        *sample = value;
+    }
+}
+
+/// Windows with zero or one samples don't have a shape, and the window functions would divide by
+/// zero when computing them. A single sample window is filled with a one instead. Returns `true` if
+/// the window was one of these trivial windows.
+fn fill_trivial_window(window: &mut [f32]) -> bool {
+    if window.len() > 1 {
+        return false;
+    }
+
+    window.fill(1.0);
+    true
+}
+
+/// The zeroth order modified Bessel function of the first kind, computed using its power series.
+fn bessel_i0(x: f64) -> f64 {
+    let half_x_squared = (x / 2.0) * (x / 2.0);
+
+    // The terms of the series are `((x / 2)^k / k!)^2`
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= half_x_squared / (k * k);
+        sum += term;
+        k += 1.0;
+    }
+
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_are_symmetric_with_unity_peak() {
+        const SIZE: usize = 33;
+
+        for window in [
+            blackman(SIZE),
+            blackman_harris(SIZE),
+            flat_top(SIZE),
+            gaussian(SIZE, 0.4),
+            hann(SIZE),
+            kaiser(SIZE, 8.6),
+        ] {
+            for i in 0..SIZE / 2 {
+                approx::assert_relative_eq!(window[i], window[SIZE - 1 - i], epsilon = 1e-5);
+            }
+            approx::assert_relative_eq!(window[SIZE / 2], 1.0, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn kaiser_without_beta_is_rectangular() {
+        for sample in kaiser(16, 0.0) {
+            assert_eq!(sample, 1.0);
+        }
+    }
+
+    #[test]
+    fn trivial_windows() {
+        for window in [
+            blackman_harris(1),
+            flat_top(1),
+            gaussian(1, 0.4),
+            kaiser(1, 8.6),
+        ] {
+            assert_eq!(window, [1.0]);
+        }
+
+        assert!(blackman_harris(0).is_empty());
+        assert!(flat_top(0).is_empty());
+        assert!(gaussian(0, 0.4).is_empty());
+        assert!(kaiser(0, 8.6).is_empty());
+    }
+
+    #[test]
+    fn multiply_with_shorter_window() {
+        let window = hann(13);
+        let mut buffer = vec![2.0; 20];
+        multiply_with_window(&mut buffer, &window);
+
+        for (sample, window_sample) in buffer.iter().zip(&window) {
+            assert_eq!(*sample, window_sample * 2.0);
+        }
+        assert!(buffer[window.len()..].iter().all(|sample| *sample == 2.0));
+    }
+}
@@ -6,6 +6,8 @@ use clap_sys::id::{clap_id, CLAP_INVALID_ID};
 use clap_sys::string_sizes::CLAP_NAME_SIZE;
 use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
+use std::ffi::CStr;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use super::wrapper::{OutputParamEvent, Task, Wrapper};
@@ -16,6 +18,10 @@ use crate::prelude::{
 };
 use crate::wrapper::util::strlcpy;
 
+/// The name of a state restore made from the editor in the host's undo history.
+const STATE_CHANGE_UNDO_NAME: &CStr =
+    unsafe { CStr::from_bytes_with_nul_unchecked(b"Load state\0") };
+
 /// An [`InitContext`] implementation for the wrapper.
 ///
 /// # Note
@@ -65,6 +71,14 @@ pub(crate) struct RemoteControlPages<'a> {
     pages: &'a mut Vec<clap_remote_controls_page>,
 }
 
+impl<P: ClapPlugin> WrapperInitContext<'_, P> {
+    /// The latency set by the plugin during this context's lifetime, if it changed. This is only
+    /// sent to the host after the context is dropped.
+    pub fn pending_latency(&self) -> Option<u32> {
+        self.pending_requests.latency_changed.get()
+    }
+}
+
 impl<P: ClapPlugin> Drop for WrapperInitContext<'_, P> {
     fn drop(&mut self) {
         if let Some(samples) = self.pending_requests.latency_changed.take() {
@@ -124,9 +138,17 @@ impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         self.wrapper.set_latency_samples(samples)
     }
 
+    fn latency_samples(&self) -> u32 {
+        self.wrapper.current_latency.load(Ordering::SeqCst)
+    }
+
     fn set_current_voice_capacity(&self, capacity: u32) {
         self.wrapper.set_current_voice_capacity(capacity)
     }
+
+    fn param_info_changed(&self) {
+        self.wrapper.param_info_changed()
+    }
 }
 
 impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
@@ -143,6 +165,10 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
         match self.wrapper.param_ptr_to_hash.get(&param) {
             Some(hash) => {
+                if !self.wrapper.has_host_undo() {
+                    self.wrapper.undo_history.record_begin_set_parameter(param);
+                }
+
                 let success = self
                     .wrapper
                     .queue_parameter_event(OutputParamEvent::BeginGesture { param_hash: *hash });
@@ -171,6 +197,12 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
         match self.wrapper.param_ptr_to_hash.get(&param) {
             Some(hash) => {
+                if !self.wrapper.has_host_undo() {
+                    self.wrapper
+                        .undo_history
+                        .record_set_parameter(param, normalized);
+                }
+
                 // We queue the parameter change event here, and it will be sent to the host either
                 // at the end of the current processing cycle or after requesting an explicit flush
                 // (when the plugin isn't processing audio). The parameter's actual value will only
@@ -208,6 +240,10 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
         match self.wrapper.param_ptr_to_hash.get(&param) {
             Some(hash) => {
+                if !self.wrapper.has_host_undo() {
+                    self.wrapper.undo_history.record_end_set_parameter(param);
+                }
+
                 let success = self
                     .wrapper
                     .queue_parameter_event(OutputParamEvent::EndGesture { param_hash: *hash });
@@ -238,7 +274,52 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     }
 
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
-        self.wrapper.set_state_object_from_gui(state)
+        // If the host supports the undo extension, then the state change is added to the host's
+        // undo history. Otherwise we'll keep track of it ourselves.
+        let old_state = self.wrapper.get_state_object();
+        let new_state = state.clone();
+        let recorded_by_host = self
+            .wrapper
+            .with_host_undo_change(STATE_CHANGE_UNDO_NAME, || {
+                self.wrapper.set_state_object_from_gui(state)
+            });
+        if !recorded_by_host {
+            self.wrapper
+                .undo_history
+                .record_set_state(old_state, new_state);
+        }
+    }
+
+    fn undo(&self) {
+        if !self.wrapper.host_undo() {
+            self.wrapper.undo_history.undo(self);
+        }
+    }
+
+    fn redo(&self) {
+        if !self.wrapper.host_redo() {
+            self.wrapper.undo_history.redo(self);
+        }
+    }
+
+    fn midi_learn(&self) -> &crate::midi::learn::MidiLearn {
+        &self.wrapper.midi_learn
+    }
+
+    fn state_slots(&self) -> &crate::wrapper::state::StateSlots {
+        &self.wrapper.state_slots
+    }
+
+    fn switch_state_slot(&self, slot: usize) {
+        self.wrapper.switch_state_slot(slot)
+    }
+
+    fn copy_state_slot(&self, from: usize, to: usize) {
+        self.wrapper.copy_state_slot(from, to)
+    }
+
+    fn param_info_changed(&self) {
+        self.wrapper.param_info_changed()
     }
 }
 
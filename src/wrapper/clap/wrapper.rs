@@ -1,5 +1,6 @@
 use atomic_float::AtomicF32;
 use atomic_refcell::{AtomicRefCell, AtomicRefMut};
+use clap_sys::audio_buffer::clap_audio_buffer;
 use clap_sys::events::{
     clap_event_header, clap_event_midi, clap_event_midi_sysex, clap_event_note,
     clap_event_note_expression, clap_event_param_gesture, clap_event_param_mod,
@@ -16,7 +17,8 @@ use clap_sys::events::{
     CLAP_TRANSPORT_IS_RECORDING, CLAP_TRANSPORT_IS_WITHIN_PRE_ROLL,
 };
 use clap_sys::ext::audio_ports::{
-    clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS,
+    clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN,
+    CLAP_AUDIO_PORT_PREFERS_64BITS, CLAP_AUDIO_PORT_SUPPORTS_64BITS, CLAP_EXT_AUDIO_PORTS,
     CLAP_PORT_MONO, CLAP_PORT_STEREO,
 };
 use clap_sys::ext::audio_ports_config::{
@@ -25,6 +27,7 @@ use clap_sys::ext::audio_ports_config::{
 use clap_sys::ext::draft::remote_controls::{
     clap_plugin_remote_controls, clap_remote_controls_page, CLAP_EXT_REMOTE_CONTROLS,
 };
+use clap_sys::ext::draft::undo::{clap_host_undo, CLAP_EXT_UNDO};
 use clap_sys::ext::gui::{
     clap_gui_resize_hints, clap_host_gui, clap_plugin_gui, clap_window, CLAP_EXT_GUI,
     CLAP_WINDOW_API_COCOA, CLAP_WINDOW_API_WIN32, CLAP_WINDOW_API_X11,
@@ -38,7 +41,8 @@ use clap_sys::ext::params::{
     clap_host_params, clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS,
     CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_BYPASS, CLAP_PARAM_IS_HIDDEN,
     CLAP_PARAM_IS_MODULATABLE, CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID, CLAP_PARAM_IS_READONLY,
-    CLAP_PARAM_IS_STEPPED, CLAP_PARAM_RESCAN_VALUES,
+    CLAP_PARAM_IS_STEPPED, CLAP_PARAM_RESCAN_ALL, CLAP_PARAM_RESCAN_INFO, CLAP_PARAM_RESCAN_TEXT,
+    CLAP_PARAM_RESCAN_VALUES,
 };
 use clap_sys::ext::render::{
     clap_plugin_render, clap_plugin_render_mode, CLAP_EXT_RENDER, CLAP_RENDER_OFFLINE,
@@ -81,17 +85,20 @@ use super::context::{WrapperGuiContext, WrapperInitContext, WrapperProcessContex
 use super::descriptor::PluginDescriptor;
 use super::util::ClapPtr;
 use crate::event_loop::{BackgroundThread, EventLoop, MainThreadExecutor, TASK_QUEUE_CAPACITY};
+use crate::midi::learn::MidiLearn;
 use crate::midi::MidiResult;
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, BufferConfig, ClapPlugin, Editor, MidiConfig,
-    NoteEvent, ParamFlags, ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent,
-    ProcessMode, ProcessStatus, SysExMessage, TaskExecutor, Transport,
+    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, ClapPlugin, Editor,
+    MidiConfig, NoteEvent, ParamFlags, ParamPtr, Params, ParentWindowHandle, Plugin,
+    PluginNoteEvent, ProcessMode, ProcessStatus, Sample, SysExMessage, TaskExecutor, Transport,
 };
 use crate::util::permit_alloc;
 use crate::wrapper::clap::context::RemoteControlPages;
 use crate::wrapper::clap::util::{read_stream, write_stream};
-use crate::wrapper::state::{self, PluginState};
+use crate::wrapper::state::{self, PluginState, StateSlots};
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
+use crate::wrapper::util::bypass::{self, BypassProcessor};
+use crate::wrapper::util::undo::UndoHistory;
 use crate::wrapper::util::{
     clamp_input_event_timing, clamp_output_event_timing, hash_param_id, process_wrapper, strlcpy,
 };
@@ -126,6 +133,9 @@ pub struct Wrapper<P: ClapPlugin> {
     editor_scaling_factor: AtomicF32,
 
     is_processing: AtomicBool,
+    /// Whether the plugin is currently activated. Some changes, like a parameter's step count
+    /// changing, can only be communicated to the host while the plugin is deactivated.
+    is_active: AtomicBool,
     /// The current IO configuration, modified through the `clap_plugin_audio_ports_config`
     /// extension. Initialized to the plugin's first audio IO configuration.
     current_audio_io_layout: AtomicCell<AudioIOLayout>,
@@ -151,6 +161,15 @@ pub struct Wrapper<P: ClapPlugin> {
     /// A data structure that helps manage and create buffers for all of the plugin's inputs and
     /// outputs based on channel pointers provided by the host.
     buffer_manager: AtomicRefCell<BufferManager>,
+    /// The same as `buffer_manager`, but for hosts that process audio at 64-bit precision. This is
+    /// only initialized when [`Plugin::F64_PROCESSING`] is enabled.
+    buffer_manager_f64: AtomicRefCell<BufferManager<f64>>,
+    /// Crossfades to a latency compensated dry signal when the plugin is bypassed, if the plugin
+    /// opted into that through [`Plugin::BYPASS_POLICY`]. Otherwise this simply calls the plugin's
+    /// process function.
+    bypass: AtomicRefCell<BypassProcessor>,
+    /// The same as `bypass`, but for hosts that process audio at 64-bit precision.
+    bypass_f64: AtomicRefCell<BypassProcessor<f64>>,
     /// The plugin is able to restore state through a method on the `GuiContext`. To avoid changing
     /// parameters mid-processing and running into garbled data if the host also tries to load state
     /// at the same time the restoring happens at the end of each processing call. If this zero
@@ -215,6 +234,15 @@ pub struct Wrapper<P: ClapPlugin> {
     /// the parameter's poly modulation ID. These IDs are then passed to the plugin, so it can
     /// quickly refer to parameter by matching on constant IDs.
     poly_mod_ids_by_hash: HashMap<u32, u32>,
+    /// The step counts for each parameter as last reported to the host, indexed by the parameter's
+    /// hash. Parameter ranges can change at runtime, and a change in step count also changes the
+    /// parameter's value range in the CLAP API. That requires a full parameter rescan, which can
+    /// only happen while the plugin is deactivated. Only accessed from the main thread.
+    param_step_counts: AtomicRefCell<HashMap<u32, Option<usize>>>,
+    /// Set when a parameter's step count changed while the plugin was activated. In that case the
+    /// wrapper requests the host to restart the plugin, and the full parameter rescan happens when
+    /// the plugin gets deactivated.
+    pending_param_rescan_all: AtomicBool,
     /// A queue of parameter changes and gestures that should be output in either the next process
     /// call or in the next parameter flush.
     ///
@@ -222,9 +250,20 @@ pub struct Wrapper<P: ClapPlugin> {
     ///      even if it does then that should still not be a problem because the host also reads it
     ///      in the same order, right?
     output_parameter_events: ArrayQueue<OutputParamEvent>,
+    /// MIDI CC mappings created through MIDI learn. These are applied to the incoming MIDI CCs
+    /// before the plugin's process function gets called.
+    pub midi_learn: MidiLearn,
+    /// Switchable state snapshots for A/B comparisons. Switching to another slot from the editor
+    /// loads that slot's snapshot at the start of the next process call.
+    pub state_slots: StateSlots,
 
     host_thread_check: AtomicRefCell<Option<ClapPtr<clap_host_thread_check>>>,
 
+    /// Parameter gestures and state restores made from the editor. This is only used when the host
+    /// doesn't support the undo extension.
+    pub undo_history: UndoHistory,
+    host_undo: AtomicRefCell<Option<ClapPtr<clap_host_undo>>>,
+
     clap_plugin_remote_controls: clap_plugin_remote_controls,
     /// The plugin's remote control pages, if it defines any. Filled when initializing the plugin.
     remote_control_pages: Vec<clap_remote_controls_page>,
@@ -279,6 +318,9 @@ pub enum Task<P: Plugin> {
     VoiceInfoChanged,
     /// Tell the host that it should rescan the current parameter values.
     RescanParamValues,
+    /// Tell the host that it should rescan the parameters' names, ranges, and values, and inform
+    /// the editor about this change.
+    RescanParamInfo,
 }
 
 /// The types of CLAP parameter updates for events.
@@ -419,6 +461,52 @@ impl<P: ClapPlugin> MainThreadExecutor<Task<P>> for Wrapper<P> {
                 }
                 None => nih_debug_assert_failure!("The host does not support parameters? What?"),
             },
+            Task::RescanParamInfo => {
+                match &*self.host_params.borrow() {
+                    Some(host_params) => {
+                        nih_debug_assert!(is_gui_thread);
+
+                        // The parameter values in the CLAP API are normalized values multiplied by
+                        // the step count, so a change in step count requires a full rescan
+                        let mut step_counts_changed = false;
+                        let mut param_step_counts = self.param_step_counts.borrow_mut();
+                        for (param_hash, param_ptr) in &self.param_by_hash {
+                            let step_count = unsafe { param_ptr.step_count() };
+                            if param_step_counts.insert(*param_hash, step_count) != Some(step_count)
+                            {
+                                step_counts_changed = true;
+                            }
+                        }
+
+                        if step_counts_changed && self.is_active.load(Ordering::SeqCst) {
+                            // This is only allowed when the plugin is deactivated, so the full
+                            // rescan happens in `deactivate()`. Until then the host can at least
+                            // already update the parameters' value strings.
+                            self.pending_param_rescan_all.store(true, Ordering::SeqCst);
+                            unsafe_clap_call! { &*self.host_callback=>request_restart(&*self.host_callback) };
+                            unsafe_clap_call! { host_params=>rescan(&*self.host_callback, CLAP_PARAM_RESCAN_TEXT) };
+                        } else if step_counts_changed {
+                            unsafe_clap_call! { host_params=>rescan(&*self.host_callback, CLAP_PARAM_RESCAN_ALL) };
+                        } else {
+                            unsafe_clap_call! {
+                                host_params=>rescan(
+                                    &*self.host_callback,
+                                    CLAP_PARAM_RESCAN_INFO | CLAP_PARAM_RESCAN_TEXT | CLAP_PARAM_RESCAN_VALUES
+                                )
+                            };
+                        }
+                    }
+                    None => {
+                        nih_debug_assert_failure!("The host does not support parameters? What?")
+                    }
+                }
+
+                if self.editor_handle.lock().is_some() {
+                    if let Some(editor) = self.editor.borrow().as_ref() {
+                        editor.lock().param_info_changed();
+                    }
+                }
+            }
         };
     }
 }
@@ -473,6 +561,10 @@ impl<P: ClapPlugin> Wrapper<P> {
             .iter()
             .map(|(_, hash, _, group)| (*hash, group.clone()))
             .collect();
+        let param_step_counts = param_id_hashes_ptrs_groups
+            .iter()
+            .map(|(_, hash, ptr, _)| (*hash, unsafe { ptr.step_count() }))
+            .collect();
         let param_id_to_hash = param_id_hashes_ptrs_groups
             .iter()
             .map(|(id, hash, _, _)| (id.clone(), *hash))
@@ -481,6 +573,16 @@ impl<P: ClapPlugin> Wrapper<P> {
             .iter()
             .map(|(_, hash, ptr, _)| (*ptr, *hash))
             .collect();
+        let bypass_param = bypass::find_bypass_param(
+            param_id_hashes_ptrs_groups
+                .iter()
+                .map(|(_, _, ptr, _)| *ptr),
+        );
+        let midi_learn = MidiLearn::new(
+            param_id_hashes_ptrs_groups
+                .iter()
+                .map(|(id, _, ptr, _)| (id, *ptr)),
+        );
         let poly_mod_ids_by_hash: HashMap<u32, u32> = param_id_hashes_ptrs_groups
             .iter()
             .filter_map(|(_, hash, ptr, _)| unsafe {
@@ -544,6 +646,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             editor_scaling_factor: AtomicF32::new(1.0),
 
             is_processing: AtomicBool::new(false),
+            is_active: AtomicBool::new(false),
             current_audio_io_layout: AtomicCell::new(
                 P::AUDIO_IO_LAYOUTS.first().copied().unwrap_or_default(),
             ),
@@ -559,6 +662,12 @@ impl<P: ClapPlugin> Wrapper<P> {
                 0,
                 AudioIOLayout::default(),
             )),
+            buffer_manager_f64: AtomicRefCell::new(BufferManager::for_audio_io_layout(
+                0,
+                AudioIOLayout::default(),
+            )),
+            bypass: AtomicRefCell::new(BypassProcessor::new(P::BYPASS_POLICY, bypass_param)),
+            bypass_f64: AtomicRefCell::new(BypassProcessor::new(P::BYPASS_POLICY, bypass_param)),
             updated_state_sender,
             updated_state_receiver,
 
@@ -641,10 +750,17 @@ impl<P: ClapPlugin> Wrapper<P> {
             param_id_to_hash,
             param_ptr_to_hash,
             poly_mod_ids_by_hash,
+            param_step_counts: AtomicRefCell::new(param_step_counts),
+            pending_param_rescan_all: AtomicBool::new(false),
             output_parameter_events: ArrayQueue::new(OUTPUT_EVENT_QUEUE_CAPACITY),
+            midi_learn,
+            state_slots: StateSlots::new(P::STATE_SLOTS),
 
             host_thread_check: AtomicRefCell::new(None),
 
+            undo_history: UndoHistory::default(),
+            host_undo: AtomicRefCell::new(None),
+
             clap_plugin_remote_controls: clap_plugin_remote_controls {
                 count: Some(Self::ext_remote_controls_count),
                 get: Some(Self::ext_remote_controls_get),
@@ -755,6 +871,159 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    /// Create the plugin's buffers for the block starting at `block_start` from the host's
+    /// `process` data, and then run `process_fn` on them. The plugin is not called if the host did
+    /// not provide valid output buffers. `channel_data` selects either a port's 32-bit or its
+    /// 64-bit channel pointers.
+    ///
+    /// # Safety
+    ///
+    /// `process` must be the host's process data for the current process call.
+    unsafe fn process_block<T: Sample>(
+        &self,
+        process: &clap_process,
+        buffer_manager: &AtomicRefCell<BufferManager<T>>,
+        bypass: &AtomicRefCell<BypassProcessor<T>>,
+        channel_data: impl Fn(&clap_audio_buffer) -> *const *const T,
+        block_start: usize,
+        block_len: usize,
+        process_fn: impl FnOnce(&mut P, &mut Buffer<T>, &mut AuxiliaryBuffers<T>) -> ProcessStatus,
+    ) -> ProcessStatus {
+        let current_audio_io_layout = self.current_audio_io_layout.load();
+        let has_main_input = current_audio_io_layout.main_input_channels.is_some();
+        let has_main_output = current_audio_io_layout.main_output_channels.is_some();
+        let aux_input_start_idx = if has_main_input { 1 } else { 0 };
+        let aux_output_start_idx = if has_main_output { 1 } else { 0 };
+
+        // The buffer manager preallocated buffer slices for all the IO and storage for any
+        // axuiliary inputs.
+        // TODO: The audio buffers have a latency field, should we use those?
+        // TODO: Like with VST3, should we expose some way to access or set the silence/constant
+        //       flags?
+        let mut buffer_manager = buffer_manager.borrow_mut();
+        let buffers = buffer_manager.create_buffers(block_start, block_len, |buffer_source| {
+            // Explicitly take plugins with no main output that does have auxiliary
+            // outputs into account. Shouldn't happen, but if we just start copying
+            // audio here then that would result in unsoundness.
+            if process.audio_outputs_count > 0
+                && !process.audio_outputs.is_null()
+                && !channel_data(&*process.audio_outputs).is_null()
+                && has_main_output
+            {
+                let audio_output = &*process.audio_outputs;
+                let ptrs = NonNull::new(channel_data(audio_output) as *mut *mut T).unwrap();
+                let num_channels = audio_output.channel_count as usize;
+
+                *buffer_source.main_output_channel_pointers =
+                    Some(ChannelPointers { ptrs, num_channels });
+            }
+
+            if process.audio_inputs_count > 0
+                && !process.audio_inputs.is_null()
+                && !channel_data(&*process.audio_inputs).is_null()
+                && has_main_input
+            {
+                let audio_input = &*process.audio_inputs;
+                let ptrs = NonNull::new(channel_data(audio_input) as *mut *mut T).unwrap();
+                let num_channels = audio_input.channel_count as usize;
+
+                *buffer_source.main_input_channel_pointers =
+                    Some(ChannelPointers { ptrs, num_channels });
+            }
+
+            if !process.audio_inputs.is_null() {
+                for (aux_input_no, aux_input_channel_pointers) in buffer_source
+                    .aux_input_channel_pointers
+                    .iter_mut()
+                    .enumerate()
+                {
+                    let aux_input_idx = aux_input_no + aux_input_start_idx;
+                    if aux_input_idx > process.audio_inputs_count as usize {
+                        break;
+                    }
+
+                    let audio_input = &*process.audio_inputs.add(aux_input_idx);
+                    match NonNull::new(channel_data(audio_input) as *mut *mut T) {
+                        Some(ptrs) => {
+                            let num_channels = audio_input.channel_count as usize;
+
+                            *aux_input_channel_pointers =
+                                Some(ChannelPointers { ptrs, num_channels });
+                        }
+                        None => continue,
+                    }
+                }
+            }
+
+            if !process.audio_outputs.is_null() {
+                for (aux_output_no, aux_output_channel_pointers) in buffer_source
+                    .aux_output_channel_pointers
+                    .iter_mut()
+                    .enumerate()
+                {
+                    let aux_output_idx = aux_output_no + aux_output_start_idx;
+                    if aux_output_idx > process.audio_outputs_count as usize {
+                        break;
+                    }
+
+                    let audio_output = &*process.audio_outputs.add(aux_output_idx);
+                    match NonNull::new(channel_data(audio_output) as *mut *mut T) {
+                        Some(ptrs) => {
+                            let num_channels = audio_output.channel_count as usize;
+
+                            *aux_output_channel_pointers =
+                                Some(ChannelPointers { ptrs, num_channels });
+                        }
+                        None => continue,
+                    }
+                }
+            }
+        });
+
+        // If the host does not provide outputs or if it does not provide the required
+        // number of channels (should not happen, but Ableton Live does this for bypassed
+        // VST3 plugins) then we'll skip audio processing. In that case
+        // `buffer_manager.create_buffers` will have set one or more of the output buffers
+        // to empty slices since there is no storage to point them to. The auxiliary input
+        // buffers always point to valid storage.
+        let mut buffer_is_valid = true;
+        for output_buffer_slice in buffers.main_buffer.as_slice_immutable().iter().chain(
+            buffers
+                .aux_outputs
+                .iter()
+                .flat_map(|buffer| buffer.as_slice_immutable().iter()),
+        ) {
+            if output_buffer_slice.is_empty() {
+                buffer_is_valid = false;
+                break;
+            }
+        }
+
+        nih_debug_assert!(buffer_is_valid);
+
+        if buffer_is_valid {
+            let mut plugin = self.plugin.lock();
+            // SAFETY: Shortening these borrows is safe as even if the plugin overwrites the
+            //         slices (which it cannot do without using unsafe code), then they
+            //         would still be reset on the next iteration
+            let mut aux = AuxiliaryBuffers {
+                inputs: buffers.aux_inputs,
+                outputs: buffers.aux_outputs,
+            };
+            let result = bypass.borrow_mut().process(
+                &mut *plugin,
+                buffers.main_buffer,
+                &mut aux,
+                &self.current_latency,
+                process_fn,
+            );
+            self.last_process_status.store(result);
+            result
+        } else {
+            ProcessStatus::Normal
+        }
+    }
+
     /// Get a parameter's ID based on a `ParamPtr`. Used in the `GuiContext` implementation for the
     /// gesture checks.
     #[allow(unused)]
@@ -972,6 +1241,46 @@ impl<P: ClapPlugin> Wrapper<P> {
         None
     }
 
+    /// Update the parameters that have been mapped to MIDI CCs through MIDI learn using the MIDI CCs
+    /// in the input events queue. The changes are sent to the host as regular parameter changes in
+    /// [`handle_out_events()`][Self::handle_out_events()]. We can't request a flush from the audio
+    /// thread, but that's not necessary since the output events are written after this process
+    /// call.
+    fn apply_midi_mappings(&self, sample_rate: f32) {
+        let input_events = self.input_events.borrow();
+        for event in input_events.iter() {
+            self.midi_learn
+                .handle_event(event, |param_ptr, normalized_value| {
+                    let param_hash = self.param_ptr_to_hash[&param_ptr];
+                    let clap_plain_value = normalized_value as f64
+                        * unsafe { param_ptr.step_count() }.unwrap_or(1) as f64;
+
+                    self.update_plain_value_by_hash(
+                        param_hash,
+                        ClapParamUpdate::PlainValueSet(clap_plain_value),
+                        Some(sample_rate),
+                    );
+
+                    let queued = self
+                        .output_parameter_events
+                        .push(OutputParamEvent::BeginGesture { param_hash })
+                        .is_ok()
+                        && self
+                            .output_parameter_events
+                            .push(OutputParamEvent::SetValue {
+                                param_hash,
+                                clap_plain_value,
+                            })
+                            .is_ok()
+                        && self
+                            .output_parameter_events
+                            .push(OutputParamEvent::EndGesture { param_hash })
+                            .is_ok();
+                    nih_debug_assert!(queued, "The parameter output queue is full");
+                });
+        }
+    }
+
     /// Write the unflushed parameter changes to the host's output event queue. The sample index is
     /// used as part of splitting up the input buffer for sample accurate automation changes. This
     /// will also modify the actual parameter values, since we should only do that while the wrapped
@@ -1684,10 +1993,51 @@ impl<P: ClapPlugin> Wrapper<P> {
             state::serialize_object::<P>(
                 self.params.clone(),
                 state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+                &self.midi_learn,
+                &self.state_slots,
             )
         }
     }
 
+    /// Get a snapshot of the plugin's parameter values and persistent fields for the state slots.
+    fn get_state_slot_snapshot(&self) -> PluginState {
+        unsafe {
+            state::serialize_snapshot_object::<P>(
+                self.params.clone(),
+                state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+            )
+        }
+    }
+
+    /// Store the current state in the active state slot and switch to another slot, called by the
+    /// plugin's editor from the GUI thread. See
+    /// [`GuiContext::switch_state_slot()`][crate::prelude::GuiContext::switch_state_slot()].
+    pub fn switch_state_slot(&self, slot: usize) {
+        let live_state = self.get_state_slot_snapshot();
+        if let Some(snapshot) = self.state_slots.switch_to(live_state, slot) {
+            self.load_state_slot_from_gui(snapshot);
+        }
+    }
+
+    /// Copy one state slot to another, called by the plugin's editor from the GUI thread. See
+    /// [`GuiContext::copy_state_slot()`][crate::prelude::GuiContext::copy_state_slot()].
+    pub fn copy_state_slot(&self, from: usize, to: usize) {
+        let live_state = self.get_state_slot_snapshot();
+        if let Some(snapshot) = self.state_slots.copy(live_state, from, to) {
+            self.load_state_slot_from_gui(snapshot);
+        }
+    }
+
+    /// Load a state slot's snapshot. If the plugin is processing audio, then this happens at the
+    /// start of the next process call. Otherwise the snapshot is loaded right away.
+    fn load_state_slot_from_gui(&self, snapshot: PluginState) {
+        self.state_slots.schedule_load(snapshot);
+        if !self.is_processing.load(Ordering::SeqCst) {
+            self.state_slots
+                .handle_pending_load(|snapshot| self.set_state_slot_inner(snapshot));
+        }
+    }
+
     /// Update the plugin's internal state, called by the plugin itself from the GUI thread. To
     /// prevent corrupting data and changing parameters during processing the actual state is only
     /// updated at the end of the audio processing cycle.
@@ -1734,6 +2084,77 @@ impl<P: ClapPlugin> Wrapper<P> {
         nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
     }
 
+    /// Whether the host supports the undo extension. In that case the host records the parameter
+    /// changes made from the editor itself, so they should not be added to the wrapper's own undo
+    /// history.
+    pub fn has_host_undo(&self) -> bool {
+        self.host_undo.borrow().is_some()
+    }
+
+    /// Ask the host to undo its last change if it supports the undo extension. Returns `false` if
+    /// the host doesn't support this, in which case the wrapper's own undo history should be used.
+    pub fn host_undo(&self) -> bool {
+        match &*self.host_undo.borrow() {
+            Some(host_undo) => {
+                unsafe_clap_call! { host_undo=>undo(&*self.host_callback) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The same as [`host_undo()`][Self::host_undo()], but for redoing changes.
+    pub fn host_redo(&self) -> bool {
+        match &*self.host_undo.borrow() {
+            Some(host_undo) => {
+                unsafe_clap_call! { host_undo=>redo(&*self.host_callback) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run `f`, and record it as a single undoable change in the host's undo history if the host
+    /// supports the undo extension. The host then uses the plugin's state to undo and redo the
+    /// change. Returns `false` if the host doesn't support this.
+    pub fn with_host_undo_change(&self, name: &CStr, f: impl FnOnce()) -> bool {
+        match &*self.host_undo.borrow() {
+            Some(host_undo) => {
+                unsafe_clap_call! { host_undo=>begin_change(&*self.host_callback) };
+                f();
+                unsafe_clap_call! {
+                    host_undo=>change_made(
+                        &*self.host_callback,
+                        name.as_ptr(),
+                        std::ptr::null(),
+                        0,
+                        std::ptr::null(),
+                        0,
+                    )
+                };
+
+                true
+            }
+            None => {
+                f();
+                false
+            }
+        }
+    }
+
+    pub fn param_info_changed(&self) {
+        let task_posted = self.schedule_gui(Task::RescanParamInfo);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
+    /// Reset the plugin and any ongoing bypass crossfades. This is called when the host resets the
+    /// plugin and when it starts processing audio.
+    fn reset_plugin(&self) {
+        self.plugin.lock().reset();
+        self.bypass.borrow_mut().reset();
+        self.bypass_f64.borrow_mut().reset();
+    }
+
     pub fn set_latency_samples(&self, samples: u32) {
         // Only make a callback if it's actually needed
         // XXX: For CLAP we could move this handling to the Plugin struct, but it may be worthwhile
@@ -1794,6 +2215,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                 state,
                 self.params.clone(),
                 state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash),
+                &self.midi_learn,
+                &self.state_slots,
                 self.current_buffer_config.load().as_ref(),
             )
         });
@@ -1836,6 +2259,28 @@ impl<P: ClapPlugin> Wrapper<P> {
         success
     }
 
+    /// Load a snapshot from the state slots. Unlike [`set_state_inner()`][Self::set_state_inner()]
+    /// this doesn't reset the smoothers or reinitialize the plugin, so it can be called at the start
+    /// of the process call.
+    ///
+    /// Implicitly emits `Task::ParameterValuesChanged` and `Task::RescanParamValues`.
+    pub fn set_state_slot_inner(&self, snapshot: &mut PluginState) {
+        // `Params::deserialize_fields()` may allocate, see `set_state_inner()`
+        permit_alloc(|| unsafe {
+            state::deserialize_snapshot_object::<P>(
+                snapshot,
+                self.params.clone(),
+                state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash),
+                self.current_buffer_config.load().as_ref(),
+            )
+        });
+
+        let task_posted = self.schedule_gui(Task::ParameterValuesChanged);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        let task_posted = self.schedule_gui(Task::RescanParamValues);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
     unsafe extern "C" fn init(plugin: *const clap_plugin) -> bool {
         check_null_ptr!(false, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
@@ -1855,6 +2300,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             &wrapper.host_callback,
             CLAP_EXT_THREAD_CHECK,
         );
+        *wrapper.host_undo.borrow_mut() =
+            query_host_extension::<clap_host_undo>(&wrapper.host_callback, CLAP_EXT_UNDO);
 
         true
     }
@@ -1900,9 +2347,31 @@ impl<P: ClapPlugin> Wrapper<P> {
             // pointers into a set of `Buffer` objects for the plugin's main and auxiliary IO
             *wrapper.buffer_manager.borrow_mut() =
                 BufferManager::for_audio_io_layout(max_frames_count as usize, audio_io_layout);
+            if P::F64_PROCESSING {
+                *wrapper.buffer_manager_f64.borrow_mut() =
+                    BufferManager::for_audio_io_layout(max_frames_count as usize, audio_io_layout);
+            }
+
+            // The delay line for the bypass' dry signal depends on the plugin's latency. Latency
+            // changes made during `initialize()` are still pending at this point.
+            let latency = init_context
+                .pending_latency()
+                .unwrap_or_else(|| wrapper.current_latency.load(Ordering::SeqCst));
+            wrapper
+                .bypass
+                .borrow_mut()
+                .initialize(&audio_io_layout, &buffer_config, latency);
+            if P::F64_PROCESSING {
+                wrapper.bypass_f64.borrow_mut().initialize(
+                    &audio_io_layout,
+                    &buffer_config,
+                    latency,
+                );
+            }
 
             // Also store this for later, so we can reinitialize the plugin after restoring state
             wrapper.current_buffer_config.store(Some(buffer_config));
+            wrapper.is_active.store(true, Ordering::SeqCst);
 
             true
         } else {
@@ -1915,6 +2384,18 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
         wrapper.plugin.lock().deactivate();
+        wrapper.is_active.store(false, Ordering::SeqCst);
+
+        // If a parameter's step count changed while the plugin was activated, then the host can
+        // now finally be informed about it
+        if wrapper
+            .pending_param_rescan_all
+            .swap(false, Ordering::SeqCst)
+        {
+            if let Some(host_params) = &*wrapper.host_params.borrow() {
+                unsafe_clap_call! { host_params=>rescan(&*wrapper.host_callback, CLAP_PARAM_RESCAN_ALL) };
+            }
+        }
     }
 
     unsafe extern "C" fn start_processing(plugin: *const clap_plugin) -> bool {
@@ -1929,7 +2410,7 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         // To be consistent with the VST3 wrapper, we'll also reset the buffers here in addition to
         // the dedicated `reset()` function.
-        process_wrapper(|| wrapper.plugin.lock().reset());
+        process_wrapper(|| wrapper.reset_plugin());
 
         true
     }
@@ -1939,13 +2420,19 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
         wrapper.is_processing.store(false, Ordering::SeqCst);
+
+        // A state slot switch may still be waiting for the next process call, so we'll need to
+        // load it now instead
+        wrapper
+            .state_slots
+            .handle_pending_load(|snapshot| wrapper.set_state_slot_inner(snapshot));
     }
 
     unsafe extern "C" fn reset(plugin: *const clap_plugin) {
         check_null_ptr!((), plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
-        process_wrapper(|| wrapper.plugin.lock().reset());
+        process_wrapper(|| wrapper.reset_plugin());
     }
 
     unsafe extern "C" fn process(
@@ -1964,11 +2451,26 @@ impl<P: ClapPlugin> Wrapper<P> {
             let process = &*process;
             let total_buffer_len = process.frames_count as usize;
 
-            let current_audio_io_layout = wrapper.current_audio_io_layout.load();
-            let has_main_input = current_audio_io_layout.main_input_channels.is_some();
-            let has_main_output = current_audio_io_layout.main_output_channels.is_some();
-            let aux_input_start_idx = if has_main_input { 1 } else { 0 };
-            let aux_output_start_idx = if has_main_output { 1 } else { 0 };
+            // If the editor switched to another state slot, then that slot's values are loaded
+            // before processing the block so they apply to the entire block
+            wrapper
+                .state_slots
+                .handle_pending_load(|snapshot| wrapper.set_state_slot_inner(snapshot));
+
+            // The host provides either 32-bit or 64-bit channel pointers for every port. It will
+            // only use 64-bit buffers if the plugin advertised support for them. The plugin
+            // processes all ports at the same precision, so mixing the two is not supported.
+            let process_f64 = match buffer_precision(process) {
+                BufferPrecision::Unknown | BufferPrecision::F32 => false,
+                BufferPrecision::F64 => P::F64_PROCESSING,
+                BufferPrecision::Mixed => {
+                    nih_debug_assert_failure!(
+                        "The host passed both 32-bit and 64-bit audio buffers to the plugin"
+                    );
+
+                    return CLAP_PROCESS_ERROR;
+                }
+            };
 
             // If `P::SAMPLE_ACCURATE_AUTOMATION` is set, then we'll split up the audio buffer into
             // chunks whenever a parameter change occurs
@@ -2036,113 +2538,6 @@ impl<P: ClapPlugin> Wrapper<P> {
                 // we can start preparing audio processing
                 let block_len = block_end - block_start;
 
-                // The buffer manager preallocated buffer slices for all the IO and storage for any
-                // axuiliary inputs.
-                // TODO: The audio buffers have a latency field, should we use those?
-                // TODO: Like with VST3, should we expose some way to access or set the silence/constant
-                //       flags?
-                let mut buffer_manager = wrapper.buffer_manager.borrow_mut();
-                let buffers =
-                    buffer_manager.create_buffers(block_start, block_len, |buffer_source| {
-                        // Explicitly take plugins with no main output that does have auxiliary
-                        // outputs into account. Shouldn't happen, but if we just start copying
-                        // audio here then that would result in unsoundness.
-                        if process.audio_outputs_count > 0
-                            && !process.audio_outputs.is_null()
-                            && !(*process.audio_outputs).data32.is_null()
-                            && has_main_output
-                        {
-                            let audio_output = &*process.audio_outputs;
-                            let ptrs = NonNull::new(audio_output.data32 as *mut *mut f32).unwrap();
-                            let num_channels = audio_output.channel_count as usize;
-
-                            *buffer_source.main_output_channel_pointers =
-                                Some(ChannelPointers { ptrs, num_channels });
-                        }
-
-                        if process.audio_inputs_count > 0
-                            && !process.audio_inputs.is_null()
-                            && !(*process.audio_inputs).data32.is_null()
-                            && has_main_input
-                        {
-                            let audio_input = &*process.audio_inputs;
-                            let ptrs = NonNull::new(audio_input.data32 as *mut *mut f32).unwrap();
-                            let num_channels = audio_input.channel_count as usize;
-
-                            *buffer_source.main_input_channel_pointers =
-                                Some(ChannelPointers { ptrs, num_channels });
-                        }
-
-                        if !process.audio_inputs.is_null() {
-                            for (aux_input_no, aux_input_channel_pointers) in buffer_source
-                                .aux_input_channel_pointers
-                                .iter_mut()
-                                .enumerate()
-                            {
-                                let aux_input_idx = aux_input_no + aux_input_start_idx;
-                                if aux_input_idx > process.audio_inputs_count as usize {
-                                    break;
-                                }
-
-                                let audio_input = &*process.audio_inputs.add(aux_input_idx);
-                                match NonNull::new(audio_input.data32 as *mut *mut f32) {
-                                    Some(ptrs) => {
-                                        let num_channels = audio_input.channel_count as usize;
-
-                                        *aux_input_channel_pointers =
-                                            Some(ChannelPointers { ptrs, num_channels });
-                                    }
-                                    None => continue,
-                                }
-                            }
-                        }
-
-                        if !process.audio_outputs.is_null() {
-                            for (aux_output_no, aux_output_channel_pointers) in buffer_source
-                                .aux_output_channel_pointers
-                                .iter_mut()
-                                .enumerate()
-                            {
-                                let aux_output_idx = aux_output_no + aux_output_start_idx;
-                                if aux_output_idx > process.audio_outputs_count as usize {
-                                    break;
-                                }
-
-                                let audio_output = &*process.audio_outputs.add(aux_output_idx);
-                                match NonNull::new(audio_output.data32 as *mut *mut f32) {
-                                    Some(ptrs) => {
-                                        let num_channels = audio_output.channel_count as usize;
-
-                                        *aux_output_channel_pointers =
-                                            Some(ChannelPointers { ptrs, num_channels });
-                                    }
-                                    None => continue,
-                                }
-                            }
-                        }
-                    });
-
-                // If the host does not provide outputs or if it does not provide the required
-                // number of channels (should not happen, but Ableton Live does this for bypassed
-                // VST3 plugins) then we'll skip audio processing. In that case
-                // `buffer_manager.create_buffers` will have set one or more of the output buffers
-                // to empty slices since there is no storage to point them to. The auxiliary input
-                // buffers always point to valid storage.
-                let mut buffer_is_valid = true;
-                for output_buffer_slice in buffers.main_buffer.as_slice_immutable().iter().chain(
-                    buffers
-                        .aux_outputs
-                        .iter()
-                        .flat_map(|buffer| buffer.as_slice_immutable().iter()),
-                ) {
-                    if output_buffer_slice.is_empty() {
-                        buffer_is_valid = false;
-                        break;
-                    }
-                }
-
-                nih_debug_assert!(buffer_is_valid);
-
                 // Some of the fields are left empty because CLAP does not provide this information,
                 // but the methods on [`Transport`] can reconstruct these values from the other
                 // fields
@@ -2235,21 +2630,37 @@ impl<P: ClapPlugin> Wrapper<P> {
                     }
                 }
 
-                let result = if buffer_is_valid {
-                    let mut plugin = wrapper.plugin.lock();
-                    // SAFETY: Shortening these borrows is safe as even if the plugin overwrites the
-                    //         slices (which it cannot do without using unsafe code), then they
-                    //         would still be reset on the next iteration
-                    let mut aux = AuxiliaryBuffers {
-                        inputs: buffers.aux_inputs,
-                        outputs: buffers.aux_outputs,
-                    };
-                    let mut context = wrapper.make_process_context(transport);
-                    let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
-                    wrapper.last_process_status.store(result);
-                    result
+                // Parameters mapped to MIDI CCs are updated before the plugin receives those CCs
+                wrapper.apply_midi_mappings(sample_rate);
+
+                // The host decides whether to use 32-bit or 64-bit buffers, and 64-bit buffers
+                // are only used if the plugin opted into that
+                let result = if process_f64 {
+                    wrapper.process_block(
+                        process,
+                        &wrapper.buffer_manager_f64,
+                        &wrapper.bypass_f64,
+                        |audio_buffer| audio_buffer.data64,
+                        block_start,
+                        block_len,
+                        |plugin, buffer, aux| {
+                            let mut context = wrapper.make_process_context(transport);
+                            plugin.process_f64(buffer, aux, &mut context)
+                        },
+                    )
                 } else {
-                    ProcessStatus::Normal
+                    wrapper.process_block(
+                        process,
+                        &wrapper.buffer_manager,
+                        &wrapper.bypass,
+                        |audio_buffer| audio_buffer.data32,
+                        block_start,
+                        block_len,
+                        |plugin, buffer, aux| {
+                            let mut context = wrapper.make_process_context(transport);
+                            plugin.process(buffer, aux, &mut context)
+                        },
+                    )
                 };
 
                 let clap_result = match result {
@@ -2568,6 +2979,9 @@ impl<P: ClapPlugin> Wrapper<P> {
         } else {
             0
         };
+        if P::F64_PROCESSING {
+            info.flags |= CLAP_AUDIO_PORT_SUPPORTS_64BITS | CLAP_AUDIO_PORT_PREFERS_64BITS;
+        }
         info.channel_count = channel_count;
         info.port_type = port_type;
         info.in_place_pair = pair_stable_id;
@@ -2935,7 +3349,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             param_info.flags |= CLAP_PARAM_IS_STEPPED
         }
         param_info.cookie = std::ptr::null_mut();
-        strlcpy(&mut param_info.name, param_ptr.name());
+        strlcpy(&mut param_info.name, &param_ptr.name());
         strlcpy(&mut param_info.module, param_group);
         // We don't use the actual minimum and maximum values here because that would not scale
         // with skewed integer ranges. Instead, just treat all parameters as `[0, 1]` normalized
@@ -3102,9 +3516,11 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(false, plugin, (*plugin).plugin_data, stream);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
-        let serialized = state::serialize_json::<P>(
+        let serialized = state::serialize_state::<P>(
             wrapper.params.clone(),
             state::make_params_iter(&wrapper.param_by_hash, &wrapper.param_id_to_hash),
+            &wrapper.midi_learn,
+            &wrapper.state_slots,
         );
         match serialized {
             Ok(serialized) => {
@@ -3143,7 +3559,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
         // CLAP does not have a way to tell how much data there is left in a stream, so we've
-        // prepended the size in front of our JSON state
+        // prepended the size in front of our state data
         let mut length_bytes = [0u8; 8];
         if !read_stream(&*stream, length_bytes.as_mut_slice()) {
             nih_debug_assert_failure!(
@@ -3162,7 +3578,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
         read_buffer.set_len(length as usize);
 
-        match state::deserialize_json(&read_buffer) {
+        match state::deserialize_state(&read_buffer) {
             Some(mut state) => {
                 let success = wrapper.set_state_inner(&mut state);
                 if success {
@@ -3212,6 +3628,55 @@ impl<P: ClapPlugin> Wrapper<P> {
     }
 }
 
+/// The sample precision of the audio buffers the host passed to the process function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BufferPrecision {
+    /// None of the ports contain any channel data, so the precision does not matter.
+    Unknown,
+    F32,
+    F64,
+    /// Some ports use 32-bit buffers while others use 64-bit buffers.
+    Mixed,
+}
+
+/// Determine the precision of the audio buffers the host passed to the process function. Every
+/// port with channel data is checked since the CLAP specification only requires each individual
+/// port to use either 32-bit or 64-bit buffers.
+unsafe fn buffer_precision(process: &clap_process) -> BufferPrecision {
+    let outputs = audio_buffers(process.audio_outputs, process.audio_outputs_count);
+    let inputs = audio_buffers(process.audio_inputs, process.audio_inputs_count);
+
+    let mut precision = BufferPrecision::Unknown;
+    for buffer in outputs.iter().chain(inputs) {
+        let port_precision = match (buffer.data32.is_null(), buffer.data64.is_null()) {
+            (false, true) => BufferPrecision::F32,
+            (true, false) => BufferPrecision::F64,
+            // Ports without any channel data work with either precision
+            _ => continue,
+        };
+
+        precision = match precision {
+            BufferPrecision::Unknown => port_precision,
+            precision if precision == port_precision => precision,
+            _ => return BufferPrecision::Mixed,
+        };
+    }
+
+    precision
+}
+
+/// Get a slice for the host's audio buffers, or an empty slice if the pointer is null.
+unsafe fn audio_buffers<'a>(
+    buffers: *const clap_audio_buffer,
+    num_buffers: u32,
+) -> &'a [clap_audio_buffer] {
+    if buffers.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(buffers, num_buffers as usize)
+    }
+}
+
 /// Convenience function to query an extension from the host.
 ///
 /// # Safety
@@ -3229,3 +3694,71 @@ unsafe fn query_host_extension<T>(
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    /// A mono audio port using the given channel pointers. At most one of them should be non-null.
+    fn audio_buffer(data32: *mut *mut f32, data64: *mut *mut f64) -> clap_audio_buffer {
+        clap_audio_buffer {
+            data32,
+            data64,
+            channel_count: 1,
+            latency: 0,
+            constant_mask: 0,
+        }
+    }
+
+    fn process(inputs: &[clap_audio_buffer], outputs: &mut [clap_audio_buffer]) -> clap_process {
+        clap_process {
+            steady_time: -1,
+            frames_count: 1,
+            transport: ptr::null(),
+            audio_inputs: inputs.as_ptr(),
+            audio_outputs: outputs.as_mut_ptr(),
+            audio_inputs_count: inputs.len() as u32,
+            audio_outputs_count: outputs.len() as u32,
+            in_events: ptr::null(),
+            out_events: ptr::null(),
+        }
+    }
+
+    #[test]
+    fn buffer_precision_per_port() {
+        let mut sample32 = 0.0f32;
+        let mut sample64 = 0.0f64;
+        let mut data32 = [&mut sample32 as *mut f32];
+        let mut data64 = [&mut sample64 as *mut f64];
+
+        let empty = audio_buffer(ptr::null_mut(), ptr::null_mut());
+        let port32 = audio_buffer(data32.as_mut_ptr(), ptr::null_mut());
+        let port64 = audio_buffer(ptr::null_mut(), data64.as_mut_ptr());
+        unsafe {
+            assert_eq!(
+                buffer_precision(&process(&[], &mut [])),
+                BufferPrecision::Unknown
+            );
+            assert_eq!(
+                buffer_precision(&process(&[port32], &mut [empty, port32])),
+                BufferPrecision::F32
+            );
+            assert_eq!(
+                buffer_precision(&process(&[port64], &mut [empty, port64])),
+                BufferPrecision::F64
+            );
+
+            // The first port with channel data does not determine the precision for all ports
+            assert_eq!(
+                buffer_precision(&process(&[port64], &mut [port32])),
+                BufferPrecision::Mixed
+            );
+            assert_eq!(
+                buffer_precision(&process(&[], &mut [port64, empty, port32])),
+                BufferPrecision::Mixed
+            );
+        }
+    }
+}
@@ -2,6 +2,7 @@
 //! of relying on a plugin host. This is mostly useful for quickly testing GUI changes.
 
 use clap::{CommandFactory, FromArgMatches};
+use std::sync::Arc;
 
 use self::backend::Backend;
 use self::config::WrapperConfig;
@@ -9,9 +10,12 @@ use self::wrapper::{Wrapper, WrapperError};
 use super::util::setup_logger;
 use crate::prelude::Plugin;
 
+pub use self::status::{StandaloneError, StandaloneStatus};
+
 mod backend;
 mod config;
 mod context;
+mod status;
 mod wrapper;
 
 /// Open an NIH-plug plugin as a standalone application. If the plugin has an editor, this will open
@@ -43,22 +47,46 @@ mod wrapper;
 /// ```
 ///
 /// By default this will connect to the 'default' audio and MIDI ports. Use the command line options
-/// to change this. `--help` lists all available options.
+/// to change this. `--help` lists all available options. If the audio device fails while the
+/// application is running, then processing continues without audio input and output while the
+/// device is periodically reopened. The `--on-device-loss` option can be used to change this.
 ///
-/// If the wrapped plugin fails to initialize or throws an error during audio processing, then this
-/// function will return `false`.
-pub fn nih_export_standalone<P: Plugin>() -> bool {
-    // TODO: If the backend fails to initialize then the standalones will exit normally instead of
-    //       with an error code. This should probably be changed.
-    nih_export_standalone_with_args::<P, _>(std::env::args())
+/// If the audio backend or the wrapped plugin fails to initialize, if the plugin throws an error
+/// during audio processing, or if the audio device fails and the application is configured to exit
+/// when that happens, then the process exits with the non-zero exit code from
+/// [`StandaloneError::exit_code()`].
+pub fn nih_export_standalone<P: Plugin>() {
+    if let Err(err) =
+        nih_export_standalone_with_status_callback::<P, _, _>(std::env::args(), |_| ())
+    {
+        std::process::exit(err.exit_code());
+    }
 }
 
 /// The same as [`nih_export_standalone()`], but with the arguments taken from an iterator instead
-/// of using [`std::env::args()`].
+/// of using [`std::env::args()`]. Returns `false` instead of exiting the process if the application
+/// fails.
 pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = String>>(
     args: Args,
 ) -> bool {
+    nih_export_standalone_with_status_callback::<P, _, _>(args, |_| ()).is_ok()
+}
+
+/// The same as [`nih_export_standalone_with_args()`], but `status_callback` is called whenever the
+/// audio backend starts, fails, or reconnects to a failed audio device. This can be used to monitor
+/// unattended installations. The callback is called from the thread that runs the audio backend,
+/// never from the audio callback itself. Returns the reason the application failed, if it did.
+pub fn nih_export_standalone_with_status_callback<P, Args, F>(
+    args: Args,
+    status_callback: F,
+) -> Result<(), StandaloneError>
+where
+    P: Plugin,
+    Args: IntoIterator<Item = String>,
+    F: Fn(StandaloneStatus) + Send + Sync + 'static,
+{
     setup_logger();
+    let status_callback: Arc<dyn Fn(StandaloneStatus) + Send + Sync> = Arc::new(status_callback);
 
     // Instead of parsing this directly, we need to take a bit of a roundabout approach to get the
     // plugin's name and vendor in here since they'd otherwise be taken from NIH-plug's own
@@ -75,7 +103,7 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
         config::BackendType::Auto => {
             let result = backend::Jack::new::<P>(config.clone()).map(|backend| {
                 nih_log!("Using the JACK backend");
-                run_wrapper::<P, _>(backend, config.clone())
+                run_wrapper::<P, _>(backend, config.clone(), status_callback.clone())
             });
 
             #[cfg(target_os = "linux")]
@@ -83,7 +111,11 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
                 match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::Alsa) {
                     Ok(backend) => {
                         nih_log!("Using the ALSA backend");
-                        Ok(run_wrapper::<P, _>(backend, config.clone()))
+                        Ok(run_wrapper::<P, _>(
+                            backend,
+                            config.clone(),
+                            status_callback.clone(),
+                        ))
                     }
                     Err(err) => {
                         nih_error!(
@@ -99,7 +131,11 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
                 match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::CoreAudio) {
                     Ok(backend) => {
                         nih_log!("Using the CoreAudio backend");
-                        Ok(run_wrapper::<P, _>(backend, config.clone()))
+                        Ok(run_wrapper::<P, _>(
+                            backend,
+                            config.clone(),
+                            status_callback.clone(),
+                        ))
                     }
                     Err(err) => {
                         nih_error!(
@@ -115,7 +151,11 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
                 match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::Wasapi) {
                     Ok(backend) => {
                         nih_log!("Using the WASAPI backend");
-                        Ok(run_wrapper::<P, _>(backend, config.clone()))
+                        Ok(run_wrapper::<P, _>(
+                            backend,
+                            config.clone(),
+                            status_callback.clone(),
+                        ))
                     }
                     Err(err) => {
                         nih_error!(
@@ -129,67 +169,77 @@ pub fn nih_export_standalone_with_args<P: Plugin, Args: IntoIterator<Item = Stri
 
             result.unwrap_or_else(|_| {
                 nih_error!("Falling back to the dummy audio backend, audio and MIDI will not work");
-                run_wrapper::<P, _>(backend::Dummy::new::<P>(config.clone()), config)
+                run_wrapper::<P, _>(
+                    backend::Dummy::new::<P>(config.clone()),
+                    config,
+                    status_callback,
+                )
             })
         }
         config::BackendType::Jack => match backend::Jack::new::<P>(config.clone()) {
-            Ok(backend) => run_wrapper::<P, _>(backend, config),
+            Ok(backend) => run_wrapper::<P, _>(backend, config, status_callback),
             Err(err) => {
                 nih_error!("Could not initialize the JACK backend: {:#}", err);
-                false
+                Err(StandaloneError::BackendInitializationFailed)
             }
         },
         #[cfg(target_os = "linux")]
         config::BackendType::Alsa => {
             match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::Alsa) {
-                Ok(backend) => run_wrapper::<P, _>(backend, config),
+                Ok(backend) => run_wrapper::<P, _>(backend, config, status_callback),
                 Err(err) => {
                     nih_error!("Could not initialize the ALSA backend: {:#}", err);
-                    false
+                    Err(StandaloneError::BackendInitializationFailed)
                 }
             }
         }
         #[cfg(target_os = "macos")]
         config::BackendType::CoreAudio => {
             match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::CoreAudio) {
-                Ok(backend) => run_wrapper::<P, _>(backend, config),
+                Ok(backend) => run_wrapper::<P, _>(backend, config, status_callback),
                 Err(err) => {
                     nih_error!("Could not initialize the CoreAudio backend: {:#}", err);
-                    false
+                    Err(StandaloneError::BackendInitializationFailed)
                 }
             }
         }
         #[cfg(target_os = "windows")]
         config::BackendType::Wasapi => {
             match backend::CpalMidir::new::<P>(config.clone(), cpal::HostId::Wasapi) {
-                Ok(backend) => run_wrapper::<P, _>(backend, config),
+                Ok(backend) => run_wrapper::<P, _>(backend, config, status_callback),
                 Err(err) => {
                     nih_error!("Could not initialize the WASAPI backend: {:#}", err);
-                    false
+                    Err(StandaloneError::BackendInitializationFailed)
                 }
             }
         }
-        config::BackendType::Dummy => {
-            run_wrapper::<P, _>(backend::Dummy::new::<P>(config.clone()), config)
-        }
+        config::BackendType::Dummy => run_wrapper::<P, _>(
+            backend::Dummy::new::<P>(config.clone()),
+            config,
+            status_callback,
+        ),
     }
 }
 
-fn run_wrapper<P: Plugin, B: Backend<P>>(backend: B, config: WrapperConfig) -> bool {
-    let wrapper = match Wrapper::<P, _>::new(backend, config) {
+fn run_wrapper<P: Plugin, B: Backend<P>>(
+    backend: B,
+    config: WrapperConfig,
+    status_callback: Arc<dyn Fn(StandaloneStatus) + Send + Sync>,
+) -> Result<(), StandaloneError> {
+    let wrapper = match Wrapper::<P, _>::new(backend, config, status_callback) {
         Ok(wrapper) => wrapper,
         Err(err) => {
             print_error(err);
-            return false;
+            return Err(err.into());
         }
     };
 
     // TODO: Add a repl while the application is running to interact with parameters
     match wrapper.run() {
-        Ok(()) => true,
+        Ok(()) => Ok(()),
         Err(err) => {
             print_error(err);
-            false
+            Err(err.into())
         }
     }
 }
@@ -199,5 +249,11 @@ fn print_error(error: WrapperError) {
         WrapperError::InitializationFailed => {
             nih_error!("The plugin failed to initialize");
         }
+        WrapperError::ProcessingFailed => {
+            nih_error!("The plugin returned an error while processing audio, exiting");
+        }
+        WrapperError::DeviceLost => {
+            nih_error!("The audio device failed, exiting");
+        }
     }
 }
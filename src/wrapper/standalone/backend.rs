@@ -1,8 +1,11 @@
+use anyhow::Result;
+
 use crate::prelude::{AuxiliaryBuffers, PluginNoteEvent, Transport};
 
 mod cpal;
 mod dummy;
 mod jack;
+mod midi_clock;
 
 pub use self::cpal::CpalMidir;
 pub use self::dummy::Dummy;
@@ -16,6 +19,10 @@ pub trait Backend<P: Plugin>: 'static + Send + Sync {
     /// there's a new block of audio to be processed. The process callback receives the audio
     /// buffers for the wrapped plugin's outputs. Any inputs will have already been copied to this
     /// buffer. This will block until the process callback returns `false`.
+    ///
+    /// Returns an error if processing stopped because the audio device failed or disappeared. In
+    /// that case [`reconnect()`][Self::reconnect()] needs to succeed before this function can be
+    /// called again.
     fn run(
         &mut self,
         cb: impl FnMut(
@@ -27,5 +34,12 @@ pub trait Backend<P: Plugin>: 'static + Send + Sync {
             ) -> bool
             + 'static
             + Send,
-    );
+    ) -> Result<()>;
+
+    /// Try to reopen the backend's audio and MIDI devices after [`run()`][Self::run()] returned an
+    /// error. Returns an error if the devices are still not available.
+    fn reconnect(&mut self) -> Result<()>;
+
+    /// The backend's name, used for logging and status reporting.
+    fn name(&self) -> &'static str;
 }
@@ -1,20 +1,29 @@
 use anyhow::{Context, Result};
 use cpal::{
     traits::*, Device, FromSample, InputCallbackInfo, OutputCallbackInfo, Sample, SampleFormat,
-    Stream, StreamConfig,
+    Stream, StreamConfig, StreamError,
 };
+use crossbeam::channel::RecvTimeoutError;
 use crossbeam::sync::{Parker, Unparker};
 use midir::{
-    MidiInput, MidiInputConnection, MidiInputPort, MidiOutput, MidiOutputConnection, MidiOutputPort,
+    MidiIO, MidiInput, MidiInputConnection, MidiInputPort, MidiOutput, MidiOutputConnection,
+    MidiOutputPort,
 };
 use parking_lot::Mutex;
 use rtrb::RingBuffer;
 use std::borrow::Borrow;
 use std::num::NonZeroU32;
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread::ScopedJoinHandle;
+use std::time::{Duration, Instant};
 
 use super::super::config::WrapperConfig;
+use super::midi_clock::{
+    MidiClockEstimator, MidiClockReceiver, MIDI_CLOCK_PPQN, MIDI_START, MIDI_STOP,
+    MIDI_TIMING_CLOCK,
+};
 use super::Backend;
 use crate::midi::MidiResult;
 use crate::prelude::{
@@ -29,12 +38,33 @@ const MIDI_EVENT_QUEUE_CAPACITY: usize = 2048;
 pub struct CpalMidir {
     config: WrapperConfig,
     audio_io_layout: AudioIOLayout,
+    /// The audio API this backend was created for. Needed to reopen the devices in `reconnect()`.
+    cpal_host_id: cpal::HostId,
 
     input: Option<CpalDevice>,
     output: CpalDevice,
+    /// The zero-indexed input device channel for each of the plugin's input channels, if that
+    /// channel is connected. The main input's channels come first, followed by the channels of
+    /// each auxiliary input port.
+    input_channel_map: Vec<Option<usize>>,
+    /// The zero-indexed output device channel for each of the plugin's output channels, if that
+    /// channel is connected. Uses the same order as `input_channel_map`.
+    output_channel_map: Vec<Option<usize>>,
+
+    midi_inputs: Mutex<Vec<MidirInputDevice>>,
+    midi_outputs: Mutex<Vec<MidirOutputDevice>>,
+}
 
-    midi_input: Mutex<Option<MidirInputDevice>>,
-    midi_output: Mutex<Option<MidirOutputDevice>>,
+/// Keeps track of errors reported by the audio streams' error callbacks. When a stream fails
+/// because the audio device was unplugged or otherwise became unavailable, `run()` stops and
+/// returns the error.
+#[derive(Default)]
+struct StreamFailure {
+    /// Set when either stream reported that the device is no longer available. The output callback
+    /// checks this so it doesn't wait indefinitely for input from a failed input stream.
+    failed: AtomicBool,
+    /// The first error reported by either stream.
+    error: Mutex<Option<StreamError>>,
 }
 
 /// All data needed for a CPAL input or output stream.
@@ -60,12 +90,16 @@ struct ActiveMidirInputDevice {
 struct MidirOutputDevice {
     pub backend: MidiOutput,
     pub port: MidiOutputPort,
+    /// The MIDI channels that are routed to this port as a bitmask, where bit `n` corresponds to
+    /// the zero-indexed channel `n`. Events without a channel, like SysEx, are always sent.
+    pub channels: u16,
 }
 
 /// An active `MidirOutputDevice`. Transformed back and from this during the `.run()` function.
 struct ActiveMidirOutputDevice {
     pub connection: MidiOutputConnection,
     pub port: MidiOutputPort,
+    pub channels: u16,
 }
 
 /// Send+Sync wrapper for `Vec<*mut f32>` so we can preallocate channel pointer vectors for use with
@@ -83,6 +117,16 @@ impl ChannelPointerVec {
     }
 }
 
+/// One of the plugin's input or output channels. Used to map device channels to plugin channels.
+#[derive(Debug, Clone, Copy)]
+enum PluginChannel {
+    /// A channel in the main input or output, with the channel's index.
+    Main(usize),
+    /// A channel in an auxiliary input or output port, with the port's index and the channel's
+    /// index within that port.
+    Aux(usize, usize),
+}
+
 /// A task for the MIDI output thread.
 enum MidiOutputTask<P: Plugin> {
     /// Send an event as MIDI data.
@@ -103,7 +147,7 @@ impl<P: Plugin> Backend<P> for CpalMidir {
             ) -> bool
             + 'static
             + Send,
-    ) {
+    ) -> Result<()> {
         // So this is a lot of fun. There are up to four separate streams here, all using their own
         // callbacks. The audio output stream acts as the primary stream, and everything else either
         // sends data to it or (in the case of the MIDI output stream) receives data from it using
@@ -119,19 +163,26 @@ impl<P: Plugin> Backend<P> for CpalMidir {
         // MIDI input is parsed in the Midir callback and the events are sent over a callback to the
         // output audio thread where the process callback happens. If that process callback outputs
         // events then those are sent over another ringbuffer to a thread that handles MIDI output.
-        // Both MIDI input and MIDI output are disabled by default.
+        // Both MIDI input and MIDI output are disabled by default. If enabled, MIDI clock messages
+        // received on the MIDI input are used to drive the transport, and the MIDI output thread
+        // can also send MIDI clock based on the configured tempo.
         //
         // The thread scope is needed to accomodate the midir MIDI output API. Outputting MIDI is
         // realtime unsafe, and to be able to output MIDI with midir you need to transform between
         // `MidiOutputPort` and `MidiOutputPortConnection` types by taking values out of an
         // `Option`.
+        //
+        // If either audio stream fails, for instance because the device was unplugged, then the
+        // streams' error callbacks unblock this function and the error is returned so the wrapper
+        // can try to reconnect.
+        let stream_failure = Arc::new(StreamFailure::default());
         std::thread::scope(|s| {
             let mut _input_stream: Option<Stream> = None;
             let mut input_rb_consumer: Option<rtrb::Consumer<f32>> = None;
             if let Some(input) = &self.input {
                 // Data is sent to the output data callback using a wait-free ring buffer
                 let (rb_producer, rb_consumer) = RingBuffer::new(
-                    self.output.config.channels as usize * self.config.period_size as usize,
+                    input.config.channels as usize * self.config.period_size as usize,
                 );
                 input_rb_consumer = Some(rb_consumer);
 
@@ -139,9 +190,12 @@ impl<P: Plugin> Backend<P> for CpalMidir {
                 let input_unparker = input_parker.unparker().clone();
                 let error_cb = {
                     let input_unparker = input_unparker.clone();
+                    let stream_failure = stream_failure.clone();
                     move |err| {
                         nih_error!("Error during capture: {err:#}");
-                        input_unparker.clone().unpark();
+                        if stream_failure.report(err) {
+                            input_unparker.clone().unpark();
+                        }
                     }
                 };
 
@@ -171,46 +225,74 @@ impl<P: Plugin> Backend<P> for CpalMidir {
                     (SampleFormat::F32, f32),
                     (SampleFormat::F64, f64)
                 )
-                .expect("Fatal error creating the capture stream");
+                .context("Could not create the capture stream")?;
                 stream
                     .play()
-                    .expect("Fatal error trying to start the capture stream");
+                    .context("Could not start the capture stream")?;
                 _input_stream = Some(stream);
 
                 // Playback is delayed one period if we're capturing audio so it has something to
                 // process
-                input_parker.park()
+                input_parker.park();
+                if let Some(err) = stream_failure.take_error() {
+                    return Err(err).context("The audio input device failed");
+                }
             }
 
-            // The output callback can read input events from this ringbuffer
-            let mut midi_input_rb_consumer: Option<rtrb::Consumer<PluginNoteEvent<P>>> = None;
-            let midi_input_connection: Option<ActiveMidirInputDevice> =
-                self.midi_input.lock().take().and_then(|midi_input| {
+            // The output callback can read input events from these ringbuffers, with one ringbuffer
+            // per MIDI input port. Events are paired with the time they were received at so the
+            // events from different ports can be merged and positioned within the buffer.
+            let mut midi_input_rb_consumers: Vec<rtrb::Consumer<(Instant, PluginNoteEvent<P>)>> =
+                Vec::new();
+            // If MIDI clock input is enabled, the first MIDI input's callback writes the received
+            // transport information to this object and the output callback then reads it
+            let mut midi_clock_receiver: Option<Arc<MidiClockReceiver>> = None;
+            let midi_input_connections: Vec<ActiveMidirInputDevice> = self
+                .midi_inputs
+                .lock()
+                .drain(..)
+                .enumerate()
+                .filter_map(|(input_idx, midi_input)| {
                     // Data is sent to the output data callback using a wait-free ring buffer
                     let (rb_producer, rb_consumer) = RingBuffer::new(MIDI_EVENT_QUEUE_CAPACITY);
-                    midi_input_rb_consumer = Some(rb_consumer);
+                    let port_midi_clock_receiver = if input_idx == 0 && self.config.midi_clock_input
+                    {
+                        let receiver = Arc::new(MidiClockReceiver::default());
+                        midi_clock_receiver = Some(receiver.clone());
+
+                        Some(receiver)
+                    } else {
+                        None
+                    };
 
                     let result = midi_input.backend.connect(
                         &midi_input.port,
                         "MIDI input",
-                        self.build_midi_input_thread::<P>(rb_producer),
+                        self.build_midi_input_thread::<P>(rb_producer, port_midi_clock_receiver),
                         (),
                     );
 
                     match result {
-                        Ok(connection) => Some(ActiveMidirInputDevice {
-                            connection,
-                            port: midi_input.port,
-                        }),
+                        Ok(connection) => {
+                            midi_input_rb_consumers.push(rb_consumer);
+
+                            Some(ActiveMidirInputDevice {
+                                connection,
+                                port: midi_input.port,
+                            })
+                        }
                         Err(err) => {
                             // We won't retry once this fails
                             nih_error!("Could not create the MIDI input connection: {err:#}");
-                            midi_input_rb_consumer = None;
+                            if input_idx == 0 {
+                                midi_clock_receiver = None;
+                            }
 
                             None
                         }
                     }
-                });
+                })
+                .collect();
 
             // The output callback can also emit MIDI events. To handle these we'll need to spawn
             // our own thread. This can be simplified a lot by using the `MidiOutputConnection`
@@ -221,60 +303,105 @@ impl<P: Plugin> Backend<P> for CpalMidir {
             //        do.
             let mut midi_output_rb_producer: Option<crossbeam::channel::Sender<MidiOutputTask<P>>> =
                 None;
-            let midi_output_connection: Option<ScopedJoinHandle<ActiveMidirOutputDevice>> =
-                self.midi_output.lock().take().and_then(|midi_output| {
+            let midi_output_connections: Option<ScopedJoinHandle<Vec<ActiveMidirOutputDevice>>> = {
+                let mut connections = Vec::new();
+                for midi_output in self.midi_outputs.lock().drain(..) {
+                    match midi_output
+                        .backend
+                        .connect(&midi_output.port, "MIDI output")
+                    {
+                        Ok(connection) => connections.push(ActiveMidirOutputDevice {
+                            connection,
+                            port: midi_output.port,
+                            channels: midi_output.channels,
+                        }),
+                        Err(err) => {
+                            nih_error!("Could not create the MIDI output connection: {err:#}")
+                        }
+                    }
+                }
+
+                if connections.is_empty() {
+                    None
+                } else {
                     // This uses crossbeam channels for the reason mentioned above, but to keep
                     // things cohesive we'll use the same naming scheme as we use for rtrb
                     let (sender, receiver) = crossbeam::channel::bounded(MIDI_EVENT_QUEUE_CAPACITY);
                     midi_output_rb_producer = Some(sender);
 
-                    let result = midi_output
-                        .backend
-                        .connect(&midi_output.port, "MIDI output");
+                    // When sending MIDI clock, the thread wakes up at every clock pulse in addition to
+                    // whenever there are new events to send
+                    let clock_interval = self.config.midi_clock_output.then(|| {
+                        Duration::from_secs_f64(60.0 / (self.config.tempo as f64 * MIDI_CLOCK_PPQN))
+                    });
 
-                    match result {
-                        Ok(mut connection) => Some(s.spawn(move || {
-                            while let Ok(task) = receiver.recv() {
-                                match task {
-                                    MidiOutputTask::Send(event) => match event.as_midi() {
+                    Some(s.spawn(move || {
+                        let mut next_clock = Instant::now();
+                        if clock_interval.is_some() {
+                            send_midi_message(&mut connections, None, &[MIDI_START]);
+                        }
+
+                        loop {
+                            let task = match clock_interval {
+                                Some(clock_interval) => match receiver.recv_deadline(next_clock) {
+                                    Ok(task) => task,
+                                    Err(RecvTimeoutError::Timeout) => {
+                                        send_midi_message(
+                                            &mut connections,
+                                            None,
+                                            &[MIDI_TIMING_CLOCK],
+                                        );
+
+                                        // Scheduling relative to the previous deadline prevents
+                                        // the clock from drifting
+                                        next_clock += clock_interval;
+                                        continue;
+                                    }
+                                    Err(RecvTimeoutError::Disconnected) => break,
+                                },
+                                None => match receiver.recv() {
+                                    Ok(task) => task,
+                                    Err(_) => break,
+                                },
+                            };
+
+                            match task {
+                                MidiOutputTask::Send(event) => {
+                                    // Events are routed to ports based on their MIDI channel
+                                    let channel = event.channel();
+                                    match event.as_midi() {
                                         Some(MidiResult::Basic(midi_data)) => {
-                                            if let Err(err) = connection.send(&midi_data) {
-                                                nih_error!("Could not send MIDI event: {err}");
-                                            }
+                                            send_midi_message(&mut connections, channel, &midi_data)
                                         }
                                         Some(MidiResult::SysEx(padded_sysex_buffer, length)) => {
                                             // The SysEx buffer may contain padding
                                             let padded_sysex_buffer = padded_sysex_buffer.borrow();
                                             nih_debug_assert!(length <= padded_sysex_buffer.len());
 
-                                            if let Err(err) =
-                                                connection.send(&padded_sysex_buffer[..length])
-                                            {
-                                                nih_error!("Could not send MIDI event: {err}");
-                                            }
+                                            send_midi_message(
+                                                &mut connections,
+                                                channel,
+                                                &padded_sysex_buffer[..length],
+                                            )
                                         }
                                         None => (),
-                                    },
-                                    MidiOutputTask::Terminate => break,
+                                    }
                                 }
+                                MidiOutputTask::Terminate => break,
                             }
+                        }
 
-                            // We'll return the same value from the join handle as what ends up
-                            // being stored in `midi_input_connection` to keep this symmetrical with
-                            // the input handling
-                            ActiveMidirOutputDevice {
-                                connection,
-                                port: midi_output.port,
-                            }
-                        })),
-                        Err(err) => {
-                            nih_error!("Could not create the MIDI output connection: {err:#}");
-                            midi_output_rb_producer = None;
-
-                            None
+                        if clock_interval.is_some() {
+                            send_midi_message(&mut connections, None, &[MIDI_STOP]);
                         }
-                    }
-                });
+
+                        // We'll return the same values from the join handle as what ends up being
+                        // stored in `midi_input_connections` to keep this symmetrical with the
+                        // input handling
+                        connections
+                    }))
+                }
+            };
 
             // This thread needs to be blocked until audio processing ends as CPAL processes the
             // streams on another thread instead of blocking
@@ -282,9 +409,12 @@ impl<P: Plugin> Backend<P> for CpalMidir {
             let unparker = parker.unparker().clone();
             let error_cb = {
                 let unparker = unparker.clone();
+                let stream_failure = stream_failure.clone();
                 move |err| {
                     nih_error!("Error during playback: {err:#}");
-                    unparker.clone().unpark();
+                    if stream_failure.report(err) {
+                        unparker.clone().unpark();
+                    }
                 }
             };
 
@@ -296,7 +426,9 @@ impl<P: Plugin> Backend<P> for CpalMidir {
                             self.build_output_data_callback::<P, $primitive_type>(
                                 unparker,
                                 input_rb_consumer,
-                                midi_input_rb_consumer,
+                                midi_input_rb_consumers,
+                                midi_clock_receiver.clone(),
+                                stream_failure.clone(),
                                 // This is a MPMC crossbeam channel instead of an rtrb ringbuffer, and we
                                 // also need it to terminate the thread
                                 midi_output_rb_producer.clone(),
@@ -322,41 +454,92 @@ impl<P: Plugin> Backend<P> for CpalMidir {
                 (SampleFormat::F32, f32),
                 (SampleFormat::F64, f64)
             )
-            .expect("Fatal error creating the output stream");
+            .context("Could not create the playback stream")?;
 
             // TODO: Wait a period before doing this when also reading the input
             output_stream
                 .play()
-                .expect("Fatal error trying to start the output stream");
+                .context("Could not start the playback stream")?;
 
             // Wait for the audio thread to exit
             parker.park();
 
             // The Midir API requires us to take things out of Options and transform between these
             // structs
-            *self.midi_input.lock() =
-                midi_input_connection.map(|midi_input_connection| MidirInputDevice {
+            *self.midi_inputs.lock() = midi_input_connections
+                .into_iter()
+                .map(|midi_input_connection| MidirInputDevice {
                     backend: midi_input_connection.connection.close().0,
                     port: midi_input_connection.port,
-                });
-            *self.midi_output.lock() =
-                midi_output_connection.map(move |midi_output_connection_handle| {
+                })
+                .collect();
+            *self.midi_outputs.lock() = midi_output_connections
+                .map(move |midi_output_connections_handle| {
                     // The thread needs to be terminated first
                     midi_output_rb_producer
                         .expect("Inconsistent internal MIDI output state")
                         .send(MidiOutputTask::Terminate)
                         .expect("Could not terminate the MIDI output thread");
 
-                    let midi_output_connection = midi_output_connection_handle
+                    midi_output_connections_handle
                         .join()
-                        .expect("MIDI output thread panicked");
+                        .expect("MIDI output thread panicked")
+                        .into_iter()
+                        .map(|midi_output_connection| MidirOutputDevice {
+                            backend: midi_output_connection.connection.close(),
+                            port: midi_output_connection.port,
+                            channels: midi_output_connection.channels,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
 
-                    MidirOutputDevice {
-                        backend: midi_output_connection.connection.close(),
-                        port: midi_output_connection.port,
-                    }
-                });
-        });
+            match stream_failure.take_error() {
+                Some(err) => Err(err).context("The audio device failed"),
+                None => Ok(()),
+            }
+        })
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        // The devices may have been assigned new identities, so everything is opened from scratch
+        *self = CpalMidir::new::<P>(self.config.clone(), self.cpal_host_id)?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        self.cpal_host_id.name()
+    }
+}
+
+impl StreamFailure {
+    /// Called from a stream's error callback. Only `StreamError::DeviceNotAvailable` means the
+    /// device is gone, other errors are only logged by the callback and are otherwise ignored.
+    /// Returns `true` if the stream has failed. Only the first error is kept.
+    fn report(&self, err: StreamError) -> bool {
+        if !matches!(err, StreamError::DeviceNotAvailable) {
+            return false;
+        }
+
+        let mut error = self.error.lock();
+        if error.is_none() {
+            *error = Some(err);
+        }
+
+        self.failed.store(true, Ordering::SeqCst);
+
+        true
+    }
+
+    /// Whether either stream has failed. Safe to call from the audio thread.
+    fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Take the first reported error, if there was one.
+    fn take_error(&self) -> Option<StreamError> {
+        self.error.lock().take()
     }
 }
 
@@ -374,12 +557,18 @@ impl CpalMidir {
             )
         }
 
-        if config.midi_input.is_none() && P::MIDI_INPUT >= MidiConfig::Basic {
+        if config.midi_input.is_empty() && P::MIDI_INPUT >= MidiConfig::Basic {
             nih_log!("Use the '--midi-input' option to select a MIDI input device.")
         }
-        if config.midi_output.is_none() && P::MIDI_OUTPUT >= MidiConfig::Basic {
+        if config.midi_output.is_empty() && P::MIDI_OUTPUT >= MidiConfig::Basic {
             nih_log!("Use the '--midi-output' option to select a MIDI output device.")
         }
+        if config.midi_clock_input && config.midi_input.is_empty() {
+            nih_warn!("'--midi-clock-input' requires a MIDI input device to be set.")
+        }
+        if config.midi_clock_output && config.midi_output.is_empty() {
+            nih_warn!("'--midi-clock-output' requires a MIDI output device to be set.")
+        }
 
         // No input device is connected unless requested by the user to avoid feedback loops
         let input_device = config
@@ -428,38 +617,86 @@ impl CpalMidir {
                 .context("No default audio output device available")?,
         };
 
-        let requested_sample_rate = cpal::SampleRate(config.sample_rate as u32);
-        let requested_buffer_size = cpal::BufferSize::Fixed(config.period_size);
-        let num_input_channels = audio_io_layout
+        // The channel maps determine how many channels the audio devices need to have
+        let num_plugin_input_channels = audio_io_layout
             .main_input_channels
             .map(NonZeroU32::get)
-            .unwrap_or_default() as usize;
+            .unwrap_or_default() as usize
+            + audio_io_layout
+                .aux_input_ports
+                .iter()
+                .map(|channel_count| channel_count.get() as usize)
+                .sum::<usize>();
+        let num_plugin_output_channels = audio_io_layout
+            .main_output_channels
+            .map(NonZeroU32::get)
+            .unwrap_or_default() as usize
+            + audio_io_layout
+                .aux_output_ports
+                .iter()
+                .map(|channel_count| channel_count.get() as usize)
+                .sum::<usize>();
+        let input_channel_map = match &config.input_channel_map {
+            Some(channel_map) => parse_channel_map(
+                channel_map,
+                num_plugin_input_channels,
+                "--input-channel-map",
+            )?,
+            None => default_channel_map(
+                audio_io_layout.main_input_channels,
+                num_plugin_input_channels,
+            ),
+        };
+        let output_channel_map = match &config.output_channel_map {
+            Some(channel_map) => parse_channel_map(
+                channel_map,
+                num_plugin_output_channels,
+                "--output-channel-map",
+            )?,
+            None => default_channel_map(
+                audio_io_layout.main_output_channels,
+                num_plugin_output_channels,
+            ),
+        };
+
+        if config.input_channel_map.is_none() && !audio_io_layout.aux_input_ports.is_empty() {
+            nih_log!(
+                "Sidechain inputs are not connected by default. Use the '--input-channel-map' \
+                 option to connect them to input device channels."
+            )
+        }
+        if config.output_channel_map.is_none() && !audio_io_layout.aux_output_ports.is_empty() {
+            nih_log!(
+                "Auxiliary outputs are not connected by default. Use the '--output-channel-map' \
+                 option to connect them to output device channels."
+            )
+        }
+
+        let requested_sample_rate = cpal::SampleRate(config.sample_rate as u32);
+        let requested_buffer_size = cpal::BufferSize::Fixed(config.period_size);
+        let num_input_device_channels = required_device_channels(&input_channel_map);
         let input = input_device
             .map(|device| -> Result<CpalDevice> {
-                let input_configs: Vec<_> = device
+                let input_config_range = device
                     .supported_input_configs()
                     .context("Could not get supported audio input configurations")?
                     .filter(|c| match c.buffer_size() {
                         cpal::SupportedBufferSize::Range { min, max } => {
-                            c.channels() as usize == num_input_channels
+                            c.channels() as usize >= num_input_device_channels
                                 && (c.min_sample_rate()..=c.max_sample_rate())
                                     .contains(&requested_sample_rate)
                                 && (min..=max).contains(&&config.period_size)
                         }
                         cpal::SupportedBufferSize::Unknown => false,
                     })
-                    .collect();
-                let input_config_range = input_configs
-                    .iter()
-                    // Prefer floating point samples to avoid conversions
-                    .find(|c| c.sample_format() == SampleFormat::F32)
-                    .or_else(|| input_configs.first())
-                    .cloned()
+                    // Prefer floating point samples to avoid conversions, and don't open more
+                    // channels than needed
+                    .min_by_key(|c| (c.sample_format() != SampleFormat::F32, c.channels()))
                     .with_context(|| {
                         format!(
                             "The audio input device does not support {} audio channels at a \
                              sample rate of {} Hz and a period size of {} samples",
-                            num_input_channels, config.sample_rate, config.period_size,
+                            num_input_device_channels, config.sample_rate, config.period_size,
                         )
                     })?;
 
@@ -479,34 +716,26 @@ impl CpalMidir {
             })
             .transpose()?;
 
-        let num_output_channels = audio_io_layout
-            .main_output_channels
-            .map(NonZeroU32::get)
-            .unwrap_or_default() as usize;
+        let num_output_device_channels = required_device_channels(&output_channel_map);
         let output = {
-            let output_configs: Vec<_> = output_device
+            let output_config_range = output_device
                 .supported_output_configs()
                 .context("Could not get supported audio output configurations")?
                 .filter(|c| match c.buffer_size() {
                     cpal::SupportedBufferSize::Range { min, max } => {
-                        c.channels() as usize == num_output_channels
+                        c.channels() as usize >= num_output_device_channels
                             && (c.min_sample_rate()..=c.max_sample_rate())
                                 .contains(&requested_sample_rate)
                             && (min..=max).contains(&&config.period_size)
                     }
                     cpal::SupportedBufferSize::Unknown => false,
                 })
-                .collect();
-            let output_config_range = output_configs
-                .iter()
-                .find(|c| c.sample_format() == SampleFormat::F32)
-                .or_else(|| output_configs.first())
-                .cloned()
+                .min_by_key(|c| (c.sample_format() != SampleFormat::F32, c.channels()))
                 .with_context(|| {
                     format!(
                         "The audio output device does not support {} audio channels at a sample \
                          rate of {} Hz and a period size of {} samples",
-                        num_output_channels, config.sample_rate, config.period_size,
+                        num_output_device_channels, config.sample_rate, config.period_size,
                     )
                 })?;
             let output_config = StreamConfig {
@@ -523,106 +752,58 @@ impl CpalMidir {
             }
         };
 
-        // There's no obvious way to do sidechain inputs and additional outputs with the CPAL
-        // backends like there is with JACK. So we'll just provide empty buffers instead.
-        if !audio_io_layout.aux_input_ports.is_empty() {
-            nih_warn!("Sidechain inputs are not supported with this audio backend");
-        }
-        if !audio_io_layout.aux_output_ports.is_empty() {
-            nih_warn!("Auxiliary outputs are not supported with this audio backend");
-        }
-
-        let midi_input = match &config.midi_input {
-            Some(midi_input_name) => {
-                // Midir lets us preemptively ignore MIDI messages we'll never use like active
-                // sensing and timing, but for maximum flexibility with NIH-plug's SysEx parsing
-                // types (which could technically be used to also parse those things) we won't do
-                // that.
-                let midi_backend = MidiInput::new(P::NAME)
-                    .context("Could not initialize the MIDI input backend")?;
-                let available_ports = midi_backend.ports();
-
-                // In case there somehow is a MIDI port with an empty name, we'll still want to
-                // preserve the behavior of an empty argument resulting in a listing of options.
-                let found_port = if !midi_input_name.is_empty() {
-                    // This API is a bit weird
-                    available_ports
-                        .iter()
-                        .find(|port| midi_backend.port_name(port).as_deref() == Ok(midi_input_name))
-                } else {
-                    None
-                };
-
-                match found_port {
-                    Some(port) => Some(MidirInputDevice {
-                        backend: midi_backend,
-                        port: port.clone(),
-                    }),
-                    None => {
-                        let mut message = format!(
-                            "Unknown input MIDI device '{midi_input_name}'. Available devices are:"
-                        );
-                        for port in available_ports {
-                            match midi_backend.port_name(&port) {
-                                Ok(device_name) => message.push_str(&format!("\n{device_name}")),
-                                Err(err) => message.push_str(&format!("\nERROR: {err:#}")),
-                            }
-                        }
-
-                        anyhow::bail!(message);
-                    }
-                }
+        // Every MIDI port gets its own backend object since midir's connection functions consume them
+        let mut midi_inputs = Vec::with_capacity(config.midi_input.len());
+        for midi_input_pattern in &config.midi_input {
+            // Midir lets us preemptively ignore MIDI messages we'll never use like active sensing
+            // and timing, but for maximum flexibility with NIH-plug's SysEx parsing types (which
+            // could technically be used to also parse those things) we won't do that.
+            let midi_backend =
+                MidiInput::new(P::NAME).context("Could not initialize the MIDI input backend")?;
+
+            match find_midi_port(&midi_backend, midi_input_pattern) {
+                Some(port) => midi_inputs.push(MidirInputDevice {
+                    backend: midi_backend,
+                    port,
+                }),
+                None => anyhow::bail!(
+                    "Unknown input MIDI device '{midi_input_pattern}'. Available devices are:{}",
+                    list_midi_ports(&midi_backend)
+                ),
             }
-            None => None,
-        };
-
-        let midi_output = match &config.midi_output {
-            Some(midi_output_name) => {
-                let midi_backend = MidiOutput::new(P::NAME)
-                    .context("Could not initialize the MIDI output backend")?;
-                let available_ports = midi_backend.ports();
-
-                let found_port = if !midi_output_name.is_empty() {
-                    available_ports.iter().find(|port| {
-                        midi_backend.port_name(port).as_deref() == Ok(midi_output_name)
-                    })
-                } else {
-                    None
-                };
-
-                match found_port {
-                    Some(port) => Some(MidirOutputDevice {
-                        backend: midi_backend,
-                        port: port.clone(),
-                    }),
-                    None => {
-                        let mut message = format!(
-                            "Unknown output MIDI device '{midi_output_name}'. Available devices \
-                             are:"
-                        );
-                        for port in available_ports {
-                            match midi_backend.port_name(&port) {
-                                Ok(device_name) => message.push_str(&format!("\n{device_name}")),
-                                Err(err) => message.push_str(&format!("\nERROR: {err:#}")),
-                            }
-                        }
+        }
 
-                        anyhow::bail!(message);
-                    }
-                }
+        let mut midi_outputs = Vec::with_capacity(config.midi_output.len());
+        for midi_output_spec in &config.midi_output {
+            let (midi_output_pattern, channels) = parse_midi_output_spec(midi_output_spec)?;
+            let midi_backend =
+                MidiOutput::new(P::NAME).context("Could not initialize the MIDI output backend")?;
+
+            match find_midi_port(&midi_backend, midi_output_pattern) {
+                Some(port) => midi_outputs.push(MidirOutputDevice {
+                    backend: midi_backend,
+                    port,
+                    channels,
+                }),
+                None => anyhow::bail!(
+                    "Unknown output MIDI device '{midi_output_pattern}'. Available devices are:{}",
+                    list_midi_ports(&midi_backend)
+                ),
             }
-            None => None,
-        };
+        }
 
         Ok(CpalMidir {
             config,
             audio_io_layout,
+            cpal_host_id,
 
             input,
             output,
+            input_channel_map,
+            output_channel_map,
 
-            midi_input: Mutex::new(midi_input),
-            midi_output: Mutex::new(midi_output),
+            midi_inputs: Mutex::new(midi_inputs),
+            midi_outputs: Mutex::new(midi_outputs),
         })
     }
 
@@ -655,14 +836,30 @@ impl CpalMidir {
 
     fn build_midi_input_thread<P: Plugin>(
         &self,
-        mut midi_input_rb_producer: rtrb::Producer<PluginNoteEvent<P>>,
+        mut midi_input_rb_producer: rtrb::Producer<(Instant, PluginNoteEvent<P>)>,
+        midi_clock_receiver: Option<Arc<MidiClockReceiver>>,
     ) -> impl FnMut(u64, &[u8], &mut ()) + Send + 'static {
-        // This callback parses the received MIDI bytes and sends them to a ring buffer
-        move |_timing, midi_data, _data| {
-            // Since this is system MIDI there's no real useful timing information and we'll set all
-            // the timings to the first sample in the buffer
+        let mut midi_clock_estimator = MidiClockEstimator::default();
+
+        // This callback parses the received MIDI bytes and sends them to a ring buffer. MIDI clock
+        // and transport messages are handled separately if MIDI clock input is enabled.
+        move |timestamp, midi_data, _data| {
+            if let Some(midi_clock_receiver) = &midi_clock_receiver {
+                if midi_clock_receiver.handle_message(
+                    &mut midi_clock_estimator,
+                    timestamp,
+                    midi_data,
+                ) {
+                    return;
+                }
+            }
+
+            // Midir's timestamps don't share a common time base between ports, so we'll record the
+            // time the event was received at instead. The actual timing within the buffer is
+            // assigned in the output callback.
+            let received_at = Instant::now();
             if let Ok(event) = NoteEvent::from_midi(0, midi_data) {
-                if midi_input_rb_producer.push(event).is_err() {
+                if midi_input_rb_producer.push((received_at, event)).is_err() {
                     nih_error!("The MIDI input event queue was full, dropping event");
                 }
             }
@@ -673,7 +870,9 @@ impl CpalMidir {
         &self,
         unparker: Unparker,
         mut input_rb_consumer: Option<rtrb::Consumer<f32>>,
-        mut input_event_rb_consumer: Option<rtrb::Consumer<PluginNoteEvent<P>>>,
+        mut input_event_rb_consumers: Vec<rtrb::Consumer<(Instant, PluginNoteEvent<P>)>>,
+        midi_clock_receiver: Option<Arc<MidiClockReceiver>>,
+        stream_failure: Arc<StreamFailure>,
         mut output_event_rb_producer: Option<crossbeam::channel::Sender<MidiOutputTask<P>>>,
         mut cb: impl FnMut(
                 &mut Buffer,
@@ -704,9 +903,8 @@ impl CpalMidir {
             .unwrap_or(0) as usize;
         let mut main_io_storage = vec![vec![0.0f32; buffer_size]; num_output_channels];
 
-        // This backend does not support auxiliary inputs and outputs, so in order to have the same
-        // behavior as the other backends we'll provide some dummy buffers that we'll zero out every
-        // time
+        // Auxiliary inputs and outputs are only connected to device channels when the channel maps
+        // specify this. Otherwise the plugin gets silent buffers, just like with the other backends.
         let mut aux_input_storage: Vec<Vec<Vec<f32>>> = Vec::new();
         for channel_count in self.audio_io_layout.aux_input_ports {
             aux_input_storage.push(vec![
@@ -748,44 +946,107 @@ impl CpalMidir {
             )));
         }
 
+        // These contain the device channel indices and the plugin channels they're connected to
+        let input_routes: Vec<(usize, PluginChannel)> = self
+            .input_channel_map
+            .iter()
+            .zip(plugin_channels(
+                self.audio_io_layout.main_input_channels,
+                self.audio_io_layout.aux_input_ports,
+            ))
+            .filter_map(|(device_channel_idx, plugin_channel)| {
+                device_channel_idx.map(|device_channel_idx| (device_channel_idx, plugin_channel))
+            })
+            .collect();
+        let output_routes: Vec<(usize, PluginChannel)> = self
+            .output_channel_map
+            .iter()
+            .zip(plugin_channels(
+                self.audio_io_layout.main_output_channels,
+                self.audio_io_layout.aux_output_ports,
+            ))
+            .filter_map(|(device_channel_idx, plugin_channel)| {
+                device_channel_idx.map(|device_channel_idx| (device_channel_idx, plugin_channel))
+            })
+            .collect();
+        // A single interleaved frame read from the input ring buffer
+        let mut input_frame = vec![
+            0.0f32;
+            self.input
+                .as_ref()
+                .map(|input| input.config.channels as usize)
+                .unwrap_or(0)
+        ];
+        let num_output_device_channels = self.output.config.channels as usize;
+
         let mut midi_input_events = Vec::with_capacity(MIDI_EVENT_QUEUE_CAPACITY);
         let mut midi_output_events = Vec::with_capacity(MIDI_EVENT_QUEUE_CAPACITY);
 
         // Can't borrow from `self` in the callback
         let config = self.config.clone();
         let mut num_processed_samples = 0usize;
+        let mut previous_callback_start: Option<Instant> = None;
         move |data, _info| {
+            let callback_start = Instant::now();
+
             let mut transport = Transport::new(config.sample_rate);
             transport.pos_samples = Some(num_processed_samples as i64);
             transport.tempo = Some(config.tempo as f64);
             transport.time_sig_numerator = Some(config.timesig_num as i32);
             transport.time_sig_denominator = Some(config.timesig_denom as i32);
             transport.playing = true;
+            if let Some(midi_clock_receiver) = &midi_clock_receiver {
+                midi_clock_receiver.update_transport(&mut transport);
+            }
+
+            // If an input was configured, then the interleaved input samples are copied to the main
+            // IO and auxiliary input buffers according to the input channel map. Any main input
+            // channels without input are filled with silence. The `BufferManager` will copy the
+            // auxiliary input data to its own storage buffers because it cannot assume that these
+            // buffers are safe to write to. Because of that we'll never need to reinitialize these,
+            // and the output storage is write-only (with `BufferManager` always zeroing them out
+            // when creating the buffers).
+            for channel in main_io_storage.iter_mut() {
+                channel.fill(0.0);
+            }
+            if let Some(input_rb_consumer) = &mut input_rb_consumer {
+                for sample_idx in 0..buffer_size {
+                    for device_sample in input_frame.iter_mut() {
+                        loop {
+                            // Keep spinning on this if the output callback somehow outpaces the
+                            // input callback
+                            if let Ok(input_sample) = input_rb_consumer.pop() {
+                                *device_sample = input_sample;
+                                break;
+                            }
 
-            // If an input was configured, then the output buffer is filled with (interleaved) input
-            // samples. Otherwise it gets filled with silence. There is no need to zero out any of
-            // the other buffers. The `BufferManager` will copy the auxiliary input data to its own
-            // storage buffers because it cannot assume that these buffers are safe to write to.
-            // Because of that we'll never need to reinitialize these, and the output storage is
-            // write-only (with `BufferManager` always zeroing them out when creating the buffers).
-            match &mut input_rb_consumer {
-                Some(input_rb_consumer) => {
-                    for channel in main_io_storage.iter_mut() {
-                        for sample in channel {
-                            loop {
-                                // Keep spinning on this if the output callback somehow outpaces the
-                                // input callback
-                                if let Ok(input_sample) = input_rb_consumer.pop() {
-                                    *sample = input_sample;
-                                    break;
+                            // No more input will arrive if the input device has failed. `run()`
+                            // will return an error shortly after this.
+                            if stream_failure.has_failed() {
+                                for output_sample in data.iter_mut() {
+                                    *output_sample = T::from_sample(0.0f32);
                                 }
+
+                                return;
                             }
                         }
                     }
-                }
-                None => {
-                    for channel in main_io_storage.iter_mut() {
-                        channel.fill(0.0);
+
+                    for (device_channel_idx, plugin_channel) in &input_routes {
+                        let channel = match *plugin_channel {
+                            // If the plugin has more main input than output channels, then the
+                            // extra input channels are dropped just like in the other backends
+                            PluginChannel::Main(channel_idx) => {
+                                main_io_storage.get_mut(channel_idx)
+                            }
+                            PluginChannel::Aux(port_idx, channel_idx) => {
+                                aux_input_storage[port_idx].get_mut(channel_idx)
+                            }
+                        };
+
+                        if let Some(channel) = channel {
+                            channel[sample_idx] = input_frame[*device_channel_idx];
+                        }
                     }
                 }
             }
@@ -866,12 +1127,47 @@ impl CpalMidir {
                     })
                 };
 
+                // Events received during the previous period are placed at the same relative
+                // position within this period. This adds a period of latency, but it preserves the
+                // timing between events. The input ports are merged by always taking the oldest
+                // event first.
                 midi_input_events.clear();
-                if let Some(input_event_rb_consumer) = &mut input_event_rb_consumer {
-                    if let Ok(event) = input_event_rb_consumer.pop() {
-                        midi_input_events.push(event);
+                while midi_input_events.len() < MIDI_EVENT_QUEUE_CAPACITY {
+                    let mut oldest_event: Option<(usize, Instant)> = None;
+                    for (consumer_idx, consumer) in input_event_rb_consumers.iter().enumerate() {
+                        if let Ok((received_at, _)) = consumer.peek() {
+                            if *received_at < callback_start
+                                && oldest_event.map_or(true, |(_, oldest_received_at)| {
+                                    *received_at < oldest_received_at
+                                })
+                            {
+                                oldest_event = Some((consumer_idx, *received_at));
+                            }
+                        }
                     }
+
+                    let (consumer_idx, received_at) = match oldest_event {
+                        Some(oldest_event) => oldest_event,
+                        None => break,
+                    };
+                    let (_, mut event) = input_event_rb_consumers[consumer_idx]
+                        .pop()
+                        .expect("Inconsistent MIDI input queue state");
+
+                    let timing = match previous_callback_start {
+                        Some(previous_callback_start) => {
+                            (received_at
+                                .saturating_duration_since(previous_callback_start)
+                                .as_secs_f64()
+                                * config.sample_rate as f64) as usize
+                        }
+                        None => 0,
+                    };
+                    event.set_timing(timing.min(buffer_size - 1) as u32);
+
+                    midi_input_events.push(event);
                 }
+                previous_callback_start = Some(callback_start);
 
                 midi_output_events.clear();
                 let mut aux = AuxiliaryBuffers {
@@ -891,13 +1187,26 @@ impl CpalMidir {
                 }
             }
 
-            // The buffer's samples need to be written to `data` in an interlaced format
+            // The buffer's samples need to be written to `data` in an interlaced format according to
+            // the output channel map. Unconnected device channels are silent.
             // SAFETY: Dropping `buffers` allows us to borrow `main_io_storage` again
-            for (output_sample, buffer_sample) in data
-                .iter_mut()
-                .zip(main_io_storage.iter().flat_map(|channels| channels.iter()))
-            {
-                *output_sample = T::from_sample(*buffer_sample);
+            for output_sample in data.iter_mut() {
+                *output_sample = T::from_sample(0.0f32);
+            }
+            for (device_channel_idx, plugin_channel) in &output_routes {
+                let channel = match *plugin_channel {
+                    PluginChannel::Main(channel_idx) => &main_io_storage[channel_idx],
+                    PluginChannel::Aux(port_idx, channel_idx) => {
+                        &aux_output_storage[port_idx][channel_idx]
+                    }
+                };
+
+                for (output_frame, buffer_sample) in data
+                    .chunks_exact_mut(num_output_device_channels)
+                    .zip(channel.iter())
+                {
+                    output_frame[*device_channel_idx] = T::from_sample(*buffer_sample);
+                }
             }
 
             if let Some(output_event_rb_producer) = &mut output_event_rb_producer {
@@ -916,3 +1225,356 @@ impl CpalMidir {
         }
     }
 }
+
+/// Send a MIDI message to all output connections that have `channel` routed to them. Messages
+/// without a channel are sent to every connection.
+fn send_midi_message(
+    connections: &mut [ActiveMidirOutputDevice],
+    channel: Option<u8>,
+    message: &[u8],
+) {
+    for output in connections.iter_mut() {
+        let routed = match channel {
+            Some(channel) => output.channels & (1 << channel) != 0,
+            None => true,
+        };
+
+        if routed {
+            if let Err(err) = output.connection.send(message) {
+                nih_error!("Could not send MIDI event: {err}");
+            }
+        }
+    }
+}
+
+/// Find the first MIDI port whose name matches `pattern` using [`port_name_matches()`]. An empty
+/// pattern never matches anything, so it can be used to list all available ports.
+fn find_midi_port<T: MidiIO>(backend: &T, pattern: &str) -> Option<T::Port> {
+    if pattern.is_empty() {
+        return None;
+    }
+
+    // Exact names take priority in case a port name contains one of the wildcard characters
+    let available_ports = backend.ports();
+    if let Some(port) = available_ports
+        .iter()
+        .find(|port| backend.port_name(port).as_deref() == Ok(pattern))
+    {
+        return Some(port.clone());
+    }
+
+    let mut matching_ports = available_ports.iter().filter(|port| {
+        backend
+            .port_name(port)
+            .map(|name| port_name_matches(pattern, &name))
+            .unwrap_or(false)
+    });
+    let found_port = matching_ports.next().cloned();
+    if found_port.is_some() && matching_ports.next().is_some() {
+        nih_warn!("'{pattern}' matches multiple MIDI ports, using the first one");
+    }
+
+    found_port
+}
+
+/// List all of `backend`'s ports, with each port name on a new line. Used in error messages.
+fn list_midi_ports<T: MidiIO>(backend: &T) -> String {
+    let mut message = String::new();
+    for port in backend.ports() {
+        match backend.port_name(&port) {
+            Ok(port_name) => message.push_str(&format!("\n{port_name}")),
+            Err(err) => message.push_str(&format!("\nERROR: {err:#}")),
+        }
+    }
+
+    message
+}
+
+/// Check whether a MIDI port name matches a pattern. A `*` in the pattern matches any number of
+/// characters and a `?` matches a single character. This makes it possible to select ports whose
+/// names contain a client or port number that changes when devices are reconnected, like
+/// `'Launchpad*MIDI 1*'`. Matching is case sensitive.
+fn port_name_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // A simple backtracking glob matcher. `star` stores the position of the last `*` in the pattern
+    // and the position in the name it was matched against.
+    let (mut pattern_idx, mut name_idx) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while name_idx < name.len() {
+        match pattern.get(pattern_idx) {
+            Some('*') => {
+                star = Some((pattern_idx, name_idx));
+                pattern_idx += 1;
+            }
+            Some(c) if *c == '?' || *c == name[name_idx] => {
+                pattern_idx += 1;
+                name_idx += 1;
+            }
+            _ => match star {
+                Some((star_pattern_idx, star_name_idx)) => {
+                    pattern_idx = star_pattern_idx + 1;
+                    name_idx = star_name_idx + 1;
+                    star = Some((star_pattern_idx, star_name_idx + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pattern_idx..].iter().all(|c| *c == '*')
+}
+
+/// Parse a `--midi-output` value. This is a port name pattern optionally followed by `@` and a
+/// comma separated list of one-indexed MIDI channels and channel ranges, like `'Synth*@1,3-4'`.
+/// Returns the pattern and a bitmask of the routed channels. Port names may also contain `@`
+/// characters, so if the part after the last `@` does not look like a channel list then the entire
+/// value is used as the port name pattern.
+fn parse_midi_output_spec(spec: &str) -> Result<(&str, u16)> {
+    let (pattern, channels) = match spec.rsplit_once('@') {
+        Some((pattern, channels))
+            if channels.contains(|c: char| c.is_ascii_digit())
+                && channels
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == ',' || c == '-' || c.is_whitespace()) =>
+        {
+            (pattern, channels)
+        }
+        _ => return Ok((spec, u16::MAX)),
+    };
+
+    let mut channel_mask = 0u16;
+    for channel_range in channels.split(',') {
+        let (first, last) = channel_range
+            .split_once('-')
+            .unwrap_or((channel_range, channel_range));
+        let parse_channel = |channel: &str| -> Result<u16> {
+            match channel.trim().parse::<u16>() {
+                Ok(channel @ 1..=16) => Ok(channel - 1),
+                _ => anyhow::bail!(
+                    "'{channel}' in '{spec}' is not a valid MIDI channel, expected a number \
+                     between 1 and 16"
+                ),
+            }
+        };
+
+        let (first, last) = (parse_channel(first)?, parse_channel(last)?);
+        if first > last {
+            anyhow::bail!(
+                "'{}' in '{spec}' is not a valid MIDI channel range, the first channel must not \
+                 come after the last channel",
+                channel_range.trim()
+            );
+        }
+
+        for channel in first..=last {
+            channel_mask |= 1 << channel;
+        }
+    }
+
+    Ok((pattern, channel_mask))
+}
+
+/// All of a plugin's input or output channels in the order used by the channel map options. The
+/// main input or output's channels come first, followed by the channels of each auxiliary port.
+fn plugin_channels(
+    main_channels: Option<NonZeroU32>,
+    aux_ports: &[NonZeroU32],
+) -> impl Iterator<Item = PluginChannel> + '_ {
+    let num_main_channels = main_channels.map(NonZeroU32::get).unwrap_or_default() as usize;
+
+    (0..num_main_channels)
+        .map(PluginChannel::Main)
+        .chain(
+            aux_ports
+                .iter()
+                .enumerate()
+                .flat_map(|(port_idx, channel_count)| {
+                    (0..channel_count.get() as usize)
+                        .map(move |channel_idx| PluginChannel::Aux(port_idx, channel_idx))
+                }),
+        )
+}
+
+/// The channel map used when no channel map option has been specified. This connects the main
+/// channels to the device's first channels and leaves the auxiliary ports unconnected.
+fn default_channel_map(
+    main_channels: Option<NonZeroU32>,
+    num_plugin_channels: usize,
+) -> Vec<Option<usize>> {
+    let num_main_channels = main_channels.map(NonZeroU32::get).unwrap_or_default() as usize;
+
+    (0..num_plugin_channels)
+        .map(|channel_idx| (channel_idx < num_main_channels).then_some(channel_idx))
+        .collect()
+}
+
+/// Parse a `--input-channel-map` or `--output-channel-map` value. This is a comma separated list
+/// of one-indexed device channels or `-` for unconnected channels, with one entry per plugin
+/// channel. Missing trailing entries are left unconnected. A device channel can only be used once.
+/// Returns the zero-indexed device channel for each plugin channel.
+fn parse_channel_map(
+    channel_map: &str,
+    num_plugin_channels: usize,
+    option_name: &str,
+) -> Result<Vec<Option<usize>>> {
+    let mut parsed_channel_map = Vec::with_capacity(num_plugin_channels);
+    for entry in channel_map.split(',').map(str::trim) {
+        match entry {
+            "-" => parsed_channel_map.push(None),
+            entry => match entry.parse::<usize>() {
+                Ok(device_channel) if device_channel >= 1 => {
+                    if parsed_channel_map.contains(&Some(device_channel - 1)) {
+                        anyhow::bail!(
+                            "Device channel {device_channel} is used more than once in \
+                             '{option_name}'"
+                        );
+                    }
+
+                    parsed_channel_map.push(Some(device_channel - 1))
+                }
+                _ => anyhow::bail!(
+                    "'{entry}' in '{option_name}' is not a valid channel, expected a channel \
+                     number starting at 1 or '-'"
+                ),
+            },
+        }
+    }
+
+    if parsed_channel_map.len() > num_plugin_channels {
+        anyhow::bail!(
+            "'{option_name}' contains {} channels, but the plugin's audio layout only has {} \
+             channels",
+            parsed_channel_map.len(),
+            num_plugin_channels
+        );
+    }
+    parsed_channel_map.resize(num_plugin_channels, None);
+
+    Ok(parsed_channel_map)
+}
+
+/// The number of channels an audio device needs to have to be used with a channel map. This is
+/// always at least one so the device can still be opened when nothing is connected.
+fn required_device_channels(channel_map: &[Option<usize>]) -> usize {
+    channel_map
+        .iter()
+        .flatten()
+        .map(|device_channel_idx| device_channel_idx + 1)
+        .max()
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_name_globs() {
+        assert!(port_name_matches("Launchpad X", "Launchpad X"));
+        assert!(port_name_matches(
+            "Launchpad*",
+            "Launchpad X:Launchpad X MIDI 1 24:0"
+        ));
+        assert!(port_name_matches(
+            "*MIDI 1*",
+            "Launchpad X:Launchpad X MIDI 1 24:0"
+        ));
+        assert!(port_name_matches(
+            "Launchpad*MIDI ? *",
+            "Launchpad X:Launchpad X MIDI 2 24:1"
+        ));
+        assert!(port_name_matches("*", "Anything"));
+        assert!(port_name_matches("a*b*c", "aXbYbZc"));
+
+        assert!(!port_name_matches("Launchpad", "Launchpad X"));
+        assert!(!port_name_matches("Launchpad?", "Launchpad"));
+        assert!(!port_name_matches(
+            "*MIDI 3*",
+            "Launchpad X:Launchpad X MIDI 1 24:0"
+        ));
+    }
+
+    #[test]
+    fn port_name_matching_is_case_sensitive() {
+        assert!(!port_name_matches("launchpad*", "Launchpad X"));
+        assert!(!port_name_matches("LAUNCHPAD X", "Launchpad X"));
+    }
+
+    #[test]
+    fn empty_port_name_pattern() {
+        assert!(port_name_matches("", ""));
+        assert!(!port_name_matches("", "Launchpad X"));
+    }
+
+    #[test]
+    fn midi_output_spec_channels() {
+        assert_eq!(
+            parse_midi_output_spec("Synth").unwrap(),
+            ("Synth", u16::MAX)
+        );
+        assert_eq!(parse_midi_output_spec("Synth*@1").unwrap(), ("Synth*", 0b1));
+        assert_eq!(
+            parse_midi_output_spec("Synth*@1,3-4").unwrap(),
+            ("Synth*", 0b1101)
+        );
+        assert_eq!(
+            parse_midi_output_spec("Synth@Home@16").unwrap(),
+            ("Synth@Home", 1 << 15)
+        );
+    }
+
+    #[test]
+    fn midi_output_spec_port_name_with_at() {
+        assert_eq!(
+            parse_midi_output_spec("Foo@Bar").unwrap(),
+            ("Foo@Bar", u16::MAX)
+        );
+        assert_eq!(parse_midi_output_spec("Foo@").unwrap(), ("Foo@", u16::MAX));
+    }
+
+    #[test]
+    fn invalid_midi_output_spec_channels() {
+        assert!(parse_midi_output_spec("Synth@0").is_err());
+        assert!(parse_midi_output_spec("Synth@17").is_err());
+        assert!(parse_midi_output_spec("Synth@1,").is_err());
+        assert!(parse_midi_output_spec("Synth@3-").is_err());
+        assert!(parse_midi_output_spec("Synth@4-3").is_err());
+    }
+
+    #[test]
+    fn channel_maps() {
+        assert_eq!(
+            parse_channel_map("1,2", 2, "--output-channel-map").unwrap(),
+            [Some(0), Some(1)]
+        );
+        assert_eq!(
+            parse_channel_map("3, -, 1", 4, "--output-channel-map").unwrap(),
+            [Some(2), None, Some(0), None]
+        );
+        assert_eq!(
+            parse_channel_map("-", 2, "--input-channel-map").unwrap(),
+            [None, None]
+        );
+
+        // The device needs to have enough channels for the highest channel in the map
+        assert_eq!(required_device_channels(&[Some(2), None, Some(0)]), 3);
+        assert_eq!(required_device_channels(&[None, None]), 1);
+    }
+
+    #[test]
+    fn invalid_channel_map_entries() {
+        assert!(parse_channel_map("0,1", 2, "--output-channel-map").is_err());
+        assert!(parse_channel_map("1,x", 2, "--output-channel-map").is_err());
+        assert!(parse_channel_map("1,", 2, "--output-channel-map").is_err());
+        assert!(parse_channel_map("1,2,3", 2, "--output-channel-map").is_err());
+    }
+
+    #[test]
+    fn duplicate_channel_map_entries() {
+        assert!(parse_channel_map("1,1", 2, "--output-channel-map").is_err());
+        assert!(parse_channel_map("2,-,2", 3, "--input-channel-map").is_err());
+        assert!(parse_channel_map("-,-", 2, "--input-channel-map").is_ok());
+    }
+}
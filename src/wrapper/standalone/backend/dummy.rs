@@ -1,3 +1,4 @@
+use anyhow::Result;
 use std::num::NonZeroU32;
 use std::ptr::NonNull;
 use std::time::{Duration, Instant};
@@ -27,7 +28,7 @@ impl<P: Plugin> Backend<P> for Dummy {
             ) -> bool
             + 'static
             + Send,
-    ) {
+    ) -> Result<()> {
         // We can't really do anything meaningful here, so we'll simply periodically call the
         // callback with empty buffers
         let interval =
@@ -175,6 +176,17 @@ impl<P: Plugin> Backend<P> for Dummy {
             let period_end = Instant::now();
             std::thread::sleep((period_start + interval).saturating_duration_since(period_end));
         }
+
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        // There are no devices that could have disappeared
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "dummy"
     }
 }
 
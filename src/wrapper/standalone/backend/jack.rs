@@ -1,13 +1,14 @@
 use std::borrow::Borrow;
 use std::num::NonZeroU32;
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use crossbeam::sync::Parker;
+use crossbeam::sync::{Parker, Unparker};
 use jack::{
-    AsyncClient, AudioIn, AudioOut, Client, ClientOptions, ClosureProcessHandler, Control, MidiIn,
-    MidiOut, Port,
+    AsyncClient, AudioIn, AudioOut, Client, ClientOptions, ClientStatus, ClosureProcessHandler,
+    Control, MidiIn, MidiOut, NotificationHandler, Port,
 };
 use parking_lot::Mutex;
 
@@ -52,6 +53,22 @@ impl ChannelPointerVec {
     }
 }
 
+/// Handles JACK's notifications. This is only used to detect when the JACK server shuts down or
+/// kicks the client out, in which case `run()` should stop blocking and return an error.
+struct JackNotifications {
+    unparker: Unparker,
+    shut_down: Arc<AtomicBool>,
+}
+
+impl NotificationHandler for JackNotifications {
+    unsafe fn shutdown(&mut self, _status: ClientStatus, _reason: &str) {
+        // This may be called from a signal handler-like context, so this can't do much more than
+        // set a flag
+        self.shut_down.store(true, Ordering::SeqCst);
+        self.unparker.unpark();
+    }
+}
+
 impl<P: Plugin> Backend<P> for Jack {
     fn run(
         &mut self,
@@ -64,8 +81,11 @@ impl<P: Plugin> Backend<P> for Jack {
             ) -> bool
             + 'static
             + Send,
-    ) {
-        let client = self.client.take().unwrap();
+    ) -> Result<()> {
+        let client = self
+            .client
+            .take()
+            .context("The JACK client needs to be reconnected first")?;
         let buffer_size = client.buffer_size();
 
         // We'll preallocate the buffers here, and then assign them to the slices belonging to the
@@ -128,28 +148,7 @@ impl<P: Plugin> Backend<P> for Jack {
                 return Control::Quit;
             }
 
-            let mut transport = Transport::new(client.sample_rate() as f32);
-            transport.tempo = Some(config.tempo as f64);
-            transport.time_sig_numerator = Some(config.timesig_num as i32);
-            transport.time_sig_denominator = Some(config.timesig_denom as i32);
-
-            if let Ok(jack_transport) = client.transport().query() {
-                transport.pos_samples = Some(jack_transport.pos.frame() as i64);
-                transport.playing = jack_transport.state == jack::TransportState::Rolling;
-
-                if let Some(bbt) = jack_transport.pos.bbt() {
-                    transport.tempo = Some(bbt.bpm);
-                    transport.time_sig_numerator = Some(bbt.sig_num as i32);
-                    transport.time_sig_denominator = Some(bbt.sig_denom as i32);
-
-                    transport.pos_beats = Some(
-                        (bbt.bar as f64 * 4.0)
-                            + (bbt.beat as f64 / bbt.sig_denom as f64 * 4.0)
-                            + (bbt.tick as f64 / bbt.ticks_per_beat),
-                    );
-                    transport.bar_number = Some(bbt.bar as i32);
-                }
-            }
+            let transport = jack_transport(client, &config);
 
             // Just like all of the plugin backends, we need to grab the output slices and copy the
             // inputs to the outputs. To do that we need to first create the same kind of `*mut *mut
@@ -302,18 +301,48 @@ impl<P: Plugin> Backend<P> for Jack {
         // PipeWire lets us connect the ports whenever we want, but JACK2 is very strict and only
         // allows us to connect the ports when the client is active. And the connections will
         // disappear when the client is deactivated. Fun.
-        let async_client = client.activate_async((), process_handler).unwrap();
+        let shut_down = Arc::new(AtomicBool::new(false));
+        let notification_handler = JackNotifications {
+            unparker: parker.unparker().clone(),
+            shut_down: shut_down.clone(),
+        };
+        let async_client = client
+            .activate_async(notification_handler, process_handler)
+            .context("Could not activate the JACK client")?;
         if let Err(err) = self.connect_ports(&async_client) {
             nih_error!("Error connecting JACK ports: {err}")
         }
 
         // The process callback happens on another thread, so we need to block this thread until we
-        // get the request to shut down or until the process callback runs into an error
+        // get the request to shut down, until the process callback runs into an error, or until
+        // the JACK server goes away
         parker.park();
 
+        if shut_down.load(Ordering::SeqCst) {
+            // The client is no longer usable at this point, so it's simply dropped. `reconnect()`
+            // will create a new one.
+            drop(async_client);
+            anyhow::bail!("The JACK server shut down or disconnected the client");
+        }
+
         // And put the client back where it belongs in case this function is called a second time
-        let (client, _, _) = async_client.deactivate().unwrap();
+        let (client, _, _) = async_client
+            .deactivate()
+            .context("Could not deactivate the JACK client")?;
         self.client = Some(client);
+
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        // The ports belong to the old client, so everything needs to be recreated
+        *self = Jack::new::<P>(self.config.clone())?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "JACK"
     }
 }
 
@@ -483,3 +512,64 @@ impl Jack {
         Ok(())
     }
 }
+
+/// Build a [`Transport`] object from JACK's transport. The tempo and time signature from the
+/// wrapper's config are used as a fallback when there is no JACK timebase master, in which case
+/// the musical position is derived from the transport's frame position. JACK's transport has no
+/// concept of loops, so the loop range fields are never set.
+fn jack_transport(client: &Client, config: &WrapperConfig) -> Transport {
+    let sample_rate = client.sample_rate() as f32;
+    let mut transport = Transport::new(sample_rate);
+    transport.tempo = Some(config.tempo as f64);
+    transport.time_sig_numerator = Some(config.timesig_num as i32);
+    transport.time_sig_denominator = Some(config.timesig_denom as i32);
+
+    let jack_transport = match client.transport().query() {
+        Ok(jack_transport) => jack_transport,
+        // Without a transport we'll act like the other backends and just keep playing from the
+        // start of the song
+        Err(_) => {
+            transport.playing = true;
+            return transport;
+        }
+    };
+
+    // `Starting` means that JACK is waiting for slow-sync clients, so audio isn't moving yet
+    transport.playing = jack_transport.state == jack::TransportState::Rolling;
+    let pos_samples = jack_transport.pos.frame() as i64;
+    transport.pos_samples = Some(pos_samples);
+    transport.pos_seconds = Some(pos_samples as f64 / sample_rate as f64);
+
+    if let Some(bbt) = jack_transport.pos.bbt() {
+        transport.tempo = Some(bbt.bpm);
+        transport.time_sig_numerator = Some(bbt.sig_num.round() as i32);
+        transport.time_sig_denominator = Some(bbt.sig_denom.round() as i32);
+
+        // JACK's beats are expressed in `sig_denom` note values while NIH-plug uses quarter notes.
+        // Bars and beats are one-indexed, but some timebase masters report zeroes anyway so those are
+        // treated as the first bar or beat.
+        let quarter_notes_per_beat = 4.0 / bbt.sig_denom as f64;
+        // Not every timebase master fills in `bar_start_tick`. If it's missing we'll assume the
+        // time signature has been constant since the start of the song.
+        let bar_start_pos_beats = if bbt.bar_start_tick > 0.0 {
+            bbt.bar_start_tick / bbt.ticks_per_beat * quarter_notes_per_beat
+        } else {
+            bbt.bar.saturating_sub(1) as f64 * bbt.sig_num as f64 * quarter_notes_per_beat
+        };
+        let mut pos_beats = bar_start_pos_beats
+            + ((bbt.beat.saturating_sub(1) as f64 + (bbt.tick as f64 / bbt.ticks_per_beat))
+                * quarter_notes_per_beat);
+
+        // If set, the BBT information refers to a point this many frames before the start of the
+        // current period
+        if let Some(bbt_offset) = jack_transport.pos.bbt_offset() {
+            pos_beats += bbt_offset as f64 / sample_rate as f64 / 60.0 * bbt.bpm;
+        }
+
+        transport.pos_beats = Some(pos_beats);
+        transport.bar_start_pos_beats = Some(bar_start_pos_beats);
+        transport.bar_number = Some(bbt.bar.saturating_sub(1) as i32);
+    }
+
+    transport
+}
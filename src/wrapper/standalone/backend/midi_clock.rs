@@ -0,0 +1,360 @@
+//! MIDI clock handling for the CPAL/midir backend. This can derive the transport from incoming MIDI
+//! clock, Start, Stop, Continue, and Song Position Pointer messages, and it defines the messages
+//! needed to send MIDI clock from the internal transport.
+
+use atomic_float::AtomicF64;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::prelude::Transport;
+
+/// MIDI clock runs at 24 pulses per quarter note.
+pub const MIDI_CLOCK_PPQN: f64 = 24.0;
+
+pub const MIDI_TIMING_CLOCK: u8 = 0xf8;
+pub const MIDI_START: u8 = 0xfa;
+pub const MIDI_CONTINUE: u8 = 0xfb;
+pub const MIDI_STOP: u8 = 0xfc;
+pub const MIDI_SONG_POSITION_POINTER: u8 = 0xf2;
+
+/// Song Position Pointer values are expressed in MIDI beats, which are sixteenth notes.
+const CLOCKS_PER_MIDI_BEAT: i64 = 6;
+/// If no clock has been received for this long, then the tempo estimate is reset. This corresponds
+/// to roughly 10 BPM.
+const CLOCK_TIMEOUT_MICROS: u64 = 250_000;
+/// The weight of a new clock interval in the exponentially smoothed tempo estimate.
+const TEMPO_SMOOTHING_WEIGHT: f64 = 0.1;
+
+/// Transport information received through MIDI clock. The MIDI input callback writes to this
+/// object and the audio thread reads from it, so everything is stored as atomics.
+pub struct MidiClockReceiver {
+    /// The reference point for `last_clock_nanos`.
+    epoch: Instant,
+
+    /// Whether we have received enough clock messages to have a tempo estimate.
+    synced: AtomicBool,
+    /// The estimated tempo in beats per minute.
+    tempo: AtomicF64,
+    /// Whether the sending device is currently playing. Set through Start, Stop, and Continue.
+    playing: AtomicBool,
+    /// The song position in MIDI clocks at the last received clock message. This is set to one
+    /// clock before the intended position on Start and Song Position Pointer messages since the
+    /// next clock message marks that position.
+    clocks: AtomicI64,
+    /// The time since `epoch` in nanoseconds at which the last clock message was received. Used to
+    /// interpolate the position in between clock messages.
+    last_clock_nanos: AtomicU64,
+}
+
+/// State used by the MIDI input callback to estimate the tempo. This only lives on the MIDI input
+/// thread.
+#[derive(Default)]
+pub struct MidiClockEstimator {
+    /// Midir's timestamp in microseconds for the last received clock message.
+    last_clock_timestamp: Option<u64>,
+    /// The exponentially smoothed interval between two clock messages in microseconds.
+    smoothed_interval: Option<f64>,
+}
+
+impl Default for MidiClockReceiver {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+
+            synced: AtomicBool::new(false),
+            tempo: AtomicF64::new(120.0),
+            playing: AtomicBool::new(false),
+            clocks: AtomicI64::new(-1),
+            last_clock_nanos: AtomicU64::new(0),
+        }
+    }
+}
+
+impl MidiClockReceiver {
+    /// Handle a MIDI message received on the MIDI input. `timestamp` is midir's timestamp in
+    /// microseconds. Returns `true` if the message was a MIDI clock or transport message, in which
+    /// case it should not be parsed as a note event.
+    pub fn handle_message(
+        &self,
+        estimator: &mut MidiClockEstimator,
+        timestamp: u64,
+        midi_data: &[u8],
+    ) -> bool {
+        match midi_data {
+            [MIDI_TIMING_CLOCK, ..] => {
+                if let Some(last_timestamp) = estimator.last_clock_timestamp {
+                    let interval = timestamp.saturating_sub(last_timestamp);
+                    if interval == 0 || interval > CLOCK_TIMEOUT_MICROS {
+                        estimator.smoothed_interval = None;
+                    } else {
+                        let smoothed_interval = match estimator.smoothed_interval {
+                            Some(smoothed_interval) => {
+                                smoothed_interval
+                                    + ((interval as f64 - smoothed_interval)
+                                        * TEMPO_SMOOTHING_WEIGHT)
+                            }
+                            None => interval as f64,
+                        };
+                        estimator.smoothed_interval = Some(smoothed_interval);
+
+                        self.tempo.store(
+                            60_000_000.0 / (smoothed_interval * MIDI_CLOCK_PPQN),
+                            Ordering::Relaxed,
+                        );
+                        self.synced.store(true, Ordering::Relaxed);
+                    }
+                }
+                estimator.last_clock_timestamp = Some(timestamp);
+
+                if self.playing.load(Ordering::Relaxed) {
+                    self.clocks.fetch_add(1, Ordering::Relaxed);
+                }
+                self.last_clock_nanos
+                    .store(self.epoch.elapsed().as_nanos() as u64, Ordering::Release);
+
+                true
+            }
+            [MIDI_START, ..] => {
+                self.clocks.store(-1, Ordering::Relaxed);
+                self.playing.store(true, Ordering::Relaxed);
+
+                true
+            }
+            [MIDI_CONTINUE, ..] => {
+                self.playing.store(true, Ordering::Relaxed);
+
+                true
+            }
+            [MIDI_STOP, ..] => {
+                self.playing.store(false, Ordering::Relaxed);
+
+                true
+            }
+            [MIDI_SONG_POSITION_POINTER, lsb, msb, ..] => {
+                let midi_beats = (*lsb as i64 & 0x7f) | ((*msb as i64 & 0x7f) << 7);
+                self.clocks
+                    .store(midi_beats * CLOCKS_PER_MIDI_BEAT - 1, Ordering::Relaxed);
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Update `transport` with the received tempo, play state, and position. The position is
+    /// interpolated between clock messages using the estimated tempo. If no clock has been received
+    /// yet, then only the play state and position are updated.
+    pub fn update_transport(&self, transport: &mut Transport) {
+        let playing = self.playing.load(Ordering::Relaxed);
+        let clocks = self.clocks.load(Ordering::Relaxed);
+        let last_clock_nanos = self.last_clock_nanos.load(Ordering::Acquire);
+
+        let mut pos_clocks = clocks.max(0) as f64;
+        if self.synced.load(Ordering::Relaxed) {
+            let tempo = self.tempo.load(Ordering::Relaxed);
+            transport.tempo = Some(tempo);
+
+            // The position is only advanced in between clocks while playing. This is capped at one
+            // clock so the position never overshoots the next clock message.
+            if playing && clocks >= 0 {
+                let clock_interval = Duration::from_secs_f64(60.0 / (tempo * MIDI_CLOCK_PPQN));
+                let since_last_clock = self
+                    .epoch
+                    .elapsed()
+                    .saturating_sub(Duration::from_nanos(last_clock_nanos));
+                pos_clocks +=
+                    (since_last_clock.as_secs_f64() / clock_interval.as_secs_f64()).clamp(0.0, 1.0);
+            }
+        }
+
+        transport.playing = playing;
+        // The other position fields are derived from the beat position and the tempo
+        transport.pos_samples = None;
+        transport.pos_seconds = None;
+        transport.pos_beats = Some(pos_clocks / MIDI_CLOCK_PPQN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The interval between two clock messages at 120 BPM in microseconds.
+    const CLOCK_INTERVAL_120_BPM: u64 = 20_833;
+
+    /// Send `num_clocks` clock messages spaced `interval` microseconds apart, starting at
+    /// `timestamp`. Returns the timestamp for the next clock message.
+    fn send_clocks(
+        receiver: &MidiClockReceiver,
+        estimator: &mut MidiClockEstimator,
+        mut timestamp: u64,
+        interval: u64,
+        num_clocks: usize,
+    ) -> u64 {
+        for _ in 0..num_clocks {
+            assert!(receiver.handle_message(estimator, timestamp, &[MIDI_TIMING_CLOCK]));
+            timestamp += interval;
+        }
+
+        timestamp
+    }
+
+    /// The transport's beat position. When the receiver is not playing this is not interpolated.
+    fn pos_beats(receiver: &MidiClockReceiver) -> f64 {
+        let mut transport = Transport::new(44100.0);
+        receiver.update_transport(&mut transport);
+
+        transport.pos_beats.unwrap()
+    }
+
+    #[test]
+    fn steady_tempo() {
+        let receiver = MidiClockReceiver::default();
+        let mut estimator = MidiClockEstimator::default();
+        let mut transport = Transport::new(44100.0);
+
+        // A single clock message is not enough to estimate the tempo
+        let timestamp = send_clocks(&receiver, &mut estimator, 0, CLOCK_INTERVAL_120_BPM, 1);
+        receiver.update_transport(&mut transport);
+        assert_eq!(transport.tempo, None);
+
+        send_clocks(
+            &receiver,
+            &mut estimator,
+            timestamp,
+            CLOCK_INTERVAL_120_BPM,
+            48,
+        );
+        receiver.update_transport(&mut transport);
+        approx::assert_relative_eq!(transport.tempo.unwrap(), 120.0, epsilon = 0.01);
+    }
+
+    #[test]
+    fn tempo_resets_after_timeout() {
+        let receiver = MidiClockReceiver::default();
+        let mut estimator = MidiClockEstimator::default();
+
+        let timestamp = send_clocks(&receiver, &mut estimator, 0, CLOCK_INTERVAL_120_BPM, 48);
+        assert!(estimator.smoothed_interval.is_some());
+
+        // After a long pause the next interval should not be smoothed with the old tempo
+        send_clocks(
+            &receiver,
+            &mut estimator,
+            timestamp + CLOCK_TIMEOUT_MICROS,
+            10_000,
+            2,
+        );
+        approx::assert_relative_eq!(
+            receiver.tempo.load(Ordering::Relaxed),
+            60_000_000.0 / (10_000.0 * MIDI_CLOCK_PPQN),
+            epsilon = 0.01
+        );
+    }
+
+    #[test]
+    fn start_stop_continue() {
+        let receiver = MidiClockReceiver::default();
+        let mut estimator = MidiClockEstimator::default();
+        let mut transport = Transport::new(44100.0);
+
+        // Clocks received while stopped don't advance the position
+        let timestamp = send_clocks(&receiver, &mut estimator, 0, CLOCK_INTERVAL_120_BPM, 10);
+        receiver.update_transport(&mut transport);
+        assert!(!transport.playing);
+        assert_eq!(transport.pos_beats, Some(0.0));
+
+        // The first clock after Start marks the start of the song
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_START]));
+        let timestamp = send_clocks(
+            &receiver,
+            &mut estimator,
+            timestamp,
+            CLOCK_INTERVAL_120_BPM,
+            1,
+        );
+        assert_eq!(receiver.clocks.load(Ordering::Relaxed), 0);
+        let timestamp = send_clocks(
+            &receiver,
+            &mut estimator,
+            timestamp,
+            CLOCK_INTERVAL_120_BPM,
+            24,
+        );
+        receiver.update_transport(&mut transport);
+        assert!(transport.playing);
+
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_STOP]));
+        let timestamp = send_clocks(
+            &receiver,
+            &mut estimator,
+            timestamp,
+            CLOCK_INTERVAL_120_BPM,
+            12,
+        );
+        assert_eq!(pos_beats(&receiver), 1.0);
+
+        // Continue resumes from the current position instead of the start of the song
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_CONTINUE]));
+        let timestamp = send_clocks(
+            &receiver,
+            &mut estimator,
+            timestamp,
+            CLOCK_INTERVAL_120_BPM,
+            12,
+        );
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_STOP]));
+        assert_eq!(pos_beats(&receiver), 1.5);
+
+        // And Start jumps back to the start
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_START]));
+        send_clocks(
+            &receiver,
+            &mut estimator,
+            timestamp,
+            CLOCK_INTERVAL_120_BPM,
+            1,
+        );
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_STOP]));
+        assert_eq!(pos_beats(&receiver), 0.0);
+    }
+
+    #[test]
+    fn song_position_pointer() {
+        let receiver = MidiClockReceiver::default();
+        let mut estimator = MidiClockEstimator::default();
+
+        // 129 MIDI beats, split into a 7-bit LSB and MSB. The next clock after Continue marks this
+        // position.
+        assert!(receiver.handle_message(&mut estimator, 0, &[MIDI_SONG_POSITION_POINTER, 1, 1]));
+        assert!(receiver.handle_message(&mut estimator, 0, &[MIDI_CONTINUE]));
+        let timestamp = send_clocks(&receiver, &mut estimator, 0, CLOCK_INTERVAL_120_BPM, 1);
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_STOP]));
+        assert_eq!(pos_beats(&receiver), 129.0 / 4.0);
+
+        // Song Position Pointer messages can also be sent while stopped
+        assert!(receiver.handle_message(
+            &mut estimator,
+            timestamp,
+            &[MIDI_SONG_POSITION_POINTER, 8, 0]
+        ));
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_CONTINUE]));
+        let timestamp = send_clocks(
+            &receiver,
+            &mut estimator,
+            timestamp,
+            CLOCK_INTERVAL_120_BPM,
+            7,
+        );
+        assert!(receiver.handle_message(&mut estimator, timestamp, &[MIDI_STOP]));
+        assert_eq!(pos_beats(&receiver), 2.0 + (6.0 / MIDI_CLOCK_PPQN));
+
+        // Truncated messages are not treated as transport messages
+        assert!(!receiver.handle_message(
+            &mut estimator,
+            timestamp,
+            &[MIDI_SONG_POSITION_POINTER, 8]
+        ));
+        assert!(!receiver.handle_message(&mut estimator, timestamp, &[0x90, 60, 100]));
+    }
+}
@@ -31,6 +31,17 @@ pub struct WrapperConfig {
     /// This setting is ignored when using the JACK backend.
     #[clap(value_parser, short = 'p', long, default_value = "512")]
     pub period_size: u32,
+    /// What to do when the audio device fails or disappears while the application is running.
+    ///
+    /// The plugin keeps its state in all cases except for 'exit'.
+    #[clap(value_parser, long, default_value = "reconnect")]
+    pub on_device_loss: DeviceLossBehavior,
+    /// The maximum time in seconds between two attempts to reopen a failed audio device.
+    ///
+    /// The first attempt is made after half a second, and the interval doubles after every failed
+    /// attempt until it reaches this value.
+    #[clap(value_parser, long, default_value = "10")]
+    pub max_reconnect_interval: f32,
 
     /// The input device for the ALSA, CoreAudio, and WASAPI backends. No input will be connected if
     /// this is not specified.
@@ -43,16 +54,59 @@ pub struct WrapperConfig {
     /// Specifying an empty string or other invalid value will list all available output devices.
     #[clap(value_parser, long)]
     pub output_device: Option<String>,
-    /// The input MIDI device for the ALSA, CoreAudio, and WASAPI backends.
+    /// A comma separated list of input device channels to connect to the plugin's input channels,
+    /// starting at 1. The main input's channels come first, followed by the channels of each
+    /// sidechain input in order. A '-' leaves a plugin channel unconnected, as do any missing
+    /// trailing entries. Every device channel can only be used once. For instance, '1,2,3,4'
+    /// connects the device's first four channels to a stereo main input and a stereo sidechain
+    /// input.
+    ///
+    /// By default the main input is connected to the device's first channels and the sidechain
+    /// inputs are left unconnected. This option is only used with the ALSA, CoreAudio, and WASAPI
+    /// backends.
+    #[clap(value_parser, long)]
+    pub input_channel_map: Option<String>,
+    /// A comma separated list of output device channels to connect the plugin's output channels
+    /// to, in the same format as '--input-channel-map'. Device channels that are not connected to
+    /// any output channel are silent.
+    ///
+    /// By default the main output is connected to the device's first channels and the auxiliary
+    /// outputs are discarded. This option is only used with the ALSA, CoreAudio, and WASAPI
+    /// backends.
+    #[clap(value_parser, long)]
+    pub output_channel_map: Option<String>,
+    /// An input MIDI device for the ALSA, CoreAudio, and WASAPI backends. This option can be
+    /// specified multiple times to merge the events from multiple MIDI devices.
+    ///
+    /// The device name may contain '*' and '?' wildcards to match devices whose names change
+    /// between sessions, like 'Launchpad*MIDI 1*'. Specifying an empty string or other invalid
+    /// value will list all available MIDI inputs.
+    #[clap(value_parser, long)]
+    pub midi_input: Vec<String>,
+    /// An output MIDI device for the ALSA, CoreAudio, and WASAPI backends. This option can be
+    /// specified multiple times to send events to multiple MIDI devices.
     ///
-    /// Specifying an empty string or other invalid value will list all available MIDI inputs.
+    /// By default all events are sent to every output device. Append '@' followed by a comma
+    /// separated list of MIDI channels or channel ranges to only send events for those channels to
+    /// this device, like 'Synth*@1,3-4'. Events without a channel, like SysEx, are always sent to
+    /// every output device. The same wildcards as in '--midi-input' can be used. Specifying an
+    /// empty string or other invalid value will list all available MIDI outputs.
     #[clap(value_parser, long)]
-    pub midi_input: Option<String>,
-    /// The output output device for the ALSA, CoreAudio, and WASAPI backends.
+    pub midi_output: Vec<String>,
+    /// If set, the transport's tempo, position, and play state will be derived from MIDI clock,
+    /// Start, Stop, Continue, and Song Position Pointer messages received on the first MIDI input
+    /// device.
     ///
-    /// Specifying an empty string or other invalid value will list all available MIDI output.
+    /// This option is only used with the ALSA, CoreAudio, and WASAPI backends.
     #[clap(value_parser, long)]
-    pub midi_output: Option<String>,
+    pub midi_clock_input: bool,
+    /// If set, MIDI clock at the tempo set with '--tempo' will be sent to all MIDI output devices.
+    /// This starts with a MIDI Start message and ends with a MIDI Stop message when the
+    /// application exits.
+    ///
+    /// This option is only used with the ALSA, CoreAudio, and WASAPI backends.
+    #[clap(value_parser, long)]
+    pub midi_clock_output: bool,
 
     /// If set to a port name ('foo:bar_1'), then all all inputs will be connected to that port. If
     /// the option is set to a comma separated list of port names ('foo:bar_1,foo:bar_2') then the
@@ -85,12 +139,18 @@ pub struct WrapperConfig {
     pub dpi_scale: f32,
 
     /// The transport's tempo.
+    ///
+    /// When using the JACK backend this is only used when there is no JACK timebase master.
     #[clap(value_parser, long, default_value = "120")]
     pub tempo: f32,
     /// The time signature's numerator.
+    ///
+    /// When using the JACK backend this is only used when there is no JACK timebase master.
     #[clap(value_parser, long, default_value = "4")]
     pub timesig_num: u32,
     /// The time signature's denominator.
+    ///
+    /// When using the JACK backend this is only used when there is no JACK timebase master.
     #[clap(value_parser, long, default_value = "4")]
     pub timesig_denom: u32,
 }
@@ -117,6 +177,19 @@ pub enum BackendType {
     Dummy,
 }
 
+/// Determines what happens when the audio device fails while the application is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DeviceLossBehavior {
+    /// Keep processing without audio input or output using the dummy backend, and periodically try
+    /// to reopen the audio device. Processing continues on the original backend once this succeeds.
+    Reconnect,
+    /// Keep processing without audio input or output using the dummy backend, without trying to
+    /// reopen the audio device.
+    Dummy,
+    /// Exit the application with a non-zero exit code.
+    Exit,
+}
+
 impl WrapperConfig {
     /// Get the audio IO layout for a plugin based on this configuration. Exits the application if
     /// the IO layout could not be parsed from the config. This doesn't return a `Result` to be able to differentiate between backend-specific errors and config parsing errors.
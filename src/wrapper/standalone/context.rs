@@ -94,9 +94,17 @@ impl<P: Plugin, B: Backend<P>> ProcessContext<P> for WrapperProcessContext<'_, P
         self.wrapper.set_latency_samples(samples)
     }
 
+    fn latency_samples(&self) -> u32 {
+        self.wrapper.latency_samples()
+    }
+
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn param_info_changed(&self) {
+        self.wrapper.param_info_changed()
+    }
 }
 
 impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
@@ -109,11 +117,13 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
         true
     }
 
-    unsafe fn raw_begin_set_parameter(&self, _param: ParamPtr) {
-        // Since there's no automation being recorded here, gestures don't mean anything
+    unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+        // Since there's no automation being recorded here, gestures are only used for the undo
+        // history
+        self.wrapper.undo_history.record_begin_set_parameter(param);
 
         #[cfg(debug_assertions)]
-        match self.wrapper.param_id_from_ptr(_param) {
+        match self.wrapper.param_id_from_ptr(param) {
             Some(param_id) => self
                 .param_gesture_checker
                 .borrow_mut()
@@ -125,6 +135,9 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
     }
 
     unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        self.wrapper
+            .undo_history
+            .record_set_parameter(param, normalized);
         self.wrapper.set_parameter(param, normalized);
 
         #[cfg(debug_assertions)]
@@ -139,9 +152,11 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
         }
     }
 
-    unsafe fn raw_end_set_parameter(&self, _param: ParamPtr) {
+    unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+        self.wrapper.undo_history.record_end_set_parameter(param);
+
         #[cfg(debug_assertions)]
-        match self.wrapper.param_id_from_ptr(_param) {
+        match self.wrapper.param_id_from_ptr(param) {
             Some(param_id) => self
                 .param_gesture_checker
                 .borrow_mut()
@@ -157,6 +172,36 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
     }
 
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
-        self.wrapper.set_state_object_from_gui(state)
+        let old_state = self.wrapper.get_state_object();
+        self.wrapper.set_state_object_from_gui(state.clone());
+        self.wrapper.undo_history.record_set_state(old_state, state);
+    }
+
+    fn undo(&self) {
+        self.wrapper.undo_history.undo(self);
+    }
+
+    fn redo(&self) {
+        self.wrapper.undo_history.redo(self);
+    }
+
+    fn midi_learn(&self) -> &crate::midi::learn::MidiLearn {
+        &self.wrapper.midi_learn
+    }
+
+    fn state_slots(&self) -> &crate::wrapper::state::StateSlots {
+        &self.wrapper.state_slots
+    }
+
+    fn switch_state_slot(&self, slot: usize) {
+        self.wrapper.switch_state_slot(slot)
+    }
+
+    fn copy_state_slot(&self, from: usize, to: usize) {
+        self.wrapper.copy_state_slot(from, to)
+    }
+
+    fn param_info_changed(&self) {
+        self.wrapper.param_info_changed()
     }
 }
@@ -0,0 +1,93 @@
+//! Status reporting for standalone applications. This lets unattended installations monitor the
+//! audio backend and distinguish between different kinds of failures.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use super::wrapper::WrapperError;
+
+/// A change in the standalone application's audio backend. These are passed to the status
+/// callback from [`nih_export_standalone_with_status_callback()`][super::nih_export_standalone_with_status_callback()].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum StandaloneStatus {
+    /// Audio processing has started using the named backend.
+    Started { backend: &'static str },
+    /// The audio device failed or disappeared while processing audio. Depending on the
+    /// `--on-device-loss` option the application either continues processing without audio input
+    /// and output using the dummy backend, or it exits with [`StandaloneError::DeviceLost`].
+    DeviceLost {
+        backend: &'static str,
+        error: String,
+    },
+    /// An attempt to reopen the failed audio device did not succeed. The next attempt is made after
+    /// `retry_in`.
+    ReconnectFailed {
+        backend: &'static str,
+        attempt: u32,
+        error: String,
+        retry_in: Duration,
+    },
+    /// The failed audio device has been reopened, and processing continues using the original
+    /// backend.
+    Reconnected {
+        backend: &'static str,
+        attempts: u32,
+    },
+}
+
+/// The reasons a standalone application can fail. [`nih_export_standalone()`][super::nih_export_standalone()]
+/// exits the process with the code from [`exit_code()`][Self::exit_code()] when this happens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StandaloneError {
+    /// None of the requested audio backends could be initialized.
+    BackendInitializationFailed,
+    /// The plugin returned `false` during initialization.
+    PluginInitializationFailed,
+    /// The plugin returned an error while processing audio.
+    ProcessingFailed,
+    /// The audio device failed and `--on-device-loss` was set to `exit`.
+    DeviceLost,
+}
+
+impl StandaloneError {
+    /// The process exit code for this error. Exit code 1 is used when the command line arguments
+    /// are invalid, and 2 is used by the argument parser for usage errors.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            StandaloneError::BackendInitializationFailed => 3,
+            StandaloneError::PluginInitializationFailed => 4,
+            StandaloneError::ProcessingFailed => 5,
+            StandaloneError::DeviceLost => 6,
+        }
+    }
+}
+
+impl Display for StandaloneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StandaloneError::BackendInitializationFailed => {
+                write!(f, "The audio backend could not be initialized")
+            }
+            StandaloneError::PluginInitializationFailed => {
+                write!(f, "The plugin failed to initialize")
+            }
+            StandaloneError::ProcessingFailed => {
+                write!(f, "The plugin returned an error while processing audio")
+            }
+            StandaloneError::DeviceLost => write!(f, "The audio device failed"),
+        }
+    }
+}
+
+impl std::error::Error for StandaloneError {}
+
+impl From<WrapperError> for StandaloneError {
+    fn from(error: WrapperError) -> Self {
+        match error {
+            WrapperError::InitializationFailed => StandaloneError::PluginInitializationFailed,
+            WrapperError::ProcessingFailed => StandaloneError::ProcessingFailed,
+            WrapperError::DeviceLost => StandaloneError::DeviceLost,
+        }
+    }
+}
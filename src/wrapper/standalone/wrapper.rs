@@ -9,23 +9,31 @@ use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
-use super::backend::Backend;
-use super::config::WrapperConfig;
+use super::backend::{Backend, Dummy};
+use super::config::{DeviceLossBehavior, WrapperConfig};
 use super::context::{WrapperGuiContext, WrapperInitContext, WrapperProcessContext};
+use super::status::StandaloneStatus;
 use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
+use crate::midi::learn::MidiLearn;
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, ParamFlags, ParamPtr, Params,
-    ParentWindowHandle, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor,
-    Transport,
+    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, Editor, ParamFlags,
+    ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus,
+    TaskExecutor, Transport,
 };
 use crate::util::permit_alloc;
-use crate::wrapper::state::{self, PluginState};
+use crate::wrapper::state::{self, PluginState, StateSlots};
+use crate::wrapper::util::bypass::{self, BypassProcessor};
 use crate::wrapper::util::process_wrapper;
+use crate::wrapper::util::undo::UndoHistory;
 
 /// How many parameter changes we can store in our unprocessed parameter change queue. Storing more
 /// than this many parameters at a time will cause changes to get lost.
 const EVENT_QUEUE_CAPACITY: usize = 2048;
+/// How long to wait before the first attempt to reopen a failed audio device. This doubles after
+/// every failed attempt, up to the `--max-reconnect-interval` option.
+const INITIAL_RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
 
 pub struct Wrapper<P: Plugin, B: Backend<P>> {
     backend: AtomicRefCell<B>,
@@ -54,6 +62,9 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// This is used to grab the DPI scaling config. Not used on macOS.
     #[allow(unused)]
     config: WrapperConfig,
+    /// Called whenever the audio backend starts, fails, or reconnects. This is called from the
+    /// thread that runs the backend, never from the audio callback.
+    status_callback: Arc<dyn Fn(StandaloneStatus) + Send + Sync>,
 
     /// A mapping from parameter pointers to string parameter IDs. This is used as part of
     /// `Task::ParamValueChanged` to send a parameter change event to the editor from the GUI
@@ -63,6 +74,14 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// A mapping from parameter string IDs to parameter pointers. Used for serialization and
     /// deserialization.
     param_id_to_ptr: HashMap<String, ParamPtr>,
+    /// MIDI CC mappings created through MIDI learn. These are applied to the incoming MIDI CCs
+    /// before the plugin's process function gets called.
+    pub midi_learn: MidiLearn,
+    /// Parameter gestures and state restores made from the editor.
+    pub undo_history: UndoHistory,
+    /// Switchable state snapshots for A/B comparisons. Switching to another slot from the editor
+    /// loads that slot's snapshot at the start of the next process call.
+    pub state_slots: StateSlots,
 
     /// The bus and buffer configurations are static for the standalone target.
     audio_io_layout: AudioIOLayout,
@@ -88,6 +107,10 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// still kept track of to avoid firing debug assertions multiple times for the same latency
     /// value.
     current_latency: AtomicU32,
+    /// Crossfades to a latency compensated dry signal when the plugin is bypassed, if the plugin
+    /// opted into that through [`Plugin::BYPASS_POLICY`]. Otherwise this simply calls the plugin's
+    /// process function.
+    bypass: AtomicRefCell<BypassProcessor>,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -104,13 +127,20 @@ pub enum Task<P: Plugin> {
     /// like in the plugin APIs, so we'll just use the `ParamPtr`s directly. These are used to index
     /// the hashmaps stored on `Wrapper`.
     ParameterValueChanged(ParamPtr, f32),
+    /// Inform the plugin that one or more parameters' names, ranges, or enum variants have changed.
+    /// There is no host to notify, so only the editor needs to know about this.
+    ParameterInfoChanged,
 }
 
-/// Errors that may arise while initializing the wrapped plugins.
+/// Errors that may arise while initializing or running the wrapped plugins.
 #[derive(Debug, Clone, Copy)]
 pub enum WrapperError {
     /// The plugin returned `false` during initialization.
     InitializationFailed,
+    /// The plugin returned an error while processing audio.
+    ProcessingFailed,
+    /// The audio device failed and the wrapper was configured to exit when that happens.
+    DeviceLost,
 }
 
 struct WrapperWindowHandler {
@@ -160,6 +190,11 @@ impl<P: Plugin, B: Backend<P>> MainThreadExecutor<Task<P>> for Wrapper<P, B> {
                     editor.lock().param_values_changed();
                 }
             }
+            Task::ParameterInfoChanged => {
+                if let Some(editor) = self.editor.borrow().as_ref() {
+                    editor.lock().param_info_changed();
+                }
+            }
             Task::ParameterValueChanged(param_ptr, normalized_value) => {
                 if let Some(editor) = self.editor.borrow().as_ref() {
                     let param_id = &self.param_ptr_to_id[&param_ptr];
@@ -175,7 +210,11 @@ impl<P: Plugin, B: Backend<P>> MainThreadExecutor<Task<P>> for Wrapper<P, B> {
 impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
     /// Instantiate a new instance of the standalone wrapper. Returns an error if the plugin does
     /// not accept the IO configuration from the wrapper config.
-    pub fn new(backend: B, config: WrapperConfig) -> Result<Arc<Self>, WrapperError> {
+    pub fn new(
+        backend: B,
+        config: WrapperConfig,
+        status_callback: Arc<dyn Fn(StandaloneStatus) + Send + Sync>,
+    ) -> Result<Arc<Self>, WrapperError> {
         // The backend has already queried this, so this will never cause the program to exit
         // TODO: Do the validation and parsing in the argument parser so this value can be stored on
         //       the config itself. Right now clap doesn't support this.
@@ -234,10 +273,21 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 .iter()
                 .map(|(param_id, param_ptr, _)| (*param_ptr, param_id.clone()))
                 .collect(),
+            midi_learn: MidiLearn::new(
+                param_map
+                    .iter()
+                    .map(|(param_id, param_ptr, _)| (param_id, *param_ptr)),
+            ),
+            bypass: AtomicRefCell::new(BypassProcessor::new(
+                P::BYPASS_POLICY,
+                bypass::find_bypass_param(param_map.iter().map(|(_, param_ptr, _)| *param_ptr)),
+            )),
             param_id_to_ptr: param_map
                 .into_iter()
                 .map(|(param_id, param_ptr, _)| (param_id, param_ptr))
                 .collect(),
+            undo_history: UndoHistory::default(),
+            state_slots: StateSlots::new(P::STATE_SLOTS),
 
             audio_io_layout,
             buffer_config: BufferConfig {
@@ -248,6 +298,7 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 process_mode: ProcessMode::Realtime,
             },
             config,
+            status_callback,
 
             unprocessed_param_changes: ArrayQueue::new(EVENT_QUEUE_CAPACITY),
             updated_state_sender,
@@ -296,7 +347,16 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             ) {
                 return Err(WrapperError::InitializationFailed);
             }
-            process_wrapper(|| plugin.reset());
+
+            wrapper.bypass.borrow_mut().initialize(
+                &wrapper.audio_io_layout,
+                &wrapper.buffer_config,
+                wrapper.current_latency.load(Ordering::SeqCst),
+            );
+            process_wrapper(|| {
+                plugin.reset();
+                wrapper.bypass.borrow_mut().reset();
+            });
         }
 
         Ok(wrapper)
@@ -317,7 +377,16 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         let audio_thread = {
             let this = self.clone();
             let terminate_audio_thread = terminate_audio_thread.clone();
-            thread::spawn(move || this.run_audio_thread(terminate_audio_thread, gui_task_sender))
+            let main_thread = thread::current();
+            thread::spawn(move || {
+                let result = this.run_audio_thread(terminate_audio_thread, gui_task_sender);
+
+                // If the plugin doesn't have an editor, then the main thread is parked until the
+                // audio thread stops
+                main_thread.unpark();
+
+                result
+            })
         };
 
         match self.editor.borrow().clone() {
@@ -378,18 +447,20 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 // TODO: Properly block until SIGINT is received if the plugin does not have an editor
                 // TODO: Make sure to handle `GuiTask::Close` here as well
                 nih_log!("{} does not have a GUI, blocking indefinitely...", P::NAME);
-                std::thread::park();
+                while !audio_thread.is_finished() {
+                    std::thread::park();
+                }
             }
         }
 
         terminate_audio_thread.store(true, Ordering::SeqCst);
-        audio_thread.join().unwrap();
+        let result = audio_thread.join().unwrap();
 
         // Some plugins may use this to clean up resources. Should not be needed for the standalone
         // application, but it seems like a good idea to stay consistent.
         self.plugin.lock().deactivate();
 
-        Ok(())
+        result
     }
 
     /// Get a parameter's ID based on a `ParamPtr`. Used in the `GuiContext` implementation for the
@@ -429,10 +500,44 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 self.param_id_to_ptr
                     .iter()
                     .map(|(param_id, param_ptr)| (param_id, *param_ptr)),
+                &self.midi_learn,
+                &self.state_slots,
             )
         }
     }
 
+    /// Get a snapshot of the plugin's parameter values and persistent fields for the state slots.
+    fn get_state_slot_snapshot(&self) -> PluginState {
+        unsafe {
+            state::serialize_snapshot_object::<P>(
+                self.params.clone(),
+                self.param_id_to_ptr
+                    .iter()
+                    .map(|(param_id, param_ptr)| (param_id, *param_ptr)),
+            )
+        }
+    }
+
+    /// Store the current state in the active state slot and switch to another slot, called by the
+    /// plugin's editor from the GUI thread. The new slot's snapshot is loaded at the start of the
+    /// next process call. See
+    /// [`GuiContext::switch_state_slot()`][crate::prelude::GuiContext::switch_state_slot()].
+    pub fn switch_state_slot(&self, slot: usize) {
+        let live_state = self.get_state_slot_snapshot();
+        if let Some(snapshot) = self.state_slots.switch_to(live_state, slot) {
+            self.state_slots.schedule_load(snapshot);
+        }
+    }
+
+    /// Copy one state slot to another, called by the plugin's editor from the GUI thread. See
+    /// [`GuiContext::copy_state_slot()`][crate::prelude::GuiContext::copy_state_slot()].
+    pub fn copy_state_slot(&self, from: usize, to: usize) {
+        let live_state = self.get_state_slot_snapshot();
+        if let Some(snapshot) = self.state_slots.copy(live_state, from, to) {
+            self.state_slots.schedule_load(snapshot);
+        }
+    }
+
     /// Update the plugin's internal state, called by the plugin itself from the GUI thread. To
     /// prevent corrupting data and changing parameters during processing the actual state is only
     /// updated at the end of the audio processing cycle.
@@ -489,6 +594,11 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         }
     }
 
+    pub fn param_info_changed(&self) {
+        let task_posted = self.schedule_gui(Task::ParameterInfoChanged);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
     pub fn set_latency_samples(&self, samples: u32) {
         // This should only change the value if it's actually needed
         let old_latency = self.current_latency.swap(samples, Ordering::SeqCst);
@@ -498,89 +608,266 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         }
     }
 
+    /// The latency last set through `set_latency_samples()`.
+    pub fn latency_samples(&self) -> u32 {
+        self.current_latency.load(Ordering::SeqCst)
+    }
+
     /// The audio thread. This should be called from another thread, and it will run until
-    /// `should_terminate` is `true`.
+    /// `should_terminate` is `true`, until the plugin returns an error, or until the audio device
+    /// fails and the wrapper is configured to exit when that happens. If the audio device fails
+    /// otherwise, then processing continues on the dummy backend while this periodically tries to
+    /// reconnect the original backend.
     fn run_audio_thread(
         self: Arc<Self>,
         should_terminate: Arc<AtomicBool>,
         gui_task_sender: channel::Sender<GuiTask>,
-    ) {
-        self.clone().backend.borrow_mut().run(
-            move |buffer, aux, transport, input_events, output_events| {
-                // TODO: This process wrapper should actually be in the backends (since the backends
-                //       should also not allocate in their audio callbacks), but that's a bit more
-                //       error prone
-                process_wrapper(|| {
-                    if should_terminate.load(Ordering::SeqCst) {
-                        return false;
-                    }
+    ) -> Result<(), WrapperError> {
+        let processing_failed = Arc::new(AtomicBool::new(false));
+        let backend_name = self.backend.borrow().name();
+        (self.status_callback)(StandaloneStatus::Started {
+            backend: backend_name,
+        });
 
-                    let sample_rate = self.buffer_config.sample_rate;
-                    {
-                        let mut plugin = self.plugin.lock();
-                        if let ProcessStatus::Error(err) = plugin.process(
-                            buffer,
-                            aux,
-                            &mut self.make_process_context(transport, input_events, output_events),
-                        ) {
-                            nih_error!("The plugin returned an error while processing:");
-                            nih_error!("{}", err);
-
-                            let push_successful = gui_task_sender.send(GuiTask::Close).is_ok();
-                            nih_debug_assert!(
-                                push_successful,
-                                "Could not queue window close, the editor will remain open"
-                            );
-
-                            return false;
-                        }
+        loop {
+            let result = self
+                .backend
+                .borrow_mut()
+                .run(self.clone().make_process_callback(
+                    should_terminate.clone(),
+                    processing_failed.clone(),
+                    gui_task_sender.clone(),
+                    None,
+                ));
+            if processing_failed.load(Ordering::SeqCst) {
+                return Err(WrapperError::ProcessingFailed);
+            }
+
+            let err = match result {
+                Ok(()) => return Ok(()),
+                // The device failing while the application is shutting down doesn't matter
+                Err(_) if should_terminate.load(Ordering::SeqCst) => return Ok(()),
+                Err(err) => err,
+            };
+
+            nih_error!("The {backend_name} audio device failed: {err:#}");
+            (self.status_callback)(StandaloneStatus::DeviceLost {
+                backend: backend_name,
+                error: format!("{err:#}"),
+            });
+
+            if self.config.on_device_loss == DeviceLossBehavior::Exit {
+                let push_successful = gui_task_sender.send(GuiTask::Close).is_ok();
+                nih_debug_assert!(
+                    push_successful,
+                    "Could not queue window close, the editor will remain open"
+                );
+
+                return Err(WrapperError::DeviceLost);
+            }
+
+            // The plugin instance is shared between the backends, so processing simply continues
+            // on the dummy backend without losing any of the plugin's state
+            nih_log!("Continuing without audio input and output using the dummy backend");
+            let mut dummy = Dummy::new::<P>(self.config.clone());
+            if self.config.on_device_loss == DeviceLossBehavior::Dummy {
+                // The dummy backend cannot fail
+                let _ = Backend::<P>::run(
+                    &mut dummy,
+                    self.clone().make_process_callback(
+                        should_terminate.clone(),
+                        processing_failed.clone(),
+                        gui_task_sender.clone(),
+                        None,
+                    ),
+                );
+
+                return if processing_failed.load(Ordering::SeqCst) {
+                    Err(WrapperError::ProcessingFailed)
+                } else {
+                    Ok(())
+                };
+            }
+
+            let max_reconnect_interval =
+                Duration::from_secs_f32(self.config.max_reconnect_interval.max(0.0));
+            let mut reconnect_interval = INITIAL_RECONNECT_INTERVAL.min(max_reconnect_interval);
+            let mut attempt = 0;
+            loop {
+                let _ = Backend::<P>::run(
+                    &mut dummy,
+                    self.clone().make_process_callback(
+                        should_terminate.clone(),
+                        processing_failed.clone(),
+                        gui_task_sender.clone(),
+                        Some(Instant::now() + reconnect_interval),
+                    ),
+                );
+                if processing_failed.load(Ordering::SeqCst) {
+                    return Err(WrapperError::ProcessingFailed);
+                }
+                if should_terminate.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+
+                attempt += 1;
+                match self.backend.borrow_mut().reconnect() {
+                    Ok(()) => {
+                        nih_log!("Reconnected to the {backend_name} audio device");
+                        (self.status_callback)(StandaloneStatus::Reconnected {
+                            backend: backend_name,
+                            attempts: attempt,
+                        });
+
+                        break;
                     }
+                    Err(err) => {
+                        reconnect_interval = (reconnect_interval * 2).min(max_reconnect_interval);
+
+                        nih_warn!(
+                            "Could not reconnect to the {backend_name} audio device, retrying in \
+                             {:.1} seconds: {err:#}",
+                            reconnect_interval.as_secs_f32()
+                        );
+                        (self.status_callback)(StandaloneStatus::ReconnectFailed {
+                            backend: backend_name,
+                            attempt,
+                            error: format!("{err:#}"),
+                            retry_in: reconnect_interval,
+                        });
+                    }
+                }
+            }
+        }
+    }
 
-                    // Any output note events are now in a vector that can be processed by the
-                    // audio/MIDI backend
-
-                    // We'll always write these events to the first sample, so even when we add note
-                    // output we shouldn't have to think about interleaving events here
-                    while let Some((param_ptr, normalized_value)) =
-                        self.unprocessed_param_changes.pop()
-                    {
-                        if unsafe { param_ptr.set_normalized_value(normalized_value) } {
-                            unsafe { param_ptr.update_smoother(sample_rate, false) };
-                            let task_posted = self.schedule_gui(Task::ParameterValueChanged(
-                                param_ptr,
-                                normalized_value,
-                            ));
-                            nih_debug_assert!(
-                                task_posted,
-                                "The task queue is full, dropping task..."
-                            );
-                        }
+    /// Create the process callback passed to the backends. This returns `false` and thus stops the
+    /// backend when `should_terminate` is set, when the plugin returns an error (in which case
+    /// `processing_failed` is set), or when `deadline` has passed.
+    fn make_process_callback(
+        self: Arc<Self>,
+        should_terminate: Arc<AtomicBool>,
+        processing_failed: Arc<AtomicBool>,
+        gui_task_sender: channel::Sender<GuiTask>,
+        deadline: Option<Instant>,
+    ) -> impl FnMut(
+        &mut Buffer,
+        &mut AuxiliaryBuffers,
+        Transport,
+        &[PluginNoteEvent<P>],
+        &mut Vec<PluginNoteEvent<P>>,
+    ) -> bool
+           + 'static
+           + Send {
+        move |buffer, aux, transport, input_events, output_events| {
+            // TODO: This process wrapper should actually be in the backends (since the backends
+            //       should also not allocate in their audio callbacks), but that's a bit more
+            //       error prone
+            process_wrapper(|| {
+                if should_terminate.load(Ordering::SeqCst) {
+                    return false;
+                }
+                if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                    return false;
+                }
+
+                // If the editor switched to another state slot, then that slot's values are
+                // loaded before processing the block so they apply to the entire block
+                self.state_slots
+                    .handle_pending_load(|snapshot| self.set_state_slot_inner(snapshot));
+
+                let sample_rate = self.buffer_config.sample_rate;
+
+                // Parameters mapped to MIDI CCs are updated before the plugin receives those CCs
+                for event in input_events {
+                    self.midi_learn
+                        .handle_event(event, |param_ptr, normalized_value| {
+                            if unsafe { param_ptr.set_normalized_value(normalized_value) } {
+                                unsafe { param_ptr.update_smoother(sample_rate, false) };
+                                let task_posted = self.schedule_gui(Task::ParameterValueChanged(
+                                    param_ptr,
+                                    normalized_value,
+                                ));
+                                nih_debug_assert!(
+                                    task_posted,
+                                    "The task queue is full, dropping task..."
+                                );
+                            }
+                        });
+                }
+
+                {
+                    let mut plugin = self.plugin.lock();
+                    let status = self.bypass.borrow_mut().process(
+                        &mut *plugin,
+                        buffer,
+                        aux,
+                        &self.current_latency,
+                        |plugin, buffer, aux| {
+                            plugin.process(
+                                buffer,
+                                aux,
+                                &mut self.make_process_context(
+                                    transport,
+                                    input_events,
+                                    output_events,
+                                ),
+                            )
+                        },
+                    );
+                    if let ProcessStatus::Error(err) = status {
+                        nih_error!("The plugin returned an error while processing:");
+                        nih_error!("{}", err);
+
+                        processing_failed.store(true, Ordering::SeqCst);
+                        let push_successful = gui_task_sender.send(GuiTask::Close).is_ok();
+                        nih_debug_assert!(
+                            push_successful,
+                            "Could not queue window close, the editor will remain open"
+                        );
+
+                        return false;
                     }
+                }
 
-                    // After processing audio, we'll check if the editor has sent us updated plugin
-                    // state.  We'll restore that here on the audio thread to prevent changing the
-                    // values during the process call and also to prevent inconsistent state when
-                    // the host also wants to load plugin state.
-                    // FIXME: Zero capacity channels allocate on receiving, find a better
-                    //        alternative that doesn't do that
-                    let updated_state = permit_alloc(|| self.updated_state_receiver.try_recv());
-                    if let Ok(mut state) = updated_state {
-                        self.set_state_inner(&mut state);
-
-                        // We'll pass the state object back to the GUI thread so deallocation can
-                        // happen there without potentially blocking the audio thread
-                        if let Err(err) = self.updated_state_sender.send(state) {
-                            nih_debug_assert_failure!(
-                                "Failed to send state object back to GUI thread: {}",
-                                err
-                            );
-                        };
+                // Any output note events are now in a vector that can be processed by the
+                // audio/MIDI backend
+
+                // We'll always write these events to the first sample, so even when we add note
+                // output we shouldn't have to think about interleaving events here
+                while let Some((param_ptr, normalized_value)) = self.unprocessed_param_changes.pop()
+                {
+                    if unsafe { param_ptr.set_normalized_value(normalized_value) } {
+                        unsafe { param_ptr.update_smoother(sample_rate, false) };
+                        let task_posted = self
+                            .schedule_gui(Task::ParameterValueChanged(param_ptr, normalized_value));
+                        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
                     }
+                }
 
-                    true
-                })
-            },
-        );
+                // After processing audio, we'll check if the editor has sent us updated plugin
+                // state.  We'll restore that here on the audio thread to prevent changing the
+                // values during the process call and also to prevent inconsistent state when
+                // the host also wants to load plugin state.
+                // FIXME: Zero capacity channels allocate on receiving, find a better
+                //        alternative that doesn't do that
+                let updated_state = permit_alloc(|| self.updated_state_receiver.try_recv());
+                if let Ok(mut state) = updated_state {
+                    self.set_state_inner(&mut state);
+
+                    // We'll pass the state object back to the GUI thread so deallocation can
+                    // happen there without potentially blocking the audio thread
+                    if let Err(err) = self.updated_state_sender.send(state) {
+                        nih_debug_assert_failure!(
+                            "Failed to send state object back to GUI thread: {}",
+                            err
+                        );
+                    };
+                }
+
+                true
+            })
+        }
     }
 
     fn make_gui_context(self: Arc<Self>) -> Arc<WrapperGuiContext<P, B>> {
@@ -633,6 +920,8 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 state,
                 self.params.clone(),
                 |param_id| self.param_id_to_ptr.get(param_id).copied(),
+                &self.midi_learn,
+                &self.state_slots,
                 Some(&self.buffer_config),
             )
         });
@@ -676,4 +965,24 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
 
         success
     }
+
+    /// Load a snapshot from the state slots. Unlike [`set_state_inner()`][Self::set_state_inner()]
+    /// this doesn't reset the smoothers or reinitialize the plugin, so it can be called at the start
+    /// of the process call.
+    ///
+    /// Implicitly emits `Task::ParameterValuesChanged`.
+    fn set_state_slot_inner(&self, snapshot: &mut PluginState) {
+        // `Params::deserialize_fields()` may allocate, see `set_state_inner()`
+        permit_alloc(|| unsafe {
+            state::deserialize_snapshot_object::<P>(
+                snapshot,
+                self.params.clone(),
+                |param_id| self.param_id_to_ptr.get(param_id).copied(),
+                Some(&self.buffer_config),
+            )
+        });
+
+        let task_posted = self.schedule_gui(Task::ParameterValuesChanged);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
 }
@@ -6,9 +6,16 @@ use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
+use crate::midi::learn::{MidiLearn, MidiMapping};
 use crate::params::ParamMut;
 use crate::prelude::{BufferConfig, Param, ParamPtr, Params, Plugin};
 
+pub use self::slots::{SerializedStateSlots, StateSlots};
+pub(crate) use self::binary::has_binary_fields;
+
+mod binary;
+mod slots;
+
 // These state objects are also exposed directly to the plugin so it can do its own internal preset
 // management
 
@@ -29,9 +36,9 @@ pub enum ParamValue {
 /// The fields are stored as `BTreeMap`s so the order in the serialized file is consistent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginState {
-    /// The plugin version this state was saved with. Right now this is not used, but later versions
-    /// of NIH-plug may allow you to modify the plugin state object directly before it is loaded to
-    /// allow migrating plugin states between breaking parameter changes.
+    /// The plugin version this state was saved with. This can be used to migrate plugin states
+    /// between breaking parameter changes, either with the `#[migrate(...)]` attribute on a
+    /// [`Params`] struct or in [`Plugin::filter_state()`].
     ///
     /// # Notes
     ///
@@ -50,6 +57,20 @@ pub struct PluginState {
     /// The individual fields are also serialized as JSON so they can safely be restored
     /// independently of the other fields.
     pub fields: BTreeMap<String, String>,
+    /// Persistent fields that are stored as raw bytes instead of JSON. These are the fields
+    /// annotated with `#[persist_bytes = "stable_name"]`. When this or any of the state slots'
+    /// snapshots contain binary fields, the wrappers save the state using the binary format from
+    /// [`PluginState::to_binary()`] so these bytes don't need to be encoded as JSON.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub binary_fields: BTreeMap<String, Vec<u8>>,
+    /// The MIDI CC mappings created through MIDI learn, keyed by the parameters' IDs. See
+    /// [`MidiLearn`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub midi_mappings: BTreeMap<String, MidiMapping>,
+    /// The snapshots stored in the plugin's [`StateSlots`], if any slots other than the first one
+    /// have been used. The snapshots themselves never contain MIDI mappings or nested slots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_slots: Option<SerializedStateSlots>,
 }
 
 /// Create a parameters iterator from the hashtables stored in the plugin wrappers. This avoids
@@ -78,13 +99,29 @@ pub(crate) fn make_params_getter<'a>(
     }
 }
 
-/// Serialize a plugin's state to a state object. This is separate from [`serialize_json()`] to
+/// Serialize a plugin's state to a state object. This is separate from [`serialize_state()`] to
 /// allow passing the raw object directly to the plugin. The parameters are not pulled directly from
 /// `plugin_params` by default to avoid unnecessary allocations in the `.param_map()` method, as the
 /// plugin wrappers will already have a list of parameters handy. See [`make_params_iter()`].
 pub(crate) unsafe fn serialize_object<'a, P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
+    midi_learn: &MidiLearn,
+    state_slots: &StateSlots,
+) -> PluginState {
+    PluginState {
+        midi_mappings: midi_learn.serialize(),
+        state_slots: state_slots.serialize(),
+        ..serialize_snapshot_object::<P>(plugin_params, params_iter)
+    }
+}
+
+/// Serialize only the plugin's parameter values and persistent fields. This is used for the
+/// snapshots stored in the [`StateSlots`], which should not contain the instance-wide MIDI mappings
+/// or the other slots.
+pub(crate) unsafe fn serialize_snapshot_object<'a, P: Plugin>(
+    plugin_params: Arc<dyn Params>,
+    params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
 ) -> PluginState {
     // We'll serialize parameter values as a simple `string_param_id: display_value` map.
     // NOTE: If the plugin is being modulated (and the plugin is a CLAP plugin in Bitwig Studio),
@@ -120,30 +157,43 @@ pub(crate) unsafe fn serialize_object<'a, P: Plugin>(
     // The plugin can also persist arbitrary fields alongside its parameters. This is useful for
     // storing things like sample data.
     let fields = plugin_params.serialize_fields();
+    let binary_fields = plugin_params.serialize_binary_fields();
 
     PluginState {
         version: String::from(P::VERSION),
         params,
         fields,
+        binary_fields,
+        midi_mappings: BTreeMap::new(),
+        state_slots: None,
     }
 }
 
-/// Serialize a plugin's state to a vector containing JSON data. This can (and should) be shared
-/// across plugin formats. If the `zstd` feature is enabled, then the state will be compressed using
-/// Zstandard.
-pub(crate) unsafe fn serialize_json<'a, P: Plugin>(
+/// Serialize a plugin's state to a vector containing JSON data, or binary data in the format used
+/// by [`PluginState::to_binary()`] if the plugin or any of its state slots has `#[persist_bytes]`
+/// fields with data. This can (and should) be shared across plugin formats. If the `zstd` feature
+/// is enabled, then the state will be compressed using Zstandard.
+pub(crate) unsafe fn serialize_state<'a, P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
+    midi_learn: &MidiLearn,
+    state_slots: &StateSlots,
 ) -> Result<Vec<u8>> {
-    let plugin_state = serialize_object::<P>(plugin_params, params_iter);
-    let json = serde_json::to_vec(&plugin_state).context("Could not format as JSON")?;
+    let plugin_state = serialize_object::<P>(plugin_params, params_iter, midi_learn, state_slots);
+    // States without raw binary data are still stored as plain JSON so they remain readable by
+    // older versions of the plugin
+    let serialized = if !binary::has_binary_fields(&plugin_state) {
+        serde_json::to_vec(&plugin_state).context("Could not format as JSON")?
+    } else {
+        plugin_state.to_binary()?
+    };
 
     #[cfg(feature = "zstd")]
     {
-        let compressed = zstd::encode_all(json.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)
+        let compressed = zstd::encode_all(serialized.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)
             .context("Could not compress state")?;
 
-        let state_bytes = json.len();
+        let state_bytes = serialized.len();
         let compressed_state_bytes = compressed.len();
         let compression_ratio = compressed_state_bytes as f32 / state_bytes as f32 * 100.0;
         nih_trace!(
@@ -155,7 +205,7 @@ pub(crate) unsafe fn serialize_json<'a, P: Plugin>(
     }
     #[cfg(not(feature = "zstd"))]
     {
-        Ok(json)
+        Ok(serialized)
     }
 }
 
@@ -170,16 +220,68 @@ pub(crate) unsafe fn serialize_json<'a, P: Plugin>(
 /// parameter values. The smoothers have already been reset by this function.
 ///
 /// The [`Plugin`] argument is used to call [`Plugin::filter_state()`] just before loading the
-/// state.
+/// state, after the migrations from [`Params::migrate_state()`] have been applied.
 pub(crate) unsafe fn deserialize_object<P: Plugin>(
+    state: &mut PluginState,
+    plugin_params: Arc<dyn Params>,
+    params_getter: impl Fn(&str) -> Option<ParamPtr>,
+    midi_learn: &MidiLearn,
+    state_slots: &StateSlots,
+    current_buffer_config: Option<&BufferConfig>,
+) -> bool {
+    // Parameter ID and persistent field key migrations declared on the `Params` struct are applied
+    // first, and then the plugin can perform its own migrations on old state if needed
+    plugin_params.migrate_state(state);
+    P::filter_state(state);
+
+    // Make sure everything starts out in sync
+    deserialize_params(state, params_getter, current_buffer_config, true);
+
+    // The plugin can also persist arbitrary fields alongside its parameters. This is useful for
+    // storing things like sample data.
+    plugin_params.deserialize_fields(&state.fields);
+    plugin_params.deserialize_binary_fields(&state.binary_fields);
+
+    // Loading a state also replaces the MIDI mappings, even if the state doesn't contain any
+    midi_learn.deserialize(&state.midi_mappings);
+
+    // The state slots are only replaced if the state contains them. This way loading a preset
+    // from the editor only replaces the active slot.
+    if let Some(serialized_slots) = &state.state_slots {
+        state_slots.deserialize(serialized_slots);
+    }
+
+    true
+}
+
+/// Deserialize one of the snapshots stored in the [`StateSlots`]. Unlike [`deserialize_object()`]
+/// this does not reset the parameters' smoothers, so the parameters glide to their new values, and
+/// the plugin should not be reinitialized afterwards. This is what makes switching between slots
+/// glitch-free. The MIDI mappings and the other slots are left untouched.
+pub(crate) unsafe fn deserialize_snapshot_object<P: Plugin>(
     state: &mut PluginState,
     plugin_params: Arc<dyn Params>,
     params_getter: impl Fn(&str) -> Option<ParamPtr>,
     current_buffer_config: Option<&BufferConfig>,
 ) -> bool {
-    // This lets the plugin perform migrations on old state if needed
+    plugin_params.migrate_state(state);
     P::filter_state(state);
+    deserialize_params(state, params_getter, current_buffer_config, false);
+    plugin_params.deserialize_fields(&state.fields);
+    plugin_params.deserialize_binary_fields(&state.binary_fields);
 
+    true
+}
+
+/// Restore the parameter values from a state object. If `reset_smoothers` is set, then the
+/// parameters' smoothers will immediately jump to the new values. Otherwise they will smoothly
+/// transition to them.
+unsafe fn deserialize_params(
+    state: &PluginState,
+    params_getter: impl Fn(&str) -> Option<ParamPtr>,
+    current_buffer_config: Option<&BufferConfig>,
+    reset_smoothers: bool,
+) {
     let sample_rate = current_buffer_config.map(|c| c.sample_rate);
     for (param_id_str, param_value) in &state.params {
         let param_ptr = match params_getter(param_id_str.as_str()) {
@@ -225,27 +327,20 @@ pub(crate) unsafe fn deserialize_object<P: Plugin>(
             }
         }
 
-        // Make sure everything starts out in sync
         if let Some(sample_rate) = sample_rate {
-            param_ptr.update_smoother(sample_rate, true);
+            param_ptr.update_smoother(sample_rate, reset_smoothers);
         }
     }
-
-    // The plugin can also persist arbitrary fields alongside its parameters. This is useful for
-    // storing things like sample data.
-    plugin_params.deserialize_fields(&state.fields);
-
-    true
 }
 
-/// Deserialize a plugin's state from a vector containing (compressed) JSON data. Doesn't load the
-/// plugin state since doing so should be accompanied by calls to `Plugin::init()` and
-/// `Plugin::reset()`, and this way all of that behavior can be encapsulated so it can be reused in
-/// multiple places. The returned state object can be passed to [`deserialize_object()`].
-pub(crate) unsafe fn deserialize_json(state: &[u8]) -> Option<PluginState> {
+/// Deserialize a plugin's state from a vector containing (compressed) JSON or binary state data.
+/// Doesn't load the plugin state since doing so should be accompanied by calls to `Plugin::init()`
+/// and `Plugin::reset()`, and this way all of that behavior can be encapsulated so it can be reused
+/// in multiple places. The returned state object can be passed to [`deserialize_object()`].
+pub(crate) unsafe fn deserialize_state(state: &[u8]) -> Option<PluginState> {
     #[cfg(feature = "zstd")]
     let result: Option<PluginState> = match zstd::decode_all(state) {
-        Ok(decompressed) => match serde_json::from_slice(decompressed.as_slice()) {
+        Ok(decompressed) => match decode_state(decompressed.as_slice()) {
             Ok(s) => {
                 let state_bytes = decompressed.len();
                 let compressed_state_bytes = state.len();
@@ -258,23 +353,23 @@ pub(crate) unsafe fn deserialize_json(state: &[u8]) -> Option<PluginState> {
                 Some(s)
             }
             Err(err) => {
-                nih_debug_assert_failure!("Error while deserializing state: {}", err);
+                nih_debug_assert_failure!("Error while deserializing state: {:#}", err);
                 None
             }
         },
         // Uncompressed state files can still be loaded after enabling this feature to prevent
         // breaking existing plugin instances
-        Err(zstd_err) => match serde_json::from_slice(state) {
+        Err(zstd_err) => match decode_state(state) {
             Ok(s) => {
                 nih_trace!("Older uncompressed state found");
                 Some(s)
             }
-            Err(json_err) => {
+            Err(err) => {
                 nih_debug_assert_failure!(
                     "Error while deserializing state as either compressed or uncompressed state: \
-                     {}, {}",
+                     {}, {:#}",
                     zstd_err,
-                    json_err
+                    err
                 );
                 None
             }
@@ -282,13 +377,23 @@ pub(crate) unsafe fn deserialize_json(state: &[u8]) -> Option<PluginState> {
     };
 
     #[cfg(not(feature = "zstd"))]
-    let result: Option<PluginState> = match serde_json::from_slice(state) {
+    let result: Option<PluginState> = match decode_state(state) {
         Ok(s) => Some(s),
         Err(err) => {
-            nih_debug_assert_failure!("Error while deserializing state: {}", err);
+            nih_debug_assert_failure!("Error while deserializing state: {:#}", err);
             None
         }
     };
 
     result
 }
+
+/// Decode an uncompressed state. This can either be JSON data, or data in the binary format used by
+/// [`PluginState::to_binary()`].
+fn decode_state(state: &[u8]) -> Result<PluginState> {
+    if binary::is_binary_state(state) {
+        PluginState::from_binary(state)
+    } else {
+        serde_json::from_slice(state).context("Could not parse the state as JSON")
+    }
+}
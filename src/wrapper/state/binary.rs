@@ -0,0 +1,362 @@
+//! A binary container format for [`PluginState`]s with raw binary fields. Storing a couple
+//! megabytes of sample data as an escaped JSON string makes saving and loading states very slow, so
+//! instead the state's binary fields are stored as length-prefixed byte strings after the rest of
+//! the state, which is still encoded as JSON.
+//!
+//! The format consists of the following parts, with all integers stored in little-endian byte
+//! order:
+//!
+//! 1. The magic bytes `NIHSTATE`.
+//! 2. The format version as a `u32`. This is currently always 1.
+//! 3. The length of the JSON encoded state as a `u64`, followed by the JSON data. This contains
+//!    everything except for [`PluginState::binary_fields`] and the binary fields of the snapshots
+//!    in [`PluginState::state_slots`].
+//! 4. The number of binary fields as a `u64`. Every binary field is then stored as the key's length
+//!    as a `u64`, the UTF-8 encoded key, the data's length as a `u64`, and finally the field's
+//!    data.
+//! 5. The number of state slot snapshots with binary fields as a `u64`. Every snapshot's binary
+//!    fields are then stored as the slot's index as a `u64`, followed by the snapshot's binary
+//!    fields in the same format as in part 4.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::{ParamValue, PluginState, SerializedStateSlots};
+use crate::midi::learn::MidiMapping;
+
+/// The bytes every state in the binary format starts with.
+const MAGIC: &[u8; 8] = b"NIHSTATE";
+/// The current version of the binary format. Decoding fails for newer versions.
+const FORMAT_VERSION: u32 = 1;
+
+/// The part of a [`PluginState`] that is stored as JSON. This mirrors `PluginState` without the
+/// binary fields, so the JSON data can be deserialized directly as a `PluginState`.
+#[derive(Serialize)]
+struct JsonPart<'a> {
+    version: &'a str,
+    params: &'a BTreeMap<String, ParamValue>,
+    fields: &'a BTreeMap<String, String>,
+    midi_mappings: &'a BTreeMap<String, MidiMapping>,
+    state_slots: Option<JsonStateSlots<'a>>,
+}
+
+/// Mirrors [`SerializedStateSlots`], with the snapshots' binary fields left out of the JSON data.
+#[derive(Serialize)]
+struct JsonStateSlots<'a> {
+    active: usize,
+    slots: Vec<Option<JsonPart<'a>>>,
+}
+
+impl<'a> JsonPart<'a> {
+    fn new(state: &'a PluginState) -> Self {
+        Self {
+            version: &state.version,
+            params: &state.params,
+            fields: &state.fields,
+            midi_mappings: &state.midi_mappings,
+            state_slots: state
+                .state_slots
+                .as_ref()
+                .map(|state_slots| JsonStateSlots {
+                    active: state_slots.active,
+                    slots: state_slots
+                        .slots
+                        .iter()
+                        .map(|snapshot| {
+                            snapshot.as_ref().map(|snapshot| {
+                                // Snapshots never contain state slots themselves
+                                nih_debug_assert!(snapshot.state_slots.is_none());
+                                JsonPart {
+                                    state_slots: None,
+                                    ..JsonPart::new(snapshot)
+                                }
+                            })
+                        })
+                        .collect(),
+                }),
+        }
+    }
+}
+
+impl PluginState {
+    /// Encode this state using nih-plug's binary state format. Unlike with JSON, the
+    /// [`binary_fields`][Self::binary_fields] are stored as is, which makes this much faster for
+    /// states containing large amounts of binary data. The state can be decoded again using
+    /// [`PluginState::from_binary()`]. This is what the wrappers use to save states with binary
+    /// fields.
+    pub fn to_binary(&self) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(&JsonPart::new(self)).context("Could not format as JSON")?;
+
+        let slot_binary_fields: Vec<(usize, &BTreeMap<String, Vec<u8>>)> = self
+            .state_slots
+            .iter()
+            .flat_map(|state_slots| state_slots.slots.iter().enumerate())
+            .filter_map(|(slot, snapshot)| Some((slot, &snapshot.as_ref()?.binary_fields)))
+            .filter(|(_, binary_fields)| !binary_fields.is_empty())
+            .collect();
+
+        let binary_fields_len = binary_fields_len(&self.binary_fields)
+            + slot_binary_fields
+                .iter()
+                .map(|(_, binary_fields)| 8 + binary_fields_len(binary_fields))
+                .sum::<usize>();
+        let mut encoded =
+            Vec::with_capacity(MAGIC.len() + 4 + 8 + json.len() + 8 + binary_fields_len);
+        encoded.extend_from_slice(MAGIC);
+        encoded.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        write_bytes(&mut encoded, &json);
+        write_binary_fields(&mut encoded, &self.binary_fields);
+        encoded.extend_from_slice(&(slot_binary_fields.len() as u64).to_le_bytes());
+        for (slot, binary_fields) in slot_binary_fields {
+            encoded.extend_from_slice(&(slot as u64).to_le_bytes());
+            write_binary_fields(&mut encoded, binary_fields);
+        }
+
+        Ok(encoded)
+    }
+
+    /// Decode a state encoded with [`PluginState::to_binary()`].
+    pub fn from_binary(encoded: &[u8]) -> Result<Self> {
+        let mut remaining = encoded
+            .strip_prefix(MAGIC.as_slice())
+            .context("The data does not contain a binary plugin state")?;
+
+        let format_version = u32::from_le_bytes(read_array(&mut remaining)?);
+        if format_version > FORMAT_VERSION {
+            bail!("Unsupported binary state format version {format_version}");
+        }
+
+        let json = read_bytes(&mut remaining)?;
+        let mut state: PluginState =
+            serde_json::from_slice(json).context("Could not parse the state's JSON data")?;
+
+        read_binary_fields(&mut remaining, &mut state.binary_fields)?;
+        let num_slots = u64::from_le_bytes(read_array(&mut remaining)?);
+        for _ in 0..num_slots {
+            let slot = u64::from_le_bytes(read_array(&mut remaining)?);
+            let snapshot = state
+                .state_slots
+                .as_mut()
+                .and_then(|state_slots| state_slots.slots.get_mut(slot as usize))
+                .and_then(|snapshot| snapshot.as_mut())
+                .with_context(|| format!("State slot {slot} does not contain a snapshot"))?;
+
+            read_binary_fields(&mut remaining, &mut snapshot.binary_fields)?;
+        }
+
+        if !remaining.is_empty() {
+            bail!("Unexpected trailing data after the binary state");
+        }
+
+        Ok(state)
+    }
+}
+
+/// Whether `state` is stored in the format used by [`PluginState::to_binary()`]. Anything else is
+/// assumed to be JSON.
+pub(crate) fn is_binary_state(state: &[u8]) -> bool {
+    state.starts_with(MAGIC)
+}
+
+/// Whether `state` or any of the snapshots in its state slots contain binary fields. These states
+/// should be saved using [`PluginState::to_binary()`] instead of as JSON.
+pub(crate) fn has_binary_fields(state: &PluginState) -> bool {
+    !state.binary_fields.is_empty()
+        || state.state_slots.iter().any(|state_slots| {
+            state_slots
+                .slots
+                .iter()
+                .flatten()
+                .any(|snapshot| !snapshot.binary_fields.is_empty())
+        })
+}
+
+/// The number of bytes needed to store `binary_fields` using [`write_binary_fields()`].
+fn binary_fields_len(binary_fields: &BTreeMap<String, Vec<u8>>) -> usize {
+    8 + binary_fields
+        .iter()
+        .map(|(key, data)| 16 + key.len() + data.len())
+        .sum::<usize>()
+}
+
+/// Write the number of binary fields followed by every field's key and data.
+fn write_binary_fields(encoded: &mut Vec<u8>, binary_fields: &BTreeMap<String, Vec<u8>>) {
+    encoded.extend_from_slice(&(binary_fields.len() as u64).to_le_bytes());
+    for (key, data) in binary_fields {
+        write_bytes(encoded, key.as_bytes());
+        write_bytes(encoded, data);
+    }
+}
+
+/// Read binary fields written by [`write_binary_fields()`] from the start of `remaining` into
+/// `binary_fields`, and advance `remaining` past them.
+fn read_binary_fields(
+    remaining: &mut &[u8],
+    binary_fields: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<()> {
+    let num_binary_fields = u64::from_le_bytes(read_array(remaining)?);
+    for _ in 0..num_binary_fields {
+        let key = std::str::from_utf8(read_bytes(remaining)?)
+            .context("Binary field key is not valid UTF-8")?;
+        let data = read_bytes(remaining)?;
+
+        binary_fields.insert(key.to_owned(), data.to_vec());
+    }
+
+    Ok(())
+}
+
+/// Write a `u64` length prefix followed by the data itself.
+fn write_bytes(encoded: &mut Vec<u8>, bytes: &[u8]) {
+    encoded.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    encoded.extend_from_slice(bytes);
+}
+
+/// Read a fixed number of bytes from the start of `remaining`, and advance `remaining` past them.
+fn read_array<const N: usize>(remaining: &mut &[u8]) -> Result<[u8; N]> {
+    if remaining.len() < N {
+        bail!("Unexpected end of binary state");
+    }
+
+    let (bytes, rest) = remaining.split_at(N);
+    *remaining = rest;
+
+    Ok(bytes.try_into().unwrap())
+}
+
+/// Read data written by [`write_bytes()`] from the start of `remaining`, and advance `remaining`
+/// past it.
+fn read_bytes<'a>(remaining: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = u64::from_le_bytes(read_array(remaining)?);
+    if (remaining.len() as u64) < len {
+        bail!("Unexpected end of binary state");
+    }
+
+    let (bytes, rest) = remaining.split_at(len as usize);
+    *remaining = rest;
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::params::persist::PersistentBytesField;
+    use crate::prelude::{ParamPtr, Params};
+
+    /// A parameters object with a single `#[persist_bytes = "samples"]` field.
+    struct SampleParams {
+        samples: Mutex<Vec<u8>>,
+    }
+
+    unsafe impl Params for SampleParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            Vec::new()
+        }
+
+        fn serialize_binary_fields(&self) -> BTreeMap<String, Vec<u8>> {
+            BTreeMap::from([(
+                String::from("samples"),
+                self.samples.map_bytes(|bytes| bytes.to_vec()),
+            )])
+        }
+
+        fn deserialize_binary_fields(&self, serialized: &BTreeMap<String, Vec<u8>>) {
+            if let Some(bytes) = serialized.get("samples") {
+                self.samples.set_bytes(bytes.clone());
+            }
+        }
+    }
+
+    fn state_with_binary_field(data: Vec<u8>) -> PluginState {
+        PluginState {
+            version: String::from("1.0.0"),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.5))]),
+            fields: BTreeMap::from([(String::from("name"), String::from("\"foo\""))]),
+            binary_fields: BTreeMap::from([(String::from("samples"), data)]),
+            midi_mappings: BTreeMap::new(),
+            state_slots: None,
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+        let state = state_with_binary_field(data.clone());
+
+        let encoded = state.to_binary().unwrap();
+        assert!(is_binary_state(&encoded));
+
+        let decoded = PluginState::from_binary(&encoded).unwrap();
+        assert_eq!(decoded.version, "1.0.0");
+        assert!(matches!(decoded.params["gain"], ParamValue::F32(v) if v == 0.5));
+        assert_eq!(decoded.fields["name"], "\"foo\"");
+        assert_eq!(decoded.binary_fields["samples"], data);
+    }
+
+    #[test]
+    fn truncated() {
+        let encoded = state_with_binary_field(vec![1, 2, 3]).to_binary().unwrap();
+
+        assert!(PluginState::from_binary(&encoded[..encoded.len() - 1]).is_err());
+        assert!(PluginState::from_binary(b"{}").is_err());
+    }
+
+    #[test]
+    fn slot_binary_fields_roundtrip() {
+        let data: Vec<u8> = (0..=255).cycle().take(4 * 1024 * 1024).collect();
+        let params = SampleParams {
+            samples: Mutex::new(data.clone()),
+        };
+        let snapshot = PluginState {
+            binary_fields: params.serialize_binary_fields(),
+            ..state_with_binary_field(Vec::new())
+        };
+        let state = PluginState {
+            binary_fields: BTreeMap::new(),
+            state_slots: Some(SerializedStateSlots {
+                active: 0,
+                slots: vec![None, Some(snapshot)],
+            }),
+            ..state_with_binary_field(Vec::new())
+        };
+        assert!(has_binary_fields(&state));
+
+        // The snapshot's binary field should be stored as is and not as a JSON array
+        let encoded = state.to_binary().unwrap();
+        assert!(encoded.len() < data.len() + 1024);
+
+        let decoded = PluginState::from_binary(&encoded).unwrap();
+        assert!(decoded.binary_fields.is_empty());
+        let state_slots = decoded.state_slots.unwrap();
+        assert!(state_slots.slots[0].is_none());
+        let snapshot = state_slots.slots[1].as_ref().unwrap();
+        assert_eq!(snapshot.fields["name"], "\"foo\"");
+
+        let restored_params = SampleParams {
+            samples: Mutex::new(Vec::new()),
+        };
+        restored_params.deserialize_binary_fields(&snapshot.binary_fields);
+        assert!(*restored_params.samples.lock() == data);
+    }
+
+    #[test]
+    fn json_only_slots() {
+        let snapshot = PluginState {
+            binary_fields: BTreeMap::new(),
+            ..state_with_binary_field(Vec::new())
+        };
+        let state = PluginState {
+            binary_fields: BTreeMap::new(),
+            state_slots: Some(SerializedStateSlots {
+                active: 1,
+                slots: vec![Some(snapshot), None],
+            }),
+            ..state_with_binary_field(Vec::new())
+        };
+        assert!(!has_binary_fields(&state));
+    }
+}
@@ -0,0 +1,291 @@
+//! Multiple switchable state snapshots per plugin instance, for A/B comparisons and the like.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use super::PluginState;
+
+/// The contents of the [`StateSlots`] as they are stored in the [`PluginState`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerializedStateSlots {
+    /// The index of the active slot.
+    pub active: usize,
+    /// The snapshot stored in each slot. The active slot and slots that have never been used are
+    /// `None`. The active slot's contents are the plugin's regular parameter values and persistent
+    /// fields.
+    pub slots: Vec<Option<PluginState>>,
+}
+
+/// Manages a fixed number of state snapshots for a plugin instance, so the user can switch between
+/// and compare multiple settings. The number of slots is set with
+/// [`Plugin::STATE_SLOTS`][crate::prelude::Plugin::STATE_SLOTS]. Every wrapper owns one of these,
+/// and the plugin's editor can access it through
+/// [`GuiContext::state_slots()`][crate::prelude::GuiContext::state_slots()]. Switching and copying
+/// slots is done through the `GuiContext` so the wrapper can load the new values.
+///
+/// Each snapshot contains the plugin's parameter values and its `#[persist]` fields. The MIDI
+/// mappings are shared between all slots. The active slot doesn't have a snapshot of its own since
+/// its contents are simply the plugin's current state. A slot that has never been used starts out
+/// as a copy of the slot that was active when switching to it.
+///
+/// When switching slots while the plugin is processing audio, the new slot is loaded at the start
+/// of the next process call without resetting the parameters' smoothers and without reinitializing
+/// the plugin. This means that your `#[persist]` fields will also be restored on the audio thread
+/// in that case.
+pub struct StateSlots {
+    /// The stored snapshots. This always contains `Plugin::STATE_SLOTS` elements.
+    slots: Mutex<Vec<Option<PluginState>>>,
+    /// The index of the active slot.
+    active: AtomicUsize,
+
+    /// A snapshot that should be loaded at the start of the next process call. This is borrowed by
+    /// the audio thread, and it is only replaced on the GUI thread so the audio thread never needs
+    /// to deallocate it.
+    pending_load: Mutex<Option<PluginState>>,
+    /// Whether `pending_load` still needs to be loaded.
+    has_pending_load: AtomicBool,
+}
+
+impl StateSlots {
+    pub(crate) fn new(num_slots: usize) -> Self {
+        Self {
+            slots: Mutex::new(vec![None; num_slots]),
+            active: AtomicUsize::new(0),
+
+            pending_load: Mutex::new(None),
+            has_pending_load: AtomicBool::new(false),
+        }
+    }
+
+    /// The number of slots. This is always
+    /// [`Plugin::STATE_SLOTS`][crate::prelude::Plugin::STATE_SLOTS].
+    pub fn num_slots(&self) -> usize {
+        self.slots.lock().len()
+    }
+
+    /// The index of the currently active slot.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Whether a slot contains a snapshot that differs from the plugin's current state. This is
+    /// `false` for the active slot and for slots that have never been used.
+    pub fn has_snapshot(&self, slot: usize) -> bool {
+        matches!(self.slots.lock().get(slot), Some(Some(_)))
+    }
+
+    /// A display name for a slot. The slots are named `A`, `B`, `C`, and so on, and they're
+    /// numbered starting at 1 if there are more than 26 of them. Out of bounds slot indices are
+    /// always numbered.
+    pub fn slot_name(&self, slot: usize) -> String {
+        let num_slots = self.num_slots();
+        nih_debug_assert!(slot < num_slots, "Slot {slot} does not exist");
+
+        if num_slots <= 26 && slot < 26 {
+            char::from(b'A' + slot as u8).to_string()
+        } else {
+            (slot + 1).to_string()
+        }
+    }
+
+    /// Make `slot` the active slot. `live_state` is the plugin's current state, which will be
+    /// stored in the previously active slot. Returns the snapshot that should be loaded, if the new
+    /// slot has one. The wrapper is responsible for actually loading it.
+    pub(crate) fn switch_to(&self, live_state: PluginState, slot: usize) -> Option<PluginState> {
+        let mut slots = self.slots.lock();
+        let active = self.active();
+        if slot >= slots.len() || slot == active {
+            return None;
+        }
+
+        slots[active] = Some(self.current_snapshot(live_state));
+        self.active.store(slot, Ordering::SeqCst);
+
+        slots[slot].take()
+    }
+
+    /// Copy the contents of slot `from` to slot `to`. `live_state` is the plugin's current state,
+    /// which is used if `from` is the active slot. Returns the snapshot that should be loaded if
+    /// `to` is the active slot.
+    pub(crate) fn copy(
+        &self,
+        live_state: PluginState,
+        from: usize,
+        to: usize,
+    ) -> Option<PluginState> {
+        let mut slots = self.slots.lock();
+        if from >= slots.len() || to >= slots.len() || from == to {
+            return None;
+        }
+
+        let active = self.active();
+        let snapshot = if from == active {
+            Some(self.current_snapshot(live_state))
+        } else {
+            // Copying an unused slot copies the active slot, since that's what the unused slot
+            // would turn into when switching to it
+            slots[from]
+                .clone()
+                .or_else(|| Some(self.current_snapshot(live_state)))
+        };
+
+        if to == active {
+            snapshot
+        } else {
+            slots[to] = snapshot;
+            None
+        }
+    }
+
+    /// Store a snapshot that should be loaded at the start of the next process call. This replaces
+    /// any snapshot that hasn't been loaded yet. Must be called from the GUI thread.
+    pub(crate) fn schedule_load(&self, snapshot: PluginState) {
+        *self.pending_load.lock() = Some(snapshot);
+        self.has_pending_load.store(true, Ordering::SeqCst);
+    }
+
+    /// Called at the start of the process call to load a snapshot scheduled using
+    /// [`schedule_load()`][Self::schedule_load()], if there is one. If the GUI thread is currently
+    /// scheduling a new snapshot, then this is retried during the next process call. The snapshot
+    /// is not deallocated here.
+    pub(crate) fn handle_pending_load(&self, load: impl FnOnce(&mut PluginState)) {
+        if !self.has_pending_load.load(Ordering::SeqCst) {
+            return;
+        }
+
+        if let Some(mut pending_load) = self.pending_load.try_lock() {
+            if let Some(snapshot) = pending_load.as_mut() {
+                load(snapshot);
+            }
+            self.has_pending_load.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Serialize the slots so they can be stored in the [`PluginState`]. Returns `None` if the
+    /// slots have never been used.
+    pub(crate) fn serialize(&self) -> Option<SerializedStateSlots> {
+        let slots = self.slots.lock();
+        let active = self.active();
+        if active == 0 && slots.iter().all(Option::is_none) {
+            return None;
+        }
+
+        Some(SerializedStateSlots {
+            active,
+            slots: slots.clone(),
+        })
+    }
+
+    /// Restore the slots from a [`PluginState`]. This is called while the plugin's state is being
+    /// restored, after the active slot's contents have been loaded. Any pending snapshot load is
+    /// cancelled.
+    pub(crate) fn deserialize(&self, serialized: &SerializedStateSlots) {
+        let mut slots = self.slots.lock();
+        let num_slots = slots.len();
+        for (slot, snapshot) in slots.iter_mut().enumerate() {
+            *snapshot = serialized.slots.get(slot).cloned().flatten();
+        }
+
+        // If the plugin now has fewer slots than before, then we'll end up in the last slot
+        let active = serialized.active.min(num_slots.saturating_sub(1));
+        if let Some(snapshot) = slots.get_mut(active) {
+            *snapshot = None;
+        }
+        self.active.store(active, Ordering::SeqCst);
+        self.has_pending_load.store(false, Ordering::SeqCst);
+    }
+
+    /// Turn the plugin's live state into a snapshot. If a snapshot is still waiting to be loaded,
+    /// then that is what the active slot contains.
+    fn current_snapshot(&self, live_state: PluginState) -> PluginState {
+        let state = if self.has_pending_load.load(Ordering::SeqCst) {
+            self.pending_load.lock().clone().unwrap_or(live_state)
+        } else {
+            live_state
+        };
+
+        PluginState {
+            midi_mappings: BTreeMap::new(),
+            state_slots: None,
+            ..state
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wrapper::state::ParamValue;
+
+    fn state_with_gain(gain: f32) -> PluginState {
+        PluginState {
+            version: String::new(),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(gain))]),
+            fields: BTreeMap::new(),
+            binary_fields: BTreeMap::new(),
+            midi_mappings: BTreeMap::new(),
+            state_slots: None,
+        }
+    }
+
+    fn gain(state: &PluginState) -> f32 {
+        match state.params["gain"] {
+            ParamValue::F32(gain) => gain,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn switch_and_copy() {
+        let slots = StateSlots::new(2);
+        assert!(slots.serialize().is_none());
+
+        // An unused slot starts out as a copy of the current state, so nothing needs to be loaded
+        assert!(slots.switch_to(state_with_gain(1.0), 1).is_none());
+        assert_eq!(slots.active(), 1);
+        assert!(slots.has_snapshot(0));
+        assert!(!slots.has_snapshot(1));
+
+        let snapshot = slots.switch_to(state_with_gain(2.0), 0).unwrap();
+        assert_eq!(gain(&snapshot), 1.0);
+
+        // Copying to the active slot returns the snapshot that should be loaded
+        let snapshot = slots.copy(state_with_gain(1.0), 1, 0).unwrap();
+        assert_eq!(gain(&snapshot), 2.0);
+        assert!(slots.copy(state_with_gain(3.0), 0, 1).is_none());
+        let snapshot = slots.switch_to(state_with_gain(3.0), 1).unwrap();
+        assert_eq!(gain(&snapshot), 3.0);
+    }
+
+    #[test]
+    fn slot_names() {
+        let slots = StateSlots::new(3);
+        assert_eq!(slots.slot_name(0), "A");
+        assert_eq!(slots.slot_name(2), "C");
+
+        let slots = StateSlots::new(26);
+        assert_eq!(slots.slot_name(25), "Z");
+
+        let slots = StateSlots::new(300);
+        assert_eq!(slots.slot_name(0), "1");
+        assert_eq!(slots.slot_name(299), "300");
+    }
+
+    #[test]
+    fn serialize_roundtrip() {
+        let slots = StateSlots::new(3);
+        slots.switch_to(state_with_gain(1.0), 2);
+
+        let serialized = slots.serialize().unwrap();
+        assert_eq!(serialized.active, 2);
+
+        let restored = StateSlots::new(3);
+        restored.deserialize(&serialized);
+        assert_eq!(restored.active(), 2);
+        assert!(restored.has_snapshot(0));
+        assert!(!restored.has_snapshot(1));
+        assert!(!restored.has_snapshot(2));
+    }
+}
@@ -6,8 +6,10 @@ use std::os::raw::c_char;
 use crate::util::permit_alloc;
 
 pub(crate) mod buffer_management;
+pub(crate) mod bypass;
 #[cfg(debug_assertions)]
 pub(crate) mod context_checks;
+pub(crate) mod undo;
 
 /// The bit that controls flush-to-zero behavior for denormals in 32 and 64-bit floating point
 /// numbers on AArch64.
@@ -3,73 +3,74 @@
 use std::num::NonZeroU32;
 use std::ptr::NonNull;
 
-use crate::prelude::{AudioIOLayout, Buffer};
+use crate::prelude::{AudioIOLayout, Buffer, Sample};
 
 /// Buffers created using [`create_buffers`]. At some point the main `Plugin::process()` should
 /// probably also take an argument like this instead of main+aux buffers if we also want to provide
 /// access to overflowing input channels for e.g. stereo to mono plugins.
-pub struct Buffers<'a, 'buffer: 'a> {
-    pub main_buffer: &'a mut Buffer<'buffer>,
+pub struct Buffers<'a, 'buffer: 'a, T = f32> {
+    pub main_buffer: &'a mut Buffer<'buffer, T>,
 
     // We can't use `AuxiliaryBuffers` here directly because we need different lifetimes for `'a`
     // and `'buffer` while `AuxiliaryBuffers` uses the same lifetime for both.
-    pub aux_inputs: &'a mut [Buffer<'buffer>],
-    pub aux_outputs: &'a mut [Buffer<'buffer>],
+    pub aux_inputs: &'a mut [Buffer<'buffer, T>],
+    pub aux_outputs: &'a mut [Buffer<'buffer, T>],
 }
 
 /// A helper for safely creating and initializing [`Buffer`]s based on the host's input and output
-/// buffers.
-pub struct BufferManager {
+/// buffers. The sample type `T` is either `f32` or `f64`, depending on the precision the host
+/// processes audio at.
+pub struct BufferManager<T = f32> {
     // These are the storage backing the fields in `BufferSource`. The wrapper needs to set these
     // values to match the channel pointers provided by the host. If audio buffers are not provided
     // for a bus, then they should be set to `None`. This helper will then copy data to the buffers
     // or fill them with zeroes if there is no data, while also accounting for in-place main IO
     // buffers.
-    main_input_channel_pointers: Option<ChannelPointers>,
-    main_output_channel_pointers: Option<ChannelPointers>,
-    aux_input_channel_pointers: Vec<Option<ChannelPointers>>,
-    aux_output_channel_pointers: Vec<Option<ChannelPointers>>,
+    main_input_channel_pointers: Option<ChannelPointers<T>>,
+    main_output_channel_pointers: Option<ChannelPointers<T>>,
+    aux_input_channel_pointers: Vec<Option<ChannelPointers<T>>>,
+    aux_output_channel_pointers: Vec<Option<ChannelPointers<T>>>,
 
     /// The backing buffers that will be filled during `create_buffers`. This `'static` lifetime
     /// will be shortened when returning a reference to these buffers in `create_buffers` to match
     /// the function's lifetime.
-    main_buffer: Buffer<'static>,
+    main_buffer: Buffer<'static, T>,
 
-    aux_input_buffers: Vec<Buffer<'static>>,
+    aux_input_buffers: Vec<Buffer<'static, T>>,
     /// Stores the data to back `aux_input_buffers`. We need to copy the host's auxiliary input
     /// buffers to our own first because the `Buffer` API is designed around mutable buffers, and
     /// the host may reuse its input buffers between plugins.
-    aux_input_storage: Vec<Vec<Vec<f32>>>,
+    aux_input_storage: Vec<Vec<Vec<T>>>,
 
-    aux_output_buffers: Vec<Buffer<'static>>,
+    aux_output_buffers: Vec<Buffer<'static, T>>,
 }
 
 // SAFETY: The raw pointers in the `ChannelPointers` fields/vectors are only used as scratch storage
 //         inside of the `create_buffers()` function.
-unsafe impl Send for BufferManager {}
-unsafe impl Sync for BufferManager {}
+unsafe impl<T: Send> Send for BufferManager<T> {}
+unsafe impl<T: Sync> Sync for BufferManager<T> {}
 
 /// Host data that the plugin's [`Buffer`]s should be created from. Leave these fields as `None`
 /// values
-pub struct BufferSource<'a> {
-    pub main_input_channel_pointers: &'a mut Option<ChannelPointers>,
-    pub main_output_channel_pointers: &'a mut Option<ChannelPointers>,
-    pub aux_input_channel_pointers: &'a mut [Option<ChannelPointers>],
-    pub aux_output_channel_pointers: &'a mut [Option<ChannelPointers>],
+pub struct BufferSource<'a, T = f32> {
+    pub main_input_channel_pointers: &'a mut Option<ChannelPointers<T>>,
+    pub main_output_channel_pointers: &'a mut Option<ChannelPointers<T>>,
+    pub aux_input_channel_pointers: &'a mut [Option<ChannelPointers<T>>],
+    pub aux_output_channel_pointers: &'a mut [Option<ChannelPointers<T>>],
 }
 
 /// Pointers to raw multichannel audio data for this port.
 #[derive(Debug, Clone, Copy)]
-pub struct ChannelPointers {
-    /// A raw pointer to an array of f32 arrays, containing one array for each channel. `ptrs` must
-    /// contain (at least) `num_channel` `*const f32`s, and each of those inner arrays must contain
-    /// (at least) `num_samples` `f32` values.
-    pub ptrs: NonNull<*mut f32>,
+pub struct ChannelPointers<T = f32> {
+    /// A raw pointer to an array of sample arrays, containing one array for each channel. `ptrs`
+    /// must contain (at least) `num_channel` `*const T`s, and each of those inner arrays must
+    /// contain (at least) `num_samples` `T` values.
+    pub ptrs: NonNull<*mut T>,
     /// The number of audio channels used for this port.
     pub num_channels: usize,
 }
 
-impl BufferManager {
+impl<T: Sample> BufferManager<T> {
     /// Initialize managed buffers for a specific audio IO layout. The actual buffers can be set up
     /// using channel pointer data using [`create_buffers()`][Self::create_buffers()].
     pub fn for_audio_io_layout(max_buffer_size: usize, audio_io_layout: AudioIOLayout) -> Self {
@@ -113,7 +114,7 @@ impl BufferManager {
 
             aux_input_buffers.push(buffer);
             aux_input_storage.push(vec![
-                vec![0.0; max_buffer_size];
+                vec![T::ZERO; max_buffer_size];
                 num_channels.get() as usize
             ]);
         }
@@ -167,8 +168,8 @@ impl BufferManager {
         &'a mut self,
         sample_offset: usize,
         num_samples: usize,
-        set_buffer_sources: impl FnOnce(&mut BufferSource),
-    ) -> Buffers<'a, 'buffer> {
+        set_buffer_sources: impl FnOnce(&mut BufferSource<T>),
+    ) -> Buffers<'a, 'buffer, T> {
         // Make sure the caller can't forget to unset previously set values
         self.main_input_channel_pointers = None;
         self.main_output_channel_pointers = None;
@@ -244,7 +245,7 @@ impl BufferManager {
             if input_channel_pointers.num_channels < output_channel_pointers.num_channels {
                 self.main_buffer.set_slices(num_samples, |output_slices| {
                     for slice in &mut output_slices[input_channel_pointers.num_channels..] {
-                        slice.fill(0.0);
+                        slice.fill(T::ZERO);
                     }
                 });
             }
@@ -275,7 +276,7 @@ impl BufferManager {
                             input_channel_pointers.ptrs.as_ptr().add(channel_idx);
 
                         nih_debug_assert!(num_samples <= channel.capacity());
-                        channel.resize(num_samples, 0.0);
+                        channel.resize(num_samples, T::ZERO);
                         channel.copy_from_slice(std::slice::from_raw_parts_mut(
                             (*input_channel_pointer).add(sample_offset),
                             num_samples,
@@ -288,12 +289,12 @@ impl BufferManager {
                         .iter_mut()
                         .skip(input_channel_pointers.num_channels)
                     {
-                        channel.fill(0.0);
+                        channel.fill(T::ZERO);
                     }
                 }
                 None => {
                     for channel in input_storage.iter_mut() {
-                        channel.fill(0.0);
+                        channel.fill(T::ZERO);
                     }
                 }
             }
@@ -307,7 +308,7 @@ impl BufferManager {
                     input_slices.iter_mut().zip(input_storage.iter_mut())
                 {
                     // SAFETY: `channel_storage` is no longer used accessed directly after this
-                    *channel_slice = &mut *(channel_storage.as_mut_slice() as *mut [f32]);
+                    *channel_slice = &mut *(channel_storage.as_mut_slice() as *mut [T]);
                 }
             });
         }
@@ -341,7 +342,7 @@ impl BufferManager {
 
                             // The host may not zero out the buffers, and assume the plugin always
                             // write something there
-                            output_slice.fill(0.0);
+                            output_slice.fill(T::ZERO);
                         }
 
                         // If the caller/host should have provided buffer pointers but didn't then
@@ -361,7 +362,7 @@ impl BufferManager {
         // SAFETY: The 'static lifetimes on the objects are needed so we can store the buffers.
         //         Their actual lifetimes are `'a`, so we need to shrink them here. The contents are
         //         valid for as long as the returned object is borrowed.
-        std::mem::transmute::<Buffers<'a, 'static>, Buffers<'a, 'buffer>>(Buffers {
+        std::mem::transmute::<Buffers<'a, 'static, T>, Buffers<'a, 'buffer, T>>(Buffers {
             main_buffer: &mut self.main_buffer,
             aux_inputs: &mut self.aux_input_buffers,
             aux_outputs: &mut self.aux_output_buffers,
@@ -0,0 +1,535 @@
+//! Wrapper-level bypass handling for plugins that opt into it through
+//! [`Plugin::BYPASS_POLICY`][crate::prelude::Plugin::BYPASS_POLICY].
+
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::prelude::{
+    AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, BypassPolicy, ParamFlags, ParamPtr,
+    Plugin, ProcessStatus, Sample,
+};
+use crate::util::delay::DelayLine;
+use crate::util::permit_alloc;
+
+/// The time it takes to crossfade between the processed signal and the dry signal when the bypass
+/// parameter changes.
+const CROSSFADE_TIME_MS: f32 = 10.0;
+
+/// Implements [`BypassPolicy::KeepProcessing`] and [`BypassPolicy::SkipProcessing`] for the
+/// wrappers. The wrapper lets this object call the plugin's process function, after which the
+/// plugin's main input delayed by the plugin's current latency is crossfaded in when the plugin is
+/// bypassed. The sample type `T` is either `f32` or `f64`, depending on the precision the host
+/// processes audio at.
+pub struct BypassProcessor<T = f32> {
+    policy: BypassPolicy,
+    /// The plugin's bypass parameter. If the policy is [`BypassPolicy::Manual`] or if the plugin
+    /// does not have a bypass parameter then this is `None`, and the plugin's process function is
+    /// always called as is.
+    bypass_param: Option<ParamPtr>,
+
+    /// Stores the plugin's main input so it can be mixed back in with latency compensation.
+    delay_line: DelayLine<T>,
+    /// Used to read the delayed dry signal. This has the same size as the maximum block size.
+    dry_scratch_buffer: Vec<T>,
+
+    /// The gain for the dry signal. This is 0.0 when the plugin is not bypassed and 1.0 when it is
+    /// fully bypassed. When the bypass parameter changes this moves towards the new value by
+    /// `gain_step` per sample.
+    dry_gain: f32,
+    gain_step: f32,
+    /// Whether the plugin's process function was skipped for the last block because of
+    /// [`BypassPolicy::SkipProcessing`]. The plugin is reset before it processes audio again.
+    processing_skipped: bool,
+    /// Whether the plugin's latency has exceeded the delay line's capacity since the processor was
+    /// last initialized. This is only logged once since it happens on every block until the host
+    /// reinitializes the plugin.
+    latency_overflow_reported: bool,
+}
+
+/// Find the parameter with the [`ParamFlags::BYPASS`] flag in a plugin's parameters.
+pub fn find_bypass_param(params: impl IntoIterator<Item = ParamPtr>) -> Option<ParamPtr> {
+    params
+        .into_iter()
+        .find(|ptr| unsafe { ptr.flags() }.contains(ParamFlags::BYPASS))
+}
+
+impl<T: Sample> BypassProcessor<T> {
+    /// Create a bypass processor for a plugin with the specified policy. `bypass_param` should be
+    /// obtained through [`find_bypass_param()`]. [`initialize()`][Self::initialize()] needs to
+    /// be called before processing audio.
+    pub fn new(policy: BypassPolicy, bypass_param: Option<ParamPtr>) -> Self {
+        let bypass_param = match policy {
+            BypassPolicy::Manual => None,
+            BypassPolicy::KeepProcessing | BypassPolicy::SkipProcessing => {
+                nih_debug_assert!(
+                    bypass_param.is_some(),
+                    "'Plugin::BYPASS_POLICY' is set, but the plugin does not have a bypass \
+                     parameter"
+                );
+
+                bypass_param
+            }
+        };
+
+        Self {
+            policy,
+            bypass_param,
+
+            delay_line: DelayLine::default(),
+            dry_scratch_buffer: Vec::new(),
+
+            dry_gain: 0.0,
+            gain_step: 1.0,
+            processing_skipped: false,
+            latency_overflow_reported: false,
+        }
+    }
+
+    /// Allocate the delay line for the plugin's audio IO layout and buffer configuration. This
+    /// should be called after the plugin has been initialized, with `latency` being the latency the
+    /// plugin currently reports.
+    pub fn initialize(
+        &mut self,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        latency: u32,
+    ) {
+        if self.bypass_param.is_none() {
+            return;
+        }
+
+        let num_channels = audio_io_layout
+            .main_output_channels
+            .map(NonZeroU32::get)
+            .unwrap_or(0) as usize;
+        let max_block_size = buffer_config.max_buffer_size as usize;
+        // Changing the latency causes the host to reinitialize the plugin, at which point the delay
+        // line can grow. Some hosts take a while to do that though, so this leaves some headroom.
+        let max_latency = (latency as usize).max(buffer_config.sample_rate.ceil() as usize);
+
+        self.delay_line
+            .resize(num_channels, max_block_size, max_latency);
+        self.dry_scratch_buffer.resize(max_block_size, T::ZERO);
+        self.gain_step = (buffer_config.sample_rate * (CROSSFADE_TIME_MS / 1000.0)).recip();
+        self.latency_overflow_reported = false;
+
+        self.reset();
+    }
+
+    /// Clear the delay line and skip any ongoing crossfade. This should be called whenever the
+    /// plugin is reset.
+    pub fn reset(&mut self) {
+        self.delay_line.reset();
+        self.dry_gain = if self.is_bypassed() { 1.0 } else { 0.0 };
+        self.processing_skipped = false;
+    }
+
+    /// Call `process_fn` with the plugin and the buffers, and then crossfade to the plugin's
+    /// input if the plugin is bypassed. The input is delayed by the wrapper's `current_latency`,
+    /// which is read after calling `process_fn` so latency changes made during the process call
+    /// immediately apply. `process_fn` is not called when the policy is
+    /// [`BypassPolicy::SkipProcessing`] and the plugin is fully bypassed.
+    pub fn process<P: Plugin>(
+        &mut self,
+        plugin: &mut P,
+        buffer: &mut Buffer<T>,
+        aux: &mut AuxiliaryBuffers<T>,
+        current_latency: &AtomicU32,
+        process_fn: impl FnOnce(&mut P, &mut Buffer<T>, &mut AuxiliaryBuffers<T>) -> ProcessStatus,
+    ) -> ProcessStatus {
+        if self.bypass_param.is_none() {
+            return process_fn(plugin, buffer, aux);
+        }
+
+        // The main buffer still contains the plugin's input at this point. This needs to be
+        // written even when the plugin is not bypassed, since the delayed input is needed as soon
+        // as the user enables the bypass.
+        self.delay_line.write(buffer);
+
+        let target_gain = if self.is_bypassed() { 1.0 } else { 0.0 };
+        let status = if self.policy == BypassPolicy::SkipProcessing
+            && target_gain == 1.0
+            && self.dry_gain == 1.0
+        {
+            // Without this the auxiliary outputs would contain whatever the host put in them
+            for aux_output in aux.outputs.iter_mut() {
+                for channel in aux_output.as_slice() {
+                    channel.fill(T::ZERO);
+                }
+            }
+
+            self.processing_skipped = true;
+            ProcessStatus::Normal
+        } else {
+            if self.processing_skipped {
+                plugin.reset();
+                self.processing_skipped = false;
+            }
+
+            process_fn(plugin, buffer, aux)
+        };
+
+        if target_gain == 0.0 && self.dry_gain == 0.0 {
+            return status;
+        }
+
+        let latency = current_latency.load(Ordering::SeqCst) as usize;
+        let latency = if latency > self.delay_line.max_delay() {
+            if !self.latency_overflow_reported {
+                permit_alloc(|| {
+                    nih_warn!(
+                        "The plugin's latency of {latency} samples exceeds the bypass delay \
+                         line's capacity, the dry signal will not be aligned until the plugin is \
+                         reinitialized"
+                    )
+                });
+                self.latency_overflow_reported = true;
+            }
+
+            self.delay_line.max_delay()
+        } else {
+            latency
+        };
+
+        let num_samples = buffer.samples();
+        let start_gain = self.dry_gain;
+        let dry = &mut self.dry_scratch_buffer[..num_samples];
+        for (channel_idx, channel) in buffer.as_slice().iter_mut().enumerate() {
+            self.delay_line.read(channel_idx, latency, dry);
+
+            let mut dry_gain = start_gain;
+            for (sample, dry_sample) in channel.iter_mut().zip(dry.iter()) {
+                dry_gain = step_towards(dry_gain, target_gain, self.gain_step);
+
+                let dry_t = T::from_f32(dry_gain);
+                *sample = (*sample * (T::ONE - dry_t)) + (*dry_sample * dry_t);
+            }
+        }
+
+        self.dry_gain = step_towards(start_gain, target_gain, self.gain_step * num_samples as f32);
+
+        status
+    }
+
+    /// Whether the bypass parameter is currently enabled.
+    fn is_bypassed(&self) -> bool {
+        self.bypass_param.map_or(
+            false,
+            |param| unsafe { param.modulated_normalized_value() } >= 0.5,
+        )
+    }
+}
+
+/// Move `value` towards `target` by `step` without overshooting it.
+#[inline]
+fn step_towards(value: f32, target: f32, step: f32) -> f32 {
+    if target > value {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::buffer::with_buffer;
+    use crate::params::{BoolParam, Param, ParamMut};
+    use crate::prelude::{Params, ProcessContext, ProcessMode};
+
+    /// With this sample rate the crossfade takes exactly 10 samples.
+    const SAMPLE_RATE: f32 = 1000.0;
+    const MAX_BLOCK_SIZE: u32 = 16;
+
+    /// A plugin without any parameters that counts how often it has been reset.
+    #[derive(Default)]
+    struct TestPlugin {
+        num_resets: usize,
+    }
+
+    struct TestParams;
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            Vec::new()
+        }
+    }
+
+    impl Plugin for TestPlugin {
+        const NAME: &'static str = "Test Plugin";
+        const VENDOR: &'static str = "Test Vendor";
+        const URL: &'static str = "";
+        const EMAIL: &'static str = "";
+        const VERSION: &'static str = "0.1.0";
+
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[];
+
+        type SysExMessage = ();
+        type BackgroundTask = ();
+
+        fn params(&self) -> Arc<dyn Params> {
+            Arc::new(TestParams)
+        }
+
+        fn reset(&mut self) {
+            self.num_resets += 1;
+        }
+
+        fn process(
+            &mut self,
+            _buffer: &mut Buffer,
+            _aux: &mut AuxiliaryBuffers,
+            _context: &mut impl ProcessContext<Self>,
+        ) -> ProcessStatus {
+            ProcessStatus::Normal
+        }
+    }
+
+    fn bypass_param(bypassed: bool) -> BoolParam {
+        BoolParam::new("Bypass", bypassed).make_bypass()
+    }
+
+    fn set_bypassed(param: &BoolParam, bypassed: bool) {
+        param.set_plain_value(bypassed);
+    }
+
+    /// A bypass processor for a mono plugin that currently reports `latency` samples of latency.
+    /// The delay line's headroom is one second, or 1000 samples.
+    fn bypass_processor(policy: BypassPolicy, param: &BoolParam, latency: u32) -> BypassProcessor {
+        let mut processor = BypassProcessor::new(policy, Some(param.as_ptr()));
+        processor.initialize(
+            &AudioIOLayout {
+                main_input_channels: NonZeroU32::new(1),
+                main_output_channels: NonZeroU32::new(1),
+                ..AudioIOLayout::const_default()
+            },
+            &BufferConfig {
+                sample_rate: SAMPLE_RATE,
+                min_buffer_size: None,
+                max_buffer_size: MAX_BLOCK_SIZE,
+                process_mode: ProcessMode::Realtime,
+            },
+            latency,
+        );
+
+        processor
+    }
+
+    /// Process a mono block. The plugin replaces its main output and its only auxiliary output with
+    /// `wet`. Returns whether the plugin's process function was called.
+    fn process_block(
+        processor: &mut BypassProcessor,
+        plugin: &mut TestPlugin,
+        block: &mut [f32],
+        aux_output: &mut [f32],
+        latency: u32,
+        wet: f32,
+    ) -> bool {
+        let num_samples = block.len();
+        let mut aux_outputs = [Buffer::default()];
+        unsafe {
+            aux_outputs[0].set_slices(num_samples, |output_slices| {
+                *output_slices = vec![aux_output];
+            })
+        };
+        let mut aux = AuxiliaryBuffers {
+            inputs: &mut [],
+            outputs: &mut aux_outputs,
+        };
+        let current_latency = AtomicU32::new(latency);
+
+        let mut processed = false;
+        with_buffer(&mut [block], |buffer| {
+            let status = processor.process(
+                plugin,
+                buffer,
+                &mut aux,
+                &current_latency,
+                |_, buffer, aux| {
+                    processed = true;
+                    for channel in buffer.as_slice() {
+                        channel.fill(wet);
+                    }
+                    for aux_output in aux.outputs.iter_mut() {
+                        for channel in aux_output.as_slice() {
+                            channel.fill(wet);
+                        }
+                    }
+
+                    ProcessStatus::Normal
+                },
+            );
+            assert_eq!(status, ProcessStatus::Normal);
+        });
+
+        processed
+    }
+
+    /// Process `input` in blocks of `block_size` samples with a silent wet signal. Returns the
+    /// output.
+    fn process_blocks(
+        processor: &mut BypassProcessor,
+        plugin: &mut TestPlugin,
+        input: &[f32],
+        block_size: usize,
+        latency: u32,
+    ) -> Vec<f32> {
+        let mut output = input.to_vec();
+        for block in output.chunks_mut(block_size) {
+            let mut aux_output = vec![0.0; block.len()];
+            process_block(processor, plugin, block, &mut aux_output, latency, 0.0);
+        }
+
+        output
+    }
+
+    #[test]
+    fn crossfade_endpoints() {
+        let param = bypass_param(false);
+        let mut processor = bypass_processor(BypassPolicy::KeepProcessing, &param, 0);
+        let mut plugin = TestPlugin::default();
+
+        // With a constant dry signal and a silent wet signal the output is the dry gain. The 10
+        // sample crossfade spans several blocks here.
+        set_bypassed(&param, true);
+        let mut output = Vec::new();
+        for _ in 0..5 {
+            let block = process_blocks(&mut processor, &mut plugin, &[1.0; 3], 3, 0);
+            approx::assert_relative_eq!(processor.dry_gain, block[2], epsilon = 1e-6);
+            output.extend(block);
+        }
+
+        approx::assert_relative_eq!(output[0], 0.1, epsilon = 1e-6);
+        assert!(output.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert_eq!(processor.dry_gain, 1.0);
+        assert!(output[10..].iter().all(|sample| *sample == 1.0));
+
+        set_bypassed(&param, false);
+        let mut output = Vec::new();
+        for _ in 0..4 {
+            let block = process_blocks(&mut processor, &mut plugin, &[1.0; 4], 4, 0);
+            approx::assert_relative_eq!(processor.dry_gain, block[3], epsilon = 1e-6);
+            output.extend(block);
+        }
+
+        approx::assert_relative_eq!(output[0], 0.9, epsilon = 1e-6);
+        assert!(output.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(processor.dry_gain, 0.0);
+        assert!(output[10..].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn skip_processing() {
+        let param = bypass_param(false);
+        let mut processor = bypass_processor(BypassPolicy::SkipProcessing, &param, 0);
+        let mut plugin = TestPlugin::default();
+
+        // The plugin keeps processing until the crossfade has finished
+        set_bypassed(&param, true);
+        let mut aux_output = [1.0; 8];
+        assert!(process_block(
+            &mut processor,
+            &mut plugin,
+            &mut [1.0; 8],
+            &mut aux_output,
+            0,
+            0.5
+        ));
+        assert_eq!(aux_output, [0.5; 8]);
+        assert!(process_block(
+            &mut processor,
+            &mut plugin,
+            &mut [1.0; 8],
+            &mut aux_output,
+            0,
+            0.5
+        ));
+        assert_eq!(processor.dry_gain, 1.0);
+
+        for _ in 0..3 {
+            let mut block = [1.0; 8];
+            let mut aux_output = [1.0; 8];
+            assert!(!process_block(
+                &mut processor,
+                &mut plugin,
+                &mut block,
+                &mut aux_output,
+                0,
+                0.5
+            ));
+            assert_eq!(block, [1.0; 8]);
+            assert_eq!(aux_output, [0.0; 8]);
+        }
+        assert_eq!(plugin.num_resets, 0);
+
+        // The plugin is reset once before it processes audio again
+        set_bypassed(&param, false);
+        for _ in 0..3 {
+            let mut aux_output = [1.0; 8];
+            assert!(process_block(
+                &mut processor,
+                &mut plugin,
+                &mut [1.0; 8],
+                &mut aux_output,
+                0,
+                0.5
+            ));
+            assert_eq!(aux_output, [0.5; 8]);
+        }
+        assert_eq!(plugin.num_resets, 1);
+    }
+
+    #[test]
+    fn dry_signal_is_latency_compensated() {
+        const LATENCY: u32 = 5;
+
+        let param = bypass_param(true);
+        let mut processor = bypass_processor(BypassPolicy::KeepProcessing, &param, LATENCY);
+        let mut plugin = TestPlugin::default();
+
+        let input: Vec<f32> = (0..64).map(|idx| idx as f32 + 1.0).collect();
+        let output = process_blocks(&mut processor, &mut plugin, &input, 7, LATENCY);
+
+        let mut delay_line = DelayLine::new(1, MAX_BLOCK_SIZE as usize, LATENCY as usize);
+        let mut expected = input;
+        for block in expected.chunks_mut(7) {
+            with_buffer(&mut [block], |buffer| {
+                delay_line.process(buffer, LATENCY as usize)
+            });
+        }
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn latency_exceeding_headroom_is_clamped() {
+        const MAX_DELAY: usize = SAMPLE_RATE as usize;
+
+        // The delay line has room for one second of latency, and the plugin then increases its
+        // latency without the host reinitializing it
+        let param = bypass_param(true);
+        let mut processor = bypass_processor(BypassPolicy::KeepProcessing, &param, 0);
+        let mut plugin = TestPlugin::default();
+        assert_eq!(processor.delay_line.max_delay(), MAX_DELAY);
+
+        let input: Vec<f32> = (0..1200).map(|idx| idx as f32 + 1.0).collect();
+        let output = process_blocks(
+            &mut processor,
+            &mut plugin,
+            &input,
+            MAX_BLOCK_SIZE as usize,
+            MAX_DELAY as u32 * 2,
+        );
+        assert!(processor.latency_overflow_reported);
+
+        let mut delay_line = DelayLine::new(1, MAX_BLOCK_SIZE as usize, MAX_DELAY);
+        let mut expected = input;
+        for block in expected.chunks_mut(MAX_BLOCK_SIZE as usize) {
+            with_buffer(&mut [block], |buffer| delay_line.process(buffer, MAX_DELAY));
+        }
+
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,455 @@
+//! An undo history for parameter gestures and state changes made from the plugin's editor. This is
+//! shared by all wrappers.
+
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::prelude::{GuiContext, MidiMapping, ParamPtr, PluginState};
+use crate::wrapper::state::ParamValue;
+
+/// The maximum number of changes that can be undone. Older changes are discarded.
+const MAX_UNDO_ENTRIES: usize = 128;
+/// The maximum amount of memory in bytes the undo history may use, as estimated by
+/// [`UndoEntry::size()`]. Older changes are discarded when this is exceeded, but the most recent
+/// change is always kept. Plugins that store samples or other large data in their state would
+/// otherwise keep up to [`MAX_UNDO_ENTRIES`] copies of that data around.
+const MAX_UNDO_BYTES: usize = 32 * 1024 * 1024;
+
+/// Records parameter gestures and state restores made through a [`GuiContext`] so they can be
+/// undone and redone later. The wrappers' `GuiContext` implementations call the `record_*`
+/// functions, and the changes are replayed through the same `GuiContext` so the host gets informed
+/// about them like any other parameter change.
+#[derive(Default)]
+pub struct UndoHistory {
+    inner: Mutex<UndoHistoryInner>,
+    /// Set while a change is being undone or redone. Any changes made through the `GuiContext` in
+    /// the meantime are part of that replay and should not be recorded.
+    replaying: AtomicBool,
+}
+
+#[derive(Default)]
+struct UndoHistoryInner {
+    /// Changes that can be undone, with the most recent change at the back.
+    undo_stack: VecDeque<UndoEntry>,
+    /// Changes that have been undone and that can be redone, with the most recently undone change
+    /// at the back. This is cleared when a new change gets recorded.
+    redo_stack: Vec<UndoEntry>,
+    /// The parameters with an active gesture, along with their normalized values at the start of
+    /// the gesture and the last normalized value set during the gesture. The parameters' actual
+    /// values may only be updated after the gesture has ended, so we can't read those.
+    active_gestures: HashMap<ParamPtr, (f32, f32)>,
+}
+
+/// A single change in the undo history.
+enum UndoEntry {
+    /// A parameter was changed during a gesture.
+    Parameter {
+        param: ParamPtr,
+        old_normalized: f32,
+        new_normalized: f32,
+    },
+    /// The plugin's state was restored from the editor. The states are boxed to keep parameter
+    /// entries small.
+    State {
+        old_state: Box<PluginState>,
+        new_state: Box<PluginState>,
+        /// The approximate size of both states in bytes, see [`state_size()`].
+        size: usize,
+    },
+}
+
+impl UndoHistory {
+    /// Called when a parameter gesture starts.
+    pub fn record_begin_set_parameter(&self, param: ParamPtr) {
+        if self.replaying.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let normalized = unsafe { param.unmodulated_normalized_value() };
+        self.inner
+            .lock()
+            .active_gestures
+            .insert(param, (normalized, normalized));
+    }
+
+    /// Called when a parameter's value is changed during a gesture.
+    pub fn record_set_parameter(&self, param: ParamPtr, normalized: f32) {
+        if self.replaying.load(Ordering::Relaxed) {
+            return;
+        }
+
+        if let Some((_, last_normalized)) = self.inner.lock().active_gestures.get_mut(&param) {
+            *last_normalized = normalized;
+        }
+    }
+
+    /// Called when a parameter gesture ends. This adds an entry to the undo history if the
+    /// parameter's value was changed during the gesture.
+    pub fn record_end_set_parameter(&self, param: ParamPtr) {
+        if self.replaying.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut inner = self.inner.lock();
+        if let Some((old_normalized, new_normalized)) = inner.active_gestures.remove(&param) {
+            if old_normalized != new_normalized {
+                inner.push(UndoEntry::Parameter {
+                    param,
+                    old_normalized,
+                    new_normalized,
+                });
+            }
+        }
+    }
+
+    /// Called after the editor restored the plugin's state from `new_state`. `old_state` is the
+    /// plugin's state from right before that.
+    pub fn record_set_state(&self, old_state: PluginState, new_state: PluginState) {
+        if self.replaying.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let size = state_size(&old_state) + state_size(&new_state);
+        self.inner.lock().push(UndoEntry::State {
+            old_state: Box::new(old_state),
+            new_state: Box::new(new_state),
+            size,
+        });
+    }
+
+    /// Undo the last change by replaying it in reverse through `context`. Does nothing if there is
+    /// nothing to undo.
+    pub fn undo(&self, context: &dyn GuiContext) {
+        let entry = {
+            let mut inner = self.inner.lock();
+            match inner.undo_stack.pop_back() {
+                Some(entry) => entry,
+                None => return,
+            }
+        };
+
+        self.replay(context, &entry, false);
+        self.inner.lock().redo_stack.push(entry);
+    }
+
+    /// Redo the last undone change by replaying it through `context`. Does nothing if there is
+    /// nothing to redo.
+    pub fn redo(&self, context: &dyn GuiContext) {
+        let entry = {
+            let mut inner = self.inner.lock();
+            match inner.redo_stack.pop() {
+                Some(entry) => entry,
+                None => return,
+            }
+        };
+
+        self.replay(context, &entry, true);
+        self.inner.lock().undo_stack.push_back(entry);
+    }
+
+    /// Apply either the old or the new side of an entry through the `GuiContext`. The lock must not
+    /// be held while doing this, since the context will call back into this object.
+    fn replay(&self, context: &dyn GuiContext, entry: &UndoEntry, redo: bool) {
+        self.replaying.store(true, Ordering::Relaxed);
+        match entry {
+            UndoEntry::Parameter {
+                param,
+                old_normalized,
+                new_normalized,
+            } => unsafe {
+                context.raw_begin_set_parameter(*param);
+                context.raw_set_parameter_normalized(
+                    *param,
+                    if redo {
+                        *new_normalized
+                    } else {
+                        *old_normalized
+                    },
+                );
+                context.raw_end_set_parameter(*param);
+            },
+            UndoEntry::State {
+                old_state,
+                new_state,
+                ..
+            } => context.set_state(if redo {
+                PluginState::clone(new_state)
+            } else {
+                PluginState::clone(old_state)
+            }),
+        }
+        self.replaying.store(false, Ordering::Relaxed);
+    }
+}
+
+impl UndoHistoryInner {
+    /// Add a new change to the undo stack. This invalidates the redo stack.
+    fn push(&mut self, entry: UndoEntry) {
+        self.redo_stack.clear();
+        self.undo_stack.push_back(entry);
+
+        let mut total_size: usize = self.undo_stack.iter().map(UndoEntry::size).sum();
+        while self.undo_stack.len() > MAX_UNDO_ENTRIES
+            || (total_size > MAX_UNDO_BYTES && self.undo_stack.len() > 1)
+        {
+            let entry = self
+                .undo_stack
+                .pop_front()
+                .expect("The undo stack cannot be empty here");
+            total_size -= entry.size();
+        }
+    }
+}
+
+impl UndoEntry {
+    /// The approximate amount of memory used by this entry, in bytes.
+    fn size(&self) -> usize {
+        match self {
+            UndoEntry::Parameter { .. } => std::mem::size_of::<UndoEntry>(),
+            UndoEntry::State { size, .. } => std::mem::size_of::<UndoEntry>() + size,
+        }
+    }
+}
+
+/// Estimate the amount of heap memory used by a plugin state, in bytes. This only needs to be
+/// accurate enough to keep the undo history from growing without bounds.
+fn state_size(state: &PluginState) -> usize {
+    let params: usize = state
+        .params
+        .iter()
+        .map(|(id, value)| match value {
+            ParamValue::String(value) => id.len() + value.len(),
+            _ => id.len(),
+        })
+        .sum();
+    let fields: usize = state
+        .fields
+        .iter()
+        .map(|(key, value)| key.len() + value.len())
+        .sum();
+    let binary_fields: usize = state
+        .binary_fields
+        .iter()
+        .map(|(key, value)| key.len() + value.len())
+        .sum();
+    let midi_mappings: usize = state
+        .midi_mappings
+        .keys()
+        .map(|id| id.len() + std::mem::size_of::<MidiMapping>())
+        .sum();
+    let state_slots: usize = state
+        .state_slots
+        .iter()
+        .flat_map(|state_slots| state_slots.slots.iter().flatten())
+        .map(state_size)
+        .sum();
+
+    state.version.len() + params + fields + binary_fields + midi_mappings + state_slots
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::prelude::{FloatParam, FloatRange, MidiLearn, Param, PluginApi, StateSlots};
+
+    /// Forwards parameter changes and state restores to the undo history the same way the
+    /// wrappers' `GuiContext` implementations do.
+    struct MockGuiContext {
+        history: UndoHistory,
+        state: Mutex<PluginState>,
+    }
+
+    impl GuiContext for MockGuiContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Standalone
+        }
+
+        fn request_resize(&self) -> bool {
+            false
+        }
+
+        unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+            self.history.record_begin_set_parameter(param);
+        }
+
+        unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+            self.history.record_set_parameter(param, normalized);
+            param.set_normalized_value(normalized);
+        }
+
+        unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+            self.history.record_end_set_parameter(param);
+        }
+
+        fn get_state(&self) -> PluginState {
+            self.state.lock().clone()
+        }
+
+        fn set_state(&self, state: PluginState) {
+            let old_state = std::mem::replace(&mut *self.state.lock(), state.clone());
+            self.history.record_set_state(old_state, state);
+        }
+
+        fn undo(&self) {
+            self.history.undo(self);
+        }
+
+        fn redo(&self) {
+            self.history.redo(self);
+        }
+
+        fn midi_learn(&self) -> &MidiLearn {
+            unimplemented!()
+        }
+
+        fn state_slots(&self) -> &StateSlots {
+            unimplemented!()
+        }
+
+        fn switch_state_slot(&self, _slot: usize) {}
+
+        fn copy_state_slot(&self, _from: usize, _to: usize) {}
+
+        fn param_info_changed(&self) {}
+    }
+
+    fn make_context() -> MockGuiContext {
+        MockGuiContext {
+            history: UndoHistory::default(),
+            state: Mutex::new(state_with_binary_field(0)),
+        }
+    }
+
+    fn make_param() -> FloatParam {
+        FloatParam::new("Gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+    }
+
+    fn state_with_binary_field(size: usize) -> PluginState {
+        PluginState {
+            version: String::new(),
+            params: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            binary_fields: BTreeMap::from([(String::from("samples"), vec![0; size])]),
+            midi_mappings: BTreeMap::new(),
+            state_slots: None,
+        }
+    }
+
+    /// Perform a single gesture that moves the parameter through `values`.
+    fn gesture(context: &MockGuiContext, param: &FloatParam, values: &[f32]) {
+        unsafe {
+            context.raw_begin_set_parameter(param.as_ptr());
+            for value in values {
+                context.raw_set_parameter_normalized(param.as_ptr(), *value);
+            }
+            context.raw_end_set_parameter(param.as_ptr());
+        }
+    }
+
+    fn stack_sizes(context: &MockGuiContext) -> (usize, usize) {
+        let inner = context.history.inner.lock();
+        (inner.undo_stack.len(), inner.redo_stack.len())
+    }
+
+    #[test]
+    fn merge_gestures() {
+        let context = make_context();
+        let param = make_param();
+
+        gesture(&context, &param, &[0.2, 0.4, 0.6]);
+        assert_eq!(stack_sizes(&context), (1, 0));
+
+        // A gesture that ends on the value it started at doesn't change anything
+        gesture(&context, &param, &[0.8, 0.6]);
+        assert_eq!(stack_sizes(&context), (1, 0));
+
+        context.undo();
+        assert_eq!(param.value(), 0.0);
+        context.redo();
+        assert_eq!(param.value(), 0.6);
+    }
+
+    #[test]
+    fn replay_without_recording() {
+        let context = make_context();
+        let param = make_param();
+
+        gesture(&context, &param, &[0.5]);
+        context.set_state(state_with_binary_field(16));
+        assert_eq!(stack_sizes(&context), (2, 0));
+
+        // Undoing and redoing goes through the same `GuiContext` functions, but that should not
+        // result in new changes being recorded
+        context.undo();
+        assert_eq!(stack_sizes(&context), (1, 1));
+        assert_eq!(context.get_state().binary_fields["samples"].len(), 0);
+        context.undo();
+        assert_eq!(stack_sizes(&context), (0, 2));
+        assert_eq!(param.value(), 0.0);
+
+        context.redo();
+        context.redo();
+        assert_eq!(stack_sizes(&context), (2, 0));
+        assert_eq!(param.value(), 0.5);
+        assert_eq!(context.get_state().binary_fields["samples"].len(), 16);
+        assert!(!context.history.replaying.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn trim_old_entries() {
+        let context = make_context();
+        let param = make_param();
+
+        for step in 1..=MAX_UNDO_ENTRIES + 10 {
+            gesture(&context, &param, &[step as f32 / 1000.0]);
+        }
+        assert_eq!(stack_sizes(&context), (MAX_UNDO_ENTRIES, 0));
+
+        // The oldest ten changes have been discarded
+        for _ in 0..MAX_UNDO_ENTRIES + 10 {
+            context.undo();
+        }
+        approx::assert_relative_eq!(param.value(), 10.0 / 1000.0);
+    }
+
+    #[test]
+    fn trim_large_states() {
+        const STATE_SIZE: usize = MAX_UNDO_BYTES / 8;
+
+        let context = make_context();
+        for _ in 0..10 {
+            context.set_state(state_with_binary_field(STATE_SIZE));
+        }
+
+        // Every entry stores two states, so these entries are slightly larger than a quarter of the
+        // limit
+        assert_eq!(stack_sizes(&context), (3, 0));
+
+        // The most recent change is kept even if it's larger than the limit
+        context.set_state(state_with_binary_field(MAX_UNDO_BYTES));
+        assert_eq!(stack_sizes(&context), (1, 0));
+    }
+
+    #[test]
+    fn new_change_clears_redo() {
+        let context = make_context();
+        let param = make_param();
+
+        gesture(&context, &param, &[0.25]);
+        gesture(&context, &param, &[0.5]);
+        context.undo();
+        assert_eq!(stack_sizes(&context), (1, 1));
+
+        gesture(&context, &param, &[0.75]);
+        assert_eq!(stack_sizes(&context), (2, 0));
+
+        // There's nothing to redo anymore
+        context.redo();
+        assert_eq!(param.value(), 0.75);
+        context.undo();
+        assert_eq!(param.value(), 0.25);
+    }
+}
@@ -5,9 +5,10 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use vst3_sys::vst::IComponentHandler;
 
+use crate::midi::learn::MidiLearn;
 use crate::prelude::{
     GuiContext, InitContext, ParamPtr, PluginApi, PluginNoteEvent, PluginState, ProcessContext,
-    Transport, Vst3Plugin,
+    StateSlots, Transport, Vst3Plugin,
 };
 
 use super::inner::{Task, WrapperInner};
@@ -54,6 +55,14 @@ pub(crate) struct WrapperGuiContext<P: Vst3Plugin> {
         atomic_refcell::AtomicRefCell<crate::wrapper::util::context_checks::ParamGestureChecker>,
 }
 
+impl<P: Vst3Plugin> WrapperInitContext<'_, P> {
+    /// The latency set by the plugin during this context's lifetime, if it changed. This is only
+    /// sent to the host after the context is dropped.
+    pub fn pending_latency(&self) -> Option<u32> {
+        self.pending_requests.latency_changed.get()
+    }
+}
+
 impl<P: Vst3Plugin> Drop for WrapperInitContext<'_, P> {
     fn drop(&mut self) {
         if let Some(samples) = self.pending_requests.latency_changed.take() {
@@ -113,9 +122,17 @@ impl<P: Vst3Plugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         self.inner.set_latency_samples(samples)
     }
 
+    fn latency_samples(&self) -> u32 {
+        self.inner.current_latency.load(Ordering::SeqCst)
+    }
+
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn param_info_changed(&self) {
+        self.inner.param_info_changed()
+    }
 }
 
 impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
@@ -138,6 +155,7 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
                 Some(hash) => {
+                    self.inner.undo_history.record_begin_set_parameter(param);
                     handler.begin_edit(*hash);
                 }
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
@@ -161,6 +179,10 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
                 Some(hash) => {
+                    self.inner
+                        .undo_history
+                        .record_set_parameter(param, normalized);
+
                     // Only update the parameters manually if the host is not processing audio. If
                     // the plugin is currently processing audio, the host will pass this change back
                     // to the plugin in the audio callback. This also prevents the values from
@@ -202,6 +224,7 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
                 Some(hash) => {
+                    self.inner.undo_history.record_end_set_parameter(param);
                     handler.end_edit(*hash);
                 }
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
@@ -226,6 +249,36 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
     }
 
     fn set_state(&self, state: PluginState) {
-        self.inner.set_state_object_from_gui(state)
+        let old_state = self.inner.get_state_object();
+        self.inner.set_state_object_from_gui(state.clone());
+        self.inner.undo_history.record_set_state(old_state, state);
+    }
+
+    fn undo(&self) {
+        self.inner.undo_history.undo(self);
+    }
+
+    fn redo(&self) {
+        self.inner.undo_history.redo(self);
+    }
+
+    fn midi_learn(&self) -> &MidiLearn {
+        &self.inner.midi_learn
+    }
+
+    fn state_slots(&self) -> &StateSlots {
+        &self.inner.state_slots
+    }
+
+    fn switch_state_slot(&self, slot: usize) {
+        self.inner.switch_state_slot(slot)
+    }
+
+    fn copy_state_slot(&self, from: usize, to: usize) {
+        self.inner.copy_state_slot(from, to)
+    }
+
+    fn param_info_changed(&self) {
+        self.inner.param_info_changed()
     }
 }
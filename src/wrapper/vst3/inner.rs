@@ -15,13 +15,16 @@ use super::param_units::ParamUnits;
 use super::util::{ObjectPtr, VstPtr, VST3_MIDI_PARAMS_END, VST3_MIDI_PARAMS_START};
 use super::view::WrapperView;
 use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
+use crate::midi::learn::MidiLearn;
 use crate::prelude::{
     AsyncExecutor, AudioIOLayout, BufferConfig, Editor, MidiConfig, ParamFlags, ParamPtr, Params,
     Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor, Transport, Vst3Plugin,
 };
 use crate::util::permit_alloc;
-use crate::wrapper::state::{self, PluginState};
+use crate::wrapper::state::{self, PluginState, StateSlots};
 use crate::wrapper::util::buffer_management::BufferManager;
+use crate::wrapper::util::bypass::{self, BypassProcessor};
+use crate::wrapper::util::undo::UndoHistory;
 use crate::wrapper::util::{hash_param_id, process_wrapper};
 
 /// The actual wrapper bits. We need this as an `Arc<T>` so we can safely use our event loop API.
@@ -81,6 +84,15 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// A data structure that helps manage and create buffers for all of the plugin's inputs and
     /// outputs based on channel pointers provided by the host.
     pub buffer_manager: AtomicRefCell<BufferManager>,
+    /// The same as `buffer_manager`, but for hosts that process audio at 64-bit precision. This is
+    /// only initialized when [`Plugin::F64_PROCESSING`] is enabled.
+    pub buffer_manager_f64: AtomicRefCell<BufferManager<f64>>,
+    /// Crossfades to a latency compensated dry signal when the plugin is bypassed, if the plugin
+    /// opted into that through [`Plugin::BYPASS_POLICY`]. Otherwise this simply calls the plugin's
+    /// process function.
+    pub bypass: AtomicRefCell<BypassProcessor>,
+    /// The same as `bypass`, but for hosts that process audio at 64-bit precision.
+    pub bypass_f64: AtomicRefCell<BypassProcessor<f64>>,
     /// The incoming events for the plugin, if `P::ACCEPTS_MIDI` is set. If
     /// `P::SAMPLE_ACCURATE_AUTOMATION`, this is also read in lockstep with the parameter change
     /// block splitting.
@@ -137,6 +149,14 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// having to add a setter function to the parameter (or even worse, have it be completely
     /// untyped).
     pub param_ptr_to_hash: HashMap<ParamPtr, u32>,
+    /// MIDI CC mappings created through MIDI learn. These are applied to the incoming MIDI CCs
+    /// before the plugin's process function gets called.
+    pub midi_learn: MidiLearn,
+    /// Parameter gestures and state restores made from the editor.
+    pub undo_history: UndoHistory,
+    /// Switchable state snapshots for A/B comparisons. Switching to another slot from the editor
+    /// loads that slot's snapshot at the start of the next process call.
+    pub state_slots: StateSlots,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -157,6 +177,13 @@ pub enum Task<P: Plugin> {
     /// Request the editor to be resized according to its current size. Right now there is no way to
     /// handle "denied resize" requests yet.
     RequestResize,
+    /// Inform the host that a parameter has been changed by a MIDI CC mapping. VST3 plugins can't
+    /// send parameter changes from the audio thread, so this is done through the component handler
+    /// instead.
+    MidiMappedParameterChanged(u32, f32),
+    /// Inform the host and the editor that one or more parameters' names, ranges, or enum variants
+    /// have changed.
+    ParameterInfoChanged,
 }
 
 /// VST3 makes audio processing pretty complicated. In order to support both block splitting for
@@ -269,6 +296,16 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             .iter()
             .map(|(id, hash, _, _)| (id.clone(), *hash))
             .collect();
+        let bypass_param = bypass::find_bypass_param(
+            param_id_hashes_ptrs_groups
+                .iter()
+                .map(|(_, _, ptr, _)| *ptr),
+        );
+        let midi_learn = MidiLearn::new(
+            param_id_hashes_ptrs_groups
+                .iter()
+                .map(|(id, _, ptr, _)| (id, *ptr)),
+        );
         let param_ptr_to_hash = param_id_hashes_ptrs_groups
             .into_iter()
             .map(|(_, hash, ptr, _)| (ptr, hash))
@@ -305,6 +342,12 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                 0,
                 AudioIOLayout::default(),
             )),
+            buffer_manager_f64: AtomicRefCell::new(BufferManager::for_audio_io_layout(
+                0,
+                AudioIOLayout::default(),
+            )),
+            bypass: AtomicRefCell::new(BypassProcessor::new(P::BYPASS_POLICY, bypass_param)),
+            bypass_f64: AtomicRefCell::new(BypassProcessor::new(P::BYPASS_POLICY, bypass_param)),
             input_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
             output_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
             note_expression_controller: AtomicRefCell::new(NoteExpressionController::default()),
@@ -318,6 +361,9 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             param_units,
             param_id_to_hash,
             param_ptr_to_hash,
+            midi_learn,
+            undo_history: UndoHistory::default(),
+            state_slots: StateSlots::new(P::STATE_SLOTS),
         });
 
         // FIXME: Right now this is safe, but if we are going to have a singleton main thread queue
@@ -461,6 +507,29 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         }
     }
 
+    /// Update the parameters that have been mapped to MIDI CCs through MIDI learn using the MIDI CCs
+    /// in the input events queue. The host is informed about these changes from the main thread.
+    pub fn apply_midi_mappings(&self, sample_rate: f32) {
+        let input_events = self.input_events.borrow();
+        for event in input_events.iter() {
+            self.midi_learn
+                .handle_event(event, |param_ptr, normalized_value| {
+                    let param_hash = self.param_ptr_to_hash[&param_ptr];
+                    self.set_normalized_value_by_hash(
+                        param_hash,
+                        normalized_value,
+                        Some(sample_rate),
+                    );
+
+                    let task_posted = self.schedule_gui(Task::MidiMappedParameterChanged(
+                        param_hash,
+                        normalized_value,
+                    ));
+                    nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+                });
+        }
+    }
+
     /// Get the plugin's state object, may be called by the plugin's GUI as part of its own preset
     /// management. The wrapper doesn't use these functions and serializes and deserializes directly
     /// the JSON in the relevant plugin API methods instead.
@@ -469,10 +538,51 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             state::serialize_object::<P>(
                 self.params.clone(),
                 state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+                &self.midi_learn,
+                &self.state_slots,
             )
         }
     }
 
+    /// Get a snapshot of the plugin's parameter values and persistent fields for the state slots.
+    fn get_state_slot_snapshot(&self) -> PluginState {
+        unsafe {
+            state::serialize_snapshot_object::<P>(
+                self.params.clone(),
+                state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+            )
+        }
+    }
+
+    /// Store the current state in the active state slot and switch to another slot, called by the
+    /// plugin's editor from the GUI thread. See
+    /// [`GuiContext::switch_state_slot()`][crate::prelude::GuiContext::switch_state_slot()].
+    pub fn switch_state_slot(&self, slot: usize) {
+        let live_state = self.get_state_slot_snapshot();
+        if let Some(snapshot) = self.state_slots.switch_to(live_state, slot) {
+            self.load_state_slot_from_gui(snapshot);
+        }
+    }
+
+    /// Copy one state slot to another, called by the plugin's editor from the GUI thread. See
+    /// [`GuiContext::copy_state_slot()`][crate::prelude::GuiContext::copy_state_slot()].
+    pub fn copy_state_slot(&self, from: usize, to: usize) {
+        let live_state = self.get_state_slot_snapshot();
+        if let Some(snapshot) = self.state_slots.copy(live_state, from, to) {
+            self.load_state_slot_from_gui(snapshot);
+        }
+    }
+
+    /// Load a state slot's snapshot. If the plugin is processing audio, then this happens at the
+    /// start of the next process call. Otherwise the snapshot is loaded right away.
+    fn load_state_slot_from_gui(&self, snapshot: PluginState) {
+        self.state_slots.schedule_load(snapshot);
+        if !self.is_processing.load(Ordering::SeqCst) {
+            self.state_slots
+                .handle_pending_load(|snapshot| self.set_state_slot_inner(snapshot));
+        }
+    }
+
     /// Update the plugin's internal state, called by the plugin itself from the GUI thread. To
     /// prevent corrupting data and changing parameters during processing the actual state is only
     /// updated at the end of the audio processing cycle.
@@ -526,6 +636,11 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
     }
 
+    pub fn param_info_changed(&self) {
+        let task_posted = self.schedule_gui(Task::ParameterInfoChanged);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
     pub fn set_latency_samples(&self, samples: u32) {
         // Only trigger a restart if it's actually needed
         let old_latency = self.current_latency.swap(samples, Ordering::SeqCst);
@@ -561,6 +676,8 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                 state,
                 self.params.clone(),
                 state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash),
+                &self.midi_learn,
+                &self.state_slots,
                 buffer_config.as_ref(),
             )
         });
@@ -603,6 +720,30 @@ impl<P: Vst3Plugin> WrapperInner<P> {
 
         success
     }
+
+    /// Load a snapshot from the state slots. Unlike [`set_state_inner()`][Self::set_state_inner()]
+    /// this doesn't reset the smoothers or reinitialize the plugin, so it can be called at the start
+    /// of the process call.
+    ///
+    /// Implicitly emits `Task::ParameterValuesChanged` and a `kParamValuesChanged` restart.
+    pub fn set_state_slot_inner(&self, snapshot: &mut PluginState) {
+        // `Params::deserialize_fields()` may allocate, see `set_state_inner()`
+        permit_alloc(|| unsafe {
+            state::deserialize_snapshot_object::<P>(
+                snapshot,
+                self.params.clone(),
+                state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash),
+                self.current_buffer_config.load().as_ref(),
+            )
+        });
+
+        let task_posted = self.schedule_gui(Task::ParameterValuesChanged);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+        let task_posted = self.schedule_gui(Task::TriggerRestart(
+            RestartFlags::kParamValuesChanged as i32,
+        ));
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
 }
 
 impl<P: Vst3Plugin> MainThreadExecutor<Task<P>> for WrapperInner<P> {
@@ -648,6 +789,41 @@ impl<P: Vst3Plugin> MainThreadExecutor<Task<P>> for WrapperInner<P> {
                 },
                 None => nih_debug_assert_failure!("Can't resize a closed editor"),
             },
+            Task::MidiMappedParameterChanged(param_hash, normalized_value) => {
+                match &*self.component_handler.borrow() {
+                    Some(handler) => unsafe {
+                        nih_debug_assert!(is_gui_thread);
+                        handler.begin_edit(param_hash);
+                        handler.perform_edit(param_hash, normalized_value as f64);
+                        handler.end_edit(param_hash);
+                    },
+                    None => nih_debug_assert_failure!("Component handler not yet set"),
+                }
+            }
+            Task::ParameterInfoChanged => {
+                match &*self.component_handler.borrow() {
+                    Some(handler) => unsafe {
+                        nih_debug_assert!(is_gui_thread);
+                        // Changing a parameter's range also changes its normalized value
+                        let flags = RestartFlags::kParamTitlesChanged as i32
+                            | RestartFlags::kParamValuesChanged as i32;
+                        let result = handler.restart_component(flags);
+                        nih_debug_assert_eq!(
+                            result,
+                            kResultOk,
+                            "Failed the restart request call with flags '{:?}'",
+                            flags
+                        );
+                    },
+                    None => nih_debug_assert_failure!("Component handler not yet set"),
+                }
+
+                if self.plug_view.read().is_some() {
+                    if let Some(editor) = self.editor.borrow().as_ref() {
+                        editor.lock().param_info_changed();
+                    }
+                }
+            }
         }
     }
 }
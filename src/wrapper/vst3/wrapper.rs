@@ -1,3 +1,4 @@
+use atomic_refcell::AtomicRefCell;
 use std::borrow::Borrow;
 use std::ffi::c_void;
 use std::mem::{self, MaybeUninit};
@@ -27,12 +28,13 @@ use super::util::{
 use super::util::{VST3_MIDI_CHANNELS, VST3_MIDI_PARAMS_END};
 use super::view::WrapperView;
 use crate::prelude::{
-    AuxiliaryBuffers, BufferConfig, MidiConfig, NoteEvent, ParamFlags, ProcessMode, ProcessStatus,
-    SysExMessage, Transport, Vst3Plugin,
+    AuxiliaryBuffers, Buffer, BufferConfig, MidiConfig, NoteEvent, ParamFlags, ProcessMode,
+    ProcessStatus, Sample, SysExMessage, Transport, Vst3Plugin,
 };
 use crate::util::permit_alloc;
 use crate::wrapper::state;
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
+use crate::wrapper::util::bypass::BypassProcessor;
 use crate::wrapper::util::{clamp_input_event_timing, clamp_output_event_timing, process_wrapper};
 
 // Alias needed for the VST3 attribute macro
@@ -55,6 +57,148 @@ impl<P: Vst3Plugin> Wrapper<P> {
     pub fn new() -> Box<Self> {
         Self::allocate(WrapperInner::new())
     }
+
+    /// Create the plugin's buffers for the block starting at `block_start` from the host's
+    /// process `data`, and then run `process_fn` on them. The plugin is not called if the host did
+    /// not provide valid output buffers. `T` must match the sample size the host is using for this
+    /// process call.
+    ///
+    /// # Safety
+    ///
+    /// `data` must be the host's process data for the current process call.
+    unsafe fn process_block<T: Sample>(
+        &self,
+        data: &vst3_sys::vst::ProcessData,
+        buffer_manager: &AtomicRefCell<BufferManager<T>>,
+        bypass: &AtomicRefCell<BypassProcessor<T>>,
+        block_start: usize,
+        block_len: usize,
+        process_fn: impl FnOnce(&mut P, &mut Buffer<T>, &mut AuxiliaryBuffers<T>) -> ProcessStatus,
+    ) -> ProcessStatus {
+        let current_audio_io_layout = self.inner.current_audio_io_layout.load();
+        let has_main_input = current_audio_io_layout.main_input_channels.is_some();
+        let has_main_output = current_audio_io_layout.main_output_channels.is_some();
+        let aux_input_start_idx = if has_main_input { 1 } else { 0 };
+        let aux_output_start_idx = if has_main_output { 1 } else { 0 };
+
+        // The buffer manager preallocated buffer slices for all the IO and storage for
+        // any axuiliary inputs.
+        let mut buffer_manager = buffer_manager.borrow_mut();
+        let buffers = buffer_manager.create_buffers(block_start, block_len, |buffer_source| {
+            if data.num_outputs > 0
+                && !data.outputs.is_null()
+                && !(*data.outputs).buffers.is_null()
+                && has_main_output
+            {
+                let audio_output = &*data.outputs;
+                let ptrs = NonNull::new(audio_output.buffers as *mut *mut T).unwrap();
+                let num_channels = audio_output.num_channels as usize;
+
+                *buffer_source.main_output_channel_pointers =
+                    Some(ChannelPointers { ptrs, num_channels });
+            }
+
+            if data.num_inputs > 0
+                && !data.inputs.is_null()
+                && !(*data.inputs).buffers.is_null()
+                && has_main_input
+            {
+                let audio_input = &*data.inputs;
+                let ptrs = NonNull::new(audio_input.buffers as *mut *mut T).unwrap();
+                let num_channels = audio_input.num_channels as usize;
+
+                *buffer_source.main_input_channel_pointers =
+                    Some(ChannelPointers { ptrs, num_channels });
+            }
+
+            if !data.inputs.is_null() {
+                for (aux_input_no, aux_input_channel_pointers) in buffer_source
+                    .aux_input_channel_pointers
+                    .iter_mut()
+                    .enumerate()
+                {
+                    let aux_input_idx = aux_input_no + aux_input_start_idx;
+                    if aux_input_idx > data.num_outputs as usize {
+                        break;
+                    }
+
+                    let audio_input = &*data.inputs.add(aux_input_idx);
+                    match NonNull::new(audio_input.buffers as *mut *mut T) {
+                        Some(ptrs) => {
+                            let num_channels = audio_input.num_channels as usize;
+
+                            *aux_input_channel_pointers =
+                                Some(ChannelPointers { ptrs, num_channels });
+                        }
+                        None => continue,
+                    }
+                }
+            }
+
+            if !data.outputs.is_null() {
+                for (aux_output_no, aux_output_channel_pointers) in buffer_source
+                    .aux_output_channel_pointers
+                    .iter_mut()
+                    .enumerate()
+                {
+                    let aux_output_idx = aux_output_no + aux_output_start_idx;
+                    if aux_output_idx > data.num_outputs as usize {
+                        break;
+                    }
+
+                    let audio_output = &*data.outputs.add(aux_output_idx);
+                    match NonNull::new(audio_output.buffers as *mut *mut T) {
+                        Some(ptrs) => {
+                            let num_channels = audio_output.num_channels as usize;
+
+                            *aux_output_channel_pointers =
+                                Some(ChannelPointers { ptrs, num_channels });
+                        }
+                        None => continue,
+                    }
+                }
+            }
+        });
+
+        // We already checked whether the host has initiated a parameter flush, but in
+        // case it still did something unexpected that we did not catch we'll still try
+        // to prevent processing audio when the slices don't contain the values we
+        // expect.
+        let mut buffer_is_valid = true;
+        for output_buffer_slice in buffers.main_buffer.as_slice_immutable().iter().chain(
+            buffers
+                .aux_outputs
+                .iter()
+                .flat_map(|buffer| buffer.as_slice_immutable().iter()),
+        ) {
+            if output_buffer_slice.is_empty() {
+                buffer_is_valid = false;
+                break;
+            }
+        }
+        nih_debug_assert!(buffer_is_valid);
+
+        if buffer_is_valid {
+            // NOTE: `parking_lot`'s mutexes sometimes allocate because of their use of thread
+            //       locals
+            let mut plugin = permit_alloc(|| self.inner.plugin.lock());
+            let mut aux = AuxiliaryBuffers {
+                inputs: buffers.aux_inputs,
+                outputs: buffers.aux_outputs,
+            };
+            let result = bypass.borrow_mut().process(
+                &mut *plugin,
+                buffers.main_buffer,
+                &mut aux,
+                &self.inner.current_latency,
+                process_fn,
+            );
+            self.inner.last_process_status.store(result);
+            result
+        } else {
+            ProcessStatus::Normal
+        }
+    }
 }
 
 impl<P: Vst3Plugin> Drop for Wrapper<P> {
@@ -394,6 +538,31 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                         buffer_config.max_buffer_size as usize,
                         audio_io_layout,
                     );
+                    if P::F64_PROCESSING {
+                        *self.inner.buffer_manager_f64.borrow_mut() =
+                            BufferManager::for_audio_io_layout(
+                                buffer_config.max_buffer_size as usize,
+                                audio_io_layout,
+                            );
+                    }
+
+                    // The delay line for the bypass' dry signal depends on the plugin's latency.
+                    // Latency changes made during `initialize()` are still pending at this point.
+                    let latency = init_context
+                        .pending_latency()
+                        .unwrap_or_else(|| self.inner.current_latency.load(Ordering::SeqCst));
+                    self.inner.bypass.borrow_mut().initialize(
+                        &audio_io_layout,
+                        &buffer_config,
+                        latency,
+                    );
+                    if P::F64_PROCESSING {
+                        self.inner.bypass_f64.borrow_mut().initialize(
+                            &audio_io_layout,
+                            &buffer_config,
+                            latency,
+                        );
+                    }
 
                     kResultOk
                 } else {
@@ -449,7 +618,7 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
             return kResultFalse;
         }
 
-        match state::deserialize_json(&read_buffer) {
+        match state::deserialize_state(&read_buffer) {
             Some(mut state) => {
                 if self.inner.set_state_inner(&mut state) {
                     nih_trace!("Loaded state ({} bytes)", read_buffer.len());
@@ -467,9 +636,11 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
 
         let state = state.upgrade().unwrap();
 
-        let serialized = state::serialize_json::<P>(
+        let serialized = state::serialize_state::<P>(
             self.inner.params.clone(),
             state::make_params_iter(&self.inner.param_by_hash, &self.inner.param_id_to_hash),
+            &self.inner.midi_learn,
+            &self.inner.state_slots,
         );
         match serialized {
             Ok(serialized) => {
@@ -571,8 +742,9 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
             let is_bypass = flags.contains(ParamFlags::BYPASS);
 
             info.id = *param_hash;
-            u16strlcpy(&mut info.title, param_ptr.name());
-            u16strlcpy(&mut info.short_title, param_ptr.name());
+            let name = param_ptr.name();
+            u16strlcpy(&mut info.title, &name);
+            u16strlcpy(&mut info.short_title, &name);
             u16strlcpy(&mut info.units, param_ptr.unit());
             info.step_count = param_ptr.step_count().unwrap_or(0) as i32;
             info.default_normalized_value = default_value as f64;
@@ -849,7 +1021,10 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
     }
 
     unsafe fn can_process_sample_size(&self, symbolic_sample_size: i32) -> tresult {
-        if symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32 {
+        if symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
+            || (P::F64_PROCESSING
+                && symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample64 as i32)
+        {
             kResultOk
         } else {
             kResultFalse
@@ -865,9 +1040,11 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
 
         // There's no special handling for offline processing at the moment
         let setup = &*setup;
-        nih_debug_assert_eq!(
-            setup.symbolic_sample_size,
-            vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
+        nih_debug_assert!(
+            setup.symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
+                || (P::F64_PROCESSING
+                    && setup.symbolic_sample_size
+                        == vst3_sys::vst::SymbolicSampleSizes::kSample64 as i32)
         );
 
         // This is needed when activating the plugin and when restoring state
@@ -921,7 +1098,17 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 }
             };
 
-            process_wrapper(|| plugin.reset());
+            process_wrapper(|| {
+                plugin.reset();
+                self.inner.bypass.borrow_mut().reset();
+                self.inner.bypass_f64.borrow_mut().reset();
+            });
+        } else {
+            // A state slot switch may still be waiting for the next process call, so we'll need to
+            // load it now instead
+            self.inner
+                .state_slots
+                .handle_pending_load(|snapshot| self.inner.set_state_slot_inner(snapshot));
         }
 
         // We don't have any special handling for suspending and resuming plugins, yet
@@ -936,6 +1123,12 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
         // Panic on allocations if the `assert_process_allocs` feature has been enabled, and make
         // sure that FTZ is set up correctly
         process_wrapper(|| {
+            // If the editor switched to another state slot, then that slot's values are loaded
+            // before processing the block so they apply to the entire block
+            self.inner
+                .state_slots
+                .handle_pending_load(|snapshot| self.inner.set_state_slot_inner(snapshot));
+
             // We need to handle incoming automation first
             let data = &*data;
             let sample_rate = self
@@ -946,19 +1139,19 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 .sample_rate;
 
             nih_debug_assert!(data.num_inputs >= 0 && data.num_outputs >= 0);
-            nih_debug_assert_eq!(
-                data.symbolic_sample_size,
-                vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
+            let process_f64 =
+                data.symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample64 as i32;
+            nih_debug_assert!(
+                !process_f64 || P::F64_PROCESSING,
+                "The host is processing audio at 64-bit precision even though the plugin does not \
+                 support it"
             );
             nih_debug_assert!(data.num_samples >= 0);
 
             let total_buffer_len = data.num_samples as usize;
 
             let current_audio_io_layout = self.inner.current_audio_io_layout.load();
-            let has_main_input = current_audio_io_layout.main_input_channels.is_some();
             let has_main_output = current_audio_io_layout.main_output_channels.is_some();
-            let aux_input_start_idx = if has_main_input { 1 } else { 0 };
-            let aux_output_start_idx = if has_main_output { 1 } else { 0 };
 
             // NOTE: VST3 hosts may trigger a 'parameter flush' by calling the process function for
             //       0 input samples. If this is the case then we'll only handle events and skip all
@@ -1219,6 +1412,9 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     }
                 }
 
+                // Parameters mapped to MIDI CCs are updated before the plugin receives those CCs
+                self.inner.apply_midi_mappings(sample_rate);
+
                 let result = if is_param_flush {
                     kResultOk
                 } else {
@@ -1226,108 +1422,6 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     // and we can start preparing audio processing
                     let block_len = block_end - block_start;
 
-                    // The buffer manager preallocated buffer slices for all the IO and storage for
-                    // any axuiliary inputs.
-                    let mut buffer_manager = self.inner.buffer_manager.borrow_mut();
-                    let buffers =
-                        buffer_manager.create_buffers(block_start, block_len, |buffer_source| {
-                            if data.num_outputs > 0
-                                && !data.outputs.is_null()
-                                && !(*data.outputs).buffers.is_null()
-                                && has_main_output
-                            {
-                                let audio_output = &*data.outputs;
-                                let ptrs =
-                                    NonNull::new(audio_output.buffers as *mut *mut f32).unwrap();
-                                let num_channels = audio_output.num_channels as usize;
-
-                                *buffer_source.main_output_channel_pointers =
-                                    Some(ChannelPointers { ptrs, num_channels });
-                            }
-
-                            if data.num_inputs > 0
-                                && !data.inputs.is_null()
-                                && !(*data.inputs).buffers.is_null()
-                                && has_main_input
-                            {
-                                let audio_input = &*data.inputs;
-                                let ptrs =
-                                    NonNull::new(audio_input.buffers as *mut *mut f32).unwrap();
-                                let num_channels = audio_input.num_channels as usize;
-
-                                *buffer_source.main_input_channel_pointers =
-                                    Some(ChannelPointers { ptrs, num_channels });
-                            }
-
-                            if !data.inputs.is_null() {
-                                for (aux_input_no, aux_input_channel_pointers) in buffer_source
-                                    .aux_input_channel_pointers
-                                    .iter_mut()
-                                    .enumerate()
-                                {
-                                    let aux_input_idx = aux_input_no + aux_input_start_idx;
-                                    if aux_input_idx > data.num_outputs as usize {
-                                        break;
-                                    }
-
-                                    let audio_input = &*data.inputs.add(aux_input_idx);
-                                    match NonNull::new(audio_input.buffers as *mut *mut f32) {
-                                        Some(ptrs) => {
-                                            let num_channels = audio_input.num_channels as usize;
-
-                                            *aux_input_channel_pointers =
-                                                Some(ChannelPointers { ptrs, num_channels });
-                                        }
-                                        None => continue,
-                                    }
-                                }
-                            }
-
-                            if !data.outputs.is_null() {
-                                for (aux_output_no, aux_output_channel_pointers) in buffer_source
-                                    .aux_output_channel_pointers
-                                    .iter_mut()
-                                    .enumerate()
-                                {
-                                    let aux_output_idx = aux_output_no + aux_output_start_idx;
-                                    if aux_output_idx > data.num_outputs as usize {
-                                        break;
-                                    }
-
-                                    let audio_output = &*data.outputs.add(aux_output_idx);
-                                    match NonNull::new(audio_output.buffers as *mut *mut f32) {
-                                        Some(ptrs) => {
-                                            let num_channels = audio_output.num_channels as usize;
-
-                                            *aux_output_channel_pointers =
-                                                Some(ChannelPointers { ptrs, num_channels });
-                                        }
-                                        None => continue,
-                                    }
-                                }
-                            }
-                        });
-
-                    // We already checked whether the host has initiated a parameter flush, but in
-                    // case it still did something unexpected that we did not catch we'll still try
-                    // to prevent processing audio when the slices don't contain the values we
-                    // expect.
-                    let mut buffer_is_valid = true;
-                    for output_buffer_slice in
-                        buffers.main_buffer.as_slice_immutable().iter().chain(
-                            buffers
-                                .aux_outputs
-                                .iter()
-                                .flat_map(|buffer| buffer.as_slice_immutable().iter()),
-                        )
-                    {
-                        if output_buffer_slice.is_empty() {
-                            buffer_is_valid = false;
-                            break;
-                        }
-                    }
-                    nih_debug_assert!(buffer_is_valid);
-
                     // Some of the fields are left empty because VST3 does not provide this
                     // information, but the methods on [`Transport`] can reconstruct these values
                     // from the other fields
@@ -1389,20 +1483,32 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                         }
                     }
 
-                    let result = if buffer_is_valid {
-                        // NOTE: `parking_lot`'s mutexes sometimes allocate because of their use of
-                        //       thread locals
-                        let mut plugin = permit_alloc(|| self.inner.plugin.lock());
-                        let mut aux = AuxiliaryBuffers {
-                            inputs: buffers.aux_inputs,
-                            outputs: buffers.aux_outputs,
-                        };
-                        let mut context = self.inner.make_process_context(transport);
-                        let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
-                        self.inner.last_process_status.store(result);
-                        result
+                    // The host tells us which sample size it uses for every process call, and it
+                    // will only use 64-bit buffers if the plugin said it supports them
+                    let result = if process_f64 {
+                        self.process_block(
+                            data,
+                            &self.inner.buffer_manager_f64,
+                            &self.inner.bypass_f64,
+                            block_start,
+                            block_len,
+                            |plugin, buffer, aux| {
+                                let mut context = self.inner.make_process_context(transport);
+                                plugin.process_f64(buffer, aux, &mut context)
+                            },
+                        )
                     } else {
-                        ProcessStatus::Normal
+                        self.process_block(
+                            data,
+                            &self.inner.buffer_manager,
+                            &self.inner.bypass,
+                            block_start,
+                            block_len,
+                            |plugin, buffer, aux| {
+                                let mut context = self.inner.make_process_context(transport);
+                                plugin.process(buffer, aux, &mut context)
+                            },
+                        )
                     };
 
                     match result {